@@ -0,0 +1,130 @@
+//! `Connection` on a Tokio socket, for tools that already run an async
+//! runtime. Reconnection is the caller's loop, or `connect_retrying`.
+
+use crate::connection::{check_accepted, next_status};
+use crate::{Accepted, ClientError, ClientOptions, HANDSHAKE_TIMEOUT, RETRY_DELAY};
+use haptic_protocol::{encode_frame, ClientRole, FrameDecoder, HapticCommand, ServerStatus};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+
+pub struct AsyncConnection {
+    stream: UnixStream,
+    decoder: FrameDecoder,
+    frame: Vec<u8>,
+    input: Vec<u8>,
+    accepted: Accepted,
+}
+
+impl AsyncConnection {
+    /// Connect, send the `Hello` for `options` in `role`, and wait up to
+    /// `HANDSHAKE_TIMEOUT` for the server to accept it.
+    pub async fn connect(options: &ClientOptions, role: ClientRole) -> Result<Self, ClientError> {
+        let stream = UnixStream::connect(&options.socket_path).await?;
+        let mut connection = Self {
+            stream,
+            decoder: FrameDecoder::new(),
+            frame: Vec::with_capacity(256),
+            input: vec![0; 8192],
+            accepted: Accepted {
+                protocol_version: haptic_protocol::PROTOCOL_VERSION,
+                capabilities: haptic_protocol::Capabilities::NONE,
+            },
+        };
+        connection.send(&options.hello(role)).await?;
+        let status = tokio::time::timeout(HANDSHAKE_TIMEOUT, connection.recv())
+            .await
+            .map_err(|_| ClientError::Timeout)??;
+        connection.accepted = check_accepted(status, options.instance_id)?;
+        Ok(connection)
+    }
+
+    /// `connect`, retrying every `RETRY_DELAY` until the server accepts.
+    pub async fn connect_retrying(options: &ClientOptions, role: ClientRole) -> Self {
+        loop {
+            if let Ok(connection) = Self::connect(options, role).await {
+                return connection;
+            }
+            tokio::time::sleep(RETRY_DELAY).await;
+        }
+    }
+
+    pub fn accepted(&self) -> Accepted {
+        self.accepted
+    }
+
+    pub async fn send(&mut self, command: &HapticCommand) -> Result<(), ClientError> {
+        encode_frame(command, &mut self.frame)?;
+        self.stream.write_all(&self.frame).await?;
+        Ok(())
+    }
+
+    /// Wait for the next status. Cancel-safe: a partial frame stays buffered.
+    pub async fn recv(&mut self) -> Result<ServerStatus, ClientError> {
+        loop {
+            if let Some(status) = next_status(&mut self.decoder)? {
+                return Ok(status);
+            }
+            let n = self.stream.read(&mut self.input).await?;
+            if n == 0 {
+                return Err(ClientError::Closed);
+            }
+            self.decoder.extend(&self.input[..n]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use haptic_protocol::{Capabilities, PROTOCOL_VERSION};
+    use tokio::net::UnixListener;
+
+    #[tokio::test]
+    async fn handshake_and_status_arrive_over_tokio() {
+        let path =
+            std::env::temp_dir().join(format!("haptic-client-async-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let mut options = ClientOptions::new(11);
+        options.socket_path = path.clone();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut decoder = FrameDecoder::new();
+            let mut input = [0u8; 256];
+            while decoder.next_frame::<HapticCommand>().unwrap().is_none() {
+                let n = stream.read(&mut input).await.unwrap();
+                decoder.extend(&input[..n]);
+            }
+            let mut frame = Vec::new();
+            for status in [
+                ServerStatus::HelloAccepted {
+                    protocol_version: PROTOCOL_VERSION,
+                    instance_id: 11,
+                    capabilities: Capabilities::NONE,
+                },
+                ServerStatus::TransducerLevels {
+                    timestamp_us: 3,
+                    levels: vec![0.5],
+                },
+            ] {
+                encode_frame(&status, &mut frame).unwrap();
+                stream.write_all(&frame).await.unwrap();
+            }
+        });
+
+        let mut connection = AsyncConnection::connect(&options, ClientRole::Observer)
+            .await
+            .unwrap();
+        assert_eq!(connection.accepted().capabilities, Capabilities::NONE);
+        assert!(matches!(
+            connection.recv().await.unwrap(),
+            ServerStatus::TransducerLevels {
+                timestamp_us: 3,
+                ..
+            }
+        ));
+        server.await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
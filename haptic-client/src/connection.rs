@@ -0,0 +1,246 @@
+use crate::{ClientError, ClientOptions, HANDSHAKE_TIMEOUT};
+use haptic_protocol::{
+    encode_frame, Capabilities, ClientRole, FrameDecoder, FrameError, HapticCommand, ServerStatus,
+    MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
+};
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::time::{Duration, Instant};
+
+/// What the server granted in its `HelloAccepted`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Accepted {
+    pub protocol_version: u16,
+    pub capabilities: Capabilities,
+}
+
+/// A blocking connection that has been accepted by the server.
+pub struct Connection {
+    stream: UnixStream,
+    decoder: FrameDecoder,
+    frame: Vec<u8>,
+    input: Vec<u8>,
+    accepted: Accepted,
+}
+
+impl Connection {
+    /// Connect, send the `Hello` for `options` in `role`, and wait up to
+    /// `HANDSHAKE_TIMEOUT` for the server to accept it.
+    pub fn connect(options: &ClientOptions, role: ClientRole) -> Result<Self, ClientError> {
+        let stream = UnixStream::connect(&options.socket_path)?;
+        let mut connection = Self {
+            stream,
+            decoder: FrameDecoder::new(),
+            frame: Vec::with_capacity(256),
+            input: vec![0; 8192],
+            accepted: Accepted {
+                protocol_version: PROTOCOL_VERSION,
+                capabilities: Capabilities::NONE,
+            },
+        };
+        connection.send(&options.hello(role))?;
+        let status = connection
+            .recv_timeout(HANDSHAKE_TIMEOUT)?
+            .ok_or(ClientError::Timeout)?;
+        connection.accepted = check_accepted(status, options.instance_id)?;
+        Ok(connection)
+    }
+
+    pub fn accepted(&self) -> Accepted {
+        self.accepted
+    }
+
+    pub fn send(&mut self, command: &HapticCommand) -> Result<(), ClientError> {
+        encode_frame(command, &mut self.frame)?;
+        self.stream.write_all(&self.frame)?;
+        Ok(())
+    }
+
+    /// A second handle that writes commands on this connection, for a
+    /// thread other than the one blocked in `recv`.
+    pub fn command_writer(&self) -> Result<CommandWriter, ClientError> {
+        Ok(CommandWriter {
+            stream: self.stream.try_clone()?,
+            frame: Vec::with_capacity(64),
+        })
+    }
+
+    /// Block until the next status arrives.
+    pub fn recv(&mut self) -> Result<ServerStatus, ClientError> {
+        self.stream.set_read_timeout(None)?;
+        loop {
+            if let Some(status) = next_status(&mut self.decoder)? {
+                return Ok(status);
+            }
+            match self.fill() {
+                Err(ClientError::Io(e)) if e.kind() == ErrorKind::Interrupted => {}
+                result => result?,
+            }
+        }
+    }
+
+    /// The next status, or `None` if none completes within `timeout`.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<Option<ServerStatus>, ClientError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(status) = next_status(&mut self.decoder)? {
+                return Ok(Some(status));
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            self.stream.set_read_timeout(Some(remaining))?;
+            match self.fill() {
+                Err(ClientError::Io(e))
+                    if matches!(
+                        e.kind(),
+                        ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted
+                    ) => {}
+                result => result?,
+            }
+        }
+    }
+
+    fn fill(&mut self) -> Result<(), ClientError> {
+        let n = self.stream.read(&mut self.input)?;
+        if n == 0 {
+            return Err(ClientError::Closed);
+        }
+        self.decoder.extend(&self.input[..n]);
+        Ok(())
+    }
+}
+
+/// Writes commands on a connection owned by another thread.
+pub struct CommandWriter {
+    stream: UnixStream,
+    frame: Vec<u8>,
+}
+
+impl CommandWriter {
+    pub fn send(&mut self, command: &HapticCommand) -> Result<(), ClientError> {
+        encode_frame(command, &mut self.frame)?;
+        self.stream.write_all(&self.frame)?;
+        Ok(())
+    }
+}
+
+/// The next complete status in `decoder`. A malformed server could claim any
+/// number of transducers, so statuses failing `is_bounded` are dropped here.
+/// A frame that fails to deserialize (say, a status from a newer server) is
+/// skipped too, as the server does with commands: its boundary is intact, so
+/// only an oversized frame leaves the stream unusable.
+pub(crate) fn next_status(decoder: &mut FrameDecoder) -> Result<Option<ServerStatus>, ClientError> {
+    loop {
+        match decoder.next_frame::<ServerStatus>() {
+            Ok(Some(status)) if status.is_bounded() => return Ok(Some(status)),
+            Ok(Some(_)) | Err(FrameError::Deserialize(_)) => {}
+            Ok(None) => return Ok(None),
+            Err(e @ FrameError::Oversized(_)) => return Err(e.into()),
+        }
+    }
+}
+
+/// Check the first status of a connection is the acceptance of `instance_id`
+/// at a version this build speaks.
+pub(crate) fn check_accepted(
+    status: ServerStatus,
+    instance_id: u64,
+) -> Result<Accepted, ClientError> {
+    match status {
+        ServerStatus::HelloAccepted {
+            protocol_version,
+            instance_id: accepted_id,
+            capabilities,
+        } if accepted_id == instance_id => {
+            if (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&protocol_version) {
+                Ok(Accepted {
+                    protocol_version,
+                    capabilities,
+                })
+            } else {
+                Err(ClientError::Incompatible(protocol_version))
+            }
+        }
+        _ => Err(ClientError::Unexpected),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+    use std::path::PathBuf;
+    use std::thread;
+
+    fn socket_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "haptic-client-{}-{}.sock",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    /// Accept one connection, read its `Hello`, and answer with `reply`
+    /// split across two writes.
+    fn answer_once(listener: UnixListener, reply: ServerStatus) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut decoder = FrameDecoder::new();
+            let mut input = [0u8; 256];
+            while decoder.next_frame::<HapticCommand>().unwrap().is_none() {
+                let n = stream.read(&mut input).unwrap();
+                decoder.extend(&input[..n]);
+            }
+            let mut frame = Vec::new();
+            encode_frame(&reply, &mut frame).unwrap();
+            let split = frame.len() / 2;
+            stream.write_all(&frame[..split]).unwrap();
+            stream.write_all(&frame[split..]).unwrap();
+        })
+    }
+
+    #[test]
+    fn handshake_completes_only_on_a_matching_ack() {
+        let path = socket_path("ack");
+        let mut options = ClientOptions::new(42);
+        options.socket_path = path.clone();
+
+        let server = answer_once(
+            UnixListener::bind(&path).unwrap(),
+            ServerStatus::HelloAccepted {
+                protocol_version: PROTOCOL_VERSION,
+                instance_id: 42,
+                capabilities: Capabilities::CONFIG_STATE,
+            },
+        );
+        let connection = Connection::connect(&options, ClientRole::Observer).unwrap();
+        assert_eq!(
+            connection.accepted(),
+            Accepted {
+                protocol_version: PROTOCOL_VERSION,
+                capabilities: Capabilities::CONFIG_STATE,
+            }
+        );
+        server.join().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let server = answer_once(
+            UnixListener::bind(&path).unwrap(),
+            ServerStatus::HelloAccepted {
+                protocol_version: PROTOCOL_VERSION + 1,
+                instance_id: 42,
+                capabilities: Capabilities::NONE,
+            },
+        );
+        assert!(matches!(
+            Connection::connect(&options, ClientRole::Controller),
+            Err(ClientError::Incompatible(version)) if version == PROTOCOL_VERSION + 1
+        ));
+        server.join().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,339 @@
+use crate::{ClientOptions, Connection, RETRY_DELAY};
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender, TrySendError};
+use haptic_protocol::{ClientRole, HapticCommand, InstanceConfig};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Commands that may wait for the connection thread.
+const COMMAND_QUEUE: usize = 256;
+
+/// Idle time after which the connection thread probes for a stopped server.
+const PROBE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Command-side reconnecting client. It waits for the server's one-shot
+/// `HelloAccepted` response, then receives no continuous status stream. A
+/// background thread keeps the connection up, re-sending the `Hello`
+/// handshake with the live config on every (re)connect, so a plugin or tool
+/// survives server restarts. `send_command` never blocks, so it is safe to
+/// call from an audio thread.
+pub struct Controller {
+    command_tx: Sender<HapticCommand>,
+    state: Arc<ControllerState>,
+}
+
+struct ControllerState {
+    connected: AtomicBool,
+    /// Increments on every successful (re)connect.
+    connect_generation: AtomicU64,
+    /// Current note-type config, re-sent in `Hello` on each (re)connect so the
+    /// server always has this instance's live configuration after a restart.
+    config: ConfigSnapshot,
+}
+
+impl Controller {
+    /// Spawn the connection thread. Infallible: if the server is down the
+    /// thread simply keeps retrying in the background. It exits once the
+    /// `Controller` is dropped.
+    pub fn spawn(options: ClientOptions) -> Self {
+        let (command_tx, command_rx) = bounded(COMMAND_QUEUE);
+        let state = Arc::new(ControllerState {
+            connected: AtomicBool::new(false),
+            connect_generation: AtomicU64::new(0),
+            config: ConfigSnapshot::new(options.config),
+        });
+        {
+            let state = state.clone();
+            thread::spawn(move || connection_thread(options, command_rx, state));
+        }
+        Self { command_tx, state }
+    }
+
+    /// Non-blocking send; drops (and reports `Err`) if the queue is full.
+    /// Commands queued while disconnected are discarded on reconnect so
+    /// stale notes don't fire late.
+    pub fn send_command(&self, command: HapticCommand) -> Result<(), TrySendError<HapticCommand>> {
+        self.command_tx.try_send(command)
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state.connected.load(Ordering::Relaxed)
+    }
+
+    /// Successful handshakes so far; more than one means reconnects.
+    pub fn connect_generation(&self) -> u64 {
+        self.state.connect_generation.load(Ordering::Relaxed)
+    }
+
+    /// Update the config re-sent on the next (re)connect. Call it alongside
+    /// the live `SetParameter` send.
+    pub fn set_config(&self, config: InstanceConfig) {
+        self.state.config.store(config);
+    }
+}
+
+fn connection_thread(
+    mut options: ClientOptions,
+    command_rx: Receiver<HapticCommand>,
+    state: Arc<ControllerState>,
+) {
+    loop {
+        options.config = state.config.load();
+        let mut connection = match Connection::connect(&options, ClientRole::Controller) {
+            Ok(connection) => connection,
+            Err(_) => {
+                // Back off before retrying; recv_timeout doubles as teardown
+                // detection — if the Controller has dropped, stop.
+                match command_rx.recv_timeout(RETRY_DELAY) {
+                    Err(RecvTimeoutError::Disconnected) => return,
+                    _ => continue, // timed out, or a command we drop while offline
+                }
+            }
+        };
+        // Drop any commands queued while disconnected so stale notes don't
+        // fire on reconnect.
+        while command_rx.try_recv().is_ok() {}
+        state.connected.store(true, Ordering::Relaxed);
+        state.connect_generation.fetch_add(1, Ordering::Relaxed);
+
+        // Write commands until the socket fails. Controllers receive no status,
+        // so a short read probe on idle intervals detects a stopped server even
+        // when nothing is being sent.
+        loop {
+            match command_rx.recv_timeout(PROBE_INTERVAL) {
+                Ok(command) => {
+                    if connection.send(&command).is_err() {
+                        break;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    if let Err(e) = connection.recv_timeout(Duration::from_millis(10)) {
+                        if e.is_disconnect() {
+                            break;
+                        }
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    state.connected.store(false, Ordering::Relaxed);
+                    return;
+                }
+            }
+        }
+
+        state.connected.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Lock-free copy of an `InstanceConfig`, written from a host's parameter
+/// callbacks and read by the connection thread. A sequence counter makes the
+/// reader retry if it overlaps a write.
+struct ConfigSnapshot {
+    sequence: AtomicU64,
+    stimulus_type: AtomicU32,
+    wave_speed: AtomicU32,
+    tw_wave_speed: AtomicU32,
+    scale_mode: AtomicU32,
+    wavelength_m: AtomicU32,
+    atten_d0_m: AtomicU32,
+    atten_exponent: AtomicU32,
+    source_height_m: AtomicU32,
+}
+
+impl ConfigSnapshot {
+    fn new(config: InstanceConfig) -> Self {
+        Self {
+            sequence: AtomicU64::new(0),
+            stimulus_type: AtomicU32::new(Self::encode_stimulus(config.stimulus_type)),
+            wave_speed: AtomicU32::new(config.wave_speed.to_bits()),
+            tw_wave_speed: AtomicU32::new(config.travelling_wave.wave_speed.to_bits()),
+            scale_mode: AtomicU32::new(Self::encode_scale_mode(config.travelling_wave.scale_mode)),
+            wavelength_m: AtomicU32::new(config.travelling_wave.wavelength_m.to_bits()),
+            atten_d0_m: AtomicU32::new(config.distance_decay.d0_m.to_bits()),
+            atten_exponent: AtomicU32::new(config.distance_decay.exponent.to_bits()),
+            source_height_m: AtomicU32::new(config.source_height_m.to_bits()),
+        }
+    }
+
+    fn store(&self, config: InstanceConfig) {
+        self.sequence.fetch_add(1, Ordering::AcqRel);
+        self.stimulus_type.store(
+            Self::encode_stimulus(config.stimulus_type),
+            Ordering::Relaxed,
+        );
+        self.wave_speed
+            .store(config.wave_speed.to_bits(), Ordering::Relaxed);
+        self.tw_wave_speed.store(
+            config.travelling_wave.wave_speed.to_bits(),
+            Ordering::Relaxed,
+        );
+        self.scale_mode.store(
+            Self::encode_scale_mode(config.travelling_wave.scale_mode),
+            Ordering::Relaxed,
+        );
+        self.wavelength_m.store(
+            config.travelling_wave.wavelength_m.to_bits(),
+            Ordering::Relaxed,
+        );
+        self.atten_d0_m
+            .store(config.distance_decay.d0_m.to_bits(), Ordering::Relaxed);
+        self.atten_exponent
+            .store(config.distance_decay.exponent.to_bits(), Ordering::Relaxed);
+        self.source_height_m
+            .store(config.source_height_m.to_bits(), Ordering::Relaxed);
+        self.sequence.fetch_add(1, Ordering::Release);
+    }
+
+    fn load(&self) -> InstanceConfig {
+        loop {
+            let before = self.sequence.load(Ordering::Acquire);
+            if before & 1 != 0 {
+                std::hint::spin_loop();
+                continue;
+            }
+            let stimulus_type = match self.stimulus_type.load(Ordering::Relaxed) {
+                0 => haptic_protocol::StimulusType::Wave,
+                _ => haptic_protocol::StimulusType::TravellingWave,
+            };
+            let wave_speed = f32::from_bits(self.wave_speed.load(Ordering::Relaxed));
+            let tw_wave_speed = f32::from_bits(self.tw_wave_speed.load(Ordering::Relaxed));
+            let scale_mode = match self.scale_mode.load(Ordering::Relaxed) {
+                0 => haptic_protocol::SpatialScaleMode::Speed,
+                _ => haptic_protocol::SpatialScaleMode::Wavelength,
+            };
+            let wavelength_m = f32::from_bits(self.wavelength_m.load(Ordering::Relaxed));
+            let atten_d0_m = f32::from_bits(self.atten_d0_m.load(Ordering::Relaxed));
+            let atten_exponent = f32::from_bits(self.atten_exponent.load(Ordering::Relaxed));
+            let source_height_m = f32::from_bits(self.source_height_m.load(Ordering::Relaxed));
+            if before == self.sequence.load(Ordering::Acquire) {
+                return InstanceConfig {
+                    stimulus_type,
+                    wave_speed,
+                    travelling_wave: haptic_protocol::TravellingWaveConfig {
+                        scale_mode,
+                        wave_speed: tw_wave_speed,
+                        wavelength_m,
+                    },
+                    distance_decay: haptic_protocol::DistanceDecay {
+                        d0_m: atten_d0_m,
+                        exponent: atten_exponent,
+                    },
+                    source_height_m,
+                };
+            }
+        }
+    }
+
+    fn encode_stimulus(stimulus_type: haptic_protocol::StimulusType) -> u32 {
+        match stimulus_type {
+            haptic_protocol::StimulusType::Wave => 0,
+            haptic_protocol::StimulusType::TravellingWave => 1,
+        }
+    }
+
+    fn encode_scale_mode(mode: haptic_protocol::SpatialScaleMode) -> u32 {
+        match mode {
+            haptic_protocol::SpatialScaleMode::Speed => 0,
+            haptic_protocol::SpatialScaleMode::Wavelength => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use haptic_protocol::{
+        encode_frame, Capabilities, FrameDecoder, ServerStatus, StimulusType, PROTOCOL_VERSION,
+    };
+    use std::io::{Read, Write};
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    #[test]
+    fn atomic_config_snapshot_roundtrips_as_one_value() {
+        let snapshot = ConfigSnapshot::new(InstanceConfig::default());
+        let expected = InstanceConfig {
+            stimulus_type: StimulusType::TravellingWave,
+            wave_speed: 3.25,
+            travelling_wave: haptic_protocol::TravellingWaveConfig {
+                scale_mode: haptic_protocol::SpatialScaleMode::Wavelength,
+                wave_speed: 4.5,
+                wavelength_m: 0.075,
+            },
+            distance_decay: haptic_protocol::DistanceDecay {
+                d0_m: 0.8,
+                exponent: 2.0,
+            },
+            source_height_m: 0.35,
+        };
+        snapshot.store(expected);
+        assert_eq!(snapshot.load(), expected);
+    }
+
+    /// Accept one connection and acknowledge its `Hello`, returning the
+    /// stream and the config it carried.
+    fn accept_hello(listener: &UnixListener) -> (UnixStream, InstanceConfig) {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut decoder = FrameDecoder::new();
+        let mut input = [0u8; 256];
+        let hello = loop {
+            if let Some(command) = decoder.next_frame::<HapticCommand>().unwrap() {
+                break command;
+            }
+            let n = stream.read(&mut input).unwrap();
+            decoder.extend(&input[..n]);
+        };
+        let HapticCommand::Hello {
+            instance_id,
+            config,
+            ..
+        } = hello
+        else {
+            panic!("first command was not Hello");
+        };
+        let mut frame = Vec::new();
+        encode_frame(
+            &ServerStatus::HelloAccepted {
+                protocol_version: PROTOCOL_VERSION,
+                instance_id,
+                capabilities: Capabilities::NONE,
+            },
+            &mut frame,
+        )
+        .unwrap();
+        stream.write_all(&frame).unwrap();
+        (stream, config)
+    }
+
+    #[test]
+    fn reconnects_with_the_live_config_after_the_server_goes_away() {
+        let path = std::env::temp_dir().join(format!(
+            "haptic-client-reconnect-{}.sock",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let mut options = ClientOptions::new(7);
+        options.socket_path = path.clone();
+        let controller = Controller::spawn(options);
+
+        let (first, config) = accept_hello(&listener);
+        assert_eq!(config, InstanceConfig::default());
+        let updated = InstanceConfig {
+            wave_speed: 5.0,
+            ..InstanceConfig::default()
+        };
+        controller.set_config(updated);
+        drop(first);
+
+        // The idle probe notices the closed socket and handshakes again
+        let (_second, config) = accept_hello(&listener);
+        assert_eq!(config, updated);
+        let deadline = std::time::Instant::now() + Duration::from_secs(2);
+        while controller.connect_generation() < 2 && std::time::Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(controller.connect_generation(), 2);
+        assert!(controller.is_connected());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,146 @@
+//! Typed clients for the haptic server socket.
+//!
+//! [`Connection`] is one blocking connection that has completed the `Hello`
+//! handshake. [`Controller`] and [`Observer`] run a connection on a
+//! background thread and reconnect whenever the server goes away, re-sending
+//! the handshake each time, so a tool survives server restarts. With the
+//! `tokio` feature, [`asynchronous::AsyncConnection`] performs the same
+//! handshake on a Tokio socket.
+
+#[cfg(feature = "tokio")]
+pub mod asynchronous;
+mod connection;
+mod controller;
+mod observer;
+
+pub use connection::{Accepted, CommandWriter, Connection};
+pub use controller::Controller;
+pub use observer::{CommandSender, Observer, ObserverEvent};
+
+use haptic_protocol::{
+    Capabilities, ClientRole, FrameError, HapticCommand, InstanceConfig, MIN_PROTOCOL_VERSION,
+    PROTOCOL_VERSION, SOCKET_PATH,
+};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How long the server has to answer a `Hello`.
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Pause between reconnection attempts.
+pub const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Who a client is and what it asks for in its `Hello`.
+#[derive(Clone, Debug)]
+pub struct ClientOptions {
+    pub socket_path: PathBuf,
+    /// Identity the server stamps on this client's notes. Must be non-zero
+    /// and unique among live connections.
+    pub instance_id: u64,
+    /// Note-type config registered for `instance_id`.
+    pub config: InstanceConfig,
+    /// Optional status kinds an observer wants; controllers receive none.
+    pub capabilities: Capabilities,
+}
+
+impl ClientOptions {
+    /// Default config and every capability, on `HAPTIC_SOCKET_PATH` if set
+    /// and the shared default socket otherwise.
+    pub fn new(instance_id: u64) -> Self {
+        Self {
+            socket_path: std::env::var_os("HAPTIC_SOCKET_PATH")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from(SOCKET_PATH)),
+            instance_id,
+            config: InstanceConfig::default(),
+            capabilities: Capabilities::ALL,
+        }
+    }
+
+    /// The handshake for these options in `role`.
+    pub fn hello(&self, role: ClientRole) -> HapticCommand {
+        HapticCommand::Hello {
+            min_protocol_version: MIN_PROTOCOL_VERSION,
+            max_protocol_version: PROTOCOL_VERSION,
+            instance_id: self.instance_id,
+            role,
+            config: self.config,
+            capabilities: self.capabilities,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ClientError {
+    Io(std::io::Error),
+    Encode(bincode::Error),
+    Frame(FrameError),
+    /// The server closed the connection. During the handshake this is how
+    /// it rejects a `Hello`.
+    Closed,
+    /// No `HelloAccepted` arrived within `HANDSHAKE_TIMEOUT`.
+    Timeout,
+    /// The server accepted a version outside this build's range.
+    Incompatible(u16),
+    /// Something other than this client's `HelloAccepted` answered the
+    /// handshake.
+    Unexpected,
+}
+
+impl ClientError {
+    /// Whether the connection is certainly gone. Read timeouts are reported
+    /// inconsistently across Unix variants, so any other transport error
+    /// leaves it presumed alive; a later write detects a dead peer too.
+    pub fn is_disconnect(&self) -> bool {
+        match self {
+            ClientError::Io(e) => matches!(
+                e.kind(),
+                std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::NotConnected
+                    | std::io::ErrorKind::UnexpectedEof
+            ),
+            ClientError::Frame(FrameError::Oversized(_)) | ClientError::Closed => true,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientError::Io(e) => write!(f, "socket error: {}", e),
+            ClientError::Encode(e) => write!(f, "command encoding failed: {}", e),
+            ClientError::Frame(e) => write!(f, "{}", e),
+            ClientError::Closed => write!(f, "server closed the connection"),
+            ClientError::Timeout => write!(f, "server did not accept the handshake"),
+            ClientError::Incompatible(version) => write!(
+                f,
+                "server chose protocol {}, this client speaks {}..={}",
+                version, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION
+            ),
+            ClientError::Unexpected => write!(f, "unexpected handshake response"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<std::io::Error> for ClientError {
+    fn from(e: std::io::Error) -> Self {
+        ClientError::Io(e)
+    }
+}
+
+impl From<bincode::Error> for ClientError {
+    fn from(e: bincode::Error) -> Self {
+        ClientError::Encode(e)
+    }
+}
+
+impl From<FrameError> for ClientError {
+    fn from(e: FrameError) -> Self {
+        ClientError::Frame(e)
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{Accepted, ClientOptions, CommandWriter, Connection, RETRY_DELAY};
+use crossbeam_channel::{bounded, Receiver, Sender};
+use haptic_protocol::{ClientRole, HapticCommand, ServerStatus};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::thread;
+
+/// Statuses that may wait for the consumer. When it falls further behind the
+/// connection thread stops reading, and the server drops the observer once
+/// its own buffer fills; the thread then reconnects.
+const EVENT_QUEUE: usize = 1024;
+
+/// What an observer's connection thread reports, in order.
+// Statuses are forwarded as decoded, like `ServerStatus` itself is unboxed.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+pub enum ObserverEvent {
+    /// A (re)connect completed its handshake. The server's greeting follows.
+    Connected(Accepted),
+    Status(ServerStatus),
+    /// The connection was lost; state learned from it is stale. A
+    /// `Connected` follows once the server is back.
+    Disconnected,
+}
+
+/// Status-side reconnecting client. A background thread keeps an observer
+/// connection up and forwards everything the server sends as
+/// `ObserverEvent`s. Commands can be written on the same connection, so an
+/// observer can also play its own notes.
+pub struct Observer {
+    events: Receiver<ObserverEvent>,
+    commands: CommandSender,
+}
+
+impl Observer {
+    /// Spawn the connection thread. It exits at its next event once every
+    /// receiver from `events` and this `Observer` is dropped.
+    pub fn spawn(options: ClientOptions) -> Self {
+        let (event_tx, events) = bounded(EVENT_QUEUE);
+        let commands = CommandSender::default();
+        {
+            let commands = commands.clone();
+            thread::spawn(move || connection_thread(options, event_tx, commands));
+        }
+        Self { events, commands }
+    }
+
+    pub fn events(&self) -> Receiver<ObserverEvent> {
+        self.events.clone()
+    }
+
+    pub fn commands(&self) -> CommandSender {
+        self.commands.clone()
+    }
+
+    /// Send on the current connection. See `CommandSender::send`.
+    pub fn send_command(&self, command: &HapticCommand) -> bool {
+        self.commands.send(command)
+    }
+}
+
+/// Cloneable handle that writes commands on an observer's current
+/// connection.
+#[derive(Clone, Default)]
+pub struct CommandSender {
+    writer: Arc<Mutex<Option<CommandWriter>>>,
+}
+
+impl CommandSender {
+    /// Write `command` now. Returns `false` if there is no connection or the
+    /// write failed; the command is not retried.
+    pub fn send(&self, command: &HapticCommand) -> bool {
+        let mut writer = self.writer.lock();
+        let Some(connection) = writer.as_mut() else {
+            return false;
+        };
+        if connection.send(command).is_err() {
+            *writer = None;
+            return false;
+        }
+        true
+    }
+}
+
+fn connection_thread(
+    options: ClientOptions,
+    event_tx: Sender<ObserverEvent>,
+    commands: CommandSender,
+) {
+    loop {
+        if let Ok(mut connection) = Connection::connect(&options, ClientRole::Observer) {
+            *commands.writer.lock() = connection.command_writer().ok();
+            if event_tx
+                .send(ObserverEvent::Connected(connection.accepted()))
+                .is_err()
+            {
+                return;
+            }
+            // Undecodable statuses are skipped inside `recv`, so this ends
+            // only on an oversized frame, a socket error or a close; reconnect
+            // to resynchronise
+            while let Ok(status) = connection.recv() {
+                if event_tx.send(ObserverEvent::Status(status)).is_err() {
+                    return;
+                }
+            }
+            *commands.writer.lock() = None;
+            if event_tx.send(ObserverEvent::Disconnected).is_err() {
+                return;
+            }
+        }
+        thread::sleep(RETRY_DELAY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use haptic_protocol::{encode_frame, Capabilities, FrameDecoder, PROTOCOL_VERSION};
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixListener;
+    use std::time::Duration;
+
+    #[test]
+    fn events_follow_the_connection_and_commands_reach_the_server() {
+        let path = std::env::temp_dir().join(format!(
+            "haptic-client-observer-{}.sock",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let mut options = ClientOptions::new(9);
+        options.socket_path = path.clone();
+        let observer = Observer::spawn(options);
+        let events = observer.events();
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut decoder = FrameDecoder::new();
+        let mut input = [0u8; 256];
+        let mut next_command = |stream: &mut std::os::unix::net::UnixStream| loop {
+            if let Some(command) = decoder.next_frame::<HapticCommand>().unwrap() {
+                break command;
+            }
+            let n = stream.read(&mut input).unwrap();
+            decoder.extend(&input[..n]);
+        };
+        assert!(matches!(
+            next_command(&mut stream),
+            HapticCommand::Hello {
+                role: ClientRole::Observer,
+                capabilities: Capabilities::ALL,
+                ..
+            }
+        ));
+        // A well-framed payload that is not a status sits between the two;
+        // it is skipped, not a reason to reconnect
+        let mut frame = Vec::new();
+        let mut send_status = |stream: &mut std::os::unix::net::UnixStream,
+                               status: ServerStatus| {
+            encode_frame(&status, &mut frame).unwrap();
+            stream.write_all(&frame).unwrap();
+        };
+        send_status(
+            &mut stream,
+            ServerStatus::HelloAccepted {
+                protocol_version: PROTOCOL_VERSION,
+                instance_id: 9,
+                capabilities: Capabilities::ALL,
+            },
+        );
+        stream.write_all(&[3, 0, 0, 0, 0xff, 0xff, 0xff]).unwrap();
+        send_status(
+            &mut stream,
+            ServerStatus::TransducerLevels {
+                timestamp_us: 5,
+                levels: vec![0.25; 4],
+            },
+        );
+
+        let timeout = Duration::from_secs(2);
+        assert!(matches!(
+            events.recv_timeout(timeout).unwrap(),
+            ObserverEvent::Connected(Accepted {
+                protocol_version: PROTOCOL_VERSION,
+                ..
+            })
+        ));
+        assert!(matches!(
+            events.recv_timeout(timeout).unwrap(),
+            ObserverEvent::Status(ServerStatus::TransducerLevels {
+                timestamp_us: 5,
+                ..
+            })
+        ));
+        assert!(observer.send_command(&HapticCommand::Panic));
+        assert!(matches!(next_command(&mut stream), HapticCommand::Panic));
+
+        drop(stream);
+        assert!(matches!(
+            events.recv_timeout(timeout).unwrap(),
+            ObserverEvent::Disconnected
+        ));
+        assert!(!observer.send_command(&HapticCommand::Panic));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
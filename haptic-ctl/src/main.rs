@@ -0,0 +1,739 @@
+//! `haptic-ctl`: command-line control of a running server, for shell scripts
+//! and unattended lab runs. Each invocation is one connection: `note` plays a
+//! note as a controller (its instance and any held voice end with the
+//! process; as an observer when it captures, which only observers may
+//! start), `route` changes the monitor routing and waits to see it in
+//! effect, `panic` silences every voice, and `dump` connects as an observer
+//! and prints what the server reports as JSON.
+
+use haptic_client::{ClientError, ClientOptions, Connection};
+use haptic_protocol::{
+    ClientRole, HapticCommand, InstanceConfig, MpeData, Parameter, ServerStatus, SpatialScaleMode,
+    StimulusType, SOCKET_PATH,
+};
+use serde_json::{json, Map, Value};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// MIDI channel for notes, clear of the channels a DAW's MPE zone uses first.
+const NOTE_CHANNEL: u8 = 15;
+
+/// Ableton A0, 55 Hz without transposition.
+const DEFAULT_NOTE: u8 = 33;
+
+/// Interval between MPE updates while the source moves.
+const MOVE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Time left for the release tail after note-off before disconnecting, which
+/// would cut the voice.
+const RELEASE_WAIT: Duration = Duration::from_millis(800);
+
+/// How long `dump` waits for the status it was asked for.
+const DUMP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long `dump status` collects, long enough for the slowest periodic
+/// report after the connect greeting.
+const STATUS_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Debug, PartialEq)]
+struct CtlOptions {
+    socket_path: String,
+    command: Command,
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Note(NoteOptions),
+    Route(Vec<(u8, u8)>),
+    Panic,
+    Dump { what: DumpKind, follow: bool },
+}
+
+#[derive(Debug, PartialEq)]
+struct NoteOptions {
+    note: u8,
+    velocity: u8,
+    duration: Duration,
+    pressure: f32,
+    /// Source position as MPE: pitch bend in -1..=1 and timbre in 0..=1.
+    from: (f32, f32),
+    /// Glide linearly to this position over the note.
+    to: Option<(f32, f32)>,
+    /// Circle the source, one turn per this many seconds.
+    orbit_period_s: Option<f32>,
+    config: InstanceConfig,
+    capture: Option<PathBuf>,
+}
+
+impl Default for NoteOptions {
+    fn default() -> Self {
+        Self {
+            note: DEFAULT_NOTE,
+            velocity: 100,
+            duration: Duration::from_secs(2),
+            pressure: 1.0,
+            from: (0.0, 0.5),
+            to: None,
+            orbit_period_s: None,
+            config: InstanceConfig::default(),
+            capture: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DumpKind {
+    Status,
+    Layout,
+    Routing,
+    Voices,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        print_usage();
+        return;
+    }
+    let options =
+        parse_options(args, std::env::var("HAPTIC_SOCKET_PATH").ok()).unwrap_or_else(|error| {
+            eprintln!("{error}");
+            print_usage();
+            std::process::exit(2);
+        });
+    if let Err(error) = run(options) {
+        // `dump ... | head` closing the pipe early is not a failure
+        if error
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|error| error.kind() == std::io::ErrorKind::BrokenPipe)
+        {
+            return;
+        }
+        eprintln!("haptic-ctl: {error}");
+        std::process::exit(1);
+    }
+}
+
+fn print_usage() {
+    eprintln!(
+        "Usage: haptic-ctl [--socket PATH] COMMAND [ARGS]\n\
+         \n\
+         Commands:\n\
+         note [NOTE] [--velocity V] [--duration S] [--pressure P] [--x X] [--y Y]\n\
+         \x20    [--to X,Y] [--orbit [--orbit-period S]] [--type wave|tw]\n\
+         \x20    [--wave-speed M/S] [--scale-mode speed|wavelength] [--wavelength M]\n\
+         \x20    [--atten-d0 M] [--atten-p P] [--z M] [--capture PATH]\n\
+         \x20                          Play NOTE (default 33, 55 Hz) for S seconds (default 2)\n\
+         \x20                          on channel 16. X is pitch bend in -1..1 and Y timbre\n\
+         \x20                          in 0..1 (default centre); --to glides there over the\n\
+         \x20                          note. Parameters configure this note's instance;\n\
+         \x20                          --capture records the output, opened by the server.\n\
+         route OUT:SRC...          Play logical channel SRC on physical output OUT.\n\
+         panic                     Silence every voice and the test signal.\n\
+         dump status|layout|routing|voices [--follow]\n\
+         \x20                          Print what the server reports as JSON. status is the\n\
+         \x20                          latest of each report, keyed by kind. --follow prints\n\
+         \x20                          one line per update until interrupted.\n\
+         \n\
+         --socket PATH             Server Unix socket (default {SOCKET_PATH}).\n\
+         HAPTIC_SOCKET_PATH        Environment alternative to --socket."
+    );
+}
+
+fn invalid(message: String) -> Box<dyn std::error::Error> {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, message).into()
+}
+
+fn parse_options(
+    args: impl IntoIterator<Item = String>,
+    environment_socket: Option<String>,
+) -> Result<CtlOptions, Box<dyn std::error::Error>> {
+    let mut socket_path = None;
+    let mut positional = Vec::new();
+    let mut note = NoteOptions::default();
+    let mut follow = false;
+    let mut args = args.into_iter();
+
+    while let Some(argument) = args.next() {
+        let mut value = |option: &str| next_option_value(&mut args, option);
+        match argument.as_str() {
+            "--socket" => socket_path = Some(value("--socket")?),
+            "--follow" => follow = true,
+            "--velocity" => note.velocity = parse_number(&value("--velocity")?, "--velocity")?,
+            "--duration" => {
+                let seconds: f32 = parse_number(&value("--duration")?, "--duration")?;
+                note.duration = Duration::try_from_secs_f32(seconds)
+                    .map_err(|_| invalid(format!("--duration {seconds} is not a duration")))?;
+            }
+            "--pressure" => note.pressure = parse_number(&value("--pressure")?, "--pressure")?,
+            "--x" => note.from.0 = parse_number(&value("--x")?, "--x")?,
+            "--y" => note.from.1 = parse_number(&value("--y")?, "--y")?,
+            "--to" => note.to = Some(parse_pair(&value("--to")?, ',', "--to")?),
+            "--orbit" => note.orbit_period_s = note.orbit_period_s.or(Some(4.0)),
+            "--orbit-period" => {
+                note.orbit_period_s =
+                    Some(parse_number(&value("--orbit-period")?, "--orbit-period")?)
+            }
+            "--type" => {
+                note.config.stimulus_type = match value("--type")?.as_str() {
+                    "wave" => StimulusType::Wave,
+                    "tw" => StimulusType::TravellingWave,
+                    other => return Err(invalid(format!("--type {other}: use wave or tw"))),
+                }
+            }
+            "--wave-speed" => {
+                let speed = parse_number(&value("--wave-speed")?, "--wave-speed")?;
+                note.config.wave_speed = speed;
+                note.config.travelling_wave.wave_speed = speed;
+            }
+            "--scale-mode" => {
+                note.config.travelling_wave.scale_mode = match value("--scale-mode")?.as_str() {
+                    "speed" => SpatialScaleMode::Speed,
+                    "wavelength" => SpatialScaleMode::Wavelength,
+                    other => {
+                        return Err(invalid(format!(
+                            "--scale-mode {other}: use speed or wavelength"
+                        )))
+                    }
+                }
+            }
+            "--wavelength" => {
+                note.config.travelling_wave.wavelength_m =
+                    parse_number(&value("--wavelength")?, "--wavelength")?
+            }
+            "--atten-d0" => {
+                note.config.distance_decay.d0_m = parse_number(&value("--atten-d0")?, "--atten-d0")?
+            }
+            "--atten-p" => {
+                note.config.distance_decay.exponent =
+                    parse_number(&value("--atten-p")?, "--atten-p")?
+            }
+            "--z" => note.config.source_height_m = parse_number(&value("--z")?, "--z")?,
+            "--capture" => {
+                let path = PathBuf::from(value("--capture")?);
+                // The server opens the path from its own working directory
+                note.capture = Some(std::path::absolute(&path).unwrap_or(path));
+            }
+            unknown if unknown.starts_with("--") => {
+                return Err(invalid(format!("unknown argument {unknown}; use --help")));
+            }
+            _ => positional.push(argument),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let command = match positional.next().as_deref() {
+        Some("note") => {
+            if let Some(value) = positional.next() {
+                note.note = parse_number(&value, "note")?;
+            }
+            Command::Note(note)
+        }
+        Some("route") => {
+            let routes = positional
+                .by_ref()
+                .map(|route| parse_pair(&route, ':', "route"))
+                .collect::<Result<Vec<_>, _>>()?;
+            if routes.is_empty() {
+                return Err(invalid("route needs at least one OUT:SRC".to_string()));
+            }
+            Command::Route(routes)
+        }
+        Some("panic") => Command::Panic,
+        Some("dump") => {
+            let what = match positional.next().as_deref() {
+                Some("status") => DumpKind::Status,
+                Some("layout") => DumpKind::Layout,
+                Some("routing") => DumpKind::Routing,
+                Some("voices") => DumpKind::Voices,
+                _ => {
+                    return Err(invalid(
+                        "dump needs status, layout, routing or voices".to_string(),
+                    ))
+                }
+            };
+            Command::Dump { what, follow }
+        }
+        Some(other) => return Err(invalid(format!("unknown command {other}; use --help"))),
+        None => return Err(invalid("no command given; use --help".to_string())),
+    };
+    if let Some(extra) = positional.next() {
+        return Err(invalid(format!("unexpected argument {extra}")));
+    }
+
+    Ok(CtlOptions {
+        socket_path: socket_path
+            .or(environment_socket)
+            .unwrap_or_else(|| SOCKET_PATH.to_string()),
+        command,
+    })
+}
+
+fn next_option_value(
+    args: &mut impl Iterator<Item = String>,
+    option: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    args.next()
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| invalid(format!("{option} requires a value")))
+}
+
+fn parse_number<T: std::str::FromStr>(
+    value: &str,
+    option: &str,
+) -> Result<T, Box<dyn std::error::Error>> {
+    value
+        .parse()
+        .map_err(|_| invalid(format!("{option}: {value} is not a valid number")))
+}
+
+fn parse_pair<T: std::str::FromStr>(
+    value: &str,
+    separator: char,
+    option: &str,
+) -> Result<(T, T), Box<dyn std::error::Error>> {
+    let (a, b) = value
+        .split_once(separator)
+        .ok_or_else(|| invalid(format!("{option}: expected A{separator}B, got {value}")))?;
+    Ok((parse_number(a, option)?, parse_number(b, option)?))
+}
+
+fn run(options: CtlOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let mut client = ClientOptions::new(new_instance_id());
+    client.socket_path = options.socket_path.into();
+    match options.command {
+        Command::Note(note) => {
+            client.config = note.config;
+            let role = if note.capture.is_some() {
+                ClientRole::Observer
+            } else {
+                ClientRole::Controller
+            };
+            let mut connection = Connection::connect(&client, role)?;
+            play_note(&mut connection, &note)?;
+        }
+        Command::Route(routes) => {
+            let mut connection = Connection::connect(&client, ClientRole::Observer)?;
+            set_routes(&mut connection, &routes)?;
+        }
+        Command::Panic => {
+            let mut connection = Connection::connect(&client, ClientRole::Controller)?;
+            connection.send(&HapticCommand::Panic)?;
+            eprintln!("panic sent");
+        }
+        Command::Dump { what, follow } => {
+            let mut connection = Connection::connect(&client, ClientRole::Observer)?;
+            dump(&mut connection, what, follow)?;
+        }
+    }
+    Ok(())
+}
+
+/// Non-zero identity, distinct across concurrent invocations.
+fn new_instance_id() -> u64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(1);
+    nanos ^ ((std::process::id() as u64) << 32) | 1
+}
+
+fn mpe(pressure: f32, (x, y): (f32, f32)) -> MpeData {
+    MpeData {
+        pressure,
+        pitch_bend: x.clamp(-1.0, 1.0),
+        timbre: y.clamp(0.0, 1.0),
+    }
+}
+
+/// Where the source is `elapsed` into the note.
+fn source_at(note: &NoteOptions, elapsed: Duration) -> (f32, f32) {
+    let progress = (elapsed.as_secs_f32() / note.duration.as_secs_f32().max(1e-6)).min(1.0);
+    let (x, y) = match note.to {
+        Some((to_x, to_y)) => (
+            note.from.0 + (to_x - note.from.0) * progress,
+            note.from.1 + (to_y - note.from.1) * progress,
+        ),
+        None => note.from,
+    };
+    match note.orbit_period_s {
+        Some(period) => {
+            let angle = elapsed.as_secs_f32() / period.max(1e-3) * std::f32::consts::TAU;
+            (x + 0.7 * angle.cos(), y + 0.35 * angle.sin())
+        }
+        None => (x, y),
+    }
+}
+
+/// Wait out `duration`, discarding whatever the server sends meanwhile so an
+/// observer connection never falls behind.
+fn idle(connection: &mut Connection, duration: Duration) -> Result<(), ClientError> {
+    let deadline = Instant::now() + duration;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(());
+        }
+        connection.recv_timeout(remaining)?;
+    }
+}
+
+/// The next `CaptureState`, skipping other statuses.
+fn next_capture_state(
+    connection: &mut Connection,
+) -> Result<(bool, String, String), Box<dyn std::error::Error>> {
+    let deadline = Instant::now() + DUMP_TIMEOUT;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match connection.recv_timeout(remaining)? {
+            Some(ServerStatus::CaptureState {
+                active,
+                path,
+                error,
+                ..
+            }) => return Ok((active, path, error)),
+            Some(_) => {}
+            None => return Err("server sent no capture state".into()),
+        }
+    }
+}
+
+fn start_capture(
+    connection: &mut Connection,
+    path: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = path.to_string_lossy().into_owned();
+    // The connect greeting's state comes first; the answer to ours follows
+    next_capture_state(connection)?;
+    connection.send(&HapticCommand::StartCapture { path: path.clone() })?;
+    match next_capture_state(connection)? {
+        (true, active_path, _) if active_path == path => Ok(()),
+        (_, _, error) => Err(format!("capture refused: {error}").into()),
+    }
+}
+
+/// The routes of the next `MonitorRouting` before `deadline`, skipping other
+/// statuses.
+fn next_routing(
+    connection: &mut Connection,
+    deadline: Instant,
+) -> Result<Option<Vec<u8>>, ClientError> {
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match connection.recv_timeout(remaining)? {
+            Some(ServerStatus::MonitorRouting { routes, .. }) => return Ok(Some(routes)),
+            Some(_) => {}
+            None => return Ok(None),
+        }
+    }
+}
+
+/// Check `routes` against the routing in the connect greeting, send them, and
+/// wait for the server to report all of them in effect.
+fn set_routes(
+    connection: &mut Connection,
+    routes: &[(u8, u8)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut expected = next_routing(connection, Instant::now() + DUMP_TIMEOUT)?
+        .ok_or("server sent no monitor routing")?;
+    let count = expected.len();
+    for &(output, source) in routes {
+        if output as usize >= count || source as usize >= count {
+            return Err(format!(
+                "route {output}:{source} is out of range, the layout has {count} transducers"
+            )
+            .into());
+        }
+        expected[output as usize] = source;
+    }
+
+    for &(output, source) in routes {
+        connection.send(&HapticCommand::SetParameter {
+            timestamp_us: 0,
+            parameter: Parameter::MonitorRoute { output, source },
+        })?;
+    }
+    // Each change is reported on its own, so earlier reports may show only
+    // some of the routes; outputs not named here may be changed by others
+    let deadline = Instant::now() + DUMP_TIMEOUT;
+    while let Some(reported) = next_routing(connection, deadline)? {
+        let in_effect = |&(output, _): &(u8, u8)| {
+            reported.get(output as usize) == expected.get(output as usize)
+        };
+        if routes.iter().all(in_effect) {
+            for &(output, source) in routes {
+                eprintln!("routed output {output} <- channel {source}");
+            }
+            return Ok(());
+        }
+    }
+    Err("server did not confirm the routing".into())
+}
+
+fn play_note(
+    connection: &mut Connection,
+    note: &NoteOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(path) = &note.capture {
+        start_capture(connection, path)?;
+        eprintln!("capturing to {}", path.display());
+    }
+
+    let moving = note.to.is_some() || note.orbit_period_s.is_some();
+    let start = Instant::now();
+    connection.send(&HapticCommand::NoteOn {
+        timestamp_us: 0,
+        note: note.note,
+        velocity: note.velocity,
+        channel: NOTE_CHANNEL,
+        mpe: mpe(note.pressure, source_at(note, Duration::ZERO)),
+    })?;
+    eprintln!(
+        "note {} on (velocity {}), {:.2}s...",
+        note.note,
+        note.velocity,
+        note.duration.as_secs_f32()
+    );
+    while start.elapsed() < note.duration {
+        if moving {
+            connection.send(&HapticCommand::MpeUpdate {
+                timestamp_us: 0,
+                channel: NOTE_CHANNEL,
+                mpe: mpe(note.pressure, source_at(note, start.elapsed())),
+            })?;
+        }
+        let remaining = note.duration.saturating_sub(start.elapsed());
+        idle(
+            connection,
+            if moving {
+                MOVE_INTERVAL.min(remaining)
+            } else {
+                remaining
+            },
+        )?;
+    }
+
+    connection.send(&HapticCommand::NoteOff {
+        timestamp_us: 0,
+        note: note.note,
+        channel: NOTE_CHANNEL,
+    })?;
+    eprintln!("note off");
+    idle(connection, RELEASE_WAIT)?;
+
+    if note.capture.is_some() {
+        connection.send(&HapticCommand::StopCapture)?;
+        idle(connection, Duration::from_millis(100))?;
+        eprintln!("capture stopped");
+    }
+    Ok(())
+}
+
+fn dump(
+    connection: &mut Connection,
+    what: DumpKind,
+    follow: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut stdout = std::io::stdout().lock();
+    if follow {
+        loop {
+            if let Some(value) = dump_value(what, &connection.recv()?) {
+                writeln!(stdout, "{value}")?;
+                stdout.flush()?;
+            }
+        }
+    }
+
+    if what == DumpKind::Status {
+        // The latest of each kind of report over the window
+        let deadline = Instant::now() + STATUS_WINDOW;
+        let mut reports = Map::new();
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            let Some(status) = connection.recv_timeout(remaining)? else {
+                break;
+            };
+            if let Some(Value::Object(report)) = dump_value(what, &status) {
+                reports.extend(report);
+            }
+        }
+        writeln!(stdout, "{}", serde_json::to_string_pretty(&reports)?)?;
+        return Ok(());
+    }
+
+    let deadline = Instant::now() + DUMP_TIMEOUT;
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        let Some(status) = connection.recv_timeout(remaining)? else {
+            break;
+        };
+        if let Some(value) = dump_value(what, &status) {
+            writeln!(stdout, "{}", serde_json::to_string_pretty(&value)?)?;
+            return Ok(());
+        }
+    }
+    Err(format!("the server sent no {what:?} report")
+        .to_lowercase()
+        .into())
+}
+
+/// The JSON for `status` if it is a report of kind `what`.
+fn dump_value(what: DumpKind, status: &ServerStatus) -> Option<Value> {
+    match (what, status) {
+        (
+            DumpKind::Layout,
+            ServerStatus::Layout {
+                positions,
+                heights,
+                gains,
+                table_m,
+                name,
+                available,
+            },
+        ) => {
+            let transducers: Vec<Value> = positions
+                .iter()
+                .zip(heights)
+                .zip(gains)
+                .enumerate()
+                .map(|(channel, ((&(x, y), &z), &gain))| {
+                    json!({ "channel": channel, "x": x, "y": y, "z": z, "gain": gain })
+                })
+                .collect();
+            Some(json!({
+                "name": name,
+                "available": available,
+                "table_m": [table_m.0, table_m.1],
+                "transducers": transducers,
+            }))
+        }
+        (
+            DumpKind::Routing,
+            ServerStatus::MonitorRouting {
+                device_channels,
+                routes,
+            },
+        ) => Some(json!({ "device_channels": device_channels, "routes": routes })),
+        (
+            DumpKind::Voices,
+            ServerStatus::OutputState {
+                timestamp_us,
+                count,
+                voices,
+                ..
+            },
+        ) => Some(json!({
+            "timestamp_us": timestamp_us,
+            "voices": voices[..(*count as usize).min(voices.len())],
+        })),
+        // Levels and output analysis are continuous streams, not reports
+        (
+            DumpKind::Status,
+            ServerStatus::TransducerLevels { .. } | ServerStatus::OutputState { .. },
+        ) => None,
+        // Serialized with the variant name as its key
+        (DumpKind::Status, status) => serde_json::to_value(status).ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CtlOptions, Box<dyn std::error::Error>> {
+        parse_options(args.iter().map(|arg| arg.to_string()), None)
+    }
+
+    #[test]
+    fn commands_parse_with_their_options() {
+        let options = parse(&[
+            "note",
+            "48",
+            "--duration",
+            "0.5",
+            "--type",
+            "tw",
+            "--to",
+            "0.5,0.25",
+            "--socket",
+            "/tmp/test.sock",
+        ])
+        .unwrap();
+        assert_eq!(options.socket_path, "/tmp/test.sock");
+        let Command::Note(note) = options.command else {
+            panic!("expected a note");
+        };
+        assert_eq!(note.note, 48);
+        assert_eq!(note.duration, Duration::from_millis(500));
+        assert_eq!(note.config.stimulus_type, StimulusType::TravellingWave);
+        assert_eq!(note.to, Some((0.5, 0.25)));
+
+        assert_eq!(
+            parse(&["route", "0:31", "1:13"]).unwrap().command,
+            Command::Route(vec![(0, 31), (1, 13)])
+        );
+        assert_eq!(
+            parse(&["dump", "voices", "--follow"]).unwrap().command,
+            Command::Dump {
+                what: DumpKind::Voices,
+                follow: true
+            }
+        );
+        assert_eq!(
+            parse_options(["panic".to_string()], Some("/tmp/env.sock".to_string()))
+                .unwrap()
+                .socket_path,
+            "/tmp/env.sock"
+        );
+
+        assert!(parse(&["route"]).is_err());
+        assert!(parse(&["route", "0-31"]).is_err());
+        assert!(parse(&["dump"]).is_err());
+        assert!(parse(&["panic", "now"]).is_err());
+        assert!(parse(&["note", "--bogus"]).is_err());
+        assert!(parse(&["note", "--velocity"]).is_err());
+    }
+
+    #[test]
+    fn moves_glide_between_positions_and_orbit_around_them() {
+        let mut note = NoteOptions {
+            from: (-0.5, 0.0),
+            to: Some((0.5, 1.0)),
+            ..NoteOptions::default()
+        };
+        assert_eq!(source_at(&note, Duration::ZERO), (-0.5, 0.0));
+        assert_eq!(source_at(&note, Duration::from_secs(1)), (0.0, 0.5));
+        assert_eq!(source_at(&note, Duration::from_secs(5)), (0.5, 1.0));
+
+        note.to = None;
+        note.orbit_period_s = Some(4.0);
+        let (x, y) = source_at(&note, Duration::from_secs(1));
+        assert!((x - -0.5).abs() < 1e-5 && (y - 0.35).abs() < 1e-5);
+    }
+
+    #[test]
+    fn reports_dump_as_json() {
+        let layout = ServerStatus::Layout {
+            positions: vec![(0.25, 0.5)],
+            heights: vec![0.125],
+            gains: vec![0.5],
+            table_m: (1.0, 2.0),
+            name: "desk".to_string(),
+            available: vec!["desk".to_string()],
+        };
+        assert_eq!(
+            dump_value(DumpKind::Layout, &layout).unwrap(),
+            json!({
+                "name": "desk",
+                "available": ["desk"],
+                "table_m": [1.0, 2.0],
+                "transducers": [{ "channel": 0, "x": 0.25, "y": 0.5, "z": 0.125, "gain": 0.5 }],
+            })
+        );
+        assert!(dump_value(DumpKind::Routing, &layout).is_none());
+        assert!(dump_value(DumpKind::Status, &layout)
+            .unwrap()
+            .get("Layout")
+            .is_some());
+    }
+}
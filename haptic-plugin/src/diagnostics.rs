@@ -0,0 +1,67 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Recent-activity snapshot shared with the editor for on-screen diagnostics.
+/// The audio thread writes to it (MIDI events, send failures); the editor
+/// reads it. Connection state and reconnects come from the `Controller`.
+pub struct Diagnostics {
+    pub instance_id: u64,
+    notes_on: AtomicU64,
+    notes_off: AtomicU64,
+    mpe_updates: AtomicU64,
+    /// Commands dropped because the outgoing queue was full (server down or
+    /// slow); a quick signal that notes are not reaching the server.
+    sends_dropped: AtomicU64,
+}
+
+impl Diagnostics {
+    pub fn new(instance_id: u64) -> Self {
+        Self {
+            instance_id,
+            notes_on: AtomicU64::new(0),
+            notes_off: AtomicU64::new(0),
+            mpe_updates: AtomicU64::new(0),
+            sends_dropped: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record(&self, notes_on: u64, notes_off: u64, mpe_updates: u64, dropped: u64) {
+        self.notes_on.fetch_add(notes_on, Ordering::Relaxed);
+        self.notes_off.fetch_add(notes_off, Ordering::Relaxed);
+        self.mpe_updates.fetch_add(mpe_updates, Ordering::Relaxed);
+        self.sends_dropped.fetch_add(dropped, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> DiagnosticSnapshot {
+        DiagnosticSnapshot {
+            notes_on: self.notes_on.load(Ordering::Relaxed),
+            notes_off: self.notes_off.load(Ordering::Relaxed),
+            mpe_updates: self.mpe_updates.load(Ordering::Relaxed),
+            sends_dropped: self.sends_dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct DiagnosticSnapshot {
+    pub notes_on: u64,
+    pub notes_off: u64,
+    pub mpe_updates: u64,
+    pub sends_dropped: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagnostic_snapshot_reads_atomic_counters() {
+        let diagnostics = Diagnostics::new(42);
+        diagnostics.record(2, 1, 7, 3);
+        let snapshot = diagnostics.snapshot();
+        assert_eq!(diagnostics.instance_id, 42);
+        assert_eq!(snapshot.notes_on, 2);
+        assert_eq!(snapshot.notes_off, 1);
+        assert_eq!(snapshot.mpe_updates, 7);
+        assert_eq!(snapshot.sends_dropped, 3);
+    }
+}
@@ -1,5 +1,6 @@
-use crate::ipc_client::{Diagnostics, IpcClient};
+use crate::diagnostics::Diagnostics;
 use crate::{HapticParams, BUILD_HASH};
+use haptic_client::Controller;
 use nih_plug::prelude::nih_log;
 use nih_plug::prelude::*;
 use nih_plug_egui::{create_egui_editor, egui, widgets, EguiState};
@@ -21,7 +22,7 @@ fn param_row<P: Param>(
 
 pub fn create(
     params: Arc<HapticParams>,
-    ipc_client: Arc<IpcClient>,
+    ipc_client: Arc<Controller>,
     diag: Arc<Diagnostics>,
 ) -> Option<Box<dyn Editor>> {
     nih_log!("Creating plugin editor UI");
@@ -70,8 +71,9 @@ pub fn create(
                     haptic_protocol::PROTOCOL_VERSION,
                     diag.instance_id & 0xffff,
                 );
-                if d.connect_generation > 1 {
-                    identity.push_str(&format!(" · reconnects {}", d.connect_generation - 1));
+                let connect_generation = ipc_client.connect_generation();
+                if connect_generation > 1 {
+                    identity.push_str(&format!(" · reconnects {}", connect_generation - 1));
                 }
                 ui.add(
                     egui::Label::new(egui::RichText::new(&identity).monospace().weak()).truncate(),
@@ -104,6 +106,7 @@ pub fn create(
 
                     param_row(ui, "decay knee", &params.atten_d0, setter, 300.0);
                     param_row(ui, "exponent", &params.atten_exponent, setter, 300.0);
+                    param_row(ui, "height", &params.source_height, setter, 300.0);
                 });
 
                 // Incoming-MIDI diagnostics: confirms events are arriving from
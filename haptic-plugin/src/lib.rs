@@ -1,3 +1,4 @@
+use haptic_client::{ClientOptions, Controller};
 use haptic_protocol::{
     DistanceDecay, HapticCommand, InstanceConfig, MpeData, Parameter, SpatialScaleMode,
     StimulusType, TravellingWaveConfig,
@@ -5,10 +6,10 @@ use haptic_protocol::{
 use nih_plug::prelude::*;
 use std::sync::Arc;
 
+mod diagnostics;
 mod editor;
-mod ipc_client;
 
-use ipc_client::{Diagnostics, IpcClient};
+use diagnostics::Diagnostics;
 
 const MIDI_CHANNELS: usize = 16;
 const CC_TIMBRE: u8 = 74; // MPE Y-axis / slide
@@ -52,7 +53,7 @@ pub struct HapticPlugin {
     params: Arc<HapticParams>,
     /// Reconnecting, write-only IPC client. Always present; its manager thread
     /// keeps the connection up in the background.
-    ipc_client: Arc<IpcClient>,
+    ipc_client: Arc<Controller>,
     /// Live diagnostics shared with the editor (incoming MIDI, send failures,
     /// connection generation).
     diag: Arc<Diagnostics>,
@@ -72,6 +73,7 @@ pub struct HapticPlugin {
     last_sent_wavelength: Option<f32>,
     last_sent_atten_d0: Option<f32>,
     last_sent_atten_exponent: Option<f32>,
+    last_sent_source_height: Option<f32>,
 }
 
 /// A process-unique, non-zero instance id (0 is the server's default-instance
@@ -107,6 +109,8 @@ pub struct HapticParams {
     pub atten_d0: FloatParam,
     #[id = "atten_p"]
     pub atten_exponent: FloatParam,
+    #[id = "src_z"]
+    pub source_height: FloatParam,
 }
 
 impl Default for HapticPlugin {
@@ -127,8 +131,17 @@ impl Default for HapticPlugin {
                 d0_m: params.atten_d0.value(),
                 exponent: params.atten_exponent.value(),
             },
+            source_height_m: params.source_height.value(),
         };
-        let ipc_client = Arc::new(IpcClient::spawn(instance_id, initial_config, diag.clone()));
+        let mut options = ClientOptions::new(instance_id);
+        options.config = initial_config;
+        options.capabilities = haptic_protocol::Capabilities::NONE;
+        nih_log!(
+            "IPC client spawned for instance {} on {}",
+            instance_id,
+            options.socket_path.display()
+        );
+        let ipc_client = Arc::new(Controller::spawn(options));
         Self {
             params,
             ipc_client,
@@ -141,6 +154,7 @@ impl Default for HapticPlugin {
             last_sent_wavelength: None,
             last_sent_atten_d0: None,
             last_sent_atten_exponent: None,
+            last_sent_source_height: None,
         }
     }
 }
@@ -196,6 +210,16 @@ impl Default for HapticParams {
                 },
             )
             .with_step_size(0.01),
+            source_height: FloatParam::new(
+                "Source Height",
+                haptic_protocol::DEFAULT_SOURCE_HEIGHT_M,
+                FloatRange::Linear {
+                    min: haptic_protocol::MIN_SOURCE_HEIGHT_M,
+                    max: haptic_protocol::MAX_SOURCE_HEIGHT_M,
+                },
+            )
+            .with_unit(" m")
+            .with_step_size(0.001),
         }
     }
 }
@@ -254,6 +278,7 @@ impl Plugin for HapticPlugin {
         self.last_sent_wavelength = None;
         self.last_sent_atten_d0 = None;
         self.last_sent_atten_exponent = None;
+        self.last_sent_source_height = None;
         true
     }
 
@@ -278,12 +303,14 @@ impl Plugin for HapticPlugin {
         let wavelength = self.params.tw_wavelength.value();
         let atten_d0 = self.params.atten_d0.value();
         let atten_exponent = self.params.atten_exponent.value();
+        let source_height = self.params.source_height.value();
         if self.last_sent_wave_speed != Some(wave_speed)
             || self.last_sent_stimulus_type != Some(stimulus_type)
             || self.last_sent_scale_mode != Some(scale_mode)
             || self.last_sent_wavelength != Some(wavelength)
             || self.last_sent_atten_d0 != Some(atten_d0)
             || self.last_sent_atten_exponent != Some(atten_exponent)
+            || self.last_sent_source_height != Some(source_height)
         {
             client.set_config(InstanceConfig {
                 stimulus_type: stimulus_type.into(),
@@ -297,6 +324,7 @@ impl Plugin for HapticPlugin {
                     d0_m: atten_d0,
                     exponent: atten_exponent,
                 },
+                source_height_m: source_height,
             });
         }
         if self.last_sent_wave_speed != Some(wave_speed)
@@ -359,6 +387,16 @@ impl Plugin for HapticPlugin {
         {
             self.last_sent_atten_exponent = Some(atten_exponent);
         }
+        if self.last_sent_source_height != Some(source_height)
+            && client
+                .send_command(HapticCommand::SetParameter {
+                    timestamp_us: base_timestamp,
+                    parameter: Parameter::SourceHeight(source_height),
+                })
+                .is_ok()
+        {
+            self.last_sent_source_height = Some(source_height);
+        }
 
         // Process MIDI events, merging each into the per-channel MPE cache.
         // Diagnostics are published with relaxed atomics once per block.
@@ -1,12 +1,28 @@
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
-/// Wire-protocol version carried by the mandatory `Hello` handshake.
+/// Newest wire-protocol version this build speaks.
 ///
-/// Bincode encodes enum variants by declaration order, so protocol changes
-/// are coordinated and versioned. A server must reject a client whose version
-/// does not exactly match this value before accepting any other command.
-pub const PROTOCOL_VERSION: u16 = 4;
+/// Bincode encodes an enum variant by its declaration index, so every
+/// variant of `HapticCommand`, `Parameter` and `ServerStatus` has a stable
+/// tag (`wire_tag`) that its position must match: variants are only ever
+/// appended, and a variant's fields never change once released (a new shape
+/// is a new variant). Optional features are negotiated as `Capabilities`.
+/// A client's `Hello` carries the range of versions it speaks; the server
+/// picks the newest both sides support, or rejects the client before
+/// accepting any other command.
+pub const PROTOCOL_VERSION: u16 = 16;
+
+/// Oldest wire-protocol version this build still speaks. Version 16 is the
+/// first with stable tags and a negotiated handshake.
+pub const MIN_PROTOCOL_VERSION: u16 = 16;
+
+/// The newest version in both `client_min..=client_max` and this build's
+/// `MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION`, if the ranges overlap.
+pub fn negotiate_version(client_min: u16, client_max: u16) -> Option<u16> {
+    let version = client_max.min(PROTOCOL_VERSION);
+    (version >= client_min.max(MIN_PROTOCOL_VERSION)).then_some(version)
+}
 
 /// Shared numeric limits used by every producer and the server validator.
 pub const MIDI_CHANNEL_COUNT: u8 = 16;
@@ -22,8 +38,25 @@ pub const DEFAULT_WAVE_SPEED: f32 = 20.0;
 pub const DEFAULT_WAVELENGTH_M: f32 = 0.2;
 pub const DEFAULT_ATTEN_D0_M: f32 = 2.0;
 pub const DEFAULT_ATTEN_EXPONENT: f32 = 1.0;
+/// Source height range in metres, relative to the plane z = 0 that planar
+/// layouts lie in.
+pub const MIN_SOURCE_HEIGHT_M: f32 = -5.0;
+pub const MAX_SOURCE_HEIGHT_M: f32 = 5.0;
+pub const DEFAULT_SOURCE_HEIGHT_M: f32 = 0.0;
 /// MIDI 33 / Ableton A0 is 55 Hz without transposition.
 pub const DEFAULT_TEST_NOTE: u8 = 33;
+/// Most transducers a server can drive. The count itself is fixed when the
+/// server starts; every per-transducer array in a status message holds one
+/// entry per configured transducer and never more than this.
+pub const MAX_TRANSDUCERS: usize = 64;
+/// Most named layouts a server config may declare, and the longest name.
+pub const MAX_LAYOUTS: usize = 16;
+pub const MAX_LAYOUT_NAME_BYTES: usize = 64;
+/// Test-signal frequency range: the haptic band the sweep covers.
+pub const MIN_TEST_SIGNAL_HZ: f32 = 20.0;
+pub const MAX_TEST_SIGNAL_HZ: f32 = 200.0;
+pub const MIN_SWEEP_PERIOD_S: f32 = 1.0;
+pub const MAX_SWEEP_PERIOD_S: f32 = 60.0;
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug)]
 pub struct MpeData {
@@ -142,6 +175,9 @@ pub struct InstanceConfig {
     pub wave_speed: f32,
     pub travelling_wave: TravellingWaveConfig,
     pub distance_decay: DistanceDecay,
+    /// Height of the source above the layout's z = 0 plane, in metres. MPE
+    /// places the source in x and y; this sets the third coordinate.
+    pub source_height_m: f32,
 }
 
 impl Default for InstanceConfig {
@@ -151,6 +187,7 @@ impl Default for InstanceConfig {
             wave_speed: DEFAULT_WAVE_SPEED,
             travelling_wave: TravellingWaveConfig::default(),
             distance_decay: DistanceDecay::default(),
+            source_height_m: DEFAULT_SOURCE_HEIGHT_M,
         }
     }
 }
@@ -167,7 +204,8 @@ pub enum Parameter {
     StimulusType(StimulusType),
     /// Connect physical device output `output` to logical transducer
     /// channel `source` — lets a stereo test device audition any of the
-    /// 32 logical channels. Default routing is identity. Server-global.
+    /// logical channels, one per configured transducer (up to
+    /// `MAX_TRANSDUCERS`). Default routing is identity. Server-global.
     MonitorRoute {
         output: u8,
         source: u8,
@@ -176,6 +214,50 @@ pub enum Parameter {
     TravellingWaveWavelength(f32),
     AttenuationD0(f32),
     AttenuationExponent(f32),
+    /// Source height in metres — patches the sender's instance config. A
+    /// held Wave keeps the height it started with; TW follows live.
+    SourceHeight(f32),
+}
+
+/// Optional protocol features, as a bit set. A client's `Hello` lists the
+/// ones it can use, and `HelloAccepted` returns those the server supports
+/// too. The server sends an observer only the statuses its negotiated set
+/// covers (`ServerStatus::capability`), so a feature added later never
+/// reaches a client that cannot decode it. Bits are stable like wire tags.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Capabilities(pub u32);
+
+impl Capabilities {
+    pub const NONE: Self = Self(0);
+    /// `StartCapture`, `StopCapture` and `CaptureState`.
+    pub const CAPTURE: Self = Self(1 << 0);
+    /// `SetTestSignal` and the `TestSignal` status.
+    pub const TEST_SIGNAL: Self = Self(1 << 1);
+    /// `AudioStream` and `AudioHealth`.
+    pub const AUDIO_STATUS: Self = Self(1 << 2);
+    /// `PerformanceMetrics`.
+    pub const PERFORMANCE_METRICS: Self = Self(1 << 3);
+    /// `ClipCounts`.
+    pub const CLIP_COUNTS: Self = Self(1 << 4);
+    /// `ConfigState`.
+    pub const CONFIG_STATE: Self = Self(1 << 5);
+    /// Every feature this build knows.
+    pub const ALL: Self = Self(
+        Self::CAPTURE.0
+            | Self::TEST_SIGNAL.0
+            | Self::AUDIO_STATUS.0
+            | Self::PERFORMANCE_METRICS.0
+            | Self::CLIP_COUNTS.0
+            | Self::CONFIG_STATE.0,
+    );
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
 }
 
 /// How a connected client relates to the server. Controllers only *send*
@@ -190,6 +272,19 @@ pub enum ClientRole {
     Observer,
 }
 
+/// Lifecycle of the server's audio output stream, as reported by
+/// `ServerStatus::AudioStream`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioStreamState {
+    /// The stream is open and the engine is rendering into it.
+    Running,
+    /// The stream failed or stalled and has been torn down; the engine and
+    /// its voices are kept while the server reopens a device.
+    Lost,
+    /// A reopen attempt failed; the next one follows after a backoff.
+    Retrying,
+}
+
 /// Maximum concurrently-active oscillator references carried in an
 /// `OutputState` status (wave and travelling-wave pools combined).
 pub const MAX_ACTIVE_VOICES: usize = 16;
@@ -220,17 +315,62 @@ pub struct VoiceInfo {
     pub reference_phase: f32,
 }
 
+/// Diagnostic signal mixed onto the logical outputs after the engine, for
+/// physical bring-up. Channels are logical transducer indices, so monitor
+/// routing applies; `level` is a linear peak amplitude in 0..=1.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub enum TestSignal {
+    #[default]
+    Off,
+    /// Steady sine on one channel.
+    Tone {
+        channel: u8,
+        frequency_hz: f32,
+        level: f32,
+    },
+    /// Logarithmic sine sweep from `MIN_TEST_SIGNAL_HZ` to
+    /// `MAX_TEST_SIGNAL_HZ` on one channel, restarting every `period_s`.
+    Sweep {
+        channel: u8,
+        period_s: f32,
+        level: f32,
+    },
+    /// Pink noise on one channel.
+    PinkNoise { channel: u8, level: f32 },
+    /// A positive pulse on `channel`, then the same pulse on `channel + 1`
+    /// a quarter second later, repeating every second. Correctly wired
+    /// neighbours move the same way for both.
+    PolarityPulses { channel: u8, level: f32 },
+}
+
+impl TestSignal {
+    /// Logical channels the signal drives, if any.
+    pub fn channels(&self) -> std::ops::Range<usize> {
+        match *self {
+            TestSignal::Off => 0..0,
+            TestSignal::Tone { channel, .. }
+            | TestSignal::Sweep { channel, .. }
+            | TestSignal::PinkNoise { channel, .. } => channel as usize..channel as usize + 1,
+            TestSignal::PolarityPulses { channel, .. } => channel as usize..channel as usize + 2,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum HapticCommand {
     /// Handshake: registers this connection's instance identity and initial
     /// note-type config. Sent once on connect, before any notes. The server
     /// binds `instance_id` to the connection and stamps every later command
-    /// from it, so notes/MPE need not carry the id themselves.
+    /// from it, so notes/MPE need not carry the id themselves. The client
+    /// speaks every protocol version from `min_protocol_version` to
+    /// `max_protocol_version` and can use `capabilities`.
     Hello {
-        protocol_version: u16,
+        min_protocol_version: u16,
+        max_protocol_version: u16,
         instance_id: u64,
         role: ClientRole,
         config: InstanceConfig,
+        capabilities: Capabilities,
     },
     NoteOn {
         timestamp_us: u64,
@@ -254,10 +394,39 @@ pub enum HapticCommand {
         parameter: Parameter,
     },
     Panic, // Stop all
+    /// Start archiving the final logical channels to `path` on the
+    /// server's filesystem (relative paths resolve against the server's
+    /// working directory). A `.wav` path writes 32-bit float
+    /// WAVE_FORMAT_EXTENSIBLE, anything else raw interleaved f32; a sidecar
+    /// `<path>.json` records the layout and session metadata. Accepted from
+    /// observers only; rejected if either file already exists or while
+    /// another capture is running.
+    StartCapture {
+        path: String,
+    },
+    StopCapture,
+    /// Replace the running test signal; `TestSignal::Off` stops it.
+    /// Accepted from observers only. `Panic` also stops it.
+    SetTestSignal {
+        signal: TestSignal,
+    },
+    /// Switch the engine to another of the layouts named in the server's
+    /// config. Every layout has the same transducer count; an unknown name
+    /// is ignored.
+    SelectLayout {
+        name: String,
+    },
 }
 
-// The fixed OutputState arrays deliberately keep the wire schema bounded and
-// mirrors the allocation-free audio-thread snapshot. Boxing it would add a
+/// Longest accepted `StartCapture` path, in bytes.
+pub const MAX_CAPTURE_PATH_BYTES: usize = 1024;
+
+/// Longest `ConfigState` path or error text, in bytes; the server truncates
+/// longer text so the status always fits one frame.
+pub const MAX_CONFIG_TEXT_BYTES: usize = 1024;
+
+// The fixed OutputState voice array deliberately keeps the wire schema bounded
+// and mirrors the allocation-free audio-thread snapshot. Boxing it would add a
 // per-status allocation and make the schema's ownership less explicit.
 #[allow(clippy::large_enum_variant)]
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -265,32 +434,59 @@ pub enum ServerStatus {
     /// Confirms that the server accepted and registered the connection's
     /// mandatory `Hello`. Clients must not report themselves connected until
     /// this arrives; a successful socket write alone does not mean the server
-    /// accepted the identity or protocol version.
+    /// accepted the identity or protocol version. `protocol_version` is the
+    /// negotiated version and `capabilities` the features both sides support.
     HelloAccepted {
         protocol_version: u16,
         instance_id: u64,
+        capabilities: Capabilities,
     },
     TransducerLevels {
         timestamp_us: u64,
-        levels: [f32; 32],
+        levels: Vec<f32>,
     },
+    /// Engine and audio-thread health, broadcast about twice a second.
+    /// Callback times are fractions of the block's real-time budget over the
+    /// interval since the previous report (1.0 is a callback that took as
+    /// long as the audio it produced); `cpu_percent` is the share of that
+    /// interval spent in callbacks. `command_queue_depth` is the engine
+    /// command ring's fill at the moment of the report. The dropped counts
+    /// are cumulative since the server started: non-critical commands
+    /// refused at the queue's reserve, and measured-output snapshots lost to
+    /// a full ring.
     PerformanceMetrics {
         active_stimuli: u8,
         cpu_percent: u8,
+        callback_p50: f32,
+        callback_p99: f32,
+        callback_max: f32,
+        command_queue_depth: u32,
+        dropped_commands: u64,
+        dropped_snapshots: u64,
+        wave_voices: u8,
+        travelling_wave_voices: u8,
+        instances: u8,
     },
     /// The server's resolved transducer layout, sent to each client on
-    /// connect and rebroadcast on config hot-reload. Distances in metres.
+    /// connect and rebroadcast on config hot-reload or layout selection.
+    /// Distances in metres; `heights` are the transducers' z coordinates,
+    /// all zero for a planar layout. `name` is the active layout and
+    /// `available` every layout the config declares, in name order.
     Layout {
-        positions: [(f32, f32); 32],
-        gains: [f32; 32],
+        positions: Vec<(f32, f32)>,
+        heights: Vec<f32>,
+        gains: Vec<f32>,
         table_m: (f32, f32),
+        name: String,
+        available: Vec<String>,
     },
     /// Physical-output → logical-channel monitor routing currently in
     /// effect, plus how many output channels the audio device has.
-    /// Sent on connect and whenever the routing changes.
+    /// `routes` has one entry per transducer. Sent on connect and whenever
+    /// the routing changes.
     MonitorRouting {
         device_channels: u16,
-        routes: [u8; 32],
+        routes: Vec<u8>,
     },
     /// Hilbert analytic signal measured from the final bounded sum of all
     /// voices on every logical transducer, after device-rate reconstruction
@@ -305,10 +501,202 @@ pub enum ServerStatus {
         sample_index: u64,
         /// False while the Hilbert history is initially filling.
         valid: bool,
-        analytic: [(f32, f32); 32],
+        /// Gain currently applied by the engine's multi-voice headroom
+        /// normaliser; exactly 1.0 when it is disabled or not reducing.
+        headroom_gain: f32,
+        analytic: Vec<(f32, f32)>,
         count: u8,
         voices: [VoiceInfo; MAX_ACTIVE_VOICES],
     },
+    /// Cumulative per-transducer clip telemetry since the engine started,
+    /// rebroadcast whenever any count changes. `render_*` counts internal
+    /// render frames at the final gain-and-clamp stage; `output_*` counts
+    /// device frames at the clamp after polyphase reconstruction. A sample
+    /// is over threshold when its pre-clamp magnitude exceeds
+    /// `warn_threshold`, so every clamped sample is also counted there.
+    ClipCounts {
+        timestamp_us: u64,
+        warn_threshold: f32,
+        render_clamped: Vec<u64>,
+        render_over_threshold: Vec<u64>,
+        output_clamped: Vec<u64>,
+        output_over_threshold: Vec<u64>,
+    },
+    /// Live capture progress, broadcast when a capture starts, about once a
+    /// second while it runs, and when it stops or fails. `frames` counts
+    /// device frames written so far; `dropped_frames` counts frames lost
+    /// because the writer fell behind. `error` is empty unless the capture
+    /// was refused or aborted.
+    CaptureState {
+        active: bool,
+        path: String,
+        frames: u64,
+        dropped_frames: u64,
+        error: String,
+    },
+    /// Audio output stream transitions, sent on each change and to every
+    /// observer on connect. `device`, `sample_rate` and `channels` describe
+    /// the open stream while `Running`, and the last one otherwise.
+    /// `fallback` is set when the configured device could not be opened and
+    /// another is standing in. `attempt` counts reopen attempts since the
+    /// loss and `retry_in_ms` is the backoff before the next; `message`
+    /// carries the error that caused a `Lost` or `Retrying` state.
+    AudioStream {
+        state: AudioStreamState,
+        device: String,
+        fallback: bool,
+        sample_rate: u32,
+        channels: u16,
+        attempt: u32,
+        retry_in_ms: u32,
+        message: String,
+    },
+    /// The test signal currently mixed onto the output, sent on each change
+    /// and to every observer on connect.
+    TestSignal {
+        signal: TestSignal,
+    },
+    /// Audio timing health, sent about every 5 s and to every observer on
+    /// connect. Counts are cumulative since the server started.
+    /// `late_callbacks` counts callbacks whose processing outlasted the
+    /// audio they produced; `gaps` counts intervals between callbacks of
+    /// more than two blocks, where the device most likely ran dry.
+    /// `realtime` is set while every audio thread runs under the requested
+    /// real-time scheduling, and `memory_locked` once the server's memory
+    /// is locked.
+    AudioHealth {
+        callbacks: u64,
+        late_callbacks: u64,
+        gaps: u64,
+        realtime: bool,
+        memory_locked: bool,
+    },
+    /// The configuration the engine is running, sent on each change and to
+    /// every observer on connect. `version` counts accepted reloads and
+    /// layout selections since startup (0 is the startup config) and only
+    /// advances once the engine has applied the new layout, so a `Layout`
+    /// follows the same confirmation. `path` is the config file. `error` is
+    /// empty unless the latest reload or selection was rejected, in which
+    /// case the engine keeps running `version`.
+    ConfigState {
+        version: u64,
+        path: String,
+        error: String,
+    },
+}
+
+impl HapticCommand {
+    /// Stable wire tag: the variant index bincode writes, which the
+    /// declaration order must keep.
+    pub fn wire_tag(&self) -> u32 {
+        match self {
+            HapticCommand::Hello { .. } => 0,
+            HapticCommand::NoteOn { .. } => 1,
+            HapticCommand::NoteOff { .. } => 2,
+            HapticCommand::MpeUpdate { .. } => 3,
+            HapticCommand::SetParameter { .. } => 4,
+            HapticCommand::Panic => 5,
+            HapticCommand::StartCapture { .. } => 6,
+            HapticCommand::StopCapture => 7,
+            HapticCommand::SetTestSignal { .. } => 8,
+            HapticCommand::SelectLayout { .. } => 9,
+        }
+    }
+}
+
+impl Parameter {
+    /// Stable wire tag, as for `HapticCommand::wire_tag`.
+    pub fn wire_tag(&self) -> u32 {
+        match self {
+            Parameter::WaveSpeed(_) => 0,
+            Parameter::StimulusType(_) => 1,
+            Parameter::MonitorRoute { .. } => 2,
+            Parameter::TravellingWaveScaleMode(_) => 3,
+            Parameter::TravellingWaveWavelength(_) => 4,
+            Parameter::AttenuationD0(_) => 5,
+            Parameter::AttenuationExponent(_) => 6,
+            Parameter::SourceHeight(_) => 7,
+        }
+    }
+}
+
+impl ServerStatus {
+    /// Stable wire tag, as for `HapticCommand::wire_tag`.
+    pub fn wire_tag(&self) -> u32 {
+        match self {
+            ServerStatus::HelloAccepted { .. } => 0,
+            ServerStatus::TransducerLevels { .. } => 1,
+            ServerStatus::PerformanceMetrics { .. } => 2,
+            ServerStatus::Layout { .. } => 3,
+            ServerStatus::MonitorRouting { .. } => 4,
+            ServerStatus::OutputState { .. } => 5,
+            ServerStatus::ClipCounts { .. } => 6,
+            ServerStatus::CaptureState { .. } => 7,
+            ServerStatus::AudioStream { .. } => 8,
+            ServerStatus::TestSignal { .. } => 9,
+            ServerStatus::AudioHealth { .. } => 10,
+            ServerStatus::ConfigState { .. } => 11,
+        }
+    }
+
+    /// The capability an observer must have negotiated to receive this
+    /// status; `NONE` for the core statuses every observer gets.
+    pub fn capability(&self) -> Capabilities {
+        match self {
+            ServerStatus::HelloAccepted { .. }
+            | ServerStatus::TransducerLevels { .. }
+            | ServerStatus::Layout { .. }
+            | ServerStatus::MonitorRouting { .. }
+            | ServerStatus::OutputState { .. } => Capabilities::NONE,
+            ServerStatus::PerformanceMetrics { .. } => Capabilities::PERFORMANCE_METRICS,
+            ServerStatus::ClipCounts { .. } => Capabilities::CLIP_COUNTS,
+            ServerStatus::CaptureState { .. } => Capabilities::CAPTURE,
+            ServerStatus::AudioStream { .. } | ServerStatus::AudioHealth { .. } => {
+                Capabilities::AUDIO_STATUS
+            }
+            ServerStatus::TestSignal { .. } => Capabilities::TEST_SIGNAL,
+            ServerStatus::ConfigState { .. } => Capabilities::CONFIG_STATE,
+        }
+    }
+
+    /// Whether every per-transducer array holds at most `MAX_TRANSDUCERS`
+    /// entries. Clients should drop a status that fails this check rather
+    /// than index past their own fixed-capacity state.
+    pub fn is_bounded(&self) -> bool {
+        let within = |len: usize| len <= MAX_TRANSDUCERS;
+        match self {
+            ServerStatus::TransducerLevels { levels, .. } => within(levels.len()),
+            ServerStatus::Layout {
+                positions,
+                heights,
+                gains,
+                available,
+                ..
+            } => {
+                within(positions.len())
+                    && heights.len() == positions.len()
+                    && gains.len() == positions.len()
+                    && available.len() <= MAX_LAYOUTS
+            }
+            ServerStatus::MonitorRouting { routes, .. } => within(routes.len()),
+            ServerStatus::OutputState { analytic, .. } => within(analytic.len()),
+            ServerStatus::ClipCounts {
+                render_clamped,
+                render_over_threshold,
+                output_clamped,
+                output_over_threshold,
+                ..
+            } => [
+                render_clamped.len(),
+                render_over_threshold.len(),
+                output_clamped.len(),
+                output_over_threshold.len(),
+            ]
+            .into_iter()
+            .all(within),
+            _ => true,
+        }
+    }
 }
 
 pub const SOCKET_PATH: &str = "/tmp/haptic-vst.sock";
@@ -359,40 +747,60 @@ pub fn encode_frame<T: Serialize>(msg: &T, out: &mut Vec<u8>) -> Result<(), binc
 }
 
 /// Accumulates raw stream bytes and yields complete deserialized frames.
+/// Decoded frames advance a read cursor; consumed bytes are reclaimed only
+/// when the buffer empties or must make room, not once per frame.
 #[derive(Default)]
 pub struct FrameDecoder {
     buf: Vec<u8>,
+    /// Start of the first undecoded byte in `buf`.
+    start: usize,
 }
 
 impl FrameDecoder {
     pub fn new() -> Self {
         Self {
             buf: Vec::with_capacity(MAX_FRAME_SIZE),
+            start: 0,
         }
     }
 
     /// Append raw bytes read from the stream.
     pub fn extend(&mut self, data: &[u8]) {
+        if self.start > 0 && self.buf.len() + data.len() > self.buf.capacity() {
+            self.buf.copy_within(self.start.., 0);
+            self.buf.truncate(self.buf.len() - self.start);
+            self.start = 0;
+        }
         self.buf.extend_from_slice(data);
     }
 
+    /// Bytes received but not yet decoded, including any partial frame.
+    pub fn buffered(&self) -> usize {
+        self.buf.len() - self.start
+    }
+
     /// Try to decode the next complete frame. Returns Ok(None) when more
     /// bytes are needed. On FrameError::Deserialize the offending frame is
     /// consumed and subsequent calls continue with the next frame; on
     /// FrameError::Oversized the stream should be abandoned.
     pub fn next_frame<T: DeserializeOwned>(&mut self) -> Result<Option<T>, FrameError> {
-        if self.buf.len() < 4 {
+        let pending = &self.buf[self.start..];
+        if pending.len() < 4 {
             return Ok(None);
         }
-        let len = u32::from_le_bytes([self.buf[0], self.buf[1], self.buf[2], self.buf[3]]) as usize;
+        let len = u32::from_le_bytes([pending[0], pending[1], pending[2], pending[3]]) as usize;
         if len > MAX_FRAME_SIZE {
             return Err(FrameError::Oversized(len));
         }
-        if self.buf.len() < 4 + len {
+        if pending.len() < 4 + len {
             return Ok(None);
         }
-        let result = bincode::deserialize(&self.buf[4..4 + len]);
-        self.buf.drain(..4 + len);
+        let result = bincode::deserialize(&pending[4..4 + len]);
+        self.start += 4 + len;
+        if self.start == self.buf.len() {
+            self.buf.clear();
+            self.start = 0;
+        }
         match result {
             Ok(msg) => Ok(Some(msg)),
             Err(e) => Err(FrameError::Deserialize(e)),
@@ -466,6 +874,26 @@ mod tests {
         assert_note(&dec.next_frame::<HapticCommand>().unwrap().unwrap(), 72);
     }
 
+    #[test]
+    fn partial_frame_survives_compaction() {
+        let mut frame = Vec::new();
+        encode_frame(&note_on(50), &mut frame).unwrap();
+        let (head, tail) = frame.split_at(frame.len() / 2);
+
+        // Half a frame is always pending, so the buffer never empties and
+        // only compaction can reclaim the decoded prefix.
+        let mut dec = FrameDecoder::new();
+        dec.extend(head);
+        for _ in 0..1000 {
+            dec.extend(tail);
+            dec.extend(head);
+            assert_note(&dec.next_frame::<HapticCommand>().unwrap().unwrap(), 50);
+            assert!(dec.next_frame::<HapticCommand>().unwrap().is_none());
+            assert_eq!(dec.buffered(), head.len());
+        }
+        assert!(dec.buf.capacity() <= MAX_FRAME_SIZE);
+    }
+
     #[test]
     fn oversized_frame_rejected() {
         let mut dec = FrameDecoder::new();
@@ -498,7 +926,8 @@ mod tests {
     #[test]
     fn hello_roundtrips() {
         let hello = HapticCommand::Hello {
-            protocol_version: PROTOCOL_VERSION,
+            min_protocol_version: MIN_PROTOCOL_VERSION,
+            max_protocol_version: PROTOCOL_VERSION,
             instance_id: 0xDEAD_BEEF_1234_5678,
             role: ClientRole::Observer,
             config: InstanceConfig {
@@ -513,7 +942,9 @@ mod tests {
                     d0_m: 0.75,
                     exponent: 1.5,
                 },
+                source_height_m: -0.4,
             },
+            capabilities: Capabilities::CAPTURE,
         };
         let mut buf = Vec::new();
         encode_frame(&hello, &mut buf).unwrap();
@@ -521,12 +952,16 @@ mod tests {
         dec.extend(&buf);
         match dec.next_frame::<HapticCommand>().unwrap().unwrap() {
             HapticCommand::Hello {
-                protocol_version,
+                min_protocol_version,
+                max_protocol_version,
                 instance_id,
                 role,
                 config,
+                capabilities,
             } => {
-                assert_eq!(protocol_version, PROTOCOL_VERSION);
+                assert_eq!(min_protocol_version, MIN_PROTOCOL_VERSION);
+                assert_eq!(max_protocol_version, PROTOCOL_VERSION);
+                assert_eq!(capabilities, Capabilities::CAPTURE);
                 assert_eq!(instance_id, 0xDEAD_BEEF_1234_5678);
                 assert_eq!(role, ClientRole::Observer);
                 assert_eq!(config.stimulus_type, StimulusType::TravellingWave);
@@ -537,11 +972,192 @@ mod tests {
                 );
                 assert_eq!(config.travelling_wave.wavelength_m, 0.125);
                 assert_eq!(config.distance_decay.d0_m, 0.75);
+                assert_eq!(config.source_height_m, -0.4);
             }
             other => panic!("unexpected: {other:?}"),
         }
     }
 
+    /// The tag bincode actually writes for `value`.
+    fn encoded_tag<T: Serialize>(value: &T) -> u32 {
+        let bytes = bincode::serialize(value).unwrap();
+        u32::from_le_bytes(bytes[..4].try_into().unwrap())
+    }
+
+    #[test]
+    fn wire_tags_match_the_encoding_and_are_contiguous() {
+        let commands = [
+            HapticCommand::Hello {
+                min_protocol_version: MIN_PROTOCOL_VERSION,
+                max_protocol_version: PROTOCOL_VERSION,
+                instance_id: 1,
+                role: ClientRole::Controller,
+                config: InstanceConfig::default(),
+                capabilities: Capabilities::ALL,
+            },
+            note_on(60),
+            HapticCommand::NoteOff {
+                timestamp_us: 0,
+                note: 60,
+                channel: 0,
+            },
+            HapticCommand::MpeUpdate {
+                timestamp_us: 0,
+                channel: 0,
+                mpe: MpeData::default(),
+            },
+            HapticCommand::SetParameter {
+                timestamp_us: 0,
+                parameter: Parameter::WaveSpeed(1.0),
+            },
+            HapticCommand::Panic,
+            HapticCommand::StartCapture { path: "a".into() },
+            HapticCommand::StopCapture,
+            HapticCommand::SetTestSignal {
+                signal: TestSignal::Off,
+            },
+            HapticCommand::SelectLayout { name: "a".into() },
+        ];
+        for (tag, command) in commands.iter().enumerate() {
+            assert_eq!(command.wire_tag(), tag as u32, "{command:?}");
+            assert_eq!(encoded_tag(command), tag as u32, "{command:?}");
+        }
+
+        let parameters = [
+            Parameter::WaveSpeed(1.0),
+            Parameter::StimulusType(StimulusType::Wave),
+            Parameter::MonitorRoute {
+                output: 0,
+                source: 0,
+            },
+            Parameter::TravellingWaveScaleMode(SpatialScaleMode::Speed),
+            Parameter::TravellingWaveWavelength(0.2),
+            Parameter::AttenuationD0(1.0),
+            Parameter::AttenuationExponent(1.0),
+            Parameter::SourceHeight(0.0),
+        ];
+        for (tag, parameter) in parameters.iter().enumerate() {
+            assert_eq!(parameter.wire_tag(), tag as u32, "{parameter:?}");
+            assert_eq!(encoded_tag(parameter), tag as u32, "{parameter:?}");
+        }
+
+        let statuses = [
+            ServerStatus::HelloAccepted {
+                protocol_version: PROTOCOL_VERSION,
+                instance_id: 1,
+                capabilities: Capabilities::ALL,
+            },
+            ServerStatus::TransducerLevels {
+                timestamp_us: 0,
+                levels: vec![],
+            },
+            ServerStatus::PerformanceMetrics {
+                active_stimuli: 0,
+                cpu_percent: 0,
+                callback_p50: 0.0,
+                callback_p99: 0.0,
+                callback_max: 0.0,
+                command_queue_depth: 0,
+                dropped_commands: 0,
+                dropped_snapshots: 0,
+                wave_voices: 0,
+                travelling_wave_voices: 0,
+                instances: 0,
+            },
+            ServerStatus::Layout {
+                positions: vec![],
+                heights: vec![],
+                gains: vec![],
+                table_m: (1.0, 2.0),
+                name: String::new(),
+                available: vec![],
+            },
+            ServerStatus::MonitorRouting {
+                device_channels: 2,
+                routes: vec![],
+            },
+            ServerStatus::OutputState {
+                timestamp_us: 0,
+                device_sample_rate: 48_000.0,
+                sample_index: 0,
+                valid: false,
+                headroom_gain: 1.0,
+                analytic: vec![],
+                count: 0,
+                voices: [VoiceInfo::default(); MAX_ACTIVE_VOICES],
+            },
+            ServerStatus::ClipCounts {
+                timestamp_us: 0,
+                warn_threshold: 0.9,
+                render_clamped: vec![],
+                render_over_threshold: vec![],
+                output_clamped: vec![],
+                output_over_threshold: vec![],
+            },
+            ServerStatus::CaptureState {
+                active: false,
+                path: String::new(),
+                frames: 0,
+                dropped_frames: 0,
+                error: String::new(),
+            },
+            ServerStatus::AudioStream {
+                state: AudioStreamState::Running,
+                device: String::new(),
+                fallback: false,
+                sample_rate: 48_000,
+                channels: 2,
+                attempt: 0,
+                retry_in_ms: 0,
+                message: String::new(),
+            },
+            ServerStatus::TestSignal {
+                signal: TestSignal::Off,
+            },
+            ServerStatus::AudioHealth {
+                callbacks: 0,
+                late_callbacks: 0,
+                gaps: 0,
+                realtime: false,
+                memory_locked: false,
+            },
+            ServerStatus::ConfigState {
+                version: 0,
+                path: String::new(),
+                error: String::new(),
+            },
+        ];
+        for (tag, status) in statuses.iter().enumerate() {
+            assert_eq!(status.wire_tag(), tag as u32, "{status:?}");
+            assert_eq!(encoded_tag(status), tag as u32, "{status:?}");
+            assert!(Capabilities::ALL.contains(status.capability()));
+        }
+    }
+
+    #[test]
+    fn newest_shared_version_is_negotiated() {
+        assert_eq!(
+            negotiate_version(MIN_PROTOCOL_VERSION, u16::MAX),
+            Some(PROTOCOL_VERSION)
+        );
+        assert_eq!(
+            negotiate_version(0, MIN_PROTOCOL_VERSION),
+            Some(MIN_PROTOCOL_VERSION)
+        );
+        assert_eq!(negotiate_version(0, MIN_PROTOCOL_VERSION - 1), None);
+        assert_eq!(negotiate_version(PROTOCOL_VERSION + 1, u16::MAX), None);
+        assert_eq!(
+            negotiate_version(PROTOCOL_VERSION, PROTOCOL_VERSION - 1),
+            None
+        );
+
+        let observer = Capabilities(Capabilities::CAPTURE.0 | 1 << 31);
+        let negotiated = observer.intersection(Capabilities::ALL);
+        assert_eq!(negotiated, Capabilities::CAPTURE);
+        assert!(negotiated.contains(Capabilities::NONE));
+        assert!(!negotiated.contains(Capabilities::CONFIG_STATE));
+    }
+
     #[test]
     fn output_state_roundtrips_within_frame_budget() {
         let mut voices = [VoiceInfo::default(); MAX_ACTIVE_VOICES];
@@ -558,13 +1174,15 @@ mod tests {
             device_sample_rate: 48_000.0,
             sample_index: 1234,
             valid: true,
-            analytic: [(0.25, -0.5); 32],
+            headroom_gain: 0.5,
+            analytic: vec![(0.25, -0.5); MAX_TRANSDUCERS],
             count: MAX_ACTIVE_VOICES as u8,
             voices,
         };
         let mut buf = Vec::new();
         encode_frame(&status, &mut buf).unwrap();
-        // A full OutputState frame must fit the framing budget.
+        // A full OutputState frame at the largest transducer count must fit
+        // the framing budget.
         assert!(
             buf.len() <= MAX_FRAME_SIZE,
             "frame {} > max {}",
@@ -589,6 +1207,76 @@ mod tests {
         }
     }
 
+    #[test]
+    fn per_transducer_arrays_are_bounded() {
+        let clips = |len: usize| ServerStatus::ClipCounts {
+            timestamp_us: 1,
+            warn_threshold: 0.9,
+            render_clamped: vec![u64::MAX; len],
+            render_over_threshold: vec![u64::MAX; len],
+            output_clamped: vec![u64::MAX; len],
+            output_over_threshold: vec![u64::MAX; len],
+        };
+        let mut buf = Vec::new();
+        encode_frame(&clips(MAX_TRANSDUCERS), &mut buf).unwrap();
+        assert!(buf.len() <= MAX_FRAME_SIZE);
+        assert!(clips(MAX_TRANSDUCERS).is_bounded());
+        assert!(clips(12).is_bounded());
+        assert!(!clips(MAX_TRANSDUCERS + 1).is_bounded());
+
+        let mut dec = FrameDecoder::new();
+        dec.extend(&buf);
+        match dec.next_frame::<ServerStatus>().unwrap().unwrap() {
+            ServerStatus::ClipCounts { output_clamped, .. } => {
+                assert_eq!(output_clamped.len(), MAX_TRANSDUCERS)
+            }
+            other => panic!("unexpected: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn largest_layout_fits_one_frame() {
+        let layout = |layouts: usize| ServerStatus::Layout {
+            positions: vec![(1.0, 2.0); MAX_TRANSDUCERS],
+            heights: vec![0.25; MAX_TRANSDUCERS],
+            gains: vec![0.5; MAX_TRANSDUCERS],
+            table_m: (1.0, 2.0),
+            name: "n".repeat(MAX_LAYOUT_NAME_BYTES),
+            available: vec!["n".repeat(MAX_LAYOUT_NAME_BYTES); layouts],
+        };
+        let mut buf = Vec::new();
+        encode_frame(&layout(MAX_LAYOUTS), &mut buf).unwrap();
+        assert!(buf.len() <= MAX_FRAME_SIZE, "frame {}", buf.len());
+        assert!(layout(MAX_LAYOUTS).is_bounded());
+        assert!(!layout(MAX_LAYOUTS + 1).is_bounded());
+    }
+
+    #[test]
+    fn longest_config_state_fits_one_frame() {
+        let status = ServerStatus::ConfigState {
+            version: u64::MAX,
+            path: "p".repeat(MAX_CONFIG_TEXT_BYTES),
+            error: "e".repeat(MAX_CONFIG_TEXT_BYTES),
+        };
+        let mut buf = Vec::new();
+        encode_frame(&status, &mut buf).unwrap();
+        assert!(buf.len() <= MAX_FRAME_SIZE, "frame {}", buf.len());
+        let mut decoder = FrameDecoder::new();
+        decoder.extend(&buf);
+        match decoder.next_frame::<ServerStatus>().unwrap() {
+            Some(ServerStatus::ConfigState {
+                version,
+                path,
+                error,
+            }) => {
+                assert_eq!(version, u64::MAX);
+                assert_eq!(path.len(), MAX_CONFIG_TEXT_BYTES);
+                assert_eq!(error.len(), MAX_CONFIG_TEXT_BYTES);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
     #[test]
     fn default_distance_decay_uses_two_metre_knee() {
         let decay = DistanceDecay::default();
@@ -1,38 +1,125 @@
-use crate::engine::{StimulusEngine, TRANSDUCER_COUNT};
+use crate::config::OutputSplit;
+use crate::engine::{StimulusEngine, MAX_TRANSDUCERS};
+use crate::realtime::{self, Promoter, RealtimeSettings, ThreadSlot};
+use crate::split::{self, DriftMonitor, SplitRouter, SplitSink};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{SampleFormat, SampleRate, SupportedStreamConfig, SupportedStreamConfigRange};
+use haptic_protocol::{AudioStreamState, ServerStatus};
 use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU64, Ordering};
-use std::sync::Arc;
-use std::time::Instant;
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
 
 /// Lock-free callback statistics, written by the audio thread and read by
 /// the monitor thread. Durations land in log2(ns) histogram buckets so
 /// percentiles can be estimated without allocation on the audio side.
+///
+/// Xruns are inferred from timing, since the backend does not report them:
+/// a callback is late when its processing outlasts the audio it produced,
+/// and a gap is an interval between callback starts of more than two
+/// blocks, where the device has most likely run dry.
 pub struct AudioStats {
     callbacks: AtomicU64,
     frames: AtomicU64,
     max_ns: AtomicU64,
     stream_errors: AtomicU64,
     hist: [AtomicU64; 64],
+    epoch: Instant,
+    /// Start of the previous callback in ns after `epoch`, plus one; zero
+    /// until the first callback of a stream.
+    last_start_ns: AtomicU64,
+    late_callbacks: AtomicU64,
+    gaps: AtomicU64,
+    /// Total time spent in callbacks.
+    busy_ns: AtomicU64,
+    /// Longest callback since the last `take_window`.
+    window_max_ns: AtomicU64,
+    /// Real-time budget of the most recent block.
+    block_ns: AtomicU64,
+}
+
+/// Histogram and busy time at the end of the previous load window.
+pub struct LoadWindow {
+    hist: [u64; 64],
+    busy_ns: u64,
+    at: Instant,
+}
+
+impl Default for LoadWindow {
+    fn default() -> Self {
+        Self {
+            hist: [0; 64],
+            busy_ns: 0,
+            at: Instant::now(),
+        }
+    }
+}
+
+/// Callback load over one window. Times are fractions of the block budget;
+/// percentiles are the upper edge of their histogram bucket.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CallbackLoad {
+    pub p50: f32,
+    pub p99: f32,
+    pub max: f32,
+    /// Share of the window's wall time spent in callbacks.
+    pub busy: f32,
 }
 
 impl AudioStats {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             callbacks: AtomicU64::new(0),
             frames: AtomicU64::new(0),
             max_ns: AtomicU64::new(0),
             stream_errors: AtomicU64::new(0),
             hist: std::array::from_fn(|_| AtomicU64::new(0)),
+            epoch: Instant::now(),
+            last_start_ns: AtomicU64::new(0),
+            late_callbacks: AtomicU64::new(0),
+            gaps: AtomicU64::new(0),
+            busy_ns: AtomicU64::new(0),
+            window_max_ns: AtomicU64::new(0),
+            block_ns: AtomicU64::new(0),
         }
     }
 
-    fn record(&self, elapsed_ns: u64, frames: u64) {
+    /// Record a callback that started at `start`, took `elapsed_ns`, and
+    /// produced `frames` at `sample_rate`. MUST NOT block or allocate.
+    fn record(&self, start: Instant, elapsed_ns: u64, frames: u64, sample_rate: f32) {
         self.callbacks.fetch_add(1, Ordering::Relaxed);
         self.frames.fetch_add(frames, Ordering::Relaxed);
         self.max_ns.fetch_max(elapsed_ns, Ordering::Relaxed);
+        self.window_max_ns.fetch_max(elapsed_ns, Ordering::Relaxed);
+        self.busy_ns.fetch_add(elapsed_ns, Ordering::Relaxed);
         let bucket = (64 - elapsed_ns.leading_zeros() as usize).min(63);
         self.hist[bucket].fetch_add(1, Ordering::Relaxed);
+
+        let block_ns = (frames as f64 * 1.0e9 / sample_rate as f64) as u64;
+        self.block_ns.store(block_ns, Ordering::Relaxed);
+        if elapsed_ns > block_ns {
+            self.late_callbacks.fetch_add(1, Ordering::Relaxed);
+        }
+        let start_ns = start.saturating_duration_since(self.epoch).as_nanos() as u64 + 1;
+        let previous = self.last_start_ns.swap(start_ns, Ordering::Relaxed);
+        if previous != 0 && start_ns.saturating_sub(previous) > 2 * block_ns {
+            self.gaps.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Forget the previous callback, so the outage before a reopened stream
+    /// is not counted as a gap.
+    fn restart(&self) {
+        self.last_start_ns.store(0, Ordering::Relaxed);
+    }
+
+    fn health(&self, realtime: bool, memory_locked: bool) -> ServerStatus {
+        ServerStatus::AudioHealth {
+            callbacks: self.callbacks.load(Ordering::Relaxed),
+            late_callbacks: self.late_callbacks.load(Ordering::Relaxed),
+            gaps: self.gaps.load(Ordering::Relaxed),
+            realtime,
+            memory_locked,
+        }
     }
 
     /// Estimate the given percentile (0..1) from the histogram; the upper
@@ -43,19 +130,41 @@ impl AudioStats {
             .iter()
             .map(|b| b.load(Ordering::Relaxed))
             .collect();
-        let total: u64 = counts.iter().sum();
-        if total == 0 {
-            return 0;
-        }
-        let target = ((total as f64) * p).ceil() as u64;
-        let mut seen = 0;
-        for (bucket, &count) in counts.iter().enumerate() {
-            seen += count;
-            if seen >= target {
-                return 1u64 << bucket;
-            }
+        histogram_percentile(&counts, p)
+    }
+
+    /// Callback load since `window` was last taken, which then starts the
+    /// next window. Only one reader should take windows, since the window
+    /// maximum is reset here.
+    pub fn take_window(&self, window: &mut LoadWindow) -> CallbackLoad {
+        let hist: [u64; 64] = std::array::from_fn(|i| self.hist[i].load(Ordering::Relaxed));
+        let delta: Vec<u64> = hist
+            .iter()
+            .zip(window.hist.iter())
+            .map(|(now, then)| now - then)
+            .collect();
+        let busy_ns = self.busy_ns.load(Ordering::Relaxed);
+        let max_ns = self.window_max_ns.swap(0, Ordering::Relaxed);
+        let now = Instant::now();
+        let wall_ns = now.duration_since(window.at).as_nanos().max(1) as f64;
+        let busy = (busy_ns - window.busy_ns) as f64 / wall_ns;
+        *window = LoadWindow {
+            hist,
+            busy_ns,
+            at: now,
+        };
+
+        let block_ns = self.block_ns.load(Ordering::Relaxed);
+        if block_ns == 0 {
+            return CallbackLoad::default();
+        }
+        let fraction = |ns: u64| (ns as f64 / block_ns as f64) as f32;
+        CallbackLoad {
+            p50: fraction(histogram_percentile(&delta, 0.50)),
+            p99: fraction(histogram_percentile(&delta, 0.99)),
+            max: fraction(max_ns),
+            busy: busy as f32,
         }
-        u64::MAX
     }
 
     fn report(&self, since_last: &mut (u64, u64)) -> String {
@@ -64,7 +173,7 @@ impl AudioStats {
         let (prev_callbacks, prev_frames) = *since_last;
         *since_last = (callbacks, frames);
         format!(
-            "audio: {} callbacks (+{}), {} frames (+{}), cb time p50={}us p99={}us max={}us, stream errors={}",
+            "audio: {} callbacks (+{}), {} frames (+{}), cb time p50={}us p99={}us max={}us, late callbacks={}, gaps={}, stream errors={}",
             callbacks,
             callbacks - prev_callbacks,
             frames,
@@ -72,14 +181,34 @@ impl AudioStats {
             self.percentile_ns(0.50) / 1000,
             self.percentile_ns(0.99) / 1000,
             self.max_ns.load(Ordering::Relaxed) / 1000,
+            self.late_callbacks.load(Ordering::Relaxed),
+            self.gaps.load(Ordering::Relaxed),
             self.stream_errors.load(Ordering::Relaxed),
         )
     }
 }
 
+/// Percentile (0..1) of log2(ns) bucket counts, as the bucket's upper edge.
+fn histogram_percentile(counts: &[u64], p: f64) -> u64 {
+    let total: u64 = counts.iter().sum();
+    if total == 0 {
+        return 0;
+    }
+    let target = ((total as f64) * p).ceil() as u64;
+    let mut seen = 0;
+    for (bucket, &count) in counts.iter().enumerate() {
+        seen += count;
+        if seen >= target {
+            return 1u64 << bucket;
+        }
+    }
+    u64::MAX
+}
+
 /// Channel-cycling test pattern for interface bring-up: a short sine burst
 /// walks across all output channels so each transducer can be identified.
 struct TestTone {
+    transducers: usize,
     phase: f32,
     frames_into_burst: u32,
     current_channel: usize,
@@ -91,24 +220,22 @@ const TEST_TONE_BURST_SECS: f32 = 0.5;
 const TEST_TONE_LEVEL: f32 = 0.5;
 const PREFERRED_SAMPLE_RATE: u32 = 48_000;
 
-/// Pick the supported f32 configuration closest to 48 kHz. For a device with
-/// 32+ channel support, retain that requirement; for a fallback device, retain
-/// its default channel count rather than unexpectedly opening a different
+/// Pick the supported f32 configuration closest to 48 kHz. When a channel
+/// count is required (the transducer count for a multichannel device, or a split device's
+/// range), retain that requirement; for a fallback device, retain its
+/// default channel count rather than unexpectedly opening a different
 /// speaker layout.
 fn preferred_output_config(
     ranges: impl IntoIterator<Item = SupportedStreamConfigRange>,
     default_channels: u16,
-    require_multichannel: bool,
+    required_channels: Option<u16>,
 ) -> Option<SupportedStreamConfig> {
     ranges
         .into_iter()
         .filter(|range| range.sample_format() == SampleFormat::F32)
-        .filter(|range| {
-            if require_multichannel {
-                range.channels() >= TRANSDUCER_COUNT as u16
-            } else {
-                range.channels() == default_channels
-            }
+        .filter(|range| match required_channels {
+            Some(required) => range.channels() >= required,
+            None => range.channels() == default_channels,
         })
         .map(|range| {
             let rate =
@@ -122,8 +249,9 @@ fn preferred_output_config(
 }
 
 impl TestTone {
-    fn new() -> Self {
+    fn new(transducers: usize) -> Self {
         Self {
+            transducers,
             phase: 0.0,
             frames_into_burst: 0,
             current_channel: 0,
@@ -132,7 +260,7 @@ impl TestTone {
 
     fn process_block(&mut self, data: &mut [f32], channels: usize, sample_rate: f32) {
         let burst_frames = (TEST_TONE_BURST_SECS * sample_rate) as u32;
-        let active_channels = channels.min(TRANSDUCER_COUNT);
+        let active_channels = channels.min(self.transducers);
         for frame in data.chunks_exact_mut(channels) {
             frame.fill(0.0);
             // Short fade at the burst edges to avoid clicks
@@ -155,199 +283,876 @@ impl TestTone {
     }
 }
 
-pub fn run_audio_loop(
+/// Resolve a requested output device name against the available names. An
+/// exact match wins; otherwise a unique case-insensitive substring match is
+/// accepted, so `--device motu` finds "MOTU 24Ao". A missing or ambiguous
+/// name is an error listing what is available.
+fn match_device_name(names: &[String], wanted: &str) -> Result<usize, String> {
+    if let Some(index) = names.iter().position(|name| name == wanted) {
+        return Ok(index);
+    }
+    let needle = wanted.to_lowercase();
+    let matches: Vec<usize> = names
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| name.to_lowercase().contains(&needle))
+        .map(|(index, _)| index)
+        .collect();
+    match matches.as_slice() {
+        [index] => Ok(*index),
+        [] => Err(format!(
+            "output device \"{wanted}\" not found; available: {} (see --list-devices)",
+            if names.is_empty() {
+                "none".to_string()
+            } else {
+                names.join(", ")
+            }
+        )),
+        several => Err(format!(
+            "output device \"{wanted}\" is ambiguous; it matches {}",
+            several
+                .iter()
+                .map(|&index| names[index].as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Which device a stream open should try. `Preferred` is the named device,
+/// or the first exposing 32+ channels when no name is given; `Fallback` is
+/// the system default, used when the preferred device cannot be opened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DeviceChoice {
+    Preferred,
+    Fallback,
+}
+
+fn select_output_device(
+    host: &cpal::Host,
+    wanted: Option<&str>,
+    choice: DeviceChoice,
+    transducers: usize,
+) -> Result<cpal::Device, Box<dyn std::error::Error>> {
+    let not_found = |message: String| std::io::Error::new(std::io::ErrorKind::NotFound, message);
+    if choice == DeviceChoice::Fallback {
+        return host
+            .default_output_device()
+            .ok_or_else(|| not_found("no output device available".to_string()).into());
+    }
+    let Some(wanted) = wanted else {
+        return host
+            .output_devices()?
+            .find(|d| {
+                if let Ok(mut configs) = d.supported_output_configs() {
+                    configs.any(|c| c.channels() as usize >= transducers)
+                } else {
+                    false
+                }
+            })
+            .ok_or_else(|| {
+                not_found(format!("no {transducers}-channel output device found")).into()
+            });
+    };
+
+    let mut devices: Vec<cpal::Device> = host.output_devices()?.collect();
+    let names: Vec<String> = devices
+        .iter()
+        .map(|d| d.name().unwrap_or_else(|_| "Unknown".to_string()))
+        .collect();
+    let index = match_device_name(&names, wanted).map_err(not_found)?;
+    Ok(devices.swap_remove(index))
+}
+
+/// Print every output device with its channel and f32 sample-rate support,
+/// for choosing a `--device` name. The system default is marked with `*`.
+pub fn list_output_devices() -> Result<(), Box<dyn std::error::Error>> {
+    let host = cpal::default_host();
+    let default_name = host.default_output_device().and_then(|d| d.name().ok());
+    println!("Output devices ({}):", host.id().name());
+    for device in host.output_devices()? {
+        let name = device.name().unwrap_or_else(|_| "Unknown".to_string());
+        let marker = if Some(&name) == default_name.as_ref() {
+            '*'
+        } else {
+            ' '
+        };
+        let ranges: Vec<SupportedStreamConfigRange> = device
+            .supported_output_configs()
+            .map(|configs| {
+                configs
+                    .filter(|range| range.sample_format() == SampleFormat::F32)
+                    .collect()
+            })
+            .unwrap_or_default();
+        match (
+            ranges.iter().map(|range| range.channels()).max(),
+            ranges.iter().map(|range| range.min_sample_rate().0).min(),
+            ranges.iter().map(|range| range.max_sample_rate().0).max(),
+        ) {
+            (Some(channels), Some(min_rate), Some(max_rate)) => println!(
+                "{marker} {name}  (up to {channels} channels, {min_rate}-{max_rate} Hz f32)"
+            ),
+            _ => println!("{marker} {name}  (no f32 output configurations)"),
+        }
+    }
+    Ok(())
+}
+
+/// A stream that delivers no callbacks for this long is treated as dead even
+/// if the backend never reports an error, as some drivers go silent when an
+/// interface is unplugged mid-period.
+const STREAM_STALL_TIMEOUT: Duration = Duration::from_secs(2);
+/// Reopen backoff: doubles from the first delay up to the cap.
+const REOPEN_BACKOFF_FIRST: Duration = Duration::from_millis(500);
+const REOPEN_BACKOFF_MAX: Duration = Duration::from_secs(10);
+/// While a fallback device stands in, check this often whether the
+/// preferred one has come back.
+const PREFERRED_PROBE_INTERVAL: Duration = Duration::from_secs(10);
+/// How long a dropped stream may take to hand the callback state back.
+const CALLBACK_RELEASE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Delay before reopen attempt `attempt + 1`, counting from 1.
+fn reopen_delay(attempt: u32) -> Duration {
+    REOPEN_BACKOFF_FIRST
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(REOPEN_BACKOFF_MAX)
+}
+
+/// Everything the output callback owns. It moves into each stream's
+/// callback and comes back when that stream is dropped, so the engine, its
+/// voices, and the test-tone position survive a device reopen.
+struct CallbackState {
     engine: StimulusEngine,
-    running: Arc<AtomicBool>,
+    tone: TestTone,
+    test_tone: bool,
+    levels_producer: rtrb::Producer<[f32; MAX_TRANSDUCERS]>,
+    /// Set while a multi-device split runs: the block is rendered at the
+    /// transducer count and this callback plays only the primary's range.
+    split: Option<SplitRouter>,
+}
+
+impl CallbackState {
+    fn new(
+        engine: StimulusEngine,
+        test_tone: bool,
+        levels_producer: rtrb::Producer<[f32; MAX_TRANSDUCERS]>,
+    ) -> Self {
+        Self {
+            tone: TestTone::new(engine.transducer_count()),
+            engine,
+            test_tone,
+            levels_producer,
+            split: None,
+        }
+    }
+
+    /// Fill one device block and publish its levels. MUST NOT block or
+    /// allocate.
+    fn process_block(&mut self, data: &mut [f32], channels: usize, sample_rate: f32) {
+        let mut levels = [0.0f32; MAX_TRANSDUCERS];
+        let (engine, tone, test_tone) = (&mut self.engine, &mut self.tone, self.test_tone);
+        match self.split.as_mut() {
+            Some(split) => {
+                let width = split.width();
+                split.process_block(data, channels, &mut levels, |block, levels| {
+                    render_block(engine, tone, test_tone, block, width, sample_rate, levels)
+                })
+            }
+            None => render_block(
+                engine,
+                tone,
+                test_tone,
+                data,
+                channels,
+                sample_rate,
+                &mut levels,
+            ),
+        }
+        // Best-effort: dropped level frames are fine, freshness wins
+        let _ = self.levels_producer.push(levels);
+    }
+}
+
+/// Render one block from the engine, or the test tone when it is enabled.
+fn render_block(
+    engine: &mut StimulusEngine,
+    tone: &mut TestTone,
     test_tone: bool,
-    mut levels_producer: rtrb::Producer<[f32; TRANSDUCER_COUNT]>,
+    data: &mut [f32],
+    channels: usize,
+    sample_rate: f32,
+    levels: &mut [f32; MAX_TRANSDUCERS],
+) {
+    if test_tone {
+        tone.process_block(data, channels, sample_rate);
+        // Per-device-channel RMS for the tone pattern
+        let frames = (data.len() / channels).max(1);
+        for frame in data.chunks_exact(channels) {
+            for (ch, &sample) in frame.iter().take(MAX_TRANSDUCERS).enumerate() {
+                levels[ch] += sample * sample;
+            }
+        }
+        for level in levels.iter_mut() {
+            *level = (*level / frames as f32).sqrt();
+        }
+    } else {
+        engine.process_block(data, channels, sample_rate, levels);
+    }
+}
+
+/// Holds the callback state inside a stream's callback and sends it back to
+/// the supervisor when the backend drops the callback, whether the stream
+/// was torn down or never managed to start.
+struct ReturnOnDrop {
+    state: Option<CallbackState>,
+    home: mpsc::Sender<CallbackState>,
+}
+
+impl Drop for ReturnOnDrop {
+    fn drop(&mut self) {
+        if let Some(state) = self.state.take() {
+            let _ = self.home.send(state);
+        }
+    }
+}
+
+/// Description of an open (or the last open) stream, for status reports.
+#[derive(Clone, Default)]
+struct StreamInfo {
+    device: String,
+    fallback: bool,
+    sample_rate: u32,
+    channels: u16,
+}
+
+struct OpenStream {
+    /// The primary stream first, then any further devices of a split.
+    streams: Vec<cpal::Stream>,
+    info: StreamInfo,
+    /// Errors reported by the backend; `true` marks one the stream cannot
+    /// survive.
+    errors: mpsc::Receiver<(bool, String)>,
+    drift: Option<DriftMonitor>,
+    /// Callback threads of `streams`, promoted as they first run.
+    threads: Promoter,
+}
+
+/// Owns device selection and the callback state between streams: opens the
+/// preferred device, watches the running stream, and after a failure tears
+/// it down, recovers the engine, and reopens with backoff.
+struct StreamSupervisor<'a> {
+    host: cpal::Host,
+    wanted: Option<&'a str>,
+    /// A `[[server.output]]` split, replacing `wanted` when not empty.
+    outputs: &'a [OutputSplit],
+    /// Logical channels the engine renders.
+    transducers: usize,
+    realtime: RealtimeSettings,
+    running: Arc<AtomicBool>,
+    stats: Arc<AudioStats>,
     device_channels: Arc<AtomicU16>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let host = cpal::default_host();
+    audio_status: mpsc::Sender<ServerStatus>,
+    home: mpsc::Sender<CallbackState>,
+    returned: mpsc::Receiver<CallbackState>,
+}
 
-    // Find device with 32+ channels
-    let device = host
-        .output_devices()?
-        .find(|d| {
-            if let Ok(mut configs) = d.supported_output_configs() {
-                configs.any(|c| c.channels() >= 32)
-            } else {
-                false
+impl StreamSupervisor<'_> {
+    fn open(&self, state: CallbackState, choice: DeviceChoice) -> Result<OpenStream, String> {
+        let slot = ReturnOnDrop {
+            state: Some(state),
+            home: self.home.clone(),
+        };
+        if !self.outputs.is_empty() {
+            return self.open_split(slot);
+        }
+        let device = select_output_device(&self.host, self.wanted, choice, self.transducers)
+            .map_err(|e| e.to_string())?;
+        let default_config = device.default_output_config().map_err(|e| e.to_string())?;
+        let has_multichannel = device
+            .supported_output_configs()
+            .map(|mut configs| configs.any(|config| config.channels() as usize >= self.transducers))
+            .unwrap_or(false);
+        let config = device
+            .supported_output_configs()
+            .ok()
+            .and_then(|configs| {
+                preferred_output_config(
+                    configs,
+                    default_config.channels(),
+                    has_multichannel.then_some(self.transducers as u16),
+                )
+            })
+            .unwrap_or(default_config);
+
+        if config.sample_format() != SampleFormat::F32 {
+            return Err(format!(
+                "selected output configuration uses {:?} samples; f32 is required",
+                config.sample_format()
+            ));
+        }
+
+        let info = StreamInfo {
+            device: device.name().unwrap_or_else(|_| "Unknown".to_string()),
+            fallback: choice == DeviceChoice::Fallback,
+            sample_rate: config.sample_rate().0,
+            channels: config.channels(),
+        };
+        eprintln!("Using audio device: {}", info.device);
+        eprintln!("Sample rate: {} Hz", info.sample_rate);
+        if info.sample_rate != PREFERRED_SAMPLE_RATE {
+            eprintln!(
+                "Warning: device does not expose 48 kHz for the selected channel layout; using {} Hz",
+                info.sample_rate
+            );
+        }
+        eprintln!("Channels: {}", info.channels);
+        eprintln!("Buffer size: {:?}", config.buffer_size());
+
+        let (errors_tx, errors) = mpsc::channel();
+        let mut threads = Promoter::new(self.realtime);
+        let stream = self.build_primary(&device, config, slot, errors_tx, threads.register())?;
+        self.stats.restart();
+        stream.play().map_err(|e| e.to_string())?;
+        eprintln!("Audio stream started");
+
+        self.device_channels.store(info.channels, Ordering::Relaxed);
+        self.notify(AudioStreamState::Running, &info, 0, Duration::ZERO, "");
+        Ok(OpenStream {
+            streams: vec![stream],
+            info,
+            errors,
+            drift: None,
+            threads,
+        })
+    }
+
+    /// Open every device of the split, each by name, at one shared sample
+    /// rate. Fresh rings are built on each open; if any device fails, all
+    /// streams opened so far are dropped with them.
+    fn open_split(&self, mut slot: ReturnOnDrop) -> Result<OpenStream, String> {
+        let mut devices = Vec::with_capacity(self.outputs.len());
+        for output in self.outputs {
+            let device = select_output_device(
+                &self.host,
+                Some(&output.device),
+                DeviceChoice::Preferred,
+                self.transducers,
+            )
+            .map_err(|e| e.to_string())?;
+            let name = device.name().unwrap_or_else(|_| "Unknown".to_string());
+            let default_config = device.default_output_config().map_err(|e| e.to_string())?;
+            let config = device
+                .supported_output_configs()
+                .ok()
+                .and_then(|configs| {
+                    preferred_output_config(
+                        configs,
+                        default_config.channels(),
+                        Some(output.channel_count as u16),
+                    )
+                })
+                .ok_or_else(|| {
+                    format!(
+                        "output device \"{name}\" has no f32 configuration with {} channels",
+                        output.channel_count
+                    )
+                })?;
+            devices.push((device, name, config));
+        }
+        let sample_rate = devices[0].2.sample_rate().0;
+        if let Some((_, name, config)) = devices
+            .iter()
+            .find(|(_, _, config)| config.sample_rate().0 != sample_rate)
+        {
+            return Err(format!(
+                "split devices must share one sample rate: {name} runs at {} Hz, {} at {sample_rate} Hz",
+                config.sample_rate().0,
+                devices[0].1
+            ));
+        }
+
+        let info = StreamInfo {
+            device: devices
+                .iter()
+                .map(|(_, name, _)| name.as_str())
+                .collect::<Vec<_>>()
+                .join(" + "),
+            fallback: false,
+            sample_rate,
+            channels: self.transducers as u16,
+        };
+        eprintln!("Sample rate: {sample_rate} Hz");
+        for ((_, name, config), output) in devices.iter().zip(self.outputs) {
+            let channels = output.channels();
+            eprintln!(
+                "Split output {name}: logical channels {}..={} on {} device channels, buffer {:?}",
+                channels.start,
+                channels.end - 1,
+                config.channels(),
+                config.buffer_size()
+            );
+        }
+
+        let (router, sinks, healths) = split::build(self.outputs);
+        if let Some(state) = slot.state.as_mut() {
+            state.split = Some(router);
+        }
+        let (errors_tx, errors) = mpsc::channel();
+        let mut threads = Promoter::new(self.realtime);
+        let mut devices = devices.into_iter();
+        let (device, _, config) = devices.next().expect("a split has at least two outputs");
+        let mut streams = vec![self.build_primary(
+            &device,
+            config,
+            slot,
+            errors_tx.clone(),
+            threads.register(),
+        )?];
+        for ((device, _, config), sink) in devices.zip(sinks) {
+            streams.push(self.build_secondary(
+                &device,
+                config,
+                sink,
+                errors_tx.clone(),
+                threads.register(),
+            )?);
+        }
+        self.stats.restart();
+        for stream in &streams {
+            stream.play().map_err(|e| e.to_string())?;
+        }
+        eprintln!("Audio streams started");
+
+        let drift = DriftMonitor::new(
+            self.outputs
+                .iter()
+                .map(|output| output.device.clone())
+                .zip(healths)
+                .collect(),
+        );
+        self.device_channels.store(info.channels, Ordering::Relaxed);
+        self.notify(AudioStreamState::Running, &info, 0, Duration::ZERO, "");
+        Ok(OpenStream {
+            streams,
+            info,
+            errors,
+            drift: Some(drift),
+            threads,
+        })
+    }
+
+    /// Build the stream whose callback owns the callback state.
+    fn build_primary(
+        &self,
+        device: &cpal::Device,
+        config: SupportedStreamConfig,
+        mut slot: ReturnOnDrop,
+        errors_tx: mpsc::Sender<(bool, String)>,
+        thread: Arc<ThreadSlot>,
+    ) -> Result<cpal::Stream, String> {
+        let sample_rate = config.sample_rate().0 as f32;
+        let channels = config.channels() as usize;
+        let stats = self.stats.clone();
+        // The callback state is owned by the audio callback: no locks
+        // anywhere on the audio path. Commands arrive through the rtrb ring
+        // buffer drained once per callback inside process_block.
+        self.build_stream(device, config, errors_tx, move |data: &mut [f32]| {
+            let start = Instant::now();
+            thread.publish();
+            if let Some(state) = slot.state.as_mut() {
+                state.process_block(data, channels, sample_rate);
             }
+            let frames = (data.len() / channels) as u64;
+            stats.record(
+                start,
+                start.elapsed().as_nanos() as u64,
+                frames,
+                sample_rate,
+            );
         })
-        .unwrap_or_else(|| {
-            // Fallback to default device for testing
-            eprintln!("Warning: No 32-channel device found, using default device");
-            host.default_output_device()
-                .expect("No output device available")
-        });
+    }
 
-    let default_config = device.default_output_config()?;
-    let has_multichannel = device
-        .supported_output_configs()
-        .map(|mut configs| configs.any(|config| config.channels() >= TRANSDUCER_COUNT as u16))
-        .unwrap_or(false);
-    let config = device
-        .supported_output_configs()
-        .ok()
-        .and_then(|configs| {
-            preferred_output_config(configs, default_config.channels(), has_multichannel)
+    /// Build a split device's stream, playing its range from the ring.
+    fn build_secondary(
+        &self,
+        device: &cpal::Device,
+        config: SupportedStreamConfig,
+        mut sink: SplitSink,
+        errors_tx: mpsc::Sender<(bool, String)>,
+        thread: Arc<ThreadSlot>,
+    ) -> Result<cpal::Stream, String> {
+        let channels = config.channels() as usize;
+        self.build_stream(device, config, errors_tx, move |data: &mut [f32]| {
+            thread.publish();
+            sink.process_block(data, channels)
         })
-        .unwrap_or(default_config);
+    }
 
-    if config.sample_format() != SampleFormat::F32 {
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Unsupported,
-            format!(
-                "selected output configuration uses {:?} samples; f32 is required",
-                config.sample_format()
-            ),
+    fn build_stream(
+        &self,
+        device: &cpal::Device,
+        config: SupportedStreamConfig,
+        errors_tx: mpsc::Sender<(bool, String)>,
+        mut callback: impl FnMut(&mut [f32]) + Send + 'static,
+    ) -> Result<cpal::Stream, String> {
+        let stats = self.stats.clone();
+        device
+            .build_output_stream(
+                &config.into(),
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| callback(data),
+                move |err| {
+                    stats.stream_errors.fetch_add(1, Ordering::Relaxed);
+                    eprintln!("Audio stream error: {}", err);
+                    let fatal = matches!(err, cpal::StreamError::DeviceNotAvailable);
+                    let _ = errors_tx.send((fatal, err.to_string()));
+                },
+                None,
+            )
+            .map_err(|e| e.to_string())
+    }
+
+    /// Wait for a dropped stream's callback to hand the state back.
+    fn recover(&self) -> Result<CallbackState, String> {
+        self.returned
+            .recv_timeout(CALLBACK_RELEASE_TIMEOUT)
+            .map_err(|_| "audio backend did not release the engine after closing a stream".into())
+    }
+
+    /// Reopen after a loss: the preferred device first, then the fallback,
+    /// backing off between rounds. A split has no fallback and retries all
+    /// of its devices each round. `None` means shutdown was requested.
+    fn reopen(
+        &self,
+        mut state: CallbackState,
+        last: &StreamInfo,
+    ) -> Result<Option<OpenStream>, String> {
+        let mut attempt = 0;
+        while self.running.load(Ordering::Relaxed) {
+            attempt += 1;
+            let mut error = String::new();
+            let choices: &[DeviceChoice] = if self.outputs.is_empty() {
+                &[DeviceChoice::Preferred, DeviceChoice::Fallback]
+            } else {
+                &[DeviceChoice::Preferred]
+            };
+            for &choice in choices {
+                match self.open(state, choice) {
+                    Ok(stream) => return Ok(Some(stream)),
+                    Err(e) => {
+                        eprintln!("Audio reopen attempt {attempt} ({choice:?}) failed: {e}");
+                        error = e;
+                        state = self.recover()?;
+                    }
+                }
+            }
+            let delay = reopen_delay(attempt);
+            self.notify(AudioStreamState::Retrying, last, attempt, delay, &error);
+            let resume = Instant::now() + delay;
+            while self.running.load(Ordering::Relaxed) && Instant::now() < resume {
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Whether the preferred device is present again while a fallback runs.
+    fn preferred_available(&self) -> bool {
+        select_output_device(
+            &self.host,
+            self.wanted,
+            DeviceChoice::Preferred,
+            self.transducers,
         )
-        .into());
+        .is_ok()
     }
 
-    eprintln!(
-        "Using audio device: {}",
-        device.name().unwrap_or_else(|_| "Unknown".to_string())
-    );
-    eprintln!("Sample rate: {} Hz", config.sample_rate().0);
-    if config.sample_rate().0 != PREFERRED_SAMPLE_RATE {
-        eprintln!(
-            "Warning: device does not expose 48 kHz for the selected channel layout; using {} Hz",
-            config.sample_rate().0
-        );
+    fn notify(
+        &self,
+        state: AudioStreamState,
+        info: &StreamInfo,
+        attempt: u32,
+        retry_in: Duration,
+        message: &str,
+    ) {
+        let _ = self.audio_status.send(ServerStatus::AudioStream {
+            state,
+            device: info.device.clone(),
+            fallback: info.fallback,
+            sample_rate: info.sample_rate,
+            channels: info.channels,
+            attempt,
+            retry_in_ms: retry_in.as_millis() as u32,
+            message: message.to_string(),
+        });
     }
-    eprintln!("Channels: {}", config.channels());
-    eprintln!("Buffer size: {:?}", config.buffer_size());
+}
+
+/// Open the output device and supervise its stream until shutdown. A device
+/// that cannot be opened at startup is an error; after that, a stream that
+/// fails or stalls is torn down and reopened with backoff, on the fallback
+/// device if the preferred one is gone, while the engine and its voices are
+/// kept. With `outputs`, the transducer channels are split across those devices and
+/// every one of them must open. Each transition is sent to observers through
+/// `audio_status`, as is the callback health every 5 s. Callback threads are
+/// promoted to real-time scheduling as `realtime` asks.
+#[allow(clippy::too_many_arguments)]
+pub fn run_audio_loop(
+    engine: StimulusEngine,
+    running: Arc<AtomicBool>,
+    test_tone: bool,
+    device_name: Option<&str>,
+    outputs: &[OutputSplit],
+    realtime: RealtimeSettings,
+    stats: Arc<AudioStats>,
+    levels_producer: rtrb::Producer<[f32; MAX_TRANSDUCERS]>,
+    device_channels: Arc<AtomicU16>,
+    audio_status: mpsc::Sender<ServerStatus>,
+) -> Result<(), Box<dyn std::error::Error>> {
     if test_tone {
         eprintln!(
             "TEST TONE mode: {} Hz bursts cycling across channels",
             TEST_TONE_FREQ
         );
     }
+    let memory_locked = realtime::lock_memory(&realtime);
+    let (home, returned) = mpsc::channel();
+    let supervisor = StreamSupervisor {
+        host: cpal::default_host(),
+        wanted: device_name,
+        outputs,
+        transducers: engine.transducer_count(),
+        realtime,
+        running: running.clone(),
+        stats,
+        device_channels,
+        audio_status,
+        home,
+        returned,
+    };
+    let state = CallbackState::new(engine, test_tone, levels_producer);
 
-    let sample_rate = config.sample_rate().0 as f32;
-    let channels = config.channels() as usize;
-    device_channels.store(config.channels(), Ordering::Relaxed);
-
-    let stats = Arc::new(AudioStats::new());
-    let stats_for_callback = stats.clone();
-    let stats_for_errors = stats.clone();
-
-    // The engine is owned by the audio callback: no locks anywhere on the
-    // audio path. Commands arrive through the rtrb ring buffer drained once
-    // per callback inside process_block.
-    let mut engine = engine;
-    let mut tone = TestTone::new();
+    // With no device named, the automatic search may settle for the default
+    // device at startup; a named device or a split must be present.
+    let mut stream = match supervisor.open(state, DeviceChoice::Preferred) {
+        Ok(stream) => stream,
+        Err(e) if device_name.is_none() && outputs.is_empty() => {
+            eprintln!("Warning: {e}, using default device");
+            let state = supervisor.recover()?;
+            supervisor.open(state, DeviceChoice::Fallback)?
+        }
+        Err(e) => return Err(e.into()),
+    };
 
-    let stream = device.build_output_stream(
-        &config.into(),
-        move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-            let start = Instant::now();
-            let mut levels = [0.0f32; TRANSDUCER_COUNT];
-            if test_tone {
-                tone.process_block(data, channels, sample_rate);
-                // Per-device-channel RMS for the tone pattern
-                let frames = (data.len() / channels).max(1);
-                for frame in data.chunks_exact(channels) {
-                    for (ch, &sample) in frame.iter().take(TRANSDUCER_COUNT).enumerate() {
-                        levels[ch] += sample * sample;
-                    }
-                }
-                for level in levels.iter_mut() {
-                    *level = (*level / frames as f32).sqrt();
-                }
-            } else {
-                engine.process_block(data, channels, sample_rate, &mut levels);
-            }
-            // Best-effort: dropped level frames are fine, freshness wins
-            let _ = levels_producer.push(levels);
-            let frames = (data.len() / channels) as u64;
-            stats_for_callback.record(start.elapsed().as_nanos() as u64, frames);
-        },
-        move |err| {
-            stats_for_errors
-                .stream_errors
-                .fetch_add(1, Ordering::Relaxed);
-            eprintln!("Audio stream error: {}", err);
-        },
-        None,
-    )?;
-
-    stream.play()?;
-    eprintln!("Audio stream started");
-
-    // Monitor loop: report callback health every 5 seconds
+    // Monitor loop: report callback health every 5 seconds and watch for a
+    // dead stream. A split counts as progressing only while every device's
+    // callbacks run.
+    let progress = |stream: &OpenStream| match &stream.drift {
+        Some(drift) => drift.slowest_frames(),
+        None => supervisor.stats.callbacks.load(Ordering::Relaxed),
+    };
     let mut since_last = (0u64, 0u64);
     let mut last_report = Instant::now();
+    let mut last_callbacks = progress(&stream);
+    let mut last_progress = Instant::now();
+    let mut last_probe = Instant::now();
+    let mut was_realtime = false;
     while running.load(Ordering::Relaxed) {
-        std::thread::sleep(std::time::Duration::from_millis(100));
-        if last_report.elapsed().as_secs() >= 5 {
-            eprintln!("{}", stats.report(&mut since_last));
+        std::thread::sleep(Duration::from_millis(100));
+        stream.threads.poll("Audio");
+        let is_realtime = stream.threads.realtime();
+        let report_due = last_report.elapsed().as_secs() >= 5;
+        if report_due {
+            eprintln!("{}", supervisor.stats.report(&mut since_last));
+            if let Some(drift) = stream.drift.as_mut() {
+                eprintln!("{}", drift.report());
+            }
             last_report = Instant::now();
         }
+        if report_due || is_realtime != was_realtime {
+            was_realtime = is_realtime;
+            let _ = supervisor
+                .audio_status
+                .send(supervisor.stats.health(is_realtime, memory_locked));
+        }
+
+        let callbacks = progress(&stream);
+        if callbacks != last_callbacks {
+            last_callbacks = callbacks;
+            last_progress = Instant::now();
+        }
+        let mut reason = None;
+        while let Ok((fatal, message)) = stream.errors.try_recv() {
+            if fatal {
+                reason = Some(message);
+            }
+        }
+        if reason.is_none() && last_progress.elapsed() >= STREAM_STALL_TIMEOUT {
+            reason = Some(format!(
+                "no audio callbacks for {} s",
+                STREAM_STALL_TIMEOUT.as_secs()
+            ));
+        }
+        if reason.is_none()
+            && stream.info.fallback
+            && last_probe.elapsed() >= PREFERRED_PROBE_INTERVAL
+        {
+            last_probe = Instant::now();
+            if supervisor.preferred_available() {
+                reason = Some("preferred device is available again".to_string());
+            }
+        }
+        let Some(reason) = reason else { continue };
+
+        eprintln!(
+            "Audio stream on {} lost: {reason}; reopening",
+            stream.info.device
+        );
+        let OpenStream {
+            streams: dead,
+            info,
+            ..
+        } = stream;
+        drop(dead);
+        let state = supervisor.recover()?;
+        supervisor.notify(AudioStreamState::Lost, &info, 0, Duration::ZERO, &reason);
+        stream = match supervisor.reopen(state, &info)? {
+            Some(stream) => stream,
+            None => break,
+        };
+        last_callbacks = progress(&stream);
+        last_progress = Instant::now();
+        last_probe = Instant::now();
     }
 
     eprintln!("Audio stream stopping");
     Ok(())
 }
 
-/// Run the complete engine on a wall-clocked in-memory 32-channel sink. This
-/// exercises command handling, DSP, levels, and measured-output snapshots without
-/// opening or locking any physical audio device.
+/// Run the complete engine on a wall-clocked in-memory sink with one channel
+/// per configured transducer (up to `MAX_TRANSDUCERS`). This exercises
+/// command handling, DSP, levels, and measured-output snapshots without
+/// opening or locking any physical audio device. With `outputs`, each device
+/// of the split is a paced in-memory sink of its range's width, the first
+/// rendering as the primary. Each sink thread asks for `realtime`
+/// scheduling before it starts, like a device callback.
+#[allow(clippy::too_many_arguments)]
 pub fn run_dummy_audio_loop(
     engine: StimulusEngine,
     running: Arc<AtomicBool>,
     test_tone: bool,
-    mut levels_producer: rtrb::Producer<[f32; TRANSDUCER_COUNT]>,
+    levels_producer: rtrb::Producer<[f32; MAX_TRANSDUCERS]>,
     device_channels: Arc<AtomicU16>,
+    audio_status: mpsc::Sender<ServerStatus>,
+    outputs: &[OutputSplit],
+    realtime: RealtimeSettings,
+    stats: Arc<AudioStats>,
 ) {
-    const DUMMY_CHANNELS: usize = TRANSDUCER_COUNT;
-    const DUMMY_BLOCK_FRAMES: usize = 512;
-
+    let dummy_channels = engine.transducer_count();
     let sample_rate = PREFERRED_SAMPLE_RATE as f32;
-    let block_period = std::time::Duration::from_secs_f64(
-        DUMMY_BLOCK_FRAMES as f64 / PREFERRED_SAMPLE_RATE as f64,
-    );
-    let mut next_block = Instant::now();
-    let mut data = vec![0.0f32; DUMMY_BLOCK_FRAMES * DUMMY_CHANNELS];
-    let mut engine = engine;
-    let mut tone = TestTone::new();
-    let stats = AudioStats::new();
+    let mut state = CallbackState::new(engine, test_tone, levels_producer);
     let mut since_last = (0u64, 0u64);
     let mut last_report = Instant::now();
+    let memory_locked = realtime::lock_memory(&realtime);
+    let mut threads = Promoter::new(realtime);
+    let primary_thread = threads.register();
 
-    device_channels.store(DUMMY_CHANNELS as u16, Ordering::Relaxed);
+    let mut primary_channels = dummy_channels;
+    let mut sinks = Vec::new();
+    let mut drift = None;
+    if !outputs.is_empty() {
+        let (router, split_sinks, healths) = split::build(outputs);
+        state.split = Some(router);
+        sinks = split_sinks;
+        primary_channels = outputs[0].channel_count;
+        drift = Some(DriftMonitor::new(
+            outputs
+                .iter()
+                .map(|output| output.device.clone())
+                .zip(healths)
+                .collect(),
+        ));
+    }
+
+    device_channels.store(dummy_channels as u16, Ordering::Relaxed);
+    let _ = audio_status.send(ServerStatus::AudioStream {
+        state: AudioStreamState::Running,
+        device: "headless".to_string(),
+        fallback: false,
+        sample_rate: PREFERRED_SAMPLE_RATE,
+        channels: dummy_channels as u16,
+        attempt: 0,
+        retry_in_ms: 0,
+        message: String::new(),
+    });
     eprintln!(
         "Dummy audio started: {} Hz, {} channels, {} frames/block",
-        PREFERRED_SAMPLE_RATE, DUMMY_CHANNELS, DUMMY_BLOCK_FRAMES
+        PREFERRED_SAMPLE_RATE, dummy_channels, DUMMY_BLOCK_FRAMES
     );
+    for output in outputs {
+        eprintln!(
+            "Dummy split sink \"{}\": logical channels {}..={}",
+            output.device,
+            output.first_channel,
+            output.channels().end - 1
+        );
+    }
 
-    while running.load(Ordering::Relaxed) {
-        let started = Instant::now();
-        let mut levels = [0.0f32; TRANSDUCER_COUNT];
-        if test_tone {
-            tone.process_block(&mut data, DUMMY_CHANNELS, sample_rate);
-            for frame in data.chunks_exact(DUMMY_CHANNELS) {
-                for (channel, &sample) in frame.iter().enumerate() {
-                    levels[channel] += sample * sample;
+    let sink_threads: Vec<_> = sinks.iter().map(|_| threads.register()).collect();
+    std::thread::scope(|scope| {
+        for ((mut sink, output), thread) in sinks
+            .into_iter()
+            .zip(outputs.iter().skip(1))
+            .zip(sink_threads)
+        {
+            let (running, threads) = (&running, &threads);
+            scope.spawn(move || {
+                thread.publish();
+                threads.poll("Dummy audio");
+                let channels = output.channel_count;
+                let mut data = vec![0.0f32; DUMMY_BLOCK_FRAMES * channels];
+                run_paced(running, || sink.process_block(&mut data, channels));
+            });
+        }
+
+        primary_thread.publish();
+        threads.poll("Dummy audio");
+        let _ = audio_status.send(stats.health(threads.realtime(), memory_locked));
+        let mut data = vec![0.0f32; DUMMY_BLOCK_FRAMES * primary_channels];
+        run_paced(&running, || {
+            let started = Instant::now();
+            state.process_block(&mut data, primary_channels, sample_rate);
+            stats.record(
+                started,
+                started.elapsed().as_nanos() as u64,
+                DUMMY_BLOCK_FRAMES as u64,
+                sample_rate,
+            );
+
+            if last_report.elapsed().as_secs() >= 5 {
+                eprintln!("dummy {}", stats.report(&mut since_last));
+                if let Some(drift) = drift.as_mut() {
+                    eprintln!("dummy {}", drift.report());
                 }
+                let _ = audio_status.send(stats.health(threads.realtime(), memory_locked));
+                last_report = Instant::now();
             }
-            for level in &mut levels {
-                *level = (*level / DUMMY_BLOCK_FRAMES as f32).sqrt();
-            }
-        } else {
-            engine.process_block(&mut data, DUMMY_CHANNELS, sample_rate, &mut levels);
-        }
-        let _ = levels_producer.push(levels);
-        stats.record(
-            started.elapsed().as_nanos() as u64,
-            DUMMY_BLOCK_FRAMES as u64,
-        );
+        });
+    });
 
-        if last_report.elapsed().as_secs() >= 5 {
-            eprintln!("dummy {}", stats.report(&mut since_last));
-            last_report = Instant::now();
-        }
+    eprintln!("Dummy audio stopping");
+}
+
+const DUMMY_BLOCK_FRAMES: usize = 512;
+
+/// Call `block` once per `DUMMY_BLOCK_FRAMES` of wall-clock time at 48 kHz
+/// until shutdown.
+fn run_paced(running: &AtomicBool, mut block: impl FnMut()) {
+    let block_period =
+        Duration::from_secs_f64(DUMMY_BLOCK_FRAMES as f64 / PREFERRED_SAMPLE_RATE as f64);
+    let mut next_block = Instant::now();
+    while running.load(Ordering::Relaxed) {
+        block();
 
         next_block += block_period;
         let now = Instant::now();
@@ -358,8 +1163,6 @@ pub fn run_dummy_audio_loop(
             next_block = now;
         }
     }
-
-    eprintln!("Dummy audio stopping");
 }
 
 #[cfg(test)]
@@ -382,7 +1185,7 @@ mod tests {
         let config = preferred_output_config(
             [range(64, 44_100, 96_000), range(32, 48_000, 48_000)],
             2,
-            true,
+            Some(32),
         )
         .unwrap();
         assert_eq!(config.sample_rate(), SampleRate(48_000));
@@ -391,11 +1194,27 @@ mod tests {
 
     #[test]
     fn output_config_uses_closest_rate_when_48k_is_unavailable() {
-        let config = preferred_output_config([range(2, 96_000, 192_000)], 2, false).unwrap();
+        let config = preferred_output_config([range(2, 96_000, 192_000)], 2, None).unwrap();
         assert_eq!(config.sample_rate(), SampleRate(96_000));
         assert_eq!(config.channels(), 2);
     }
 
+    #[test]
+    fn device_names_match_exactly_then_by_unique_substring() {
+        let names: Vec<String> = ["MOTU 24Ao", "MOTU 24Ao (2)", "Built-in Output"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(match_device_name(&names, "MOTU 24Ao"), Ok(0));
+        assert_eq!(match_device_name(&names, "built-in"), Ok(2));
+        assert_eq!(match_device_name(&names, "(2)"), Ok(1));
+        let ambiguous = match_device_name(&names, "motu").unwrap_err();
+        assert!(ambiguous.contains("ambiguous"), "{ambiguous}");
+        let missing = match_device_name(&names, "Dante").unwrap_err();
+        assert!(missing.contains("not found"), "{missing}");
+        assert!(missing.contains("Built-in Output"), "{missing}");
+    }
+
     #[test]
     fn dummy_audio_advances_engine_without_a_device() {
         let (engine, _commands, _layouts, _voices) =
@@ -403,11 +1222,22 @@ mod tests {
         let (levels_tx, mut levels_rx) = rtrb::RingBuffer::new(8);
         let running = Arc::new(AtomicBool::new(true));
         let device_channels = Arc::new(AtomicU16::new(0));
+        let (status_tx, status_rx) = mpsc::channel();
         let thread = {
             let running = running.clone();
             let device_channels = device_channels.clone();
             std::thread::spawn(move || {
-                run_dummy_audio_loop(engine, running, false, levels_tx, device_channels)
+                run_dummy_audio_loop(
+                    engine,
+                    running,
+                    false,
+                    levels_tx,
+                    device_channels,
+                    status_tx,
+                    &[],
+                    RealtimeSettings::default(),
+                    Arc::new(AudioStats::new()),
+                )
             })
         };
 
@@ -419,5 +1249,111 @@ mod tests {
         running.store(false, Ordering::Relaxed);
         thread.join().unwrap();
         assert_eq!(device_channels.load(Ordering::Relaxed), 32);
+        assert!(matches!(
+            status_rx.try_recv(),
+            Ok(ServerStatus::AudioStream {
+                state: AudioStreamState::Running,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn late_callbacks_and_gaps_count_as_xruns() {
+        let stats = AudioStats::new();
+        // 480 frames at 48 kHz: a 10 ms block
+        let block = |n: u64| stats.epoch + Duration::from_millis(10 * n);
+        stats.record(block(1), 2_000_000, 480, 48_000.0);
+        stats.record(block(2), 2_000_000, 480, 48_000.0);
+        stats.record(block(3), 12_000_000, 480, 48_000.0);
+        stats.record(block(6), 2_000_000, 480, 48_000.0);
+        stats.record(block(7), 2_000_000, 480, 48_000.0);
+        assert_eq!(stats.late_callbacks.load(Ordering::Relaxed), 1);
+        assert_eq!(stats.gaps.load(Ordering::Relaxed), 1);
+
+        // A reopened stream starts afresh
+        stats.restart();
+        stats.record(block(100), 2_000_000, 480, 48_000.0);
+        assert!(matches!(
+            stats.health(false, false),
+            ServerStatus::AudioHealth {
+                callbacks: 6,
+                late_callbacks: 1,
+                gaps: 1,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn load_windows_measure_callbacks_against_the_block_budget() {
+        let stats = AudioStats::new();
+        let mut window = LoadWindow::default();
+        let start = Instant::now();
+        // 480-frame blocks at 48 kHz have a 10 ms budget
+        for _ in 0..99 {
+            stats.record(start, 1_000_000, 480, 48_000.0);
+        }
+        stats.record(start, 9_000_000, 480, 48_000.0);
+        let load = stats.take_window(&mut window);
+        // 1 ms falls in the bucket topped by 2^20 ns
+        assert!((load.p50 - 0.104_857_6).abs() < 1e-6, "{load:?}");
+        assert!((load.max - 0.9).abs() < 1e-6, "{load:?}");
+        assert!(load.busy > 0.0);
+
+        // The next window sees only its own callbacks
+        stats.record(start, 4_000_000, 480, 48_000.0);
+        let load = stats.take_window(&mut window);
+        assert!((load.max - 0.4).abs() < 1e-6, "{load:?}");
+        assert!(load.p50 > 0.4 && load.p50 <= 0.42, "{load:?}");
+    }
+
+    #[test]
+    fn reopen_backoff_doubles_up_to_its_cap() {
+        assert_eq!(reopen_delay(1), REOPEN_BACKOFF_FIRST);
+        assert_eq!(reopen_delay(2), REOPEN_BACKOFF_FIRST * 2);
+        assert_eq!(reopen_delay(3), REOPEN_BACKOFF_FIRST * 4);
+        assert_eq!(reopen_delay(40), REOPEN_BACKOFF_MAX);
+    }
+
+    #[test]
+    fn engine_survives_a_dropped_stream_callback() {
+        let (engine, mut commands, _layouts, _voices) =
+            StimulusEngine::new(crate::config::TransducerLayout::default());
+        let (levels_tx, _levels_rx) = rtrb::RingBuffer::new(8);
+        let (home, returned) = mpsc::channel();
+        let mut slot = ReturnOnDrop {
+            state: Some(CallbackState::new(engine, false, levels_tx)),
+            home,
+        };
+        // Stand-in for a backend's callback closure
+        let mut data = vec![0.0f32; 512 * 2];
+        let mut callback = move |data: &mut [f32]| {
+            if let Some(state) = slot.state.as_mut() {
+                state.process_block(data, 2, 48_000.0);
+            }
+        };
+        commands
+            .push(crate::engine::EngineCommand::NoteOn {
+                instance_id: 0,
+                note: 60,
+                velocity: 100,
+                channel: 1,
+                mpe: haptic_protocol::MpeData {
+                    pressure: 1.0,
+                    pitch_bend: 0.0,
+                    timbre: 0.5,
+                },
+            })
+            .unwrap();
+        for _ in 0..20 {
+            callback(&mut data);
+        }
+        drop(callback);
+
+        // The held note keeps sounding through the recovered engine
+        let mut state = returned.try_recv().expect("state returns on drop");
+        state.process_block(&mut data, 2, 48_000.0);
+        assert!(data.iter().any(|&sample| sample.abs() > 1e-3));
     }
 }
@@ -0,0 +1,678 @@
+//! Live capture of the final logical output to disk.
+//!
+//! The audio callback pushes `CaptureItem`s into a ring preallocated at
+//! startup (see `StimulusEngine::attach_capture`). A non-real-time writer
+//! thread drains it and streams frames to a one-channel-per-transducer WAV or raw f32 file,
+//! beside a sidecar `<path>.json` recording the layout, routing, and session
+//! metadata. The IPC thread opens each capture's files and hands the session
+//! to the writer *before* asking the engine to start, so no frame can arrive
+//! without a destination, and a path that cannot be created is refused
+//! immediately rather than after the engine has begun. Both files must be
+//! new: a capture never truncates, and so never deletes on abort, a file it
+//! did not create.
+
+use crate::config::{LayoutSpace, TransducerLayout};
+use crate::engine::{CaptureItem, EngineCommand, MAX_TRANSDUCERS};
+use crate::wav::{frame_bytes, write_wav_header, OutputFormat, MAX_WAV_DATA_BYTES};
+use haptic_protocol::{ServerStatus, PROTOCOL_VERSION};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Interval between progress reports while a capture runs.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Most ring items handled before the writer rechecks its control channel.
+const WRITER_BATCH_ITEMS: usize = 4096;
+
+/// Everything the sidecar records about how a capture was requested.
+pub struct CaptureRequest {
+    pub path: PathBuf,
+    pub requested_by: u64,
+    pub layout: TransducerLayout,
+    pub routes: [u8; MAX_TRANSDUCERS],
+}
+
+/// An open capture destination, owned by the IPC thread until handed over
+/// and by the writer thread afterwards.
+pub struct Session {
+    request: CaptureRequest,
+    sidecar_path: PathBuf,
+    format: OutputFormat,
+    out: BufWriter<File>,
+    created_unix_ms: u64,
+    sample_rate: u32,
+    start_device_frame: u64,
+    frames: u64,
+}
+
+impl Session {
+    /// Create the output and sidecar files, refusing either if it already
+    /// exists; nothing is recorded until the engine's `Begin` marker arrives.
+    pub fn create(request: CaptureRequest) -> Result<Self, String> {
+        let format = OutputFormat::for_path(&request.path);
+        let mut sidecar_path = request.path.clone().into_os_string();
+        sidecar_path.push(".json");
+        let sidecar_path = PathBuf::from(sidecar_path);
+        let file = create_new(&request.path)?;
+        if let Err(error) = create_new(&sidecar_path) {
+            drop(file);
+            let _ = std::fs::remove_file(&request.path);
+            return Err(error);
+        }
+        let session = Self {
+            request,
+            sidecar_path,
+            format,
+            out: BufWriter::new(file),
+            created_unix_ms: unix_ms(),
+            sample_rate: 0,
+            start_device_frame: 0,
+            frames: 0,
+        };
+        if let Err(error) = session.write_sidecar("pending", 0, None) {
+            session.discard();
+            return Err(error);
+        }
+        Ok(session)
+    }
+
+    fn begin(&mut self, sample_rate: f32, device_frame_index: u64) -> Result<(), String> {
+        self.sample_rate = sample_rate.round() as u32;
+        self.start_device_frame = device_frame_index;
+        if self.format == OutputFormat::Wav {
+            // Placeholder sizes; rewritten in place by `finish`
+            write_wav_header(
+                &mut self.out,
+                self.sample_rate,
+                self.request.layout.count,
+                0,
+            )?;
+        }
+        self.write_sidecar("recording", 0, None)
+    }
+
+    fn write_frame(&mut self, frame: &[f32; MAX_TRANSDUCERS]) -> Result<(), String> {
+        let channels = self.request.layout.count;
+        if self.format == OutputFormat::Wav
+            && (self.frames + 1) * frame_bytes(channels) > MAX_WAV_DATA_BYTES
+        {
+            return Err(
+                "WAV size limit reached; capture to a raw .f32 file for longer sessions".into(),
+            );
+        }
+        let mut bytes = [0u8; frame_bytes(MAX_TRANSDUCERS) as usize];
+        for (chunk, sample) in bytes.chunks_exact_mut(4).zip(frame.iter()) {
+            chunk.copy_from_slice(&sample.to_le_bytes());
+        }
+        self.out
+            .write_all(&bytes[..frame_bytes(channels) as usize])
+            .map_err(|e| format!("write to {} failed: {}", self.request.path.display(), e))?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Flush, patch the WAV sizes, and mark the sidecar complete.
+    fn finish(mut self, dropped_frames: u64) -> Result<u64, String> {
+        let io_error = |e: std::io::Error| format!("finishing capture failed: {}", e);
+        self.out.flush().map_err(io_error)?;
+        if self.format == OutputFormat::Wav {
+            let file = self.out.get_mut();
+            file.seek(SeekFrom::Start(0)).map_err(io_error)?;
+            write_wav_header(
+                file,
+                self.sample_rate,
+                self.request.layout.count,
+                self.frames,
+            )?;
+            file.flush().map_err(io_error)?;
+        }
+        self.write_sidecar("complete", dropped_frames, Some(unix_ms()))?;
+        Ok(self.frames)
+    }
+
+    /// Remove the files of a capture that never started.
+    fn discard(self) {
+        let Self {
+            request,
+            sidecar_path,
+            out,
+            ..
+        } = self;
+        drop(out);
+        let _ = std::fs::remove_file(&request.path);
+        let _ = std::fs::remove_file(sidecar_path);
+    }
+
+    fn write_sidecar(
+        &self,
+        state: &str,
+        dropped_frames: u64,
+        stopped_unix_ms: Option<u64>,
+    ) -> Result<(), String> {
+        let layout = &self.request.layout;
+        let pairs = |values: &mut dyn Iterator<Item = (f32, f32)>| {
+            values
+                .map(|(a, b)| format!("[{a}, {b}]"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let list = |values: &mut dyn Iterator<Item = String>| values.collect::<Vec<_>>().join(", ");
+        let json = format!(
+            "{{\n  \"state\": \"{state}\",\n  \"path\": {path},\n  \"format\": \"{format}\",\n  \"channels\": {channels},\n  \"sample_rate\": {sample_rate},\n  \"start_device_frame\": {start},\n  \"frames\": {frames},\n  \"dropped_frames\": {dropped_frames},\n  \"created_unix_ms\": {created},\n  \"stopped_unix_ms\": {stopped},\n  \"requested_by_instance\": {requested_by},\n  \"server_version\": \"{version}\",\n  \"protocol_version\": {protocol},\n  \"monitor_routes\": [{routes}],\n  \"layout\": {{\n    \"space\": \"{space}\",\n    \"table_m\": [{table_w}, {table_l}],\n    \"positions_m\": [{positions}],\n    \"physical_positions_m\": [{physical}],\n    \"heights_m\": [{heights}],\n    \"gains\": [{gains}],\n    \"headroom\": {{ \"enabled\": {headroom_enabled}, \"attack_ms\": {attack}, \"release_ms\": {release} }}\n  }}\n}}\n",
+            path = json_string(&self.request.path.display().to_string()),
+            format = self.format.name(),
+            channels = layout.count,
+            sample_rate = self.sample_rate,
+            start = self.start_device_frame,
+            frames = self.frames,
+            created = self.created_unix_ms,
+            stopped = stopped_unix_ms.map_or("null".to_string(), |ms| ms.to_string()),
+            requested_by = self.request.requested_by,
+            version = env!("CARGO_PKG_VERSION"),
+            protocol = PROTOCOL_VERSION,
+            routes = list(
+                &mut self.request.routes[..layout.count]
+                    .iter()
+                    .map(|r| r.to_string())
+            ),
+            table_w = layout.table_m.0,
+            table_l = layout.table_m.1,
+            space = match layout.space {
+                LayoutSpace::Table => "table",
+                LayoutSpace::Body => "body",
+            },
+            positions = pairs(&mut layout.positions().iter().copied()),
+            physical = pairs(&mut layout.physical_positions[..layout.count].iter().copied()),
+            heights = list(&mut layout.heights().iter().map(|z| z.to_string())),
+            gains = list(&mut layout.gains().iter().map(|g| g.to_string())),
+            headroom_enabled = layout.headroom.enabled,
+            attack = layout.headroom.attack_ms,
+            release = layout.headroom.release_ms,
+        );
+        std::fs::write(&self.sidecar_path, json)
+            .map_err(|e| format!("cannot write {}: {}", self.sidecar_path.display(), e))
+    }
+}
+
+fn create_new(path: &Path) -> Result<File, String> {
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| format!("cannot create {}: {}", path.display(), e))
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn unix_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+pub enum WriterControl {
+    /// Destination for the next `Begin` marker.
+    Start(Box<Session>),
+    /// The engine never received the matching start; drop the session.
+    Abort,
+}
+
+pub enum CaptureEvent {
+    Progress {
+        frames: u64,
+    },
+    /// Writing failed; the session is closed and later frames are discarded
+    /// until the engine's `End` marker.
+    Failed {
+        error: String,
+    },
+    /// The engine's `End` marker was handled; the writer is idle again.
+    Ended {
+        completed: bool,
+        frames: u64,
+        dropped_frames: u64,
+    },
+}
+
+/// Spawn the writer thread over the consumer end of the capture ring. It
+/// exits once `running` clears and the ring is empty, finishing any open
+/// capture so its file stays readable after Ctrl-C.
+pub fn spawn_writer(
+    ring: rtrb::Consumer<CaptureItem>,
+    running: Arc<AtomicBool>,
+) -> (CaptureLink, thread::JoinHandle<()>) {
+    let (control_tx, control_rx) = mpsc::channel();
+    let (events_tx, events_rx) = mpsc::channel();
+    let handle = thread::spawn(move || writer_loop(ring, control_rx, events_tx, running));
+    (
+        CaptureLink {
+            control: control_tx,
+            events: events_rx,
+            phase: CapturePhase::Idle,
+            stop_pending: false,
+            dirty: false,
+            path: String::new(),
+            frames: 0,
+            dropped_frames: 0,
+            error: String::new(),
+        },
+        handle,
+    )
+}
+
+fn writer_loop(
+    mut ring: rtrb::Consumer<CaptureItem>,
+    control: mpsc::Receiver<WriterControl>,
+    events: mpsc::Sender<CaptureEvent>,
+    running: Arc<AtomicBool>,
+) {
+    let mut pending: Option<Box<Session>> = None;
+    let mut active: Option<Session> = None;
+    let mut last_progress = Instant::now();
+    let take_control = |pending: &mut Option<Box<Session>>| {
+        while let Ok(message) = control.try_recv() {
+            match message {
+                WriterControl::Start(session) => {
+                    if let Some(stale) = pending.replace(session) {
+                        stale.discard();
+                    }
+                }
+                WriterControl::Abort => {
+                    if let Some(session) = pending.take() {
+                        session.discard();
+                    }
+                }
+            }
+        }
+    };
+
+    loop {
+        take_control(&mut pending);
+        let mut handled = 0;
+        while handled < WRITER_BATCH_ITEMS {
+            let Ok(item) = ring.pop() else { break };
+            handled += 1;
+            match item {
+                CaptureItem::Begin {
+                    sample_rate,
+                    device_frame_index,
+                } => {
+                    // The session is sent before the engine command that
+                    // produced this marker, but possibly after this
+                    // iteration's first look at the control channel.
+                    take_control(&mut pending);
+                    if let Some(mut session) = pending.take() {
+                        match session.begin(sample_rate, device_frame_index) {
+                            Ok(()) => active = Some(*session),
+                            Err(error) => {
+                                let _ = events.send(CaptureEvent::Failed { error });
+                            }
+                        }
+                    }
+                }
+                CaptureItem::Frame(frame) => {
+                    if let Some(session) = active.as_mut() {
+                        if let Err(error) = session.write_frame(&frame) {
+                            // Keep what was written readable
+                            if let Some(session) = active.take() {
+                                let _ = session.finish(0);
+                            }
+                            let _ = events.send(CaptureEvent::Failed { error });
+                        }
+                    }
+                }
+                CaptureItem::End { dropped_frames } => {
+                    let ended = match active.take() {
+                        Some(session) => match session.finish(dropped_frames) {
+                            Ok(frames) => CaptureEvent::Ended {
+                                completed: true,
+                                frames,
+                                dropped_frames,
+                            },
+                            Err(error) => {
+                                let _ = events.send(CaptureEvent::Failed { error });
+                                CaptureEvent::Ended {
+                                    completed: false,
+                                    frames: 0,
+                                    dropped_frames,
+                                }
+                            }
+                        },
+                        None => CaptureEvent::Ended {
+                            completed: false,
+                            frames: 0,
+                            dropped_frames,
+                        },
+                    };
+                    let _ = events.send(ended);
+                }
+            }
+        }
+
+        if let Some(session) = active.as_ref() {
+            if last_progress.elapsed() >= PROGRESS_INTERVAL {
+                last_progress = Instant::now();
+                let _ = events.send(CaptureEvent::Progress {
+                    frames: session.frames,
+                });
+            }
+        }
+
+        if handled == 0 {
+            if !running.load(Ordering::Relaxed) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    if let Some(session) = active.take() {
+        let path = session.request.path.clone();
+        match session.finish(0) {
+            Ok(frames) => eprintln!(
+                "Capture {} closed at shutdown after {} frames",
+                path.display(),
+                frames
+            ),
+            Err(error) => eprintln!("Capture {} failed at shutdown: {}", path.display(), error),
+        }
+    }
+    if let Some(session) = pending.take() {
+        session.discard();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CapturePhase {
+    Idle,
+    Active,
+    /// The engine has been told to stop; waiting for the writer's `Ended`.
+    Stopping,
+}
+
+/// The IPC thread's end of the capture machinery. Only one capture runs at a
+/// time, and a new one cannot start until the previous one has been closed
+/// by the writer, so at most one Begin/End pair is ever outstanding in the
+/// ring's reserved marker slots.
+pub struct CaptureLink {
+    control: mpsc::Sender<WriterControl>,
+    events: mpsc::Receiver<CaptureEvent>,
+    phase: CapturePhase,
+    stop_pending: bool,
+    /// The state changed since the last `take_dirty`.
+    dirty: bool,
+    path: String,
+    frames: u64,
+    dropped_frames: u64,
+    error: String,
+}
+
+impl CaptureLink {
+    /// Open the destination and hand it to the writer. On success the caller
+    /// must queue `SetCapture { enabled: true }`, or call `abort` if it could
+    /// not.
+    pub fn start(&mut self, request: CaptureRequest) -> Result<(), String> {
+        if self.phase != CapturePhase::Idle {
+            return Err(format!("capture to {} is still running", self.path));
+        }
+        let path = request.path.display().to_string();
+        let session = Session::create(request).inspect_err(|error| {
+            self.error = error.clone();
+            self.dirty = true;
+        })?;
+        self.control
+            .send(WriterControl::Start(Box::new(session)))
+            .map_err(|_| "capture writer is not running".to_string())?;
+        self.phase = CapturePhase::Active;
+        self.path = path;
+        self.frames = 0;
+        self.dropped_frames = 0;
+        self.error.clear();
+        self.dirty = true;
+        Ok(())
+    }
+
+    pub fn abort(&mut self) {
+        let _ = self.control.send(WriterControl::Abort);
+        self.phase = CapturePhase::Idle;
+        self.error = "command queue full; capture not started".into();
+        self.dirty = true;
+    }
+
+    /// Whether a `StopCapture` should be forwarded to the engine.
+    pub fn can_stop(&self) -> bool {
+        self.phase == CapturePhase::Active
+    }
+
+    /// The engine accepted `SetCapture { enabled: false }`.
+    pub fn stopping(&mut self) {
+        self.phase = CapturePhase::Stopping;
+        self.dirty = true;
+    }
+
+    /// Apply writer events. A failed write stops the engine here, retrying
+    /// on later calls if the command queue is momentarily full.
+    pub fn poll(&mut self, command_producer: &mut rtrb::Producer<EngineCommand>) {
+        while let Ok(event) = self.events.try_recv() {
+            self.dirty = true;
+            match event {
+                CaptureEvent::Progress { frames } => self.frames = frames,
+                CaptureEvent::Failed { error } => {
+                    eprintln!("Capture {} failed: {}", self.path, error);
+                    self.error = error;
+                    if self.phase == CapturePhase::Active {
+                        self.stop_pending = true;
+                    }
+                }
+                CaptureEvent::Ended {
+                    completed,
+                    frames,
+                    dropped_frames,
+                } => {
+                    if completed {
+                        self.frames = frames;
+                        eprintln!(
+                            "Capture {} complete: {} frames, {} dropped",
+                            self.path, frames, dropped_frames
+                        );
+                    }
+                    self.dropped_frames = dropped_frames;
+                    self.phase = CapturePhase::Idle;
+                }
+            }
+        }
+        if self.stop_pending
+            && command_producer
+                .push(EngineCommand::SetCapture { enabled: false })
+                .is_ok()
+        {
+            self.stop_pending = false;
+            self.phase = CapturePhase::Stopping;
+        }
+    }
+
+    /// Whether the state changed and should be rebroadcast; clears the flag.
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+
+    pub fn status(&self) -> ServerStatus {
+        ServerStatus::CaptureState {
+            active: self.phase != CapturePhase::Idle,
+            path: self.path.clone(),
+            frames: self.frames,
+            dropped_frames: self.dropped_frames,
+            error: self.error.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait_for_end(link: &mut CaptureLink) {
+        let (mut commands, _consumer) = rtrb::RingBuffer::new(4);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while link.phase != CapturePhase::Idle {
+            assert!(Instant::now() < deadline, "writer never ended the capture");
+            link.poll(&mut commands);
+            thread::sleep(Duration::from_millis(2));
+        }
+    }
+
+    #[test]
+    fn writer_streams_a_wav_and_completes_its_sidecar() {
+        let dir = std::env::temp_dir().join(format!("haptic-capture-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.wav");
+
+        let running = Arc::new(AtomicBool::new(true));
+        let (mut ring, consumer) = rtrb::RingBuffer::new(64);
+        let (mut link, writer) = spawn_writer(consumer, running.clone());
+        link.start(CaptureRequest {
+            path: path.clone(),
+            requested_by: 42,
+            layout: TransducerLayout::default(),
+            routes: std::array::from_fn(|i| i as u8),
+        })
+        .unwrap();
+        assert!(link
+            .start(CaptureRequest {
+                path: dir.join("second.wav"),
+                requested_by: 42,
+                layout: TransducerLayout::default(),
+                routes: [0; MAX_TRANSDUCERS],
+            })
+            .is_err());
+
+        ring.push(CaptureItem::Begin {
+            sample_rate: 48_000.0,
+            device_frame_index: 960,
+        })
+        .unwrap();
+        for i in 0..10 {
+            ring.push(CaptureItem::Frame([i as f32 * 0.1; MAX_TRANSDUCERS]))
+                .unwrap();
+        }
+        ring.push(CaptureItem::End { dropped_frames: 2 }).unwrap();
+        link.stopping();
+        wait_for_end(&mut link);
+
+        let wav = std::fs::read(&path).unwrap();
+        assert_eq!(wav.len(), 80 + 10 * frame_bytes(32) as usize);
+        assert_eq!(
+            u32::from_le_bytes(wav[76..80].try_into().unwrap()),
+            10 * 128
+        );
+        assert_eq!(
+            f32::from_le_bytes(wav[80 + 128..84 + 128].try_into().unwrap()),
+            0.1
+        );
+        let sidecar = std::fs::read_to_string(dir.join("session.wav.json")).unwrap();
+        assert!(sidecar.contains("\"state\": \"complete\""), "{sidecar}");
+        assert!(sidecar.contains("\"start_device_frame\": 960"), "{sidecar}");
+        assert!(sidecar.contains("\"dropped_frames\": 2"), "{sidecar}");
+        assert!(
+            sidecar.contains("\"requested_by_instance\": 42"),
+            "{sidecar}"
+        );
+        match link.status() {
+            ServerStatus::CaptureState {
+                active,
+                frames,
+                dropped_frames,
+                ..
+            } => {
+                assert!(!active);
+                assert_eq!(frames, 10);
+                assert_eq!(dropped_frames, 2);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+
+        running.store(false, Ordering::Relaxed);
+        writer.join().unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unwritable_destination_is_refused_before_the_engine_starts() {
+        let running = Arc::new(AtomicBool::new(false));
+        let (_ring, consumer) = rtrb::RingBuffer::new(4);
+        let (mut link, writer) = spawn_writer(consumer, running);
+        let error = link
+            .start(CaptureRequest {
+                path: PathBuf::from("/nonexistent-haptic-dir/session.wav"),
+                requested_by: 1,
+                layout: TransducerLayout::default(),
+                routes: [0; MAX_TRANSDUCERS],
+            })
+            .unwrap_err();
+        assert!(error.contains("cannot create"), "{error}");
+        assert!(!link.can_stop());
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn existing_files_survive_a_refused_capture() {
+        let dir =
+            std::env::temp_dir().join(format!("haptic-capture-existing-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let existing = dir.join("notes.txt");
+        std::fs::write(&existing, "keep me").unwrap();
+        let fresh = dir.join("fresh.wav");
+        let existing_sidecar = dir.join("fresh.wav.json");
+        std::fs::write(&existing_sidecar, "keep me too").unwrap();
+
+        let running = Arc::new(AtomicBool::new(false));
+        let (_ring, consumer) = rtrb::RingBuffer::new(4);
+        let (mut link, writer) = spawn_writer(consumer, running);
+        let request = |path: PathBuf| CaptureRequest {
+            path,
+            requested_by: 1,
+            layout: TransducerLayout::default(),
+            routes: [0; MAX_TRANSDUCERS],
+        };
+        let error = link.start(request(existing.clone())).unwrap_err();
+        assert!(error.contains("cannot create"), "{error}");
+        assert!(!dir.join("notes.txt.json").exists());
+        let error = link.start(request(fresh.clone())).unwrap_err();
+        assert!(error.contains("fresh.wav.json"), "{error}");
+        assert!(!fresh.exists());
+        assert!(!link.can_stop());
+
+        assert_eq!(std::fs::read_to_string(&existing).unwrap(), "keep me");
+        assert_eq!(
+            std::fs::read_to_string(&existing_sidecar).unwrap(),
+            "keep me too"
+        );
+        writer.join().unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn sidecar_strings_are_json_escaped() {
+        assert_eq!(json_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\u000a\"");
+    }
+}
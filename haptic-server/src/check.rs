@@ -0,0 +1,204 @@
+//! `--check-config`: parse a config without starting the server, print every
+//! resolved layout as a table, and warn about entries that parse cleanly but
+//! would make the table feel wrong — overlapping or off-table transducers,
+//! silent channels, repeated overrides, and Wave delays beyond the delay-line
+//! capacity at the slowest wave speed.
+
+use crate::config::{self, Axis, DistanceMetric, LayoutSpace, TransducerLayout};
+use crate::engine::{farthest_source_distance, max_wave_delay_s};
+use haptic_protocol::{DEFAULT_SOURCE_HEIGHT_M, MIN_WAVE_SPEED};
+use std::path::Path;
+
+/// Transducers closer than this are reported as overlapping.
+const OVERLAP_DISTANCE_M: f32 = 0.02;
+
+/// Print the report for the config at `path`. An invalid config is an
+/// error; warnings alone still succeed.
+pub fn run(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    let (layouts, mut warnings) =
+        config::check_config(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let multiple = layouts.names().count() > 1;
+    for (name, layout) in layouts.iter() {
+        print_layout(name, name == layouts.active_name(), layout);
+        let prefix = if multiple {
+            format!("layout \"{name}\": ")
+        } else {
+            String::new()
+        };
+        warnings.extend(
+            layout_warnings(layout)
+                .into_iter()
+                .map(|w| format!("{prefix}{w}")),
+        );
+    }
+
+    println!();
+    if warnings.is_empty() {
+        println!("{}: OK", path.display());
+    } else {
+        for warning in &warnings {
+            println!("warning: {warning}");
+        }
+        println!("{}: valid, {} warning(s)", path.display(), warnings.len());
+    }
+    Ok(())
+}
+
+fn print_layout(name: &str, active: bool, layout: &TransducerLayout) {
+    let (width, length) = layout.table_m;
+    let space = match layout.space {
+        LayoutSpace::Table => "table",
+        LayoutSpace::Body => "body",
+    };
+    println!(
+        "Layout \"{name}\"{}: {} transducers, {space} {width} x {length} m, {} metric",
+        if active { " (active)" } else { "" },
+        layout.count,
+        metric_name(layout.metric),
+    );
+    let body = layout.space == LayoutSpace::Body;
+    if body {
+        println!("   ch       u       v  table x  table y    gain");
+    } else {
+        println!("   ch       x       y       z    gain");
+    }
+    for channel in 0..layout.count {
+        let (x, y) = layout.positions[channel];
+        if body {
+            let (px, py) = layout.physical_positions[channel];
+            println!(
+                "{channel:>5} {x:>7.3} {y:>7.3} {px:>8.3} {py:>8.3} {:>7.3}",
+                layout.gains[channel]
+            );
+        } else {
+            println!(
+                "{channel:>5} {x:>7.3} {y:>7.3} {:>7.3} {:>7.3}",
+                layout.heights[channel], layout.gains[channel]
+            );
+        }
+    }
+
+    let delay = worst_wave_delay_s(layout);
+    println!(
+        "Worst-case Wave delay at {MIN_WAVE_SPEED} m/s: {delay:.2} s of {:.2} s capacity",
+        max_wave_delay_s()
+    );
+}
+
+fn metric_name(metric: DistanceMetric) -> &'static str {
+    match metric {
+        DistanceMetric::Euclidean => "euclidean",
+        DistanceMetric::Torus {
+            wrap_x: true,
+            wrap_y: true,
+        } => "torus (x, y)",
+        DistanceMetric::Torus { wrap_x: true, .. } => "torus (x)",
+        DistanceMetric::Torus { .. } => "torus (y)",
+        DistanceMetric::Cylinder { axis: Axis::X } => "cylinder (x)",
+        DistanceMetric::Cylinder { axis: Axis::Y } => "cylinder (y)",
+        DistanceMetric::Graph => "graph",
+    }
+}
+
+/// Longest delay any in-table source produces at the wave-speed floor.
+fn worst_wave_delay_s(layout: &TransducerLayout) -> f32 {
+    farthest_source_distance(layout, DEFAULT_SOURCE_HEIGHT_M) / MIN_WAVE_SPEED
+}
+
+/// Likely mistakes in one resolved layout.
+fn layout_warnings(layout: &TransducerLayout) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let count = layout.count;
+
+    for a in 0..count {
+        for b in a + 1..count {
+            let (pa, pb) = (layout.physical_positions[a], layout.physical_positions[b]);
+            let dz = if layout.space == LayoutSpace::Table {
+                layout.heights[a] - layout.heights[b]
+            } else {
+                0.0
+            };
+            let distance = ((pa.0 - pb.0).powi(2) + (pa.1 - pb.1).powi(2) + dz * dz).sqrt();
+            if distance < OVERLAP_DISTANCE_M {
+                warnings.push(format!(
+                    "transducers {a} and {b} overlap ({:.1} mm apart)",
+                    distance * 1000.0
+                ));
+            }
+        }
+    }
+
+    if layout.space == LayoutSpace::Table {
+        let (width, length) = layout.table_m;
+        for (channel, &(x, y)) in layout.positions().iter().enumerate() {
+            if !(0.0..=width).contains(&x) || !(0.0..=length).contains(&y) {
+                warnings.push(format!(
+                    "transducer {channel} at ({x}, {y}) is outside the {width} x {length} m table"
+                ));
+            }
+        }
+    }
+
+    for (channel, &gain) in layout.gains().iter().enumerate() {
+        if gain == 0.0 {
+            warnings.push(format!("transducer {channel} has zero gain and is silent"));
+        }
+    }
+
+    let delay = worst_wave_delay_s(layout);
+    let capacity = max_wave_delay_s();
+    if delay > capacity {
+        let slowest = delay / capacity * MIN_WAVE_SPEED;
+        warnings.push(format!(
+            "Wave delays reach {delay:.1} s at {MIN_WAVE_SPEED} m/s, beyond the {capacity:.1} s \
+             delay lines; they clamp below {slowest:.2} m/s"
+        ));
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_layout_has_no_warnings() {
+        assert!(layout_warnings(&TransducerLayout::default()).is_empty());
+    }
+
+    #[test]
+    fn suspicious_layouts_are_flagged() {
+        let (layouts, parse_warnings) = config::check_config(
+            r#"
+            [[transducer]]
+            channel = 0
+            x = 0.125
+            y = 0.375
+            gain = 0.0
+
+            [[transducer]]
+            channel = 3
+            x = 8.0
+
+            [[transducer]]
+            channel = 3
+            x = 8.5
+            "#,
+        )
+        .unwrap();
+        assert_eq!(parse_warnings.len(), 1);
+        assert!(parse_warnings[0].contains("transducer 3 is overridden"));
+
+        let warnings = layout_warnings(layouts.active());
+        let has = |needle: &str| warnings.iter().any(|w| w.contains(needle));
+        // Channel 0 moved onto channel 4's cell centre
+        assert!(has("transducers 0 and 4 overlap"));
+        assert!(has("transducer 0 has zero gain"));
+        assert!(has("transducer 3 at (8.5, 0.125) is outside"));
+        // 8.5 m from the far table corner is far beyond 22 s at 0.1 m/s
+        assert!(has("Wave delays reach"));
+    }
+}
@@ -1,17 +1,27 @@
-//! Transducer layout configuration.
+//! Transducer layout and server configuration.
 //!
-//! Loaded from TOML at startup and hot-reloaded when the file changes.
+//! Loaded from TOML at startup and hot-reloaded when the file changes; only
+//! the layout hot-reloads, `[server]` settings take effect on restart. The
+//! transducer count is fixed at startup too: a reload that changes it is
+//! rejected. A config may declare several named layouts sharing that count;
+//! clients switch between them at runtime.
 //! All distances are physical metres — the wave-propagation model derives
 //! per-transducer delays from real distances and wave speed (m/s), so the
 //! layout must use real dimensions, not normalised coordinates.
 
-use crate::engine::TRANSDUCER_COUNT;
+use crate::engine::MAX_TRANSDUCERS;
+use crate::realtime::{RealtimeMode, RealtimeSettings};
+use haptic_protocol::{MAX_LAYOUTS, MAX_LAYOUT_NAME_BYTES};
 use serde::Deserialize;
+use std::collections::BTreeMap;
 
 /// Default table extents: 1 m across (x), 2 m along (y).
 pub const DEFAULT_TABLE_WIDTH_M: f32 = 1.0;
 pub const DEFAULT_TABLE_LENGTH_M: f32 = 2.0;
 
+/// Name of the single layout of a config without `[layout.NAME]` sections.
+pub const DEFAULT_LAYOUT_NAME: &str = "default";
+
 /// Default per-transducer output gain when none is configured. Trimmed to 0.5
 /// (−6 dB) for headroom against the delay line's Doppler amplitude gain: an
 /// advancing source at the SOURCE_SPEED_FRACTION = 0.5·c speed limit bunches
@@ -20,15 +30,114 @@ pub const DEFAULT_TABLE_LENGTH_M: f32 = 2.0;
 /// the ±1 output clamp. An explicit `gain` in the layout overrides this.
 pub const DEFAULT_TRANSDUCER_GAIN: f32 = 0.5;
 
-/// Resolved layout consumed by the engine.
+/// Default headroom normaliser time constants. Attack is fast enough to catch
+/// a chord's note-on ramp (the envelope attack is 100 ms); release is slow so
+/// the gain does not pump audibly as individual voices decay.
+pub const DEFAULT_HEADROOM_ATTACK_MS: f32 = 5.0;
+pub const DEFAULT_HEADROOM_RELEASE_MS: f32 = 300.0;
+
+/// Engine-level multi-voice gain normaliser. When enabled, the engine scales
+/// the whole field by `1 / Σ(amplitude · envelope · pressure)` over active
+/// voices whenever that sum exceeds unity, so a single voice is never reduced
+/// while dense chords stay clear of the output clamp.
 #[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HeadroomConfig {
+    pub enabled: bool,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+}
+
+impl Default for HeadroomConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            attack_ms: DEFAULT_HEADROOM_ATTACK_MS,
+            release_ms: DEFAULT_HEADROOM_RELEASE_MS,
+        }
+    }
+}
+
+/// How the engine measures the propagation distance from a source to a
+/// transducer. Wrapped metrics take their periods from the table extents.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DistanceMetric {
+    /// Straight-line distance.
+    #[default]
+    Euclidean,
+    /// The table wraps across x and/or y like a torus, so a wave leaving one
+    /// edge arrives from the opposite one; the shorter way round counts.
+    Torus { wrap_x: bool, wrap_y: bool },
+    /// The table is rolled around `axis` into a cylinder whose circumference
+    /// is that axis' extent; the distance is the chord through it, and z
+    /// moves a transducer off the surface radially.
+    Cylinder { axis: Axis },
+    /// Straight to the transducer nearest the source, then only along the
+    /// layout's transducer edges.
+    Graph,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+impl DistanceMetric {
+    /// The (x, y) periods over which the metric repeats, if any.
+    pub fn periods(&self, table_m: (f32, f32)) -> (Option<f32>, Option<f32>) {
+        match *self {
+            Self::Torus { wrap_x, wrap_y } => {
+                (wrap_x.then_some(table_m.0), wrap_y.then_some(table_m.1))
+            }
+            Self::Cylinder { axis: Axis::X } => (Some(table_m.0), None),
+            Self::Cylinder { axis: Axis::Y } => (None, Some(table_m.1)),
+            Self::Euclidean | Self::Graph => (None, None),
+        }
+    }
+}
+
+/// The coordinate space a layout's positions are in.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LayoutSpace {
+    /// Physical positions on the table.
+    #[default]
+    Table,
+    /// Abstract body coordinates from a `[body]` plan, so a composition
+    /// lands on the same anatomy on differently built rigs.
+    Body,
+}
+
+/// Shortest path in metres between each pair of transducers.
+pub type GraphDistances = [[f32; MAX_TRANSDUCERS]; MAX_TRANSDUCERS];
+
+/// Resolved layout consumed by the engine. Storage is sized for
+/// `MAX_TRANSDUCERS` so the layout crosses the hot-reload ring without
+/// allocating; only the first `count` entries are transducers, the rest stay
+/// zeroed.
+#[derive(Clone, Debug, PartialEq)]
 pub struct TransducerLayout {
-    /// (x, y) in metres, origin at one corner of the table.
-    pub positions: [(f32, f32); TRANSDUCER_COUNT],
+    /// Number of transducers (logical channels), 1..=MAX_TRANSDUCERS.
+    pub count: usize,
+    /// (x, y) in metres, origin at one corner of the table; body
+    /// coordinates instead when `space` is `Body`. Stimuli render here.
+    pub positions: [(f32, f32); MAX_TRANSDUCERS],
+    /// Where the transducers physically sit on the table; equal to
+    /// `positions` in table space.
+    pub physical_positions: [(f32, f32); MAX_TRANSDUCERS],
+    pub space: LayoutSpace,
+    /// z in metres above the table plane; zero for a planar layout.
+    pub heights: [f32; MAX_TRANSDUCERS],
     /// Linear output gain per transducer (1.0 = unity).
-    pub gains: [f32; TRANSDUCER_COUNT],
-    /// (width, length) of the table in metres, for visualisation.
+    pub gains: [f32; MAX_TRANSDUCERS],
+    /// (width, length) of the table, or of the body space, in metres.
     pub table_m: (f32, f32),
+    /// Multi-voice headroom normaliser settings.
+    pub headroom: HeadroomConfig,
+    /// Source-to-transducer distance metric.
+    pub metric: DistanceMetric,
+    /// Paths along the configured edges, for `DistanceMetric::Graph` only.
+    /// Boxed so other layouts do not carry the 16 KiB table.
+    pub graph_m: Option<Box<GraphDistances>>,
 }
 
 impl Default for TransducerLayout {
@@ -47,7 +156,9 @@ impl Default for TransducerLayout {
 }
 
 impl TransducerLayout {
-    /// Cell-centred cols × rows grid over a width × length table.
+    /// Cell-centred cols × rows grid over a width × length table; the grid
+    /// size sets the transducer count. The table dimensions are checked by
+    /// `build_layout`, once for every kind of placement.
     pub fn grid(
         cols: usize,
         rows: usize,
@@ -55,20 +166,15 @@ impl TransducerLayout {
         length_m: f32,
         gain: f32,
     ) -> Result<Self, String> {
-        if cols * rows != TRANSDUCER_COUNT {
+        let count = cols.saturating_mul(rows);
+        if !(1..=MAX_TRANSDUCERS).contains(&count) {
             return Err(format!(
-                "grid is {}x{} = {} transducers; exactly {} required",
-                cols,
-                rows,
-                cols * rows,
-                TRANSDUCER_COUNT
+                "grid is {}x{} = {} transducers; 1 to {} supported",
+                cols, rows, count, MAX_TRANSDUCERS
             ));
         }
-        if !(width_m > 0.0 && length_m > 0.0) {
-            return Err("table dimensions must be positive".into());
-        }
-        let mut positions = [(0.0, 0.0); TRANSDUCER_COUNT];
-        for (i, pos) in positions.iter_mut().enumerate() {
+        let mut positions = [(0.0, 0.0); MAX_TRANSDUCERS];
+        for (i, pos) in positions[..count].iter_mut().enumerate() {
             let col = i % cols;
             let row = i / cols;
             *pos = (
@@ -76,12 +182,182 @@ impl TransducerLayout {
                 (row as f32 + 0.5) * length_m / rows as f32,
             );
         }
+        let mut gains = [0.0; MAX_TRANSDUCERS];
+        gains[..count].fill(gain);
         Ok(Self {
+            count,
             positions,
-            gains: [gain; TRANSDUCER_COUNT],
+            physical_positions: positions,
+            space: LayoutSpace::Table,
+            heights: [0.0; MAX_TRANSDUCERS],
+            gains,
             table_m: (width_m, length_m),
+            headroom: HeadroomConfig::default(),
+            metric: DistanceMetric::Euclidean,
+            graph_m: None,
         })
     }
+
+    /// Transducers placed by layout generators, whose runs must together
+    /// cover channels `0..count` exactly once.
+    fn generated(runs: &[GeneratedRun], width_m: f32, length_m: f32) -> Result<Self, String> {
+        let mut positions = [(0.0, 0.0); MAX_TRANSDUCERS];
+        let mut heights = [0.0; MAX_TRANSDUCERS];
+        let mut gains = [0.0; MAX_TRANSDUCERS];
+        let mut claimed: [Option<&str>; MAX_TRANSDUCERS] = [None; MAX_TRANSDUCERS];
+        let mut count = 0;
+        for run in runs {
+            let end = run.first_channel + run.positions.len();
+            if end > MAX_TRANSDUCERS {
+                return Err(format!(
+                    "{} channels {}..{} exceed the {} supported transducers",
+                    run.kind, run.first_channel, end, MAX_TRANSDUCERS
+                ));
+            }
+            for (i, &position) in run.positions.iter().enumerate() {
+                let channel = run.first_channel + i;
+                if let Some(other) = claimed[channel].replace(run.kind) {
+                    return Err(format!(
+                        "channel {} is placed by both {} and {}",
+                        channel, other, run.kind
+                    ));
+                }
+                positions[channel] = position;
+                heights[channel] = run.z;
+                gains[channel] = run.gain;
+            }
+            count = count.max(end);
+        }
+        if let Some(gap) = claimed[..count].iter().position(Option::is_none) {
+            return Err(format!(
+                "channel {} is not placed by any generator; channels must run 0..{} without gaps",
+                gap, count
+            ));
+        }
+        Ok(Self {
+            count,
+            positions,
+            physical_positions: positions,
+            space: LayoutSpace::Table,
+            heights,
+            gains,
+            table_m: (width_m, length_m),
+            headroom: HeadroomConfig::default(),
+            metric: DistanceMetric::Euclidean,
+            graph_m: None,
+        })
+    }
+
+    /// Positions of the `count` transducers.
+    pub fn positions(&self) -> &[(f32, f32)] {
+        &self.positions[..self.count]
+    }
+
+    /// Heights of the `count` transducers.
+    pub fn heights(&self) -> &[f32] {
+        &self.heights[..self.count]
+    }
+
+    /// Gains of the `count` transducers.
+    pub fn gains(&self) -> &[f32] {
+        &self.gains[..self.count]
+    }
+}
+
+/// Every layout a config declares and the one driving the engine. Layouts
+/// are kept in name order and all share one transducer count.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayoutSet {
+    layouts: Vec<(String, TransducerLayout)>,
+    active: usize,
+}
+
+impl Default for LayoutSet {
+    fn default() -> Self {
+        Self::single(TransducerLayout::default())
+    }
+}
+
+impl LayoutSet {
+    /// A set holding only `layout`, named `DEFAULT_LAYOUT_NAME`.
+    pub fn single(layout: TransducerLayout) -> Self {
+        Self {
+            layouts: vec![(DEFAULT_LAYOUT_NAME.to_string(), layout)],
+            active: 0,
+        }
+    }
+
+    pub fn active(&self) -> &TransducerLayout {
+        &self.layouts[self.active].1
+    }
+
+    pub fn active_name(&self) -> &str {
+        &self.layouts[self.active].0
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.layouts.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Every layout with its name, in name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &TransducerLayout)> {
+        self.layouts
+            .iter()
+            .map(|(name, layout)| (name.as_str(), layout))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names().any(|candidate| candidate == name)
+    }
+
+    /// Make `name` the active layout.
+    pub fn select(&mut self, name: &str) -> Result<(), String> {
+        let index = self
+            .names()
+            .position(|candidate| candidate == name)
+            .ok_or_else(|| format!("no layout named \"{name}\""))?;
+        self.active = index;
+        Ok(())
+    }
+}
+
+/// What the config watcher tells the IPC thread after a reload or a layout
+/// selection. An accepted set is numbered; observers only see it once the
+/// engine reports running that version.
+#[derive(Clone, Debug)]
+pub enum ConfigUpdate {
+    Accepted { version: u64, layouts: LayoutSet },
+    Rejected { error: String },
+}
+
+/// Startup-only settings from the `[server]` section. Command-line options
+/// take precedence over these.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ServerSettings {
+    /// Output device name to open instead of the automatic multichannel
+    /// search.
+    pub device: Option<String>,
+    /// Logical channel ranges spread across several output devices, each
+    /// with its own stream. Empty for the usual single device.
+    pub outputs: Vec<OutputSplit>,
+    /// Scheduling and memory locking for the audio threads.
+    pub realtime: RealtimeSettings,
+}
+
+/// One device of a multi-device split: logical channels
+/// `first_channel..first_channel + channel_count` play on its outputs
+/// 1..=channel_count.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputSplit {
+    pub device: String,
+    pub first_channel: usize,
+    pub channel_count: usize,
+}
+
+impl OutputSplit {
+    pub fn channels(&self) -> std::ops::Range<usize> {
+        self.first_channel..self.first_channel + self.channel_count
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -95,6 +371,117 @@ struct RawConfig {
     grid: Option<RawGrid>,
     #[serde(default, rename = "transducer")]
     transducers: Vec<RawTransducer>,
+    #[serde(default)]
+    ring: Vec<RawRing>,
+    #[serde(default)]
+    arc: Vec<RawArc>,
+    #[serde(default)]
+    line: Vec<RawLine>,
+    #[serde(default)]
+    hex: Vec<RawHex>,
+    metric: Option<RawMetric>,
+    body: Option<RawBody>,
+    #[serde(default)]
+    layout: BTreeMap<String, RawLayout>,
+    active_layout: Option<String>,
+    headroom: Option<RawHeadroom>,
+    server: Option<RawServer>,
+}
+
+impl RawConfig {
+    /// The layout described by the top-level sections, for a config without
+    /// named layouts; `None` if it has none of them.
+    fn take_top_level_layout(&mut self) -> Option<RawLayout> {
+        let layout = RawLayout {
+            table: self.table.take(),
+            grid: self.grid.take(),
+            transducers: std::mem::take(&mut self.transducers),
+            ring: std::mem::take(&mut self.ring),
+            arc: std::mem::take(&mut self.arc),
+            line: std::mem::take(&mut self.line),
+            hex: std::mem::take(&mut self.hex),
+            metric: self.metric.take(),
+            body: self.body.take(),
+        };
+        let empty = layout.table.is_none()
+            && layout.grid.is_none()
+            && layout.metric.is_none()
+            && layout.body.is_none()
+            && layout.transducers.is_empty()
+            && layout.ring.is_empty()
+            && layout.arc.is_empty()
+            && layout.line.is_empty()
+            && layout.hex.is_empty();
+        (!empty).then_some(layout)
+    }
+}
+
+/// One layout: the top level of a config, or a `[layout.NAME]` section.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawLayout {
+    table: Option<RawTable>,
+    grid: Option<RawGrid>,
+    #[serde(default, rename = "transducer")]
+    transducers: Vec<RawTransducer>,
+    #[serde(default)]
+    ring: Vec<RawRing>,
+    #[serde(default)]
+    arc: Vec<RawArc>,
+    #[serde(default)]
+    line: Vec<RawLine>,
+    #[serde(default)]
+    hex: Vec<RawHex>,
+    metric: Option<RawMetric>,
+    body: Option<RawBody>,
+}
+
+/// A body plan: a `width_m` × `length_m` body coordinate space with named
+/// polygonal regions in it.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawBody {
+    width_m: f32,
+    length_m: f32,
+    #[serde(default, rename = "region")]
+    regions: Vec<RawRegion>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRegion {
+    name: String,
+    polygon: Vec<[f32; 2]>,
+}
+
+/// `kind` is "euclidean" (the default), "torus" wrapping the `wrap` axes
+/// (default both), "cylinder" rolled around `axis`, or "graph" along the
+/// undirected transducer `edges`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawMetric {
+    kind: String,
+    wrap: Option<Vec<String>>,
+    axis: Option<String>,
+    edges: Option<Vec<[usize; 2]>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawServer {
+    device: Option<String>,
+    output: Option<Vec<RawOutput>>,
+    realtime: Option<String>,
+    realtime_priority: Option<i32>,
+    lock_memory: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawOutput {
+    device: String,
+    first_channel: usize,
+    channel_count: usize,
 }
 
 #[derive(Deserialize)]
@@ -109,76 +496,803 @@ struct RawTable {
 struct RawGrid {
     cols: usize,
     rows: usize,
+    z: Option<f32>,
+    gain: Option<f32>,
+}
+
+/// `count` transducers evenly around a full circle, starting at `start_deg`
+/// (0° along +x, 90° along +y) and turning `direction` "ccw" (towards +y,
+/// the default) or "cw".
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRing {
+    first_channel: usize,
+    count: usize,
+    centre: [f32; 2],
+    radius_m: f32,
+    start_deg: Option<f32>,
+    direction: Option<String>,
+    z: Option<f32>,
+    gain: Option<f32>,
+}
+
+/// `count` transducers evenly along a circular arc from `start_deg` to
+/// `end_deg` inclusive, measured as for a ring.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawArc {
+    first_channel: usize,
+    count: usize,
+    centre: [f32; 2],
+    radius_m: f32,
+    start_deg: f32,
+    end_deg: f32,
+    z: Option<f32>,
+    gain: Option<f32>,
+}
+
+/// `count` transducers evenly along a straight line, both ends included.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawLine {
+    first_channel: usize,
+    count: usize,
+    from: [f32; 2],
+    to: [f32; 2],
+    z: Option<f32>,
+    gain: Option<f32>,
+}
+
+/// A hexagonal grid: rows of `cols` at `spacing_m` along x, rows
+/// `spacing_m * √3/2` apart along y, odd rows shifted half a spacing, so
+/// every neighbour is `spacing_m` away. The first transducer sits at
+/// `origin`; channels run along each row first.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawHex {
+    first_channel: usize,
+    cols: usize,
+    rows: usize,
+    origin: [f32; 2],
+    spacing_m: f32,
+    z: Option<f32>,
     gain: Option<f32>,
 }
 
+/// One generator's transducers, in channel order from `first_channel`, all
+/// at height `z`.
+struct GeneratedRun {
+    kind: &'static str,
+    first_channel: usize,
+    positions: Vec<(f32, f32)>,
+    z: f32,
+    gain: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawHeadroom {
+    enabled: Option<bool>,
+    attack_ms: Option<f32>,
+    release_ms: Option<f32>,
+}
+
 #[derive(Deserialize)]
 #[serde(deny_unknown_fields)]
 struct RawTransducer {
     channel: usize,
-    x: f32,
-    y: f32,
+    x: Option<f32>,
+    y: Option<f32>,
+    z: Option<f32>,
+    body: Option<[f32; 2]>,
+    region: Option<String>,
     gain: Option<f32>,
 }
 
-/// Parse a TOML document into a layout. The `[grid]` section (or the default
-/// 4×8 grid) lays out all transducers; `[[transducer]]` entries then override
-/// individual channels.
-pub fn parse_layout(text: &str) -> Result<TransducerLayout, String> {
-    let raw: RawConfig = toml::from_str(text).map_err(|e| format!("TOML parse error: {}", e))?;
+/// Parse a TOML document into its layouts and startup server settings. In
+/// each layout the `[grid]` section (or the default 4×8 grid) sets the
+/// transducer count and lays them out, unless `[[ring]]`, `[[arc]]`,
+/// `[[line]]` or `[[hex]]` generators place them instead; `[[transducer]]`
+/// entries then override individual channels. `[layout.NAME]` sections each
+/// hold the same keys as the top level and replace it; `active_layout` picks
+/// the one the server starts with.
+pub fn parse_config(text: &str) -> Result<(LayoutSet, ServerSettings), String> {
+    parse_config_with_warnings(text, &mut Vec::new())
+}
+
+/// `parse_config` for `--check-config`: also returns the warnings for
+/// entries that parse but are likely mistakes.
+pub fn check_config(text: &str) -> Result<(LayoutSet, Vec<String>), String> {
+    let mut warnings = Vec::new();
+    let (layouts, _) = parse_config_with_warnings(text, &mut warnings)?;
+    Ok((layouts, warnings))
+}
+
+fn parse_config_with_warnings(
+    text: &str,
+    warnings: &mut Vec<String>,
+) -> Result<(LayoutSet, ServerSettings), String> {
+    let mut raw: RawConfig =
+        toml::from_str(text).map_err(|e| format!("TOML parse error: {}", e))?;
+
+    let headroom = parse_headroom(raw.headroom.as_ref())?;
+    let top_level = raw.take_top_level_layout();
+    let named = std::mem::take(&mut raw.layout);
+    let layouts = if named.is_empty() {
+        if raw.active_layout.is_some() {
+            return Err("active_layout needs [layout.NAME] sections".into());
+        }
+        let layout = build_layout(&top_level.unwrap_or_default(), headroom, warnings)?;
+        LayoutSet::single(layout)
+    } else {
+        if top_level.is_some() {
+            return Err("with [layout.NAME] sections, put every layout section inside one".into());
+        }
+        if named.len() > MAX_LAYOUTS {
+            return Err(format!(
+                "{} layouts declared; at most {} supported",
+                named.len(),
+                MAX_LAYOUTS
+            ));
+        }
+        let mut layouts = Vec::with_capacity(named.len());
+        for (name, raw_layout) in &named {
+            if name.len() > MAX_LAYOUT_NAME_BYTES {
+                return Err(format!(
+                    "layout name \"{name}\" is longer than {MAX_LAYOUT_NAME_BYTES} bytes"
+                ));
+            }
+            let mut layout_warnings = Vec::new();
+            let layout = build_layout(raw_layout, headroom, &mut layout_warnings)
+                .map_err(|e| format!("layout \"{name}\": {e}"))?;
+            warnings.extend(
+                layout_warnings
+                    .into_iter()
+                    .map(|w| format!("layout \"{name}\": {w}")),
+            );
+            if let Some((first, other)) = layouts.first() {
+                let other: &TransducerLayout = other;
+                if other.count != layout.count {
+                    return Err(format!(
+                        "layout \"{name}\" has {} transducers but \"{first}\" has {}; all layouts must share one count",
+                        layout.count, other.count
+                    ));
+                }
+            }
+            layouts.push((name.clone(), layout));
+        }
+        let mut set = LayoutSet { layouts, active: 0 };
+        match raw.active_layout.as_deref() {
+            Some(name) => set
+                .select(name)
+                .map_err(|e| format!("active_layout: {e}"))?,
+            None if named.len() > 1 => {
+                return Err("active_layout must name the starting layout".into());
+            }
+            None => {}
+        }
+        set
+    };
+    let count = layouts.active().count;
+
+    let mut server = ServerSettings::default();
+    if let Some(s) = raw.server {
+        if s.device
+            .as_deref()
+            .is_some_and(|name| name.trim().is_empty())
+        {
+            return Err("server device must not be empty".into());
+        }
+        server.device = s.device;
+        server.outputs = parse_outputs(s.output.unwrap_or_default(), count)?;
+        if server.device.is_some() && !server.outputs.is_empty() {
+            return Err("server device and [[server.output]] are mutually exclusive".into());
+        }
+        if let Some(mode) = s.realtime {
+            server.realtime.mode = RealtimeMode::parse(&mode)?;
+        }
+        if let Some(priority) = s.realtime_priority {
+            if !(1..=99).contains(&priority) {
+                return Err(format!(
+                    "realtime_priority is {priority}; 1 to 99 supported"
+                ));
+            }
+            server.realtime.priority = priority;
+        }
+        server.realtime.lock_memory = s.lock_memory.unwrap_or(false);
+    }
+
+    Ok((layouts, server))
+}
 
+/// Resolve one layout's table, placement and per-transducer overrides.
+fn build_layout(
+    raw: &RawLayout,
+    headroom: HeadroomConfig,
+    warnings: &mut Vec<String>,
+) -> Result<TransducerLayout, String> {
     let (width_m, length_m) = match &raw.table {
         Some(t) => (t.width_m, t.length_m),
         None => (DEFAULT_TABLE_WIDTH_M, DEFAULT_TABLE_LENGTH_M),
     };
+    // Also rejects NaN; infinite tables would place transducers at infinity
+    if !(width_m > 0.0 && length_m > 0.0 && width_m.is_finite() && length_m.is_finite()) {
+        return Err("table dimensions must be positive and finite".into());
+    }
 
+    let runs = generate_runs(raw)?;
     let mut layout = match &raw.grid {
-        Some(g) => TransducerLayout::grid(
-            g.cols,
-            g.rows,
-            width_m,
-            length_m,
-            g.gain.unwrap_or(DEFAULT_TRANSDUCER_GAIN),
-        )?,
+        Some(_) if !runs.is_empty() => {
+            return Err("use either [grid] or layout generators, not both".into());
+        }
+        None if !runs.is_empty() => TransducerLayout::generated(&runs, width_m, length_m)?,
+        Some(g) => {
+            let mut layout = TransducerLayout::grid(
+                g.cols,
+                g.rows,
+                width_m,
+                length_m,
+                g.gain.unwrap_or(DEFAULT_TRANSDUCER_GAIN),
+            )?;
+            let z = g.z.unwrap_or(0.0);
+            if !z.is_finite() {
+                return Err("grid z must be finite".into());
+            }
+            layout.heights[..layout.count].fill(z);
+            layout
+        }
         None => TransducerLayout::grid(4, 8, width_m, length_m, DEFAULT_TRANSDUCER_GAIN)?,
     };
 
+    let regions = match &raw.body {
+        Some(body) => parse_regions(body)?,
+        None => Vec::new(),
+    };
+    let mut body_positions = [None; MAX_TRANSDUCERS];
+    let mut overridden = [0usize; MAX_TRANSDUCERS];
     for t in &raw.transducers {
-        if t.channel >= TRANSDUCER_COUNT {
+        if t.channel >= layout.count {
             return Err(format!(
                 "transducer channel {} out of range (0-{})",
                 t.channel,
-                TRANSDUCER_COUNT - 1
+                layout.count - 1
+            ));
+        }
+        overridden[t.channel] += 1;
+        if overridden[t.channel] == 2 {
+            warnings.push(format!(
+                "transducer {} is overridden more than once; later entries win",
+                t.channel
             ));
         }
-        if !(t.x.is_finite() && t.y.is_finite()) {
+        let (x, y) = layout.positions[t.channel];
+        let (x, y) = (t.x.unwrap_or(x), t.y.unwrap_or(y));
+        let z = t.z.unwrap_or(layout.heights[t.channel]);
+        if !(x.is_finite() && y.is_finite() && z.is_finite()) {
             return Err(format!("transducer {} has non-finite position", t.channel));
         }
-        layout.positions[t.channel] = (t.x, t.y);
+        layout.positions[t.channel] = (x, y);
+        layout.heights[t.channel] = z;
         if let Some(gain) = t.gain {
             layout.gains[t.channel] = gain;
         }
+        if (t.body.is_some() || t.region.is_some()) && raw.body.is_none() {
+            return Err(format!(
+                "transducer {} has a body coordinate but there is no [body] plan",
+                t.channel
+            ));
+        }
+        body_positions[t.channel] = body_position(t, &regions)?;
+    }
+
+    layout.physical_positions = layout.positions;
+    if let Some(body) = &raw.body {
+        for (channel, position) in body_positions[..layout.count].iter().enumerate() {
+            let Some((u, v)) = *position else {
+                return Err(format!(
+                    "transducer {channel} has no body coordinate; give it body = [u, v] or a region"
+                ));
+            };
+            if !(0.0..=body.width_m).contains(&u) || !(0.0..=body.length_m).contains(&v) {
+                return Err(format!(
+                    "transducer {channel} body coordinate ({u}, {v}) is outside the body space"
+                ));
+            }
+            layout.positions[channel] = (u, v);
+        }
+        // Body space is a surface; physical heights stay on the table side
+        layout.heights = [0.0; MAX_TRANSDUCERS];
+        layout.table_m = (body.width_m, body.length_m);
+        layout.space = LayoutSpace::Body;
     }
 
-    for (i, &gain) in layout.gains.iter().enumerate() {
+    for (i, &gain) in layout.gains().iter().enumerate() {
         if !gain.is_finite() || gain < 0.0 {
             return Err(format!("gain for transducer {} must be finite and >= 0", i));
         }
     }
 
+    layout.headroom = headroom;
+    if let Some(metric) = &raw.metric {
+        parse_metric(metric, &mut layout)?;
+    }
     Ok(layout)
 }
 
-pub fn load_layout(path: &std::path::Path) -> Result<TransducerLayout, String> {
-    let text = std::fs::read_to_string(path)
-        .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
-    parse_layout(&text)
+/// The `[body]` plan's regions, checked: unique names and simple-enough
+/// polygons of at least three finite vertices.
+fn parse_regions(body: &RawBody) -> Result<Vec<&RawRegion>, String> {
+    if !(body.width_m > 0.0 && body.length_m > 0.0) {
+        return Err("body dimensions must be positive".into());
+    }
+    let mut regions: Vec<&RawRegion> = Vec::with_capacity(body.regions.len());
+    for region in &body.regions {
+        let name = region.name.as_str();
+        if name.trim().is_empty() {
+            return Err("body region names must not be empty".into());
+        }
+        if regions.iter().any(|other| other.name == name) {
+            return Err(format!("body region \"{name}\" is declared twice"));
+        }
+        if region.polygon.len() < 3
+            || region.polygon.iter().flatten().any(|v| !v.is_finite())
+            || polygon_area(&region.polygon).abs() < 1e-9
+        {
+            return Err(format!(
+                "body region \"{name}\" needs a polygon of at least 3 finite, non-collinear vertices"
+            ));
+        }
+        regions.push(region);
+    }
+    Ok(regions)
+}
+
+/// A transducer entry's body coordinate: its explicit `body`, which must lie
+/// inside its `region` if it names one, or else the region's centroid.
+fn body_position(t: &RawTransducer, regions: &[&RawRegion]) -> Result<Option<(f32, f32)>, String> {
+    let polygon = match &t.region {
+        Some(name) => Some(
+            regions
+                .iter()
+                .find(|region| &region.name == name)
+                .map(|region| region.polygon.as_slice())
+                .ok_or_else(|| {
+                    format!(
+                        "transducer {} names unknown body region \"{name}\"",
+                        t.channel
+                    )
+                })?,
+        ),
+        None => None,
+    };
+    match (t.body, polygon) {
+        (Some([u, v]), polygon) => {
+            if !(u.is_finite() && v.is_finite()) {
+                return Err(format!(
+                    "transducer {} has a non-finite body coordinate",
+                    t.channel
+                ));
+            }
+            if polygon.is_some_and(|polygon| !polygon_contains(polygon, (u, v))) {
+                return Err(format!(
+                    "transducer {} body coordinate ({u}, {v}) is outside its region",
+                    t.channel
+                ));
+            }
+            Ok(Some((u, v)))
+        }
+        (None, Some(polygon)) => Ok(Some(polygon_centroid(polygon))),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Signed area by the shoelace formula; positive when counter-clockwise.
+fn polygon_area(polygon: &[[f32; 2]]) -> f32 {
+    let mut twice_area = 0.0;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        twice_area += a[0] * b[1] - b[0] * a[1];
+    }
+    0.5 * twice_area
+}
+
+fn polygon_centroid(polygon: &[[f32; 2]]) -> (f32, f32) {
+    let area = polygon_area(polygon);
+    let (mut cx, mut cy) = (0.0, 0.0);
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        let cross = a[0] * b[1] - b[0] * a[1];
+        cx += (a[0] + b[0]) * cross;
+        cy += (a[1] + b[1]) * cross;
+    }
+    (cx / (6.0 * area), cy / (6.0 * area))
+}
+
+/// Even-odd ray casting; points on an edge may land either side.
+fn polygon_contains(polygon: &[[f32; 2]], (x, y): (f32, f32)) -> bool {
+    let mut inside = false;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        if (a[1] > y) != (b[1] > y) && x < a[0] + (y - a[1]) * (b[0] - a[0]) / (b[1] - a[1]) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Apply a `[metric]` section to a placed layout; graph path lengths come
+/// from the final transducer positions.
+fn parse_metric(raw: &RawMetric, layout: &mut TransducerLayout) -> Result<(), String> {
+    let parse_axis = |name: &str| match name {
+        "x" => Ok(Axis::X),
+        "y" => Ok(Axis::Y),
+        other => Err(format!(
+            "metric axis must be \"x\" or \"y\", not \"{other}\""
+        )),
+    };
+    let unused = |key: &str, present: bool| {
+        if present {
+            Err(format!(
+                "metric {key} does not apply to kind \"{}\"",
+                raw.kind
+            ))
+        } else {
+            Ok(())
+        }
+    };
+    if raw.kind != "torus" {
+        unused("wrap", raw.wrap.is_some())?;
+    }
+    if raw.kind != "cylinder" {
+        unused("axis", raw.axis.is_some())?;
+    }
+    if raw.kind != "graph" {
+        unused("edges", raw.edges.is_some())?;
+    }
+    layout.metric = match raw.kind.as_str() {
+        "euclidean" => DistanceMetric::Euclidean,
+        "torus" => {
+            let (mut wrap_x, mut wrap_y) = (false, false);
+            for name in raw.wrap.as_deref().unwrap_or(&["x".into(), "y".into()]) {
+                match parse_axis(name)? {
+                    Axis::X => wrap_x = true,
+                    Axis::Y => wrap_y = true,
+                }
+            }
+            if !(wrap_x || wrap_y) {
+                return Err("torus metric must wrap at least one axis".into());
+            }
+            DistanceMetric::Torus { wrap_x, wrap_y }
+        }
+        "cylinder" => DistanceMetric::Cylinder {
+            axis: parse_axis(raw.axis.as_deref().ok_or("cylinder metric needs an axis")?)?,
+        },
+        "graph" => {
+            let edges = raw.edges.as_deref().unwrap_or_default();
+            layout.graph_m = Some(graph_paths(layout, edges)?);
+            DistanceMetric::Graph
+        }
+        other => {
+            return Err(format!(
+                "metric kind must be \"euclidean\", \"torus\", \"cylinder\" or \"graph\", not \"{other}\""
+            ))
+        }
+    };
+    // Wrapping divides by the extent, which a body plan sets too
+    let (period_x, period_y) = layout.metric.periods(layout.table_m);
+    if [period_x, period_y]
+        .into_iter()
+        .flatten()
+        .any(|period| !(period.is_finite() && period > 0.0))
+    {
+        return Err(format!(
+            "{} metric needs a finite, positive extent to wrap across",
+            raw.kind
+        ));
+    }
+    Ok(())
+}
+
+/// All-pairs shortest paths along undirected edges, each as long as the
+/// straight line between its transducers. Every transducer must be reachable
+/// so that no propagation distance is infinite.
+fn graph_paths(
+    layout: &TransducerLayout,
+    edges: &[[usize; 2]],
+) -> Result<Box<GraphDistances>, String> {
+    let n = layout.count;
+    let mut paths = Box::new([[f32::INFINITY; MAX_TRANSDUCERS]; MAX_TRANSDUCERS]);
+    for (i, row) in paths[..n].iter_mut().enumerate() {
+        row[i] = 0.0;
+    }
+    for &[a, b] in edges {
+        if a >= n || b >= n {
+            return Err(format!(
+                "graph edge [{a}, {b}] is out of range (0-{})",
+                n - 1
+            ));
+        }
+        if a == b {
+            return Err(format!(
+                "graph edge [{a}, {b}] joins a transducer to itself"
+            ));
+        }
+        let (pa, pb) = (layout.positions[a], layout.positions[b]);
+        let dz = layout.heights[a] - layout.heights[b];
+        let length = ((pa.0 - pb.0).powi(2) + (pa.1 - pb.1).powi(2) + dz * dz).sqrt();
+        paths[a][b] = paths[a][b].min(length);
+        paths[b][a] = paths[a][b];
+    }
+    // Floyd–Warshall; at most 64³ steps, once per load
+    for k in 0..n {
+        for i in 0..n {
+            for j in 0..n {
+                let via = paths[i][k] + paths[k][j];
+                if via < paths[i][j] {
+                    paths[i][j] = via;
+                }
+            }
+        }
+    }
+    if let Some(i) = (1..n).find(|&i| paths[0][i].is_infinite()) {
+        return Err(format!(
+            "graph metric: transducer {i} is not connected to transducer 0"
+        ));
+    }
+    for row in &mut paths[n..] {
+        row.fill(0.0);
+    }
+    for row in &mut paths[..n] {
+        row[n..].fill(0.0);
+    }
+    Ok(paths)
+}
+
+/// The `[headroom]` section, shared by every layout.
+fn parse_headroom(raw: Option<&RawHeadroom>) -> Result<HeadroomConfig, String> {
+    let defaults = HeadroomConfig::default();
+    let Some(h) = raw else {
+        return Ok(defaults);
+    };
+    let headroom = HeadroomConfig {
+        // Writing a [headroom] section opts in unless it says otherwise.
+        enabled: h.enabled.unwrap_or(true),
+        attack_ms: h.attack_ms.unwrap_or(defaults.attack_ms),
+        release_ms: h.release_ms.unwrap_or(defaults.release_ms),
+    };
+    for (name, value) in [
+        ("attack_ms", headroom.attack_ms),
+        ("release_ms", headroom.release_ms),
+    ] {
+        if !value.is_finite() || value <= 0.0 {
+            return Err(format!("headroom {} must be finite and > 0", name));
+        }
+    }
+    Ok(headroom)
+}
+
+/// Expand every layout generator into its run of positions, checking each
+/// generator's own parameters; `TransducerLayout::generated` checks how the
+/// runs fit together.
+fn generate_runs(raw: &RawLayout) -> Result<Vec<GeneratedRun>, String> {
+    let finite = |kind: &str, values: &[f32]| {
+        if values.iter().all(|v| v.is_finite()) {
+            Ok(())
+        } else {
+            Err(format!("{kind} values must be finite"))
+        }
+    };
+    let counted = |kind: &str, count: usize| {
+        if (1..=MAX_TRANSDUCERS).contains(&count) {
+            Ok(())
+        } else {
+            Err(format!(
+                "{kind} count is {count}; 1 to {MAX_TRANSDUCERS} supported"
+            ))
+        }
+    };
+    let positive = |kind: &str, name: &str, value: f32| {
+        if value > 0.0 {
+            Ok(())
+        } else {
+            Err(format!("{kind} {name} must be > 0"))
+        }
+    };
+    let on_circle = |centre: [f32; 2], radius: f32, degrees: f32| {
+        let angle = degrees.to_radians();
+        (
+            centre[0] + radius * angle.cos(),
+            centre[1] + radius * angle.sin(),
+        )
+    };
+    let gain = |gain: Option<f32>| gain.unwrap_or(DEFAULT_TRANSDUCER_GAIN);
+
+    let mut runs = Vec::new();
+    for r in &raw.ring {
+        finite(
+            "ring",
+            &[
+                r.centre[0],
+                r.centre[1],
+                r.radius_m,
+                r.start_deg.unwrap_or(0.0),
+                r.z.unwrap_or(0.0),
+            ],
+        )?;
+        counted("ring", r.count)?;
+        positive("ring", "radius_m", r.radius_m)?;
+        let turn = match r.direction.as_deref().unwrap_or("ccw") {
+            "ccw" => 1.0,
+            "cw" => -1.0,
+            other => {
+                return Err(format!(
+                    "ring direction must be \"ccw\" or \"cw\", not \"{other}\""
+                ))
+            }
+        };
+        let step = turn * 360.0 / r.count as f32;
+        let start = r.start_deg.unwrap_or(0.0);
+        runs.push(GeneratedRun {
+            kind: "ring",
+            first_channel: r.first_channel,
+            positions: (0..r.count)
+                .map(|i| on_circle(r.centre, r.radius_m, start + step * i as f32))
+                .collect(),
+            z: r.z.unwrap_or(0.0),
+            gain: gain(r.gain),
+        });
+    }
+    for a in &raw.arc {
+        finite(
+            "arc",
+            &[
+                a.centre[0],
+                a.centre[1],
+                a.radius_m,
+                a.start_deg,
+                a.end_deg,
+                a.z.unwrap_or(0.0),
+            ],
+        )?;
+        counted("arc", a.count)?;
+        positive("arc", "radius_m", a.radius_m)?;
+        let step = (a.end_deg - a.start_deg) / (a.count - 1).max(1) as f32;
+        runs.push(GeneratedRun {
+            kind: "arc",
+            first_channel: a.first_channel,
+            positions: (0..a.count)
+                .map(|i| on_circle(a.centre, a.radius_m, a.start_deg + step * i as f32))
+                .collect(),
+            z: a.z.unwrap_or(0.0),
+            gain: gain(a.gain),
+        });
+    }
+    for l in &raw.line {
+        finite(
+            "line",
+            &[l.from[0], l.from[1], l.to[0], l.to[1], l.z.unwrap_or(0.0)],
+        )?;
+        counted("line", l.count)?;
+        let steps = (l.count - 1).max(1) as f32;
+        runs.push(GeneratedRun {
+            kind: "line",
+            first_channel: l.first_channel,
+            positions: (0..l.count)
+                .map(|i| {
+                    let t = i as f32 / steps;
+                    (
+                        l.from[0] + (l.to[0] - l.from[0]) * t,
+                        l.from[1] + (l.to[1] - l.from[1]) * t,
+                    )
+                })
+                .collect(),
+            z: l.z.unwrap_or(0.0),
+            gain: gain(l.gain),
+        });
+    }
+    for h in &raw.hex {
+        finite(
+            "hex",
+            &[h.origin[0], h.origin[1], h.spacing_m, h.z.unwrap_or(0.0)],
+        )?;
+        counted("hex", h.cols.saturating_mul(h.rows))?;
+        positive("hex", "spacing_m", h.spacing_m)?;
+        let row_pitch = h.spacing_m * 3.0f32.sqrt() / 2.0;
+        runs.push(GeneratedRun {
+            kind: "hex",
+            first_channel: h.first_channel,
+            positions: (0..h.cols * h.rows)
+                .map(|i| {
+                    let (row, col) = (i / h.cols, i % h.cols);
+                    let shift = if row % 2 == 1 { 0.5 } else { 0.0 };
+                    (
+                        h.origin[0] + (col as f32 + shift) * h.spacing_m,
+                        h.origin[1] + row as f32 * row_pitch,
+                    )
+                })
+                .collect(),
+            z: h.z.unwrap_or(0.0),
+            gain: gain(h.gain),
+        });
+    }
+    Ok(runs)
+}
+
+fn parse_outputs(raw: Vec<RawOutput>, count: usize) -> Result<Vec<OutputSplit>, String> {
+    let mut claimed = [false; MAX_TRANSDUCERS];
+    let mut outputs: Vec<OutputSplit> = Vec::with_capacity(raw.len());
+    for output in raw {
+        let split = OutputSplit {
+            device: output.device,
+            first_channel: output.first_channel,
+            channel_count: output.channel_count,
+        };
+        if split.device.trim().is_empty() {
+            return Err("output device must not be empty".into());
+        }
+        if outputs.iter().any(|other| other.device == split.device) {
+            return Err(format!(
+                "output device \"{}\" is listed twice",
+                split.device
+            ));
+        }
+        if split.channel_count == 0 || split.channels().end > count {
+            return Err(format!(
+                "output \"{}\" channels {}..{} must be a non-empty range within 0..{}",
+                split.device,
+                split.first_channel,
+                split.channels().end,
+                count
+            ));
+        }
+        for channel in split.channels() {
+            if std::mem::replace(&mut claimed[channel], true) {
+                return Err(format!(
+                    "logical channel {} is mapped to more than one output",
+                    channel
+                ));
+            }
+        }
+        outputs.push(split);
+    }
+    if outputs.len() == 1 {
+        return Err(
+            "[[server.output]] needs at least two devices; use server device for one".into(),
+        );
+    }
+    Ok(outputs)
+}
+
+fn read_config(path: &std::path::Path) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))
+}
+
+/// Load the layouts for hot reload, which must keep the running transducer
+/// count.
+pub fn load_layouts(path: &std::path::Path, count: usize) -> Result<LayoutSet, String> {
+    let (layouts, _) = parse_config(&read_config(path)?)?;
+    let loaded = layouts.active().count;
+    if loaded != count {
+        return Err(format!(
+            "transducer count changed from {} to {}; restart the server to apply it",
+            count, loaded
+        ));
+    }
+    Ok(layouts)
+}
+
+pub fn load_config(path: &std::path::Path) -> Result<(LayoutSet, ServerSettings), String> {
+    parse_config(&read_config(path)?)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn parse_layout(text: &str) -> Result<TransducerLayout, String> {
+        parse_config(text).map(|(layouts, _)| layouts.active().clone())
+    }
+
     #[test]
     fn default_layout_is_cell_centred_4x8_over_1x2m() {
         let layout = TransducerLayout::default();
@@ -186,9 +1300,10 @@ mod tests {
         assert_eq!(layout.positions[0], (0.125, 0.125));
         assert_eq!(layout.positions[3], (0.875, 0.125)); // end of first row
         assert_eq!(layout.positions[31], (0.875, 1.875));
-        assert!(layout.gains.iter().all(|&g| g == DEFAULT_TRANSDUCER_GAIN));
+        assert_eq!(layout.count, 32);
+        assert!(layout.gains().iter().all(|&g| g == DEFAULT_TRANSDUCER_GAIN));
         // All positions inside the table
-        for &(x, y) in layout.positions.iter() {
+        for &(x, y) in layout.positions() {
             assert!(x > 0.0 && x < DEFAULT_TABLE_WIDTH_M);
             assert!(y > 0.0 && y < DEFAULT_TABLE_LENGTH_M);
         }
@@ -217,7 +1332,42 @@ mod tests {
         assert_eq!(layout.positions[0], (0.125, 0.5));
         assert_eq!(layout.positions[7], (1.875, 0.5));
         assert_eq!(layout.positions[31], (1.875, 3.5));
-        assert!(layout.gains.iter().all(|&g| g == 0.5));
+        assert!(layout.gains().iter().all(|&g| g == 0.5));
+    }
+
+    #[test]
+    fn grid_size_sets_the_transducer_count() {
+        let wearable = parse_layout("[grid]\ncols = 3\nrows = 4").unwrap();
+        assert_eq!(wearable.count, 12);
+        assert_eq!(wearable.positions().len(), 12);
+        assert_eq!(wearable.positions[11], (5.0 / 6.0, 1.75));
+        assert!(wearable.gains[12..].iter().all(|&g| g == 0.0));
+        assert!(parse_layout("[[transducer]]\nchannel = 11\nx = 0.0\ny = 0.0").is_ok());
+        assert!(parse_layout(
+            "[grid]\ncols = 3\nrows = 4\n[[transducer]]\nchannel = 12\nx = 0.0\ny = 0.0"
+        )
+        .is_err());
+
+        let floor = parse_layout("[grid]\ncols = 8\nrows = 8").unwrap();
+        assert_eq!(floor.count, 64);
+        assert!(parse_layout("[grid]\ncols = 5\nrows = 13").is_err());
+        assert!(parse_layout("[grid]\ncols = 0\nrows = 8").is_err());
+        // A split may only map configured channels
+        let split = "[grid]\ncols = 3\nrows = 4\n\
+            [[server.output]]\ndevice = \"A\"\nfirst_channel = 0\nchannel_count = 8\n\
+            [[server.output]]\ndevice = \"B\"\nfirst_channel = 8\nchannel_count = ";
+        assert!(parse_config(&format!("{split}4")).is_ok());
+        assert!(parse_config(&format!("{split}8")).is_err());
+    }
+
+    #[test]
+    fn hot_reload_keeps_the_transducer_count() {
+        let path = std::env::temp_dir().join(format!("haptic-count-{}.toml", std::process::id()));
+        std::fs::write(&path, "[grid]\ncols = 3\nrows = 4").unwrap();
+        assert_eq!(load_layouts(&path, 12).unwrap().active().count, 12);
+        let error = load_layouts(&path, 32).unwrap_err();
+        assert!(error.contains("restart"), "{error}");
+        std::fs::remove_file(&path).ok();
     }
 
     #[test]
@@ -242,10 +1392,463 @@ mod tests {
         assert_eq!(layout.gains[0], DEFAULT_TRANSDUCER_GAIN);
     }
 
+    #[test]
+    fn z_coordinates_come_from_the_grid_generators_and_overrides() {
+        let layout = parse_layout(
+            r#"
+            [grid]
+            cols = 4
+            rows = 8
+            z = 0.1
+
+            [[transducer]]
+            channel = 3
+            x = 0.5
+            y = 1.0
+            z = -0.2
+
+            [[transducer]]
+            channel = 4
+            x = 0.5
+            y = 1.5
+            "#,
+        )
+        .unwrap();
+        assert_eq!(layout.heights()[0], 0.1);
+        assert_eq!(layout.heights()[3], -0.2);
+        // An override without z keeps the grid's height
+        assert_eq!(layout.heights()[4], 0.1);
+        assert!(TransducerLayout::default()
+            .heights()
+            .iter()
+            .all(|&z| z == 0.0));
+
+        let ring = parse_layout(
+            "[[ring]]\nfirst_channel = 0\ncount = 4\ncentre = [0.5, 1.0]\nradius_m = 0.2\nz = 0.3",
+        )
+        .unwrap();
+        assert_eq!(ring.heights(), &[0.3; 4]);
+        assert!(parse_layout("[grid]\ncols = 4\nrows = 8\nz = inf").is_err());
+    }
+
+    #[test]
+    fn ring_and_line_generators_combine_at_their_channel_offsets() {
+        let layout = parse_layout(
+            r#"
+            [[ring]]
+            first_channel = 0
+            count = 4
+            centre = [0.5, 1.0]
+            radius_m = 0.25
+            start_deg = 90.0
+            direction = "cw"
+
+            [[line]]
+            first_channel = 4
+            count = 3
+            from = [0.0, 0.0]
+            to = [1.0, 0.0]
+            gain = 0.3
+            "#,
+        )
+        .unwrap();
+        assert_eq!(layout.count, 7);
+        let expected = [
+            (0.5, 1.25),
+            (0.75, 1.0),
+            (0.5, 0.75),
+            (0.25, 1.0),
+            (0.0, 0.0),
+            (0.5, 0.0),
+            (1.0, 0.0),
+        ];
+        for (channel, (&(x, y), (ex, ey))) in layout.positions().iter().zip(expected).enumerate() {
+            assert!(
+                (x - ex).abs() < 1e-6 && (y - ey).abs() < 1e-6,
+                "channel {channel} at ({x}, {y})"
+            );
+        }
+        assert_eq!(layout.gains[0], DEFAULT_TRANSDUCER_GAIN);
+        assert_eq!(layout.gains[6], 0.3);
+    }
+
+    #[test]
+    fn arc_and_hex_generators_space_their_transducers_evenly() {
+        let layout = parse_layout(
+            r#"
+            [[arc]]
+            first_channel = 6
+            count = 3
+            centre = [0.0, 0.0]
+            radius_m = 1.0
+            start_deg = 0.0
+            end_deg = 90.0
+
+            [[hex]]
+            first_channel = 0
+            cols = 3
+            rows = 2
+            origin = [0.1, 0.1]
+            spacing_m = 0.2
+            "#,
+        )
+        .unwrap();
+        assert_eq!(layout.count, 9);
+        let distance = |a: usize, b: usize| {
+            let (pa, pb) = (layout.positions[a], layout.positions[b]);
+            ((pa.0 - pb.0).powi(2) + (pa.1 - pb.1).powi(2)).sqrt()
+        };
+        // Hex neighbours along and between rows are all one spacing apart
+        for (a, b) in [(0, 1), (0, 3), (1, 3), (1, 4), (2, 4)] {
+            assert!((distance(a, b) - 0.2).abs() < 1e-5, "{a}-{b}");
+        }
+        let mid = layout.positions[7];
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        assert!((mid.0 - half).abs() < 1e-6 && (mid.1 - half).abs() < 1e-6);
+        assert!((layout.positions[8].1 - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn generators_must_tile_the_channels_exactly() {
+        let ring = |first: usize, count: usize| {
+            format!(
+                "[[ring]]\nfirst_channel = {first}\ncount = {count}\ncentre = [0.5, 1.0]\nradius_m = 0.3\n"
+            )
+        };
+        assert!(parse_layout(&(ring(0, 4) + &ring(4, 4))).is_ok());
+        let overlap = parse_layout(&(ring(0, 4) + &ring(3, 4))).unwrap_err();
+        assert!(overlap.contains("channel 3"), "{overlap}");
+        let gap = parse_layout(&(ring(0, 4) + &ring(5, 4))).unwrap_err();
+        assert!(gap.contains("channel 4"), "{gap}");
+        assert!(parse_layout(&ring(60, 8)).is_err());
+        assert!(parse_layout(&(ring(0, 4) + "[grid]\ncols = 2\nrows = 2")).is_err());
+        assert!(parse_layout(&ring(0, 0)).is_err());
+        assert!(parse_layout(
+            "[[ring]]\nfirst_channel = 0\ncount = 4\ncentre = [0.5, 1.0]\nradius_m = 0.0"
+        )
+        .is_err());
+        assert!(parse_layout(&(ring(0, 4) + "direction = \"up\"")).is_err());
+        // The table is checked whatever places the transducers
+        for (width, length) in [
+            ("0.0", "2.0"),
+            ("1.0", "-1.0"),
+            ("nan", "2.0"),
+            ("inf", "2.0"),
+        ] {
+            let text = format!(
+                "[table]\nwidth_m = {width}\nlength_m = {length}\n{}",
+                ring(0, 4)
+            );
+            let error = parse_layout(&text).unwrap_err();
+            assert!(error.contains("table dimensions"), "{error}");
+        }
+        // Per-channel overrides still apply on top
+        let layout =
+            parse_layout(&(ring(0, 4) + "[[transducer]]\nchannel = 2\nx = 0.1\ny = 0.2")).unwrap();
+        assert_eq!(layout.positions[2], (0.1, 0.2));
+    }
+
+    #[test]
+    fn named_layouts_share_a_count_and_start_from_active_layout() {
+        let text = r#"
+            active_layout = "table"
+
+            [headroom]
+
+            [layout.table.grid]
+            cols = 4
+            rows = 2
+
+            [layout.bed.table]
+            width_m = 1.5
+            length_m = 2.0
+
+            [[layout.bed.line]]
+            first_channel = 0
+            count = 8
+            from = [0.25, 0.5]
+            to = [1.25, 0.5]
+            "#;
+        let (mut layouts, _) = parse_config(text).unwrap();
+        assert_eq!(layouts.names().collect::<Vec<_>>(), ["bed", "table"]);
+        assert_eq!(layouts.active_name(), "table");
+        assert_eq!(layouts.active().positions[0], (0.125, 0.5));
+        // [headroom] applies to every layout
+        assert!(layouts.active().headroom.enabled);
+
+        layouts.select("bed").unwrap();
+        assert_eq!(layouts.active().table_m, (1.5, 2.0));
+        assert_eq!(layouts.active().positions[7], (1.25, 0.5));
+        assert!(layouts.active().headroom.enabled);
+        assert!(layouts.select("chair").is_err());
+        assert_eq!(layouts.active_name(), "bed");
+
+        // A file without named layouts has a single default one
+        let (single, _) = parse_config("[grid]\ncols = 2\nrows = 2").unwrap();
+        assert_eq!(single.names().collect::<Vec<_>>(), [DEFAULT_LAYOUT_NAME]);
+    }
+
+    #[test]
+    fn metric_section_selects_wrapping_and_graph_distances() {
+        assert_eq!(parse_layout("").unwrap().metric, DistanceMetric::Euclidean);
+        assert_eq!(
+            parse_layout("[metric]\nkind = \"torus\"").unwrap().metric,
+            DistanceMetric::Torus {
+                wrap_x: true,
+                wrap_y: true
+            }
+        );
+        assert_eq!(
+            parse_layout("[metric]\nkind = \"torus\"\nwrap = [\"y\"]")
+                .unwrap()
+                .metric,
+            DistanceMetric::Torus {
+                wrap_x: false,
+                wrap_y: true
+            }
+        );
+        assert_eq!(
+            parse_layout("[metric]\nkind = \"cylinder\"\naxis = \"x\"")
+                .unwrap()
+                .metric,
+            DistanceMetric::Cylinder { axis: Axis::X }
+        );
+
+        // A path 0-1-2 along the first grid row, 0.25 m per hop; 3 joins 1
+        let layout = parse_layout(
+            r#"
+            [grid]
+            cols = 4
+            rows = 1
+
+            [metric]
+            kind = "graph"
+            edges = [[0, 1], [1, 2], [3, 1]]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(layout.metric, DistanceMetric::Graph);
+        let graph_m = layout.graph_m.as_deref().unwrap();
+        assert!((graph_m[0][2] - 0.5).abs() < 1e-6);
+        assert!((graph_m[3][0] - 0.75).abs() < 1e-6);
+        assert_eq!(graph_m[2][2], 0.0);
+
+        let graph = "[grid]\ncols = 4\nrows = 1\n[metric]\nkind = \"graph\"\n";
+        // Transducer 3 unreachable
+        assert!(parse_layout(&format!("{graph}edges = [[0, 1], [1, 2]]")).is_err());
+        assert!(parse_layout(&format!("{graph}edges = [[0, 4]]")).is_err());
+        assert!(parse_layout(&format!("{graph}edges = [[1, 1]]")).is_err());
+        assert!(parse_layout("[metric]\nkind = \"sphere\"").is_err());
+        assert!(parse_layout("[metric]\nkind = \"cylinder\"").is_err());
+        assert!(parse_layout("[metric]\nkind = \"torus\"\nwrap = []").is_err());
+        assert!(parse_layout("[metric]\nkind = \"euclidean\"\naxis = \"x\"").is_err());
+        // An unbounded body space has no period to wrap across
+        let body = "[grid]\ncols = 1\nrows = 1\n[body]\nwidth_m = inf\nlength_m = 0.7\n\
+                    [[transducer]]\nchannel = 0\nbody = [0.2, 0.1]\n";
+        assert!(parse_layout(body).is_ok());
+        let wrapped = parse_layout(&format!(
+            "{body}[metric]\nkind = \"cylinder\"\naxis = \"x\""
+        ));
+        assert!(wrapped.unwrap_err().contains("extent"));
+        assert!(parse_layout(&format!(
+            "{body}[metric]\nkind = \"cylinder\"\naxis = \"y\""
+        ))
+        .is_ok());
+    }
+
+    #[test]
+    fn body_plan_maps_transducers_into_body_space() {
+        let layout = parse_layout(
+            r#"
+            [grid]
+            cols = 3
+            rows = 1
+
+            [body]
+            width_m = 0.5
+            length_m = 0.7
+
+            [[body.region]]
+            name = "lumbar"
+            polygon = [[0.1, 0.0], [0.4, 0.0], [0.4, 0.2], [0.1, 0.2]]
+
+            [[body.region]]
+            name = "left_scapula"
+            polygon = [[0.05, 0.5], [0.2, 0.5], [0.2, 0.65]]
+
+            [[transducer]]
+            channel = 0
+            region = "lumbar"
+
+            [[transducer]]
+            channel = 1
+            region = "lumbar"
+            body = [0.3, 0.05]
+
+            [[transducer]]
+            channel = 2
+            x = 0.9
+            body = [0.25, 0.4]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(layout.space, LayoutSpace::Body);
+        assert_eq!(layout.table_m, (0.5, 0.7));
+        // Region centroid, explicit coordinate inside the region, free coordinate
+        let (u, v) = layout.positions[0];
+        assert!((u - 0.25).abs() < 1e-6 && (v - 0.1).abs() < 1e-6);
+        assert_eq!(layout.positions[1], (0.3, 0.05));
+        assert_eq!(layout.positions[2], (0.25, 0.4));
+        // Physical placement is kept alongside
+        assert_eq!(layout.physical_positions[2].0, 0.9);
+        assert_eq!(
+            layout.physical_positions[0],
+            TransducerLayout::grid(3, 1, 1.0, 2.0, 0.5)
+                .unwrap()
+                .positions[0]
+        );
+
+        let plan = "[grid]\ncols = 1\nrows = 1\n[body]\nwidth_m = 0.5\nlength_m = 0.7\n\
+                    [[body.region]]\nname = \"lumbar\"\npolygon = [[0.1, 0.0], [0.4, 0.0], [0.4, 0.2]]\n";
+        // Every transducer needs a body coordinate
+        assert!(parse_layout(plan).is_err());
+        let entry = "[[transducer]]\nchannel = 0\n";
+        assert!(parse_layout(&format!("{plan}{entry}region = \"sacrum\"")).is_err());
+        assert!(parse_layout(&format!(
+            "{plan}{entry}region = \"lumbar\"\nbody = [0.1, 0.19]"
+        ))
+        .is_err());
+        assert!(parse_layout(&format!("{plan}{entry}body = [0.6, 0.1]")).is_err());
+        assert!(parse_layout(&format!("{plan}{entry}body = [0.2, 0.1]")).is_ok());
+        // Body coordinates without a plan
+        assert!(parse_layout("[[transducer]]\nchannel = 0\nbody = [0.1, 0.1]").is_err());
+    }
+
+    #[test]
+    fn invalid_named_layouts_are_rejected() {
+        let grid =
+            |name: &str, cols: usize| format!("[layout.{name}.grid]\ncols = {cols}\nrows = 2\n");
+        let two = format!("active_layout = \"a\"\n{}{}", grid("a", 4), grid("b", 4));
+        assert!(parse_config(&two).is_ok());
+        for bad in [
+            // Counts differ
+            format!("active_layout = \"a\"\n{}{}", grid("a", 4), grid("b", 3)),
+            // Starting layout unnamed or undeclared
+            format!("{}{}", grid("a", 4), grid("b", 4)),
+            format!("active_layout = \"c\"\n{}{}", grid("a", 4), grid("b", 4)),
+            // Top-level layout sections mixed with named ones
+            format!("[table]\nwidth_m = 1.0\nlength_m = 1.0\n{}", grid("a", 4)),
+            // active_layout without named layouts
+            "active_layout = \"a\"\n[grid]\ncols = 2\nrows = 2".to_string(),
+            // Errors inside a named layout
+            "[layout.a.grid]\ncols = 0\nrows = 2".to_string(),
+            format!("[layout.{}]", "n".repeat(MAX_LAYOUT_NAME_BYTES + 1)),
+        ] {
+            assert!(parse_config(&bad).is_err(), "{bad}");
+        }
+        let many: String = (0..=MAX_LAYOUTS)
+            .map(|i| grid(&format!("l{i}"), 2))
+            .collect();
+        assert!(parse_config(&format!("active_layout = \"l0\"\n{many}")).is_err());
+    }
+
+    #[test]
+    fn headroom_section_opts_in_with_default_time_constants() {
+        assert!(!parse_layout("").unwrap().headroom.enabled);
+        let layout = parse_layout("[headroom]\nrelease_ms = 500.0").unwrap();
+        assert!(layout.headroom.enabled);
+        assert_eq!(layout.headroom.attack_ms, DEFAULT_HEADROOM_ATTACK_MS);
+        assert_eq!(layout.headroom.release_ms, 500.0);
+        assert!(
+            !parse_layout("[headroom]\nenabled = false")
+                .unwrap()
+                .headroom
+                .enabled
+        );
+        assert!(parse_layout("[headroom]\nattack_ms = 0.0").is_err());
+    }
+
+    #[test]
+    fn server_section_names_the_output_device() {
+        let (layouts, server) = parse_config("[server]\ndevice = \"MOTU 24Ao\"").unwrap();
+        assert_eq!(layouts, LayoutSet::default());
+        assert_eq!(server.device.as_deref(), Some("MOTU 24Ao"));
+        assert_eq!(parse_config("").unwrap().1, ServerSettings::default());
+        assert!(parse_config("[server]\ndevice = \" \"").is_err());
+    }
+
+    #[test]
+    fn server_section_opts_into_realtime_scheduling() {
+        let (_, server) = parse_config(
+            "[server]\nrealtime = \"rtkit\"\nrealtime_priority = 15\nlock_memory = true",
+        )
+        .unwrap();
+        assert_eq!(
+            server.realtime,
+            RealtimeSettings {
+                mode: RealtimeMode::Rtkit,
+                priority: 15,
+                lock_memory: true,
+            }
+        );
+        assert_eq!(parse_config("").unwrap().1.realtime.mode, RealtimeMode::Off);
+        assert!(parse_config("[server]\nrealtime = \"rr\"").is_err());
+        assert!(parse_config("[server]\nrealtime_priority = 0").is_err());
+        assert!(parse_config("[server]\nrealtime_priority = 100").is_err());
+    }
+
+    #[test]
+    fn server_outputs_split_logical_channels_across_devices() {
+        let split = |a: &str, b: &str| {
+            format!(
+                "[[server.output]]\ndevice = \"A\"\n{a}\n\n[[server.output]]\ndevice = \"B\"\n{b}"
+            )
+        };
+        let (_, server) = parse_config(&split(
+            "first_channel = 0\nchannel_count = 16",
+            "first_channel = 16\nchannel_count = 16",
+        ))
+        .unwrap();
+        assert_eq!(server.outputs.len(), 2);
+        assert_eq!(server.outputs[1].device, "B");
+        assert_eq!(server.outputs[1].channels(), 16..32);
+
+        // Overlapping ranges, ranges past channel 31, and empty ranges
+        for (a, b) in [
+            (
+                "first_channel = 0\nchannel_count = 17",
+                "first_channel = 16\nchannel_count = 16",
+            ),
+            (
+                "first_channel = 0\nchannel_count = 16",
+                "first_channel = 20\nchannel_count = 16",
+            ),
+            (
+                "first_channel = 0\nchannel_count = 0",
+                "first_channel = 16\nchannel_count = 16",
+            ),
+        ] {
+            assert!(parse_config(&split(a, b)).is_err(), "{a} / {b}");
+        }
+        // A lone output, or one combined with a single device name
+        assert!(parse_config(
+            "[[server.output]]\ndevice = \"A\"\nfirst_channel = 0\nchannel_count = 32"
+        )
+        .is_err());
+        let both = format!(
+            "[server]\ndevice = \"C\"\n{}",
+            split(
+                "first_channel = 0\nchannel_count = 16",
+                "first_channel = 16\nchannel_count = 16"
+            )
+        );
+        assert!(parse_config(&both).is_err());
+    }
+
     #[test]
     fn invalid_configs_are_rejected() {
-        // Wrong transducer count
-        assert!(parse_layout("[grid]\ncols = 4\nrows = 4").is_err());
+        // Too many transducers
+        assert!(parse_layout("[grid]\ncols = 9\nrows = 8").is_err());
         // Channel out of range
         assert!(parse_layout("[[transducer]]\nchannel = 32\nx = 0.0\ny = 0.0").is_err());
         // Negative gain
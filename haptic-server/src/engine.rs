@@ -1,45 +1,55 @@
-use crate::config::TransducerLayout;
+use crate::config::{Axis, DistanceMetric, GraphDistances, HeadroomConfig, TransducerLayout};
 use crate::output_analysis::{OutputAnalyzer, HILBERT_DELAY_SAMPLES};
+use crate::test_signal::TestSignalGenerator;
 #[cfg(test)]
 use haptic_protocol::DEFAULT_TEST_NOTE;
 use haptic_protocol::{
     distance_gain, effective_wavelength, DistanceDecay, HapticCommand, InstanceConfig, MpeData,
-    Parameter, SpatialScaleMode, StimulusType, TravellingWaveConfig, VoiceInfo, DEFAULT_ATTEN_D0_M,
-    DEFAULT_ATTEN_EXPONENT, DEFAULT_WAVE_SPEED, MAX_ACTIVE_VOICES, MAX_ATTEN_D0_M,
-    MAX_ATTEN_EXPONENT, MAX_WAVELENGTH_M, MAX_WAVE_SPEED, MIN_ATTEN_D0_M, MIN_ATTEN_EXPONENT,
-    MIN_WAVELENGTH_M, MIN_WAVE_SPEED,
+    Parameter, SpatialScaleMode, StimulusType, TestSignal, TravellingWaveConfig, VoiceInfo,
+    DEFAULT_ATTEN_D0_M, DEFAULT_ATTEN_EXPONENT, DEFAULT_SOURCE_HEIGHT_M, DEFAULT_WAVE_SPEED,
+    MAX_ACTIVE_VOICES, MAX_ATTEN_D0_M, MAX_ATTEN_EXPONENT, MAX_SOURCE_HEIGHT_M, MAX_WAVELENGTH_M,
+    MAX_WAVE_SPEED, MIN_ATTEN_D0_M, MIN_ATTEN_EXPONENT, MIN_SOURCE_HEIGHT_M, MIN_WAVELENGTH_M,
+    MIN_WAVE_SPEED,
 };
 
-// Constants from requirements
-pub const TRANSDUCER_COUNT: usize = 32;
+// Constants from requirements. Per-transducer storage is sized for
+// MAX_TRANSDUCERS; the layout's count, fixed at startup, bounds the work.
+pub use haptic_protocol::MAX_TRANSDUCERS;
 const MAX_WAVE_STIMULI: usize = 8;
 const MAX_TRAVELLING_WAVE_STIMULI: usize = 8;
 const _: () = assert!(MAX_ACTIVE_VOICES >= MAX_WAVE_STIMULI + MAX_TRAVELLING_WAVE_STIMULI);
-// Delay-line capacity in *internal-rate* samples (see RENDER_DECIMATION):
-// 34,000 samples at the preferred 48 kHz / 32 = 1.5 kHz is ~22.7 s of
-// propagation. That covers the 2.236 m diagonal of the default 1x2 m table at
-// the 0.1 m/s wave-speed floor. Delays beyond capacity (possible with custom
-// out-of-table positions or higher non-preferred device rates) are clamped,
-// not wrapped. Buffers are heap-owned so this capacity does not inflate the
-// callback stack.
+// Delay-line capacity in *internal-rate* samples (see INTERNAL_RATE_HZ):
+// 34,000 samples at 1.5 kHz is ~22.7 s of propagation at every device rate.
+// That covers the 2.236 m diagonal of the default 1x2 m table at the 0.1 m/s
+// wave-speed floor. Delays beyond capacity (possible with custom out-of-table
+// positions) are clamped, not wrapped. Buffers are heap-owned so this capacity
+// does not inflate the callback stack.
 const MAX_DELAY_SAMPLES: usize = 34_000;
 
-/// The wave field is synthesised at the device rate divided by this factor
-/// (48 kHz -> 1.5 kHz) and upsampled to the device rate at the output
-/// stage. The internal Nyquist (750 Hz at 48 kHz) comfortably covers the
-/// 20-200 Hz transducer band, per-sample delay-line work drops 32x, and
-/// delay-line capacity in seconds stretches 32x, which is what lets slow
-/// wave speeds run without clamping.
-pub const RENDER_DECIMATION: usize = 32;
-
-/// Reconstruction filter length per polyphase branch. The interpolator is a
-/// windowed-sinc lowpass with cutoff at the internal Nyquist, evaluated
-/// polyphase: each device frame is a FIR_TAPS_PER_PHASE-tap dot product
-/// against the most recent internal frames. 16 taps x Kaiser beta 10 gives
-/// ~100 dB image rejection with the transition band comfortably between
-/// the 200 Hz content edge and the first image at internal_rate - 200 Hz.
+/// The wave field is synthesised at this fixed rate and reconstructed to the
+/// device rate at the output stage, so Wave and TW timing, delay capacity in
+/// seconds, and filter behaviour are identical on 44.1, 48, and 96 kHz
+/// interfaces. The internal Nyquist (750 Hz) comfortably covers the 20-200 Hz
+/// transducer band, and per-sample delay-line work is a small fraction of the
+/// device rate, which is what lets slow wave speeds run without clamping.
+pub const INTERNAL_RATE_HZ: u32 = 1_500;
+const INTERNAL_RATE: f32 = INTERNAL_RATE_HZ as f32;
+
+/// Reconstruction filter length in internal frames. The interpolator is a
+/// Kaiser-windowed sinc lowpass with cutoff at the internal Nyquist, spanning
+/// FIR_TAPS_PER_PHASE internal frames. It is tabulated at FIR_PHASES + 1
+/// fractional positions and linearly interpolated between them, so any
+/// device/internal ratio (147/5 at 44.1 kHz, 32 at 48 kHz, 64 at 96 kHz) is
+/// served by one table: each device frame is a FIR_TAPS_PER_PHASE-tap dot
+/// product against the most recent internal frames. 16 taps x Kaiser beta 10
+/// gives ~100 dB image rejection with the transition band comfortably between
+/// the 200 Hz content edge and the first image at 1500 - 200 Hz; 256 phases
+/// keep the table interpolation error below that.
 const FIR_TAPS_PER_PHASE: usize = 16;
-const FIR_LEN: usize = FIR_TAPS_PER_PHASE * RENDER_DECIMATION;
+const FIR_PHASES: usize = 256;
+const FIR_LEN: usize = FIR_TAPS_PER_PHASE * (FIR_PHASES + 1);
+/// Group delay of the reconstruction filter, in internal frames.
+const RECONSTRUCTION_DELAY: f64 = FIR_TAPS_PER_PHASE as f64 * 0.5;
 
 /// Bandlimited scatter (deposit) kernel for the delay line. Each emitted
 /// sample is splatted across `SPLAT_TAPS` ring cells straddling its fractional
@@ -102,6 +112,15 @@ const MOTION_ACCELERATION_RISE_TIME_S: f32 = 0.05;
 /// dropout gaps on recession), so 2-tap linear scatter needs no special-casing.
 const SOURCE_SPEED_FRACTION: f32 = 0.5;
 
+/// Time over which rendered gains glide to a hot-reloaded layout's gains.
+const LAYOUT_GAIN_RAMP_S: f32 = 0.05;
+
+/// Time over which rendered transducer positions and heights glide to a
+/// hot-reloaded layout. Wave voices also slew each delay by at most
+/// SOURCE_SPEED_FRACTION samples per frame, so a slow wave follows a moved
+/// transducer more gradually than this.
+const LAYOUT_POSITION_RAMP_S: f32 = 0.5;
+
 /// Capacity of the IPC → audio thread command ring buffer. Sized for a
 /// worst-case burst of MPE traffic within one audio callback.
 const COMMAND_QUEUE_CAPACITY: usize = 1024;
@@ -111,6 +130,81 @@ const COMMAND_QUEUE_CAPACITY: usize = 1024;
 /// allocation on the audio thread.
 const MAX_INSTANCES: usize = 16;
 
+/// Pre-clamp magnitude above which a sample counts as running hot (−1 dBFS).
+/// Counted alongside hard clamps so a layout can be trimmed before it clips.
+pub const CLIP_WARN_THRESHOLD: f32 = 0.891;
+
+/// Cumulative per-transducer clip telemetry since engine construction.
+/// `render_*` counts internal frames at the gain-and-clamp stage of
+/// `render_frame`; `output_*` counts device frames at the clamp after
+/// polyphase reconstruction. Totals only ever grow, so a consumer that misses
+/// snapshots (the output ring drops when full) still sees every event.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ClipCounters {
+    pub render_clamped: [u64; MAX_TRANSDUCERS],
+    pub render_over_threshold: [u64; MAX_TRANSDUCERS],
+    pub output_clamped: [u64; MAX_TRANSDUCERS],
+    pub output_over_threshold: [u64; MAX_TRANSDUCERS],
+}
+
+impl Default for ClipCounters {
+    fn default() -> Self {
+        Self {
+            render_clamped: [0; MAX_TRANSDUCERS],
+            render_over_threshold: [0; MAX_TRANSDUCERS],
+            output_clamped: [0; MAX_TRANSDUCERS],
+            output_over_threshold: [0; MAX_TRANSDUCERS],
+        }
+    }
+}
+
+/// Clamp `samples` to ±1 in place, counting per channel how many exceeded
+/// the warning threshold and how many were actually clamped.
+fn clamp_counting(
+    samples: &mut [f32; MAX_TRANSDUCERS],
+    clamped: &mut [u64; MAX_TRANSDUCERS],
+    over_threshold: &mut [u64; MAX_TRANSDUCERS],
+) {
+    for ((sample, clamped), over) in samples
+        .iter_mut()
+        .zip(clamped.iter_mut())
+        .zip(over_threshold.iter_mut())
+    {
+        let magnitude = sample.abs();
+        if magnitude > CLIP_WARN_THRESHOLD {
+            *over += 1;
+            if magnitude > 1.0 {
+                *clamped += 1;
+            }
+        }
+        *sample = sample.clamp(-1.0, 1.0);
+    }
+}
+
+/// Device frames the live-capture ring holds (~1.4 s at 48 kHz). Allocated
+/// once at startup; the writer thread drains it every few milliseconds.
+pub const CAPTURE_RING_FRAMES: usize = 1 << 16;
+
+/// Capture-ring slots never filled with frames, so the Begin/End markers of a
+/// start/stop round trip always fit even when the writer has fallen behind.
+const CAPTURE_MARKER_RESERVE: usize = 4;
+
+/// Items the audio callback pushes to the live-capture writer thread.
+/// Frames travel by value: boxing them would allocate in the callback.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Copy, Debug)]
+pub enum CaptureItem {
+    /// Capture starts with the next frame, at this device frame index.
+    Begin {
+        sample_rate: f32,
+        device_frame_index: u64,
+    },
+    /// One final logical output frame, as analysed and before monitor routing.
+    Frame([f32; MAX_TRANSDUCERS]),
+    /// Capture stopped; `dropped_frames` were lost to a full ring.
+    End { dropped_frames: u64 },
+}
+
 /// Per-block snapshot of the measured final output and every active oscillator
 /// reference, exported to the IPC thread. Fixed-size and callback-safe.
 #[derive(Clone, Copy)]
@@ -118,9 +212,44 @@ pub struct OutputSnapshot {
     pub device_sample_rate: f32,
     pub sample_index: u64,
     pub valid: bool,
-    pub analytic: [(f32, f32); TRANSDUCER_COUNT],
+    pub headroom_gain: f32,
+    pub clips: ClipCounters,
+    pub analytic: [(f32, f32); MAX_TRANSDUCERS],
     pub count: u8,
     pub voices: [VoiceInfo; MAX_ACTIVE_VOICES],
+    pub load: EngineLoad,
+}
+
+/// A layout tagged with the config version it belongs to, as it travels
+/// from the config watcher to the engine.
+pub type VersionedLayout = (u64, TransducerLayout);
+
+/// The config watcher's end of the engine's layout ring. Layouts the engine
+/// replaces come back through it and are freed here, since a graph layout
+/// owns a heap table the audio thread must not deallocate.
+pub struct LayoutSender {
+    layouts: rtrb::Producer<VersionedLayout>,
+    retired: rtrb::Consumer<TransducerLayout>,
+}
+
+impl LayoutSender {
+    /// Queue `layout` as config `version`; `false` if the ring is full.
+    pub fn push(&mut self, version: u64, layout: TransducerLayout) -> bool {
+        while self.retired.pop().is_ok() {}
+        self.layouts.push((version, layout)).is_ok()
+    }
+}
+
+/// Engine occupancy published with each output snapshot.
+/// `dropped_snapshots` is cumulative, so it survives the drops it counts.
+/// `config_version` is the version of the last layout the engine applied.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EngineLoad {
+    pub wave_voices: u8,
+    pub travelling_wave_voices: u8,
+    pub instances: u8,
+    pub dropped_snapshots: u64,
+    pub config_version: u64,
 }
 
 /// Map a MIDI note to its standard equal-tempered frequency, then clamp it to
@@ -133,7 +262,7 @@ pub fn note_to_haptic_frequency(note: u8) -> f32 {
 
 // Core trait - must be Send + Sync for thread safety
 pub trait Stimulus: Send + Sync {
-    fn process(&mut self, context: &ProcessContext<'_>) -> [f32; TRANSDUCER_COUNT];
+    fn process(&mut self, context: &ProcessContext<'_>) -> [f32; MAX_TRANSDUCERS];
     fn is_active(&self) -> bool;
     fn is_releasing(&self) -> bool;
     fn note_on(&mut self, frequency: f32, velocity: u8, mpe: MpeData);
@@ -144,6 +273,9 @@ pub trait Stimulus: Send + Sync {
         // Default implementation does nothing (for stimuli that don't use wave speed)
     }
     fn set_distance_decay(&mut self, _decay: DistanceDecay) {}
+    /// Instantaneous source amplitude (`amplitude · envelope · pressure`),
+    /// the per-voice contribution to the headroom normaliser's demand.
+    fn source_level(&self) -> f32;
 }
 
 // Static allocation pool
@@ -153,9 +285,11 @@ pub struct StimulusPool<T: Stimulus + Default, const N: usize> {
 }
 
 impl<T: Stimulus + Default, const N: usize> StimulusPool<T, N> {
-    pub fn new() -> Self {
+    /// Fill every slot from `make`, which allocates any per-transducer
+    /// state up front.
+    pub fn new(mut make: impl FnMut() -> T) -> Self {
         Self {
-            stimuli: std::array::from_fn(|_| T::default()),
+            stimuli: std::array::from_fn(|_| make()),
             active_mask: [false; N],
         }
     }
@@ -186,6 +320,11 @@ impl<T: Stimulus + Default, const N: usize> StimulusPool<T, N> {
         self.active_mask[slot]
     }
 
+    /// Occupied slots, releasing voices included.
+    fn active_count(&self) -> usize {
+        self.active_mask.iter().filter(|&&active| active).count()
+    }
+
     fn slot_releasing(&self, slot: usize) -> bool {
         self.stimuli[slot].is_releasing()
     }
@@ -197,10 +336,20 @@ impl<T: Stimulus + Default, const N: usize> StimulusPool<T, N> {
         }
     }
 
+    /// Summed instantaneous source amplitude of every occupied slot.
+    fn source_level_sum(&self) -> f32 {
+        self.stimuli
+            .iter()
+            .zip(self.active_mask.iter())
+            .filter(|(_, &active)| active)
+            .map(|(stimulus, _)| stimulus.source_level())
+            .sum()
+    }
+
     pub fn process_all(
         &mut self,
         context: &ProcessContext<'_>,
-        output: &mut [f32; TRANSDUCER_COUNT],
+        output: &mut [f32; MAX_TRANSDUCERS],
     ) {
         for (i, stimulus) in self.stimuli.iter_mut().enumerate() {
             if self.active_mask[i] {
@@ -271,32 +420,56 @@ pub struct StimulusEngine {
     // Lock-free SPSC command queues, consumer ends (IPC thread holds the
     // command producer, the config watcher holds the layout producer)
     command_queue: rtrb::Consumer<EngineCommand>,
-    layout_queue: rtrb::Consumer<TransducerLayout>,
+    layout_queue: rtrb::Consumer<VersionedLayout>,
+    retired_layouts: rtrb::Producer<TransducerLayout>,
+    config_version: u64,
 
     // Final-output snapshots out to the IPC thread (drops when full)
     output_producer: rtrb::Producer<OutputSnapshot>,
+    dropped_snapshots: u64,
 
     // Physical output p plays logical channel monitor_routes[p]
-    monitor_routes: [u8; TRANSDUCER_COUNT],
+    monitor_routes: [u8; MAX_TRANSDUCERS],
 
-    // Transducer configuration (hot-swappable via layout_queue)
+    // Transducer configuration (hot-swappable via layout_queue), and the
+    // positions, heights, and gains actually rendered while they glide to it
     layout: TransducerLayout,
+    glide: LayoutGlide,
+
+    // Multi-voice gain normaliser, applied before layout gains and clamping
+    headroom: HeadroomNormaliser,
+
+    // Cumulative clamp and over-threshold counts, published with each snapshot
+    clips: ClipCounters,
 
-    // Output upsampler state: the engine renders at the device rate divided
-    // by RENDER_DECIMATION; device frames are reconstructed by a polyphase
-    // windowed-sinc filter over the most recent internal frames (newest at
-    // history[history_pos], ring order oldest-ward).
-    history: [[f32; TRANSDUCER_COUNT]; FIR_TAPS_PER_PHASE],
+    // Live capture of final logical frames to the writer thread (attached
+    // at startup; absent for offline renders and most tests)
+    capture: Option<rtrb::Producer<CaptureItem>>,
+    capturing: bool,
+    capture_dropped: u64,
+    device_sample_rate: f32,
+
+    // Diagnostic signal mixed onto the final logical output
+    test_signal: TestSignalGenerator,
+
+    // Output upsampler state: the engine renders at INTERNAL_RATE_HZ; device
+    // frames are reconstructed by a fractional polyphase windowed-sinc filter
+    // over the most recent internal frames (newest at history[history_pos],
+    // ring order oldest-ward). `interp_acc / device_rate` is the time since
+    // the newest internal frame in internal frames; it advances by exactly
+    // INTERNAL_RATE_HZ per device frame, so the ratio never drifts. `None`
+    // forces a render on the very first device frame.
+    history: [[f32; MAX_TRANSDUCERS]; FIR_TAPS_PER_PHASE],
     history_pos: usize,
-    interp_phase: usize,
+    interp_acc: Option<u64>,
     fir: Box<[f32; FIR_LEN]>,
 
     // Hilbert analysis of final bounded logical device samples. The device
     // frame counters align every active oscillator reference to the same
     // reconstruction and analysis group delay.
-    output_analyzer: OutputAnalyzer<TRANSDUCER_COUNT>,
+    output_analyzer: OutputAnalyzer<MAX_TRANSDUCERS>,
     device_frame_index: u64,
-    last_render_device_frame: u64,
+    last_render_device_time: f64,
 
     // Bandlimited scatter kernel for the delay lines (see design_splat_kernel)
     splat_kernel: Box<[f32; SPLAT_LEN]>,
@@ -305,9 +478,17 @@ pub struct StimulusEngine {
 pub struct ProcessContext<'a> {
     pub sample_rate: f32,
     pub dt: f32,
-    pub transducer_positions: &'a [(f32, f32); TRANSDUCER_COUNT],
-    /// (width, length) of the table, for MPE -> source-position mapping.
+    /// One position per configured transducer.
+    pub transducer_positions: &'a [(f32, f32)],
+    /// One height per configured transducer, paired with the positions.
+    pub transducer_heights: &'a [f32],
+    /// (width, length) of the table, for MPE -> source-position mapping
+    /// and the periods of wrapped metrics.
     pub table_m: (f32, f32),
+    /// How source-to-transducer distances are measured.
+    pub metric: DistanceMetric,
+    /// Transducer-to-transducer path lengths for `DistanceMetric::Graph`.
+    pub graph_m: Option<&'a GraphDistances>,
     /// Bandlimited scatter kernel shared by the voices' delay lines.
     pub splat_kernel: &'a [f32; SPLAT_LEN],
 }
@@ -350,14 +531,24 @@ pub enum EngineCommand {
         parameter: Parameter,
     },
     Panic,
+    /// Start or stop pushing final logical frames to the capture ring.
+    SetCapture {
+        enabled: bool,
+    },
+    /// Replace the test signal mixed onto the final logical output.
+    SetTestSignal {
+        signal: TestSignal,
+    },
 }
 
 impl EngineCommand {
     /// Convert a wire command into an engine command, stamping it with the
     /// `instance_id` of the connection it arrived on. `Hello` carries its own
-    /// id/config; everything else inherits the connection's bound id.
-    pub fn from_wire(cmd: HapticCommand, instance_id: u64) -> Self {
-        match cmd {
+    /// id/config; everything else inherits the connection's bound id. `None`
+    /// for commands the engine never sees: `SelectLayout` is resolved by the
+    /// config watcher, which feeds the layout ring.
+    pub fn from_wire(cmd: HapticCommand, instance_id: u64) -> Option<Self> {
+        let command = match cmd {
             HapticCommand::Hello {
                 instance_id,
                 config,
@@ -394,7 +585,12 @@ impl EngineCommand {
                 parameter,
             },
             HapticCommand::Panic => EngineCommand::Panic,
-        }
+            HapticCommand::StartCapture { .. } => EngineCommand::SetCapture { enabled: true },
+            HapticCommand::StopCapture => EngineCommand::SetCapture { enabled: false },
+            HapticCommand::SetTestSignal { signal } => EngineCommand::SetTestSignal { signal },
+            HapticCommand::SelectLayout { .. } => return None,
+        };
+        Some(command)
     }
 }
 
@@ -408,36 +604,69 @@ impl StimulusEngine {
     ) -> (
         Self,
         rtrb::Producer<EngineCommand>,
-        rtrb::Producer<TransducerLayout>,
+        LayoutSender,
         rtrb::Consumer<OutputSnapshot>,
     ) {
+        let count = layout.count;
         let (producer, consumer) = rtrb::RingBuffer::new(COMMAND_QUEUE_CAPACITY);
         let (layout_producer, layout_consumer) = rtrb::RingBuffer::new(4);
+        let (retired_producer, retired_consumer) = rtrb::RingBuffer::new(4);
         let (output_producer, output_consumer) = rtrb::RingBuffer::new(256);
 
         let engine = Self {
-            wave_pool: StimulusPool::new(),
-            travelling_wave_pool: StimulusPool::new(),
+            wave_pool: StimulusPool::new(|| WaveStimulus::with_transducers(count)),
+            travelling_wave_pool: StimulusPool::new(TravellingWaveStimulus::default),
             wave_owners: [None; MAX_WAVE_STIMULI],
             travelling_wave_owners: [None; MAX_TRAVELLING_WAVE_STIMULI],
             next_seq: 0,
             instances: [None; MAX_INSTANCES],
             command_queue: consumer,
             layout_queue: layout_consumer,
+            retired_layouts: retired_producer,
+            config_version: 0,
             output_producer,
+            dropped_snapshots: 0,
             monitor_routes: std::array::from_fn(|i| i as u8),
+            glide: LayoutGlide::new(&layout),
             layout,
-            history: [[0.0; TRANSDUCER_COUNT]; FIR_TAPS_PER_PHASE],
+            headroom: HeadroomNormaliser::default(),
+            clips: ClipCounters::default(),
+            capture: None,
+            capturing: false,
+            capture_dropped: 0,
+            test_signal: TestSignalGenerator::default(),
+            device_sample_rate: 0.0,
+            history: [[0.0; MAX_TRANSDUCERS]; FIR_TAPS_PER_PHASE],
             history_pos: 0,
-            // Force a render on the very first device frame
-            interp_phase: RENDER_DECIMATION - 1,
+            interp_acc: None,
             fir: design_reconstruction_fir(),
-            output_analyzer: OutputAnalyzer::new(),
+            output_analyzer: OutputAnalyzer::new(count),
             device_frame_index: 0,
-            last_render_device_frame: 0,
+            last_render_device_time: 0.0,
             splat_kernel: design_splat_kernel(),
         };
-        (engine, producer, layout_producer, output_consumer)
+        let layout_sender = LayoutSender {
+            layouts: layout_producer,
+            retired: retired_consumer,
+        };
+        (engine, producer, layout_sender, output_consumer)
+    }
+
+    /// Number of transducers, fixed by the layout the engine was built with.
+    pub fn transducer_count(&self) -> usize {
+        self.layout.count
+    }
+
+    /// Attach the preallocated live-capture ring. Frames flow only between a
+    /// `SetCapture { enabled: true }` and the matching disable.
+    pub fn attach_capture(&mut self, producer: rtrb::Producer<CaptureItem>) {
+        self.capture = Some(producer);
+    }
+
+    /// Start from saved monitor routing, before the audio thread takes the
+    /// engine.
+    pub fn restore_monitor_routes(&mut self, routes: [u8; MAX_TRANSDUCERS]) {
+        self.monitor_routes = routes;
     }
 
     /// Config for `instance_id`, or the default if the instance has not
@@ -523,6 +752,7 @@ impl StimulusEngine {
                 stim.note_on(frequency, velocity, mpe);
                 stim.set_wave_speed(config.wave_speed);
                 stim.configure_distance_decay(config.distance_decay);
+                stim.set_source_height(config.source_height_m);
                 self.wave_owners[slot] = Some(owner);
             }
             StimulusType::TravellingWave => {
@@ -540,6 +770,7 @@ impl StimulusEngine {
                 let stim = self.travelling_wave_pool.get_mut(slot);
                 stim.note_on(frequency, velocity, mpe);
                 stim.configure(config.travelling_wave, config.distance_decay, true);
+                stim.set_source_height(config.source_height_m, true);
                 self.travelling_wave_owners[slot] = Some(owner);
             }
         }
@@ -684,9 +915,9 @@ impl StimulusEngine {
                 }
                 // MonitorRoute is a server-global device concern.
                 Parameter::MonitorRoute { output, source } => {
-                    if (output as usize) < TRANSDUCER_COUNT {
-                        self.monitor_routes[output as usize] =
-                            source.min(TRANSDUCER_COUNT as u8 - 1);
+                    let count = self.layout.count;
+                    if (output as usize) < count {
+                        self.monitor_routes[output as usize] = source.min(count as u8 - 1);
                     }
                 }
                 Parameter::TravellingWaveScaleMode(mode) => {
@@ -726,12 +957,53 @@ impl StimulusEngine {
                     }
                     self.set_instance_decay(instance_id, None, Some(exponent));
                 }
+                // Held Waves keep their height; TW voices glide to it
+                Parameter::SourceHeight(height_m) => {
+                    let height_m = height_m.clamp(MIN_SOURCE_HEIGHT_M, MAX_SOURCE_HEIGHT_M);
+                    if let Some(cfg) = self.instance_config_mut(instance_id) {
+                        cfg.source_height_m = height_m;
+                    }
+                    for (slot, owner) in self.travelling_wave_owners.iter().enumerate() {
+                        if owner.is_some_and(|owner| owner.instance_id == instance_id) {
+                            self.travelling_wave_pool
+                                .get_mut(slot)
+                                .set_source_height(height_m, false);
+                        }
+                    }
+                }
             },
             EngineCommand::Panic => {
                 self.wave_pool.reset_all();
                 self.travelling_wave_pool.reset_all();
                 self.wave_owners = [None; MAX_WAVE_STIMULI];
                 self.travelling_wave_owners = [None; MAX_TRAVELLING_WAVE_STIMULI];
+                self.test_signal.stop();
+            }
+            EngineCommand::SetTestSignal { signal } => self.test_signal.set(signal),
+            EngineCommand::SetCapture { enabled } => {
+                let Some(ring) = self.capture.as_mut() else {
+                    return;
+                };
+                // Markers are pushed at the exact command position, so a
+                // capture spans precisely the frames between its commands.
+                // The frame reserve guarantees they fit.
+                if enabled && !self.capturing {
+                    let begin = CaptureItem::Begin {
+                        sample_rate: self.device_sample_rate,
+                        device_frame_index: self.device_frame_index,
+                    };
+                    if ring.push(begin).is_ok() {
+                        self.capturing = true;
+                        self.capture_dropped = 0;
+                    }
+                } else if !enabled && self.capturing {
+                    let end = CaptureItem::End {
+                        dropped_frames: self.capture_dropped,
+                    };
+                    if ring.push(end).is_ok() {
+                        self.capturing = false;
+                    }
+                }
             }
         }
     }
@@ -740,10 +1012,27 @@ impl StimulusEngine {
         while let Ok(cmd) = self.command_queue.pop() {
             self.apply_command(cmd);
         }
-        // Hot config reload is a fixed-size Copy through the preallocated ring:
-        // no allocation or deallocation occurs on the audio thread.
-        while let Ok(layout) = self.layout_queue.pop() {
-            self.layout = layout;
+        // Hot config reload moves a fixed-size layout through the
+        // preallocated ring, and each layout it replaces goes back through
+        // the retired ring to be freed by the watcher, so no allocation or
+        // deallocation occurs on the audio thread. A new layout waits while
+        // there is nowhere to return the old one. The voices' delay lines
+        // are sized for the startup count, so a layout with another count is
+        // never applied (the watcher rejects it). Positions, heights, and
+        // gains glide from wherever they are; the metric, table, and
+        // headroom settings switch at once.
+        while self.retired_layouts.slots() > 0 {
+            let Ok((version, layout)) = self.layout_queue.pop() else {
+                break;
+            };
+            let retired = if layout.count == self.layout.count {
+                self.glide.retarget();
+                self.config_version = version;
+                std::mem::replace(&mut self.layout, layout)
+            } else {
+                layout
+            };
+            let _ = self.retired_layouts.push(retired);
         }
     }
 
@@ -753,64 +1042,85 @@ impl StimulusEngine {
     /// borrowing the layout and heap-owned scatter kernel immutably. Keeping
     /// those borrows field-local avoids copying the kernel onto the audio
     /// callback stack; at 16 taps and 1024 phases that table is 64 KiB.
-    fn render_frame(&mut self, sample_rate: f32, output: &mut [f32; TRANSDUCER_COUNT]) {
+    fn render_frame(&mut self, sample_rate: f32, output: &mut [f32; MAX_TRANSDUCERS]) {
+        let count = self.layout.count;
+        self.glide.step(&self.layout, 1.0 / sample_rate);
         let context = ProcessContext {
             sample_rate,
             dt: 1.0 / sample_rate,
-            transducer_positions: &self.layout.positions,
+            transducer_positions: &self.glide.positions[..count],
+            transducer_heights: &self.glide.heights[..count],
             table_m: self.layout.table_m,
+            metric: self.layout.metric,
+            graph_m: self.layout.graph_m.as_deref(),
             splat_kernel: &self.splat_kernel,
         };
         output.fill(0.0);
         self.wave_pool.process_all(&context, output);
         self.travelling_wave_pool.process_all(&context, output);
 
-        // Per-transducer gain, then safety limiting
-        for (sample, &gain) in output.iter_mut().zip(self.layout.gains.iter()) {
-            *sample = (*sample * gain).clamp(-1.0, 1.0);
+        let demand =
+            self.wave_pool.source_level_sum() + self.travelling_wave_pool.source_level_sum();
+        let headroom = self.headroom.step(demand, self.layout.headroom, context.dt);
+
+        // Headroom and per-transducer gain, then safety limiting
+        for (sample, &gain) in output.iter_mut().zip(self.glide.gains.iter()) {
+            *sample *= headroom * gain;
         }
+        clamp_counting(
+            output,
+            &mut self.clips.render_clamped,
+            &mut self.clips.render_over_threshold,
+        );
     }
 
     /// Audio-callback entry point: drains pending commands once, then fills
     /// the interleaved `data` buffer at the device rate. The wave field is
-    /// rendered at `sample_rate / RENDER_DECIMATION` and linearly upsampled
-    /// per device frame; the upsampler state persists across calls, so block
-    /// sizes need not be multiples of the decimation factor. Writes the
-    /// block RMS of each of the 32 logical transducer outputs into
-    /// `levels_out` (computed pre-truncation, so levels are meaningful even
-    /// on a stereo fallback device). MUST NOT block or allocate.
+    /// rendered at INTERNAL_RATE_HZ and reconstructed per device frame; the
+    /// upsampler state persists across calls, so block sizes need not be
+    /// multiples of the device/internal ratio. Writes the
+    /// block RMS of each logical transducer output into the first
+    /// `transducer_count()` entries of `levels_out` (computed pre-truncation,
+    /// so levels are meaningful even on a stereo fallback device). MUST NOT
+    /// block or allocate.
     pub fn process_block(
         &mut self,
         data: &mut [f32],
         channels: usize,
         sample_rate: f32,
-        levels_out: &mut [f32; TRANSDUCER_COUNT],
+        levels_out: &mut [f32; MAX_TRANSDUCERS],
     ) {
+        self.device_sample_rate = sample_rate;
         self.drain_commands();
 
-        let engine_rate = sample_rate / RENDER_DECIMATION as f32;
-        let mut sum_squares = [0.0f32; TRANSDUCER_COUNT];
+        let device_rate = (sample_rate.round() as u64).max(1);
+        let count = self.layout.count;
+        let mut sum_squares = [0.0f32; MAX_TRANSDUCERS];
         let frames = data.len() / channels;
         let routes = self.monitor_routes;
+        let mut coeffs = [0.0f32; FIR_TAPS_PER_PHASE];
         for frame in data[..frames * channels].chunks_exact_mut(channels) {
-            self.interp_phase += 1;
-            if self.interp_phase >= RENDER_DECIMATION {
-                self.interp_phase = 0;
-                let mut cur = [0.0f32; TRANSDUCER_COUNT];
-                self.render_frame(engine_rate, &mut cur);
+            let mut acc = self
+                .interp_acc
+                .map_or(device_rate, |acc| acc + INTERNAL_RATE_HZ as u64);
+            while acc >= device_rate {
+                acc -= device_rate;
+                let mut cur = [0.0f32; MAX_TRANSDUCERS];
+                self.render_frame(INTERNAL_RATE, &mut cur);
                 self.history_pos = (self.history_pos + FIR_TAPS_PER_PHASE - 1) % FIR_TAPS_PER_PHASE;
                 self.history[self.history_pos] = cur;
-                self.last_render_device_frame = self.device_frame_index;
+                self.last_render_device_time =
+                    self.device_frame_index as f64 - acc as f64 / INTERNAL_RATE_HZ as f64;
             }
-            // Polyphase reconstruction: device frame at phase p is the dot
-            // product of branch p of the windowed-sinc filter with the
-            // FIR_TAPS_PER_PHASE most recent internal frames
-            let n = channels.min(TRANSDUCER_COUNT);
-            let mut interp = [0.0f32; TRANSDUCER_COUNT];
-            for k in 0..FIR_TAPS_PER_PHASE {
-                let coeff = self.fir[k * RENDER_DECIMATION + self.interp_phase];
+            self.interp_acc = Some(acc);
+            // Fractional polyphase reconstruction: the device frame lies
+            // `acc / device_rate` internal frames after the newest one
+            reconstruction_coefficients(&self.fir, acc as f32 / device_rate as f32, &mut coeffs);
+            let n = channels.min(count);
+            let mut interp = [0.0f32; MAX_TRANSDUCERS];
+            for (k, &coeff) in coeffs.iter().enumerate() {
                 let frame_k = &self.history[(self.history_pos + k) % FIR_TAPS_PER_PHASE];
-                for (out, &sample) in interp.iter_mut().zip(frame_k.iter()) {
+                for (out, &sample) in interp[..count].iter_mut().zip(frame_k.iter()) {
                     *out += coeff * sample;
                 }
             }
@@ -819,16 +1129,28 @@ impl StimulusEngine {
             // device-rate reconstruction overshoot is bounded. The analyser
             // sees this exact vector before physical monitor routing.
             let mut logical = interp;
-            for sample in &mut logical {
-                *sample = sample.clamp(-1.0, 1.0);
-            }
+            self.test_signal.mix(&mut logical[..count], sample_rate);
+            clamp_counting(
+                &mut logical,
+                &mut self.clips.output_clamped,
+                &mut self.clips.output_over_threshold,
+            );
             self.output_analyzer
                 .process(&logical, self.device_frame_index, sample_rate);
+            if self.capturing {
+                if let Some(ring) = self.capture.as_mut() {
+                    if ring.slots() <= CAPTURE_MARKER_RESERVE
+                        || ring.push(CaptureItem::Frame(logical)).is_err()
+                    {
+                        self.capture_dropped += 1;
+                    }
+                }
+            }
             for (sum, &sample) in sum_squares.iter_mut().zip(logical.iter()) {
                 *sum += sample * sample;
             }
             // Physical outputs play their routed logical channel (identity
-            // by default; a stereo device can audition any of the 32)
+            // by default; a stereo device can audition any transducer)
             for (p, sample) in frame[..n].iter_mut().enumerate() {
                 *sample = logical[routes[p] as usize];
             }
@@ -863,7 +1185,7 @@ impl StimulusEngine {
             .unwrap_or_else(|| self.device_frame_index.saturating_sub(1));
         let analytic = latest
             .map(|frame| frame.samples)
-            .unwrap_or([(0.0, 0.0); TRANSDUCER_COUNT]);
+            .unwrap_or([(0.0, 0.0); MAX_TRANSDUCERS]);
         let analysis_decimation = self.output_analyzer.decimation();
         let mut voices = [VoiceInfo::default(); MAX_ACTIVE_VOICES];
         let mut count = 0usize;
@@ -891,7 +1213,7 @@ impl StimulusEngine {
                     stim.phase,
                     stim.frequency,
                     device_sample_rate,
-                    self.last_render_device_frame,
+                    self.last_render_device_time,
                     sample_index,
                     analysis_decimation,
                 ),
@@ -922,27 +1244,40 @@ impl StimulusEngine {
                     stim.phase,
                     stim.frequency,
                     device_sample_rate,
-                    self.last_render_device_frame,
+                    self.last_render_device_time,
                     sample_index,
                     analysis_decimation,
                 ),
             };
             count += 1;
         }
-        let _ = self.output_producer.push(OutputSnapshot {
+        let load = EngineLoad {
+            wave_voices: self.wave_pool.active_count() as u8,
+            travelling_wave_voices: self.travelling_wave_pool.active_count() as u8,
+            instances: self.instances.iter().flatten().count() as u8,
+            dropped_snapshots: self.dropped_snapshots,
+            config_version: self.config_version,
+        };
+        let pushed = self.output_producer.push(OutputSnapshot {
             device_sample_rate,
             sample_index,
             valid,
+            headroom_gain: self.headroom.gain,
+            clips: self.clips,
             analytic,
             count: count as u8,
             voices,
+            load,
         });
+        if pushed.is_err() {
+            self.dropped_snapshots += 1;
+        }
     }
 
     /// Single-frame variant used by tests: renders one *internal-rate*
     /// frame directly at `sample_rate` (no upsampling stage).
     #[cfg(test)]
-    pub fn process(&mut self, output: &mut [f32; TRANSDUCER_COUNT], sample_rate: f32) {
+    pub fn process(&mut self, output: &mut [f32; MAX_TRANSDUCERS], sample_rate: f32) {
         self.drain_commands();
         self.render_frame(sample_rate, output);
         self.reap_finished_voices();
@@ -952,24 +1287,26 @@ impl StimulusEngine {
 /// Phase of an ideal source sine oscillator at the exact time represented by
 /// the measured analytic output. `phase_after_latest_render` is the phase for
 /// the next internal render frame, so first step back to the phase used by the
-/// latest render, then compensate both linear-phase filters. The `-pi/2`
-/// offset is the analytic phase of `sin(theta)` under the Hilbert convention
-/// used by `OutputAnalyzer`.
+/// latest render, then compensate both linear-phase filters. The latest render
+/// represents a fractional device time, since internal frames need not align
+/// with device frames. The `-pi/2` offset is the analytic phase of
+/// `sin(theta)` under the Hilbert convention used by `OutputAnalyzer`.
 fn aligned_reference_phase(
     phase_after_latest_render: f32,
     frequency: f32,
     device_sample_rate: f32,
-    latest_render_device_frame: u64,
+    latest_render_device_time: f64,
     analysis_sample_index: u64,
     analysis_decimation: usize,
 ) -> f32 {
-    let phase_used_at_latest_render = phase_after_latest_render as f64
-        - frequency as f64 * RENDER_DECIMATION as f64 / device_sample_rate as f64;
-    let reconstruction_delay = (FIR_LEN - 1) as f64 * 0.5;
+    let phase_used_at_latest_render =
+        phase_after_latest_render as f64 - frequency as f64 / INTERNAL_RATE as f64;
+    let reconstruction_delay =
+        RECONSTRUCTION_DELAY * device_sample_rate as f64 / INTERNAL_RATE as f64;
     let hilbert_delay = HILBERT_DELAY_SAMPLES as f64 * analysis_decimation as f64;
     let represented_device_frame =
         analysis_sample_index as f64 - hilbert_delay - reconstruction_delay;
-    let elapsed_frames = represented_device_frame - latest_render_device_frame as f64;
+    let elapsed_frames = represented_device_frame - latest_render_device_time;
     let cycles =
         phase_used_at_latest_render + frequency as f64 * elapsed_frames / device_sample_rate as f64;
     (std::f64::consts::TAU * cycles - std::f64::consts::FRAC_PI_2).rem_euclid(std::f64::consts::TAU)
@@ -993,29 +1330,53 @@ fn bessel_i0(x: f64) -> f64 {
     sum
 }
 
-/// Design the polyphase reconstruction filter: a Kaiser-windowed sinc
-/// lowpass with cutoff at the internal Nyquist (device_rate / (2 *
-/// RENDER_DECIMATION)), scaled by RENDER_DECIMATION so each polyphase
-/// branch has unity DC gain. This single filter both interpolates the
-/// zero-stuffed internal-rate signal and suppresses its spectral images
-/// (~100 dB at Kaiser beta 10). Runs once at engine construction.
+/// Design the reconstruction filter table: a Kaiser-windowed sinc lowpass
+/// with cutoff at the internal Nyquist, as a continuous function of the time
+/// since each internal frame, centred RECONSTRUCTION_DELAY frames back.
+/// Row `j` holds the FIR_TAPS_PER_PHASE taps for a device frame `j /
+/// FIR_PHASES` internal frames after the newest one, normalised to unity DC
+/// gain so the output carries no ripple at the internal rate. This single
+/// filter both interpolates and suppresses the internal-rate images (~100 dB
+/// at Kaiser beta 10). Runs once at engine construction.
 fn design_reconstruction_fir() -> Box<[f32; FIR_LEN]> {
     let beta = 10.0f64;
     let denom = bessel_i0(beta);
-    let center = (FIR_LEN - 1) as f64 / 2.0;
-    let fc = 0.5 / RENDER_DECIMATION as f64; // internal Nyquist, normalised to the device rate
     let mut h = Box::new([0.0f32; FIR_LEN]);
-    for (n, tap) in h.iter_mut().enumerate() {
-        let t = n as f64 - center;
-        let x = std::f64::consts::PI * 2.0 * fc * t;
-        let sinc = if x.abs() < 1e-9 { 1.0 } else { x.sin() / x };
-        let r = t / center;
-        let window = bessel_i0(beta * (1.0 - r * r).max(0.0).sqrt()) / denom;
-        *tap = (2.0 * fc * RENDER_DECIMATION as f64 * sinc * window) as f32;
+    for (j, row) in h.chunks_exact_mut(FIR_TAPS_PER_PHASE).enumerate() {
+        let frac = j as f64 / FIR_PHASES as f64;
+        let mut taps = [0.0f64; FIR_TAPS_PER_PHASE];
+        for (k, tap) in taps.iter_mut().enumerate() {
+            let t = k as f64 + frac - RECONSTRUCTION_DELAY;
+            let x = std::f64::consts::PI * t;
+            let sinc = if x.abs() < 1e-9 { 1.0 } else { x.sin() / x };
+            let r = t / RECONSTRUCTION_DELAY;
+            *tap = sinc * bessel_i0(beta * (1.0 - r * r).max(0.0).sqrt()) / denom;
+        }
+        let sum: f64 = taps.iter().sum();
+        for (out, tap) in row.iter_mut().zip(taps) {
+            *out = (tap / sum) as f32;
+        }
     }
     h
 }
 
+/// Taps for a device frame `frac` (0..1) internal frames after the newest
+/// internal frame, linearly interpolated between the two nearest table rows.
+fn reconstruction_coefficients(
+    fir: &[f32; FIR_LEN],
+    frac: f32,
+    out: &mut [f32; FIR_TAPS_PER_PHASE],
+) {
+    let position = frac.clamp(0.0, 1.0) * FIR_PHASES as f32;
+    let row = (position as usize).min(FIR_PHASES - 1);
+    let weight = position - row as f32;
+    let lower = &fir[row * FIR_TAPS_PER_PHASE..(row + 1) * FIR_TAPS_PER_PHASE];
+    let upper = &fir[(row + 1) * FIR_TAPS_PER_PHASE..(row + 2) * FIR_TAPS_PER_PHASE];
+    for ((tap, &a), &b) in out.iter_mut().zip(lower).zip(upper) {
+        *tap = a + (b - a) * weight;
+    }
+}
+
 /// Design the bandlimited scatter kernel (see `SPLAT_TAPS`): for each of
 /// `SPLAT_PHASES` fractional arrival positions, a Kaiser-windowed sinc sampled
 /// onto the `SPLAT_TAPS` integer cells straddling that position, normalised so
@@ -1102,6 +1463,15 @@ impl MotionController2d {
         self.target = target;
     }
 
+    /// Translate the whole trajectory, e.g. by one period of a wrapped
+    /// metric; velocity and acceleration carry over unchanged.
+    fn shift(&mut self, offset: (f32, f32)) {
+        self.position.0 += offset.0;
+        self.position.1 += offset.1;
+        self.target.0 += offset.0;
+        self.target.1 += offset.1;
+    }
+
     fn step(&mut self, dt: f32, max_speed: f32) -> (f32, f32) {
         let omega = std::f32::consts::TAU * MOTION_NATURAL_FREQUENCY_HZ;
         let omega_sq = omega * omega;
@@ -1242,6 +1612,108 @@ impl ScalarRamp {
     }
 }
 
+/// Transducer positions, heights, and gains as rendered. A hot reload
+/// retargets them from wherever they are, and each frame moves them linearly
+/// toward the applied layout, so a calibration tweak glides rather than
+/// stepping the field or clicking a channel.
+struct LayoutGlide {
+    positions: [(f32, f32); MAX_TRANSDUCERS],
+    heights: [f32; MAX_TRANSDUCERS],
+    gains: [f32; MAX_TRANSDUCERS],
+    from_positions: [(f32, f32); MAX_TRANSDUCERS],
+    from_heights: [f32; MAX_TRANSDUCERS],
+    from_gains: [f32; MAX_TRANSDUCERS],
+    /// Progress through the position and gain ramps, 1.0 once settled.
+    position_t: f32,
+    gain_t: f32,
+}
+
+impl LayoutGlide {
+    /// Settled on `layout`.
+    fn new(layout: &TransducerLayout) -> Self {
+        Self {
+            positions: layout.positions,
+            heights: layout.heights,
+            gains: layout.gains,
+            from_positions: layout.positions,
+            from_heights: layout.heights,
+            from_gains: layout.gains,
+            position_t: 1.0,
+            gain_t: 1.0,
+        }
+    }
+
+    /// Start both ramps from the values rendered now.
+    fn retarget(&mut self) {
+        self.from_positions = self.positions;
+        self.from_heights = self.heights;
+        self.from_gains = self.gains;
+        self.position_t = 0.0;
+        self.gain_t = 0.0;
+    }
+
+    fn step(&mut self, layout: &TransducerLayout, dt: f32) {
+        let count = layout.count;
+        if self.position_t < 1.0 {
+            self.position_t = (self.position_t + dt / LAYOUT_POSITION_RAMP_S).min(1.0);
+            let t = self.position_t;
+            for i in 0..count {
+                let (from, to) = (self.from_positions[i], layout.positions[i]);
+                self.positions[i] = (lerp(from.0, to.0, t), lerp(from.1, to.1, t));
+                self.heights[i] = lerp(self.from_heights[i], layout.heights[i], t);
+            }
+        }
+        if self.gain_t < 1.0 {
+            self.gain_t = (self.gain_t + dt / LAYOUT_GAIN_RAMP_S).min(1.0);
+            for i in 0..count {
+                self.gains[i] = lerp(self.from_gains[i], layout.gains[i], self.gain_t);
+            }
+        }
+    }
+}
+
+/// Exactly `to` at `t = 1`, so a finished ramp lands on the layout's values.
+fn lerp(from: f32, to: f32, t: f32) -> f32 {
+    if t >= 1.0 {
+        to
+    } else {
+        from + (to - from) * t
+    }
+}
+
+/// Engine-level gain normaliser driven by the summed instantaneous source
+/// amplitude of every active voice. A demand at or below unity (any solo
+/// voice) targets unity gain; above it the target is `1 / demand`. The gain
+/// follows its target through a one-pole with separate attack (falling) and
+/// release (rising) time constants, advanced at the internal render rate.
+struct HeadroomNormaliser {
+    gain: f32,
+}
+
+impl Default for HeadroomNormaliser {
+    fn default() -> Self {
+        Self { gain: 1.0 }
+    }
+}
+
+impl HeadroomNormaliser {
+    fn step(&mut self, demand: f32, config: HeadroomConfig, dt: f32) -> f32 {
+        if !config.enabled {
+            self.gain = 1.0;
+            return self.gain;
+        }
+        let target = if demand > 1.0 { 1.0 / demand } else { 1.0 };
+        let tau_ms = if target < self.gain {
+            config.attack_ms
+        } else {
+            config.release_ms
+        };
+        let coeff = 1.0 - (-dt * 1000.0 / tau_ms.max(f32::MIN_POSITIVE)).exp();
+        self.gain += (target - self.gain) * coeff;
+        self.gain
+    }
+}
+
 // Delay line for wave propagation
 /// A propagation delay line for a *moving source, fixed listener*, using
 /// **scatter writes and a sequential read** (interpolating write / fixed
@@ -1361,7 +1833,8 @@ impl Default for DelayLine {
 
 #[derive(Default)]
 pub struct WaveStimulus {
-    delay_lines: [DelayLine; TRANSDUCER_COUNT],
+    /// One per transducer, allocated when the voice pool is built.
+    delay_lines: Vec<DelayLine>,
 
     // Source state
     frequency: f32,
@@ -1372,12 +1845,20 @@ pub struct WaveStimulus {
     source_pos: (f32, f32),
     /// Latest raw position requested by MPE.
     requested_pos: (f32, f32),
+    /// Source z, latched at note-on: the motion controller bounds only XY
+    /// velocity, so a live height change could outrun the wave.
+    source_height: f32,
     /// Latest complete controller sample. Repeated callback-boundary updates
     /// intentionally overwrite this target without mutating motion state.
     spatial_mpe_target: MpeData,
     motion: MotionController2d,
     /// Note-on initializes the controller when table dimensions are available.
     motion_needs_jump: bool,
+    /// Delay in frames last written per transducer, allocated with the delay
+    /// lines. Successive delays differ by at most SOURCE_SPEED_FRACTION,
+    /// whatever moved: the source, a gliding transducer, a metric switch, or
+    /// the wave speed.
+    delays: Vec<f32>,
     wave_speed: f32, // Individual wave speed for this stimulus
     decay_d0: ScalarRamp,
     decay_exponent: ScalarRamp,
@@ -1456,6 +1937,116 @@ fn mpe_source_position(mpe: MpeData, table_m: (f32, f32)) -> (f32, f32) {
     )
 }
 
+/// Longest physical propagation a Wave delay line represents, in seconds;
+/// longer delays are clamped to it (see `DelayLine::write_and_read`).
+pub fn max_wave_delay_s() -> f32 {
+    (MAX_DELAY_SAMPLES - SPLAT_TAPS - 2 - SPLAT_HALF) as f32 / INTERNAL_RATE
+}
+
+/// Farthest a source anywhere on the layout's table, at `source_height`, can
+/// be from any transducer under the layout's metric. Sources are sampled on a
+/// grid that includes the table's corners.
+pub fn farthest_source_distance(layout: &TransducerLayout, source_height: f32) -> f32 {
+    const STEPS: usize = 32;
+    let kernel = design_splat_kernel();
+    let ctx = ProcessContext {
+        sample_rate: INTERNAL_RATE,
+        dt: 1.0 / INTERNAL_RATE,
+        transducer_positions: layout.positions(),
+        transducer_heights: layout.heights(),
+        table_m: layout.table_m,
+        metric: layout.metric,
+        graph_m: layout.graph_m.as_deref(),
+        splat_kernel: &kernel,
+    };
+    let mut distances = [0.0; MAX_TRANSDUCERS];
+    let mut farthest = 0.0f32;
+    for i in 0..=STEPS {
+        for j in 0..=STEPS {
+            let source = (
+                layout.table_m.0 * i as f32 / STEPS as f32,
+                layout.table_m.1 * j as f32 / STEPS as f32,
+            );
+            source_distances(&ctx, source, source_height, &mut distances);
+            farthest = distances[..layout.count]
+                .iter()
+                .fold(farthest, |a, &b| a.max(b));
+        }
+    }
+    farthest
+}
+
+/// Displacement reduced to the shorter way round a period.
+#[inline]
+fn wrapped(delta: f32, period: Option<f32>) -> f32 {
+    match period {
+        Some(period) => delta - period * (delta / period).round(),
+        None => delta,
+    }
+}
+
+/// Chord between two points on a cylinder of circumference `period` rolled
+/// around the `around` coordinate, each `height` off its surface.
+#[inline]
+fn cylinder_chord(around: (f32, f32), along: f32, heights: (f32, f32), period: f32) -> f32 {
+    let radius = period / std::f32::consts::TAU;
+    let (r0, r1) = ((radius + heights.0).max(0.0), (radius + heights.1).max(0.0));
+    let angle = std::f32::consts::TAU * (around.1 - around.0) / period;
+    (r0 * r0 + r1 * r1 - 2.0 * r0 * r1 * angle.cos() + along * along)
+        .max(0.0)
+        .sqrt()
+}
+
+/// Distance from the source to every transducer under the layout's metric,
+/// one entry per transducer in `ctx`.
+fn source_distances(
+    ctx: &ProcessContext,
+    source_pos: (f32, f32),
+    source_height: f32,
+    distances: &mut [f32; MAX_TRANSDUCERS],
+) {
+    let (period_x, period_y) = ctx.metric.periods(ctx.table_m);
+    for ((distance, &pos), &height) in distances
+        .iter_mut()
+        .zip(ctx.transducer_positions)
+        .zip(ctx.transducer_heights)
+    {
+        *distance = match ctx.metric {
+            DistanceMetric::Cylinder { axis: Axis::X } => cylinder_chord(
+                (source_pos.0, pos.0),
+                pos.1 - source_pos.1,
+                (source_height, height),
+                ctx.table_m.0,
+            ),
+            DistanceMetric::Cylinder { axis: Axis::Y } => cylinder_chord(
+                (source_pos.1, pos.1),
+                pos.0 - source_pos.0,
+                (source_height, height),
+                ctx.table_m.1,
+            ),
+            DistanceMetric::Euclidean | DistanceMetric::Torus { .. } | DistanceMetric::Graph => {
+                let dx = wrapped(pos.0 - source_pos.0, period_x);
+                let dy = wrapped(pos.1 - source_pos.1, period_y);
+                let dz = height - source_height;
+                (dx * dx + dy * dy + dz * dz).sqrt()
+            }
+        };
+    }
+    if let (DistanceMetric::Graph, Some(graph_m)) = (ctx.metric, ctx.graph_m) {
+        // Straight to the nearest transducer, then only along the edges.
+        // Distances step when the nearest transducer changes.
+        let count = ctx.transducer_positions.len();
+        let (entry, &offset) = distances[..count]
+            .iter()
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(b.1))
+            .expect("layouts have at least one transducer");
+        for (distance, path) in distances[..count].iter_mut().zip(&graph_m[entry]) {
+            *distance = offset + path;
+        }
+    }
+}
+
 #[inline]
 fn advance_oscillator_phase(phase: &mut f32, frequency: f32, dt: f32) {
     *phase += frequency * dt;
@@ -1465,8 +2056,8 @@ fn advance_oscillator_phase(phase: &mut f32, frequency: f32, dt: f32) {
 }
 
 impl Stimulus for WaveStimulus {
-    fn process(&mut self, ctx: &ProcessContext<'_>) -> [f32; TRANSDUCER_COUNT] {
-        let mut output = [0.0; TRANSDUCER_COUNT];
+    fn process(&mut self, ctx: &ProcessContext<'_>) -> [f32; MAX_TRANSDUCERS] {
+        let mut output = [0.0; MAX_TRANSDUCERS];
 
         if self.env_state == EnvelopeState::Idle && self.tail_frames_remaining == 0 {
             return output;
@@ -1496,16 +2087,33 @@ impl Stimulus for WaveStimulus {
         // Advance one coherent source in the XY plane. Vector velocity is
         // bounded to SOURCE_SPEED_FRACTION*c, which automatically bounds every
         // transducer's radial velocity and keeps scatter arrivals monotonic.
+        // On a wrapped metric it heads for the nearest image of the request
+        // and folds back into the table after crossing an edge, so a source
+        // can keep circling without sweeping back across the table.
+        let periods = ctx.metric.periods(ctx.table_m);
+        let jumped = self.motion_needs_jump;
         if self.motion_needs_jump {
             self.motion_needs_jump = false;
             self.motion.jump(self.requested_pos);
             self.source_pos = self.requested_pos;
         } else {
-            self.motion.set_target(self.requested_pos);
-            self.source_pos = self.motion.step(
+            let from = self.motion.position;
+            self.motion.set_target((
+                from.0 + wrapped(self.requested_pos.0 - from.0, periods.0),
+                from.1 + wrapped(self.requested_pos.1 - from.1, periods.1),
+            ));
+            let pos = self.motion.step(
                 ctx.dt,
                 SOURCE_SPEED_FRACTION * self.wave_speed.max(MIN_WAVE_SPEED),
             );
+            let fold = |p: f32, period: Option<f32>| {
+                period.map_or(0.0, |period| -period * (p / period).floor())
+            };
+            let offset = (fold(pos.0, periods.0), fold(pos.1, periods.1));
+            if offset != (0.0, 0.0) {
+                self.motion.shift(offset);
+            }
+            self.source_pos = self.motion.position;
         }
 
         // Generate source signal
@@ -1517,13 +2125,26 @@ impl Stimulus for WaveStimulus {
         let mut latest_arrival_frames = 0usize;
 
         // Process through delay lines
-        for (i, &transducer_pos) in ctx.transducer_positions.iter().enumerate() {
-            let dx = transducer_pos.0 - self.source_pos.0;
-            let dy = transducer_pos.1 - self.source_pos.1;
-            let distance = (dx * dx + dy * dy).sqrt();
-
+        let mut distances = [0.0; MAX_TRANSDUCERS];
+        source_distances(ctx, self.source_pos, self.source_height, &mut distances);
+        for (((out, line), &distance), last_delay) in output
+            .iter_mut()
+            .zip(self.delay_lines.iter_mut())
+            .zip(&distances[..ctx.transducer_positions.len()])
+            .zip(self.delays.iter_mut())
+        {
             let delay_time = distance / self.wave_speed.max(MIN_WAVE_SPEED); // per-stimulus wave speed, floor avoids div by zero
-            let delay_samples = delay_time * ctx.sample_rate;
+            let mut delay_samples = delay_time * ctx.sample_rate;
+            // Source motion alone stays within the bound; anything else that
+            // moves a delay (a hot-reloaded layout, above all) is slewed so
+            // arrivals stay monotonic and gapless instead of sweeping
+            if !jumped {
+                delay_samples = delay_samples.clamp(
+                    *last_delay - SOURCE_SPEED_FRACTION,
+                    *last_delay + SOURCE_SPEED_FRACTION,
+                );
+            }
+            *last_delay = delay_samples;
             latest_arrival_frames = latest_arrival_frames.max(
                 (delay_samples.ceil().max(0.0) as usize + SPLAT_TAPS + SPLAT_HALF)
                     .min(MAX_DELAY_SAMPLES - 1),
@@ -1534,8 +2155,7 @@ impl Stimulus for WaveStimulus {
             // the sequential read is then raw. Doppler amplitude gain from
             // bunched arrivals rides on top of this geometric spreading loss.
             let emitted = source * distance_gain(distance, decay);
-            output[i] =
-                self.delay_lines[i].write_and_read(emitted, delay_samples, ctx.splat_kernel);
+            *out = line.write_and_read(emitted, delay_samples, ctx.splat_kernel);
         }
 
         if source_active {
@@ -1595,6 +2215,7 @@ impl Stimulus for WaveStimulus {
         self.phase = 0.0;
         self.source_pos = (0.0, 0.0);
         self.requested_pos = (0.0, 0.0);
+        self.source_height = DEFAULT_SOURCE_HEIGHT_M;
         self.spatial_mpe_target = MpeData::default();
         self.motion = MotionController2d::default();
         self.motion_needs_jump = true;
@@ -1617,13 +2238,30 @@ impl Stimulus for WaveStimulus {
         self.decay_d0.set_target(decay.d0_m);
         self.decay_exponent.set_target(decay.exponent);
     }
+
+    fn source_level(&self) -> f32 {
+        self.amplitude * self.env_level * self.mpe.value.pressure
+    }
 }
 
 impl WaveStimulus {
+    /// A voice driving `count` transducers, with its delay lines allocated.
+    pub fn with_transducers(count: usize) -> Self {
+        Self {
+            delay_lines: (0..count).map(|_| DelayLine::new()).collect(),
+            delays: vec![0.0; count],
+            ..Self::default()
+        }
+    }
+
     fn configure_distance_decay(&mut self, decay: DistanceDecay) {
         self.decay_d0.jump(decay.d0_m);
         self.decay_exponent.jump(decay.exponent);
     }
+
+    fn set_source_height(&mut self, height_m: f32) {
+        self.source_height = height_m;
+    }
 }
 
 /// Instantaneous radial travelling wave. Unlike `WaveStimulus`, this owns no
@@ -1635,6 +2273,7 @@ pub struct TravellingWaveStimulus {
     phase: f32,
     amplitude: f32,
     source_pos: (f32, f32),
+    source_height: ScalarRamp,
     scale_mode: SpatialScaleMode,
     wave_speed: f32,
     configured_wavelength_m: f32,
@@ -1659,6 +2298,14 @@ impl TravellingWaveStimulus {
         std::f32::consts::TAU / wavelength.max(MIN_WAVELENGTH_M)
     }
 
+    fn set_source_height(&mut self, height_m: f32, immediate: bool) {
+        if immediate {
+            self.source_height.jump(height_m);
+        } else {
+            self.source_height.set_target(height_m);
+        }
+    }
+
     fn configure(&mut self, config: TravellingWaveConfig, decay: DistanceDecay, immediate: bool) {
         self.scale_mode = config.scale_mode;
         self.wave_speed = config.wave_speed.clamp(MIN_WAVE_SPEED, MAX_WAVE_SPEED);
@@ -1695,8 +2342,8 @@ impl TravellingWaveStimulus {
 }
 
 impl Stimulus for TravellingWaveStimulus {
-    fn process(&mut self, ctx: &ProcessContext<'_>) -> [f32; TRANSDUCER_COUNT] {
-        let mut output = [0.0; TRANSDUCER_COUNT];
+    fn process(&mut self, ctx: &ProcessContext<'_>) -> [f32; MAX_TRANSDUCERS] {
+        let mut output = [0.0; MAX_TRANSDUCERS];
 
         if self.env_state == EnvelopeState::Idle {
             return output;
@@ -1711,6 +2358,7 @@ impl Stimulus for TravellingWaveStimulus {
 
         let mpe = self.mpe.step(ctx.dt);
         self.source_pos = mpe_source_position(mpe, ctx.table_m);
+        let source_height = self.source_height.step(ctx.dt);
         let k = self.wavenumber.step(ctx.dt);
         let decay = DistanceDecay {
             d0_m: self.decay_d0.step(ctx.dt),
@@ -1719,10 +2367,12 @@ impl Stimulus for TravellingWaveStimulus {
 
         let gain = self.amplitude * self.env_level * mpe.pressure;
         let theta = self.phase * std::f32::consts::TAU;
-        for (sample, &pos) in output.iter_mut().zip(ctx.transducer_positions.iter()) {
-            let dx = pos.0 - self.source_pos.0;
-            let dy = pos.1 - self.source_pos.1;
-            let distance = (dx * dx + dy * dy).sqrt();
+        let mut distances = [0.0; MAX_TRANSDUCERS];
+        source_distances(ctx, self.source_pos, source_height, &mut distances);
+        for (sample, &distance) in output
+            .iter_mut()
+            .zip(&distances[..ctx.transducer_positions.len()])
+        {
             *sample = gain * distance_gain(distance, decay) * (theta - k * distance).sin();
         }
 
@@ -1775,6 +2425,7 @@ impl Stimulus for TravellingWaveStimulus {
         self.release_start_level = 0.0;
         self.mpe = MpeInterp::default();
         self.source_pos = (0.0, 0.0);
+        self.source_height.jump(DEFAULT_SOURCE_HEIGHT_M);
         self.wave_speed = DEFAULT_WAVE_SPEED;
         self.configured_wavelength_m = haptic_protocol::DEFAULT_WAVELENGTH_M;
         self.scale_mode = SpatialScaleMode::Speed;
@@ -1794,6 +2445,10 @@ impl Stimulus for TravellingWaveStimulus {
         self.decay_d0.set_target(decay.d0_m);
         self.decay_exponent.set_target(decay.exponent);
     }
+
+    fn source_level(&self) -> f32 {
+        self.amplitude * self.env_level * self.mpe.value.pressure
+    }
 }
 
 #[cfg(test)]
@@ -1812,7 +2467,7 @@ mod tests {
 
     fn run_samples(engine: &mut StimulusEngine, n: usize) -> f32 {
         let mut peak = 0.0f32;
-        let mut output = [0.0f32; TRANSDUCER_COUNT];
+        let mut output = [0.0f32; MAX_TRANSDUCERS];
         for _ in 0..n {
             engine.process(&mut output, SAMPLE_RATE);
             for &s in output.iter() {
@@ -1850,10 +2505,9 @@ mod tests {
     }
 
     #[test]
-    fn preferred_rate_delay_capacity_covers_default_table_at_speed_floor() {
+    fn delay_capacity_covers_default_table_at_speed_floor() {
         let diagonal = 1.0f32.hypot(2.0);
-        let required = diagonal / MIN_WAVE_SPEED * (SAMPLE_RATE / RENDER_DECIMATION as f32)
-            + SPLAT_TAPS as f32;
+        let required = diagonal / MIN_WAVE_SPEED * INTERNAL_RATE + SPLAT_TAPS as f32;
         assert!(
             required < MAX_DELAY_SAMPLES as f32,
             "required {required} internal frames exceeds {MAX_DELAY_SAMPLES}"
@@ -2443,15 +3097,18 @@ mod tests {
     #[test]
     fn wave_voice_drains_propagation_tail_after_envelope_release() {
         let kernel = design_splat_kernel();
-        let positions = [(1.0, 0.0); TRANSDUCER_COUNT];
+        let positions = [(1.0, 0.0); MAX_TRANSDUCERS];
         let context = ProcessContext {
             sample_rate: 1_500.0,
             dt: 1.0 / 1_500.0,
             transducer_positions: &positions,
+            transducer_heights: &[0.0; MAX_TRANSDUCERS],
             table_m: (1.0, 2.0),
+            metric: DistanceMetric::Euclidean,
+            graph_m: None,
             splat_kernel: &kernel,
         };
-        let mut stimulus = WaveStimulus::default();
+        let mut stimulus = WaveStimulus::with_transducers(MAX_TRANSDUCERS);
         stimulus.note_on(
             40.0,
             127,
@@ -2488,12 +3145,15 @@ mod tests {
     #[test]
     fn note_off_during_attack_releases_from_current_level() {
         let kernel = design_splat_kernel();
-        let positions = [(0.0, 0.0); TRANSDUCER_COUNT];
+        let positions = [(0.0, 0.0); MAX_TRANSDUCERS];
         let context = ProcessContext {
             sample_rate: 1_000.0,
             dt: 0.001,
             transducer_positions: &positions,
+            transducer_heights: &[0.0; MAX_TRANSDUCERS],
             table_m: (1.0, 2.0),
+            metric: DistanceMetric::Euclidean,
+            graph_m: None,
             splat_kernel: &kernel,
         };
         let mut stimulus = TravellingWaveStimulus::default();
@@ -2516,7 +3176,10 @@ mod tests {
             sample_rate: 1_500.0,
             dt: 1.0 / 1_500.0,
             transducer_positions: &positions,
+            transducer_heights: &[0.0; MAX_TRANSDUCERS],
             table_m: (1.0, 2.0),
+            metric: DistanceMetric::Euclidean,
+            graph_m: None,
             splat_kernel: &kernel,
         };
         let mut stimulus = TravellingWaveStimulus::default();
@@ -2543,15 +3206,163 @@ mod tests {
         }
     }
 
+    #[test]
+    fn distance_metrics_wrap_roll_and_follow_graph_edges() {
+        let kernel = design_splat_kernel();
+        let positions = [(0.1, 1.0), (0.9, 1.0), (0.5, 1.9)];
+        let mut graph_m = [[0.0; MAX_TRANSDUCERS]; MAX_TRANSDUCERS];
+        // 0 and 1 are joined only through 2
+        graph_m[0][1] = 2.0;
+        graph_m[1][0] = 2.0;
+        graph_m[0][2] = 1.0;
+        graph_m[2][0] = 1.0;
+        graph_m[1][2] = 1.0;
+        graph_m[2][1] = 1.0;
+        let distances = |metric| {
+            let context = ProcessContext {
+                sample_rate: 1_500.0,
+                dt: 1.0 / 1_500.0,
+                transducer_positions: &positions,
+                transducer_heights: &[0.0; 3],
+                table_m: (1.0, 2.0),
+                metric,
+                graph_m: Some(&graph_m),
+                splat_kernel: &kernel,
+            };
+            let mut distances = [0.0; MAX_TRANSDUCERS];
+            source_distances(&context, (0.05, 1.0), 0.0, &mut distances);
+            distances
+        };
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-5;
+
+        let flat = distances(DistanceMetric::Euclidean);
+        assert!(close(flat[1], 0.85));
+        // Across the x edge the far transducer is only 0.15 m away
+        let torus = distances(DistanceMetric::Torus {
+            wrap_x: true,
+            wrap_y: false,
+        });
+        assert!(close(torus[0], 0.05) && close(torus[1], 0.15));
+        // Rolled around x into a 1 m circumference: the chord is shorter
+        // than the 0.15 m arc
+        let radius = 1.0 / std::f32::consts::TAU;
+        let chord = 2.0 * radius * (std::f32::consts::PI * 0.15).sin();
+        let cylinder = distances(DistanceMetric::Cylinder { axis: Axis::X });
+        assert!(close(cylinder[1], chord) && cylinder[1] < torus[1]);
+        // Graph: enter at the nearest transducer, then 2.0 m to the far one
+        let graph = distances(DistanceMetric::Graph);
+        assert!(close(graph[0], 0.05));
+        assert!(close(graph[1], 2.05));
+        assert!(close(graph[2], 1.05));
+    }
+
+    #[test]
+    fn layout_selection_is_not_an_engine_command() {
+        let select = HapticCommand::SelectLayout { name: "bed".into() };
+        assert!(EngineCommand::from_wire(select, 7).is_none());
+        assert!(matches!(
+            EngineCommand::from_wire(HapticCommand::Panic, 7),
+            Some(EngineCommand::Panic)
+        ));
+    }
+
+    #[test]
+    fn generated_torus_layout_renders_finite_output() {
+        let (layouts, _) = crate::config::parse_config(
+            r#"
+            [table]
+            width_m = 0.8
+            length_m = 0.8
+
+            [[ring]]
+            first_channel = 0
+            count = 8
+            centre = [0.4, 0.4]
+            radius_m = 0.3
+
+            [metric]
+            kind = "torus"
+            "#,
+        )
+        .unwrap();
+        let (mut engine, mut producer, _layout_producer, _voices) =
+            StimulusEngine::new(layouts.active().clone());
+        send(
+            &mut producer,
+            EngineCommand::NoteOn {
+                instance_id: 0,
+                note: 60,
+                velocity: 100,
+                channel: 1,
+                mpe: full_mpe(),
+            },
+        );
+        // A few blocks, into the attack
+        let mut data = vec![0.0; 256 * 8];
+        let mut levels = [0.0; MAX_TRANSDUCERS];
+        let mut peak = 0.0f32;
+        for _ in 0..20 {
+            engine.process_block(&mut data, 8, SAMPLE_RATE, &mut levels);
+            assert!(data.iter().all(|s| s.is_finite()));
+            peak = data.iter().fold(peak, |peak, s| peak.max(s.abs()));
+        }
+        assert!(peak > 0.0);
+    }
+
+    #[test]
+    fn travelling_wave_distance_includes_transducer_and_source_height() {
+        let kernel = design_splat_kernel();
+        let positions = TransducerLayout::default().positions;
+        let mut heights = [0.0; MAX_TRANSDUCERS];
+        for (i, z) in heights.iter_mut().enumerate() {
+            *z = 0.05 * (i % 4) as f32;
+        }
+        let context = ProcessContext {
+            sample_rate: 1_500.0,
+            dt: 1.0 / 1_500.0,
+            transducer_positions: &positions,
+            transducer_heights: &heights,
+            table_m: (1.0, 2.0),
+            metric: DistanceMetric::Euclidean,
+            graph_m: None,
+            splat_kernel: &kernel,
+        };
+        let mut stimulus = TravellingWaveStimulus::default();
+        stimulus.note_on(100.0, 127, full_mpe());
+        stimulus.configure(
+            TravellingWaveConfig {
+                scale_mode: SpatialScaleMode::Wavelength,
+                wave_speed: 20.0,
+                wavelength_m: 0.5,
+            },
+            DistanceDecay::default(),
+            true,
+        );
+        stimulus.set_source_height(0.3, true);
+        stimulus.env_state = EnvelopeState::Sustain;
+        stimulus.phase = 0.25;
+        let output = stimulus.process(&context);
+        for ((sample, &(x, y)), &z) in output.iter().zip(positions.iter()).zip(heights.iter()) {
+            let (dx, dy, dz) = (x - 0.5, y - 1.0, z - 0.3);
+            let distance = (dx * dx + dy * dy + dz * dz).sqrt();
+            let expected = distance_gain(distance, DistanceDecay::default())
+                * (std::f32::consts::FRAC_PI_2 - std::f32::consts::TAU * distance / 0.5).sin();
+            assert!((sample - expected).abs() < 1e-5, "{sample} != {expected}");
+        }
+    }
+
     #[test]
     fn fixed_wavelength_field_is_frequency_independent_at_equal_phase() {
         let kernel = design_splat_kernel();
-        let positions = [(0.75, 1.0); TRANSDUCER_COUNT];
+        let positions = [(0.75, 1.0); MAX_TRANSDUCERS];
         let context = ProcessContext {
             sample_rate: 1_500.0,
             dt: 1.0 / 1_500.0,
             transducer_positions: &positions,
+            transducer_heights: &[0.0; MAX_TRANSDUCERS],
             table_m: (1.0, 2.0),
+            metric: DistanceMetric::Euclidean,
+            graph_m: None,
             splat_kernel: &kernel,
         };
         let make = |frequency| {
@@ -2580,12 +3391,15 @@ mod tests {
     #[test]
     fn travelling_wave_scale_changes_ramp_without_retrigger_or_tail() {
         let kernel = design_splat_kernel();
-        let positions = [(0.75, 1.0); TRANSDUCER_COUNT];
+        let positions = [(0.75, 1.0); MAX_TRANSDUCERS];
         let context = ProcessContext {
             sample_rate: 1_500.0,
             dt: 1.0 / 1_500.0,
             transducer_positions: &positions,
+            transducer_heights: &[0.0; MAX_TRANSDUCERS],
             table_m: (1.0, 2.0),
+            metric: DistanceMetric::Euclidean,
+            graph_m: None,
             splat_kernel: &kernel,
         };
         let mut stimulus = TravellingWaveStimulus::default();
@@ -2620,12 +3434,15 @@ mod tests {
     #[test]
     fn travelling_wave_source_follows_smoothed_mpe_without_velocity_chase() {
         let kernel = design_splat_kernel();
-        let positions = [(0.0, 0.0); TRANSDUCER_COUNT];
+        let positions = [(0.0, 0.0); MAX_TRANSDUCERS];
         let context = ProcessContext {
             sample_rate: 1_500.0,
             dt: 1.0 / 1_500.0,
             transducer_positions: &positions,
+            transducer_heights: &[0.0; MAX_TRANSDUCERS],
             table_m: (1.0, 2.0),
+            metric: DistanceMetric::Euclidean,
+            graph_m: None,
             splat_kernel: &kernel,
         };
         let mut stimulus = TravellingWaveStimulus::default();
@@ -2764,7 +3581,7 @@ mod tests {
             },
         );
 
-        let mut output = [0.0f32; TRANSDUCER_COUNT];
+        let mut output = [0.0f32; MAX_TRANSDUCERS];
         let mut near_peak = 0.0f32;
         let mut far_early_peak = 0.0f32;
         let mut far_late_peak = 0.0f32;
@@ -2901,10 +3718,11 @@ mod tests {
     #[test]
     fn layout_gains_apply_and_hot_swap_takes_effect() {
         let muted = TransducerLayout {
-            gains: [0.0; TRANSDUCER_COUNT],
+            gains: [0.0; MAX_TRANSDUCERS],
             ..TransducerLayout::default()
         };
-        let (mut engine, mut producer, mut layout_producer, _voices) = StimulusEngine::new(muted);
+        let (mut engine, mut producer, mut layout_producer, mut outputs) =
+            StimulusEngine::new(muted);
 
         send(
             &mut producer,
@@ -2920,9 +3738,170 @@ mod tests {
         assert_eq!(peak, 0.0, "zero gains must mute all output");
 
         // Hot-swap to unity gains: same note keeps sounding, now audible
-        layout_producer.push(TransducerLayout::default()).unwrap();
+        assert!(layout_producer.push(1, TransducerLayout::default()));
         let peak = run_samples(&mut engine, (0.2 * SAMPLE_RATE) as usize);
         assert!(peak > 0.0, "restored gains must un-mute the running voice");
+
+        // The applied version is confirmed through the output snapshots
+        let mut data = [0.0f32; 32 * 32];
+        let mut levels = [0.0f32; MAX_TRANSDUCERS];
+        engine.process_block(&mut data, 32, SAMPLE_RATE, &mut levels);
+        assert_eq!(outputs.pop().unwrap().load.config_version, 1);
+    }
+
+    #[test]
+    fn replaced_layouts_return_to_the_watcher() {
+        let (mut engine, _producer, mut layouts, _outputs) =
+            StimulusEngine::new(TransducerLayout::default());
+        let graph = |version: u64| TransducerLayout {
+            metric: DistanceMetric::Graph,
+            graph_m: Some(Box::new(
+                [[version as f32; MAX_TRANSDUCERS]; MAX_TRANSDUCERS],
+            )),
+            ..TransducerLayout::default()
+        };
+        for version in 1..=4 {
+            assert!(layouts.push(version, graph(version)));
+        }
+        run_samples(&mut engine, 1);
+        assert_eq!(engine.config_version, 4);
+        assert_eq!(layouts.retired.slots(), 4);
+
+        // With every replaced layout still waiting to be freed, the next
+        // one stays queued rather than being freed on the audio thread
+        layouts.layouts.push((5, graph(5))).unwrap();
+        run_samples(&mut engine, 1);
+        assert_eq!(engine.config_version, 4);
+
+        // The watcher's next push frees them and both layouts apply
+        assert!(layouts.push(6, graph(6)));
+        run_samples(&mut engine, 1);
+        assert_eq!(engine.config_version, 6);
+        assert_eq!(engine.layout.graph_m.as_ref().unwrap()[0][0], 6.0);
+        assert_eq!(layouts.retired.slots(), 2);
+    }
+
+    #[test]
+    fn hot_reload_glides_gains_and_slews_wave_delays() {
+        let (mut engine, mut producer, mut layout_producer, _outputs) =
+            StimulusEngine::new(TransducerLayout::default());
+        // Slow enough that the half-second position glide outruns the
+        // delay bound
+        send(
+            &mut producer,
+            EngineCommand::SetParameter {
+                instance_id: 0,
+                parameter: Parameter::WaveSpeed(1.0),
+            },
+        );
+        send(
+            &mut producer,
+            EngineCommand::NoteOn {
+                instance_id: 0,
+                note: 60,
+                velocity: 100,
+                channel: 1,
+                mpe: full_mpe(),
+            },
+        );
+        run_samples(&mut engine, 1000);
+
+        let start_gain = TransducerLayout::default().gains[1];
+        let mut moved = TransducerLayout::default();
+        // Toward the source at the table centre
+        moved.positions[0].1 += 0.75;
+        moved.gains[1] = 0.0;
+        assert!(layout_producer.push(1, moved.clone()));
+
+        let delay = |engine: &StimulusEngine| {
+            engine
+                .wave_pool
+                .stimuli
+                .iter()
+                .find(|voice| voice.is_active())
+                .unwrap()
+                .delays[0]
+        };
+        let mut output = [0.0f32; MAX_TRANSDUCERS];
+        let mut last = delay(&engine);
+        let mut largest_step = 0.0f32;
+        let ramp_frames = (LAYOUT_GAIN_RAMP_S * SAMPLE_RATE) as usize;
+        for frame in 0..(3.0 * SAMPLE_RATE) as usize {
+            engine.process(&mut output, SAMPLE_RATE);
+            if frame == ramp_frames / 2 {
+                let gain = engine.glide.gains[1];
+                assert!(
+                    (gain / start_gain - 0.5).abs() < 0.01,
+                    "gain {gain} halfway"
+                );
+            }
+            let current = delay(&engine);
+            largest_step = largest_step.max((current - last).abs());
+            last = current;
+        }
+        // The delay slewed at the limit, then settled on the new position
+        assert!(largest_step <= SOURCE_SPEED_FRACTION + 1e-3);
+        assert!(
+            largest_step > SOURCE_SPEED_FRACTION - 1e-3,
+            "largest step {largest_step}"
+        );
+        engine.process(&mut output, SAMPLE_RATE);
+        assert!((delay(&engine) - last).abs() < 1e-3);
+        assert_eq!(engine.glide.positions[0], moved.positions[0]);
+        assert_eq!(engine.glide.gains[1], 0.0);
+    }
+
+    #[test]
+    fn headroom_normaliser_reduces_chords_but_not_solo_voices() {
+        let layout = TransducerLayout {
+            headroom: HeadroomConfig {
+                enabled: true,
+                ..HeadroomConfig::default()
+            },
+            ..TransducerLayout::default()
+        };
+        let (mut engine, mut producer, _, _) = StimulusEngine::new(layout);
+        send(
+            &mut producer,
+            EngineCommand::NoteOn {
+                instance_id: 0,
+                note: 40,
+                velocity: 127,
+                channel: 0,
+                mpe: full_mpe(),
+            },
+        );
+        run_samples(&mut engine, (0.3 * SAMPLE_RATE) as usize);
+        assert_eq!(engine.headroom.gain, 1.0, "a solo voice keeps full level");
+
+        for channel in 1..4u8 {
+            send(
+                &mut producer,
+                EngineCommand::NoteOn {
+                    instance_id: 0,
+                    note: 40 + channel,
+                    velocity: 127,
+                    channel,
+                    mpe: full_mpe(),
+                },
+            );
+        }
+        run_samples(&mut engine, (0.3 * SAMPLE_RATE) as usize);
+        assert!(
+            (engine.headroom.gain - 0.25).abs() < 0.01,
+            "four full voices settle at 1/4, got {}",
+            engine.headroom.gain
+        );
+
+        send(&mut producer, EngineCommand::Panic);
+        run_samples(&mut engine, (0.05 * SAMPLE_RATE) as usize);
+        let partially_released = engine.headroom.gain;
+        assert!(
+            partially_released < 0.5,
+            "release is slower than attack, got {partially_released}"
+        );
+        run_samples(&mut engine, (3.0 * SAMPLE_RATE) as usize);
+        assert!(engine.headroom.gain > 0.99);
     }
 
     #[test]
@@ -2972,7 +3951,7 @@ mod tests {
         // 60 ms stereo block: past ch0's delay, well before ch31's
         let frames = (0.06 * SAMPLE_RATE) as usize;
         let mut data = vec![0.0f32; frames * 2];
-        let mut levels = [0.0f32; TRANSDUCER_COUNT];
+        let mut levels = [0.0f32; MAX_TRANSDUCERS];
         engine.process_block(&mut data, 2, SAMPLE_RATE, &mut levels);
 
         let left_peak = data.iter().step_by(2).fold(0.0f32, |m, &s| m.max(s.abs()));
@@ -2992,6 +3971,35 @@ mod tests {
         assert!(levels[0] > 0.0, "logical levels stay pre-routing");
     }
 
+    #[test]
+    fn test_signal_mixes_onto_its_logical_channel_until_panic() {
+        let (mut engine, mut producer, _lp, _voices) =
+            StimulusEngine::new(TransducerLayout::default());
+        send(
+            &mut producer,
+            EngineCommand::SetTestSignal {
+                signal: TestSignal::Tone {
+                    channel: 5,
+                    frequency_hz: 100.0,
+                    level: 0.5,
+                },
+            },
+        );
+        let frames = (0.1 * SAMPLE_RATE) as usize;
+        let mut data = vec![0.0f32; frames * 32];
+        let mut levels = [0.0f32; MAX_TRANSDUCERS];
+        engine.process_block(&mut data, 32, SAMPLE_RATE, &mut levels);
+        assert!(levels[5] > 0.3, "tone RMS {}", levels[5]);
+        assert!(levels
+            .iter()
+            .enumerate()
+            .all(|(channel, &level)| channel == 5 || level == 0.0));
+
+        send(&mut producer, EngineCommand::Panic);
+        engine.process_block(&mut data, 32, SAMPLE_RATE, &mut levels);
+        assert!(levels.iter().all(|&level| level == 0.0));
+    }
+
     #[test]
     fn output_snapshots_include_travelling_wave_references() {
         let (mut engine, mut producer, _, mut snapshots) =
@@ -3018,7 +4026,7 @@ mod tests {
             },
         );
         let mut data = [0.0f32; 32 * 32];
-        let mut levels = [0.0f32; TRANSDUCER_COUNT];
+        let mut levels = [0.0f32; MAX_TRANSDUCERS];
         engine.process_block(&mut data, 32, SAMPLE_RATE, &mut levels);
 
         let snapshot = snapshots.pop().unwrap();
@@ -3027,12 +4035,57 @@ mod tests {
         assert_eq!(snapshot.voices[0].note_type, StimulusType::TravellingWave);
     }
 
+    #[test]
+    fn output_snapshots_report_engine_load_and_their_own_drops() {
+        let (mut engine, mut producer, _, mut snapshots) =
+            StimulusEngine::new(TransducerLayout::default());
+        send(
+            &mut producer,
+            EngineCommand::RegisterInstance {
+                instance_id: 7,
+                config: InstanceConfig::default(),
+            },
+        );
+        for note in [48, 52] {
+            send(
+                &mut producer,
+                EngineCommand::NoteOn {
+                    instance_id: 7,
+                    note,
+                    velocity: 100,
+                    channel: 1,
+                    mpe: full_mpe(),
+                },
+            );
+        }
+        let mut data = [0.0f32; 32 * 32];
+        let mut levels = [0.0f32; MAX_TRANSDUCERS];
+        // Nobody drains the 256-slot ring, so the last ten blocks are lost
+        for _ in 0..266 {
+            engine.process_block(&mut data, 32, SAMPLE_RATE, &mut levels);
+        }
+        let first = snapshots.pop().unwrap();
+        assert_eq!(
+            first.load,
+            EngineLoad {
+                wave_voices: 2,
+                travelling_wave_voices: 0,
+                instances: 1,
+                dropped_snapshots: 0,
+                config_version: 0,
+            }
+        );
+        engine.process_block(&mut data, 32, SAMPLE_RATE, &mut levels);
+        let latest = std::iter::from_fn(|| snapshots.pop().ok()).last().unwrap();
+        assert_eq!(latest.load.dropped_snapshots, 10);
+    }
+
     #[test]
     fn zero_distance_tw_output_is_aligned_with_its_reference_across_rates() {
         for sample_rate in [44_100.0, 48_000.0, 96_000.0] {
             let layout = TransducerLayout {
-                positions: [(0.5, 1.0); TRANSDUCER_COUNT],
-                gains: [0.5; TRANSDUCER_COUNT],
+                positions: [(0.5, 1.0); MAX_TRANSDUCERS],
+                gains: [0.5; MAX_TRANSDUCERS],
                 ..TransducerLayout::default()
             };
             let (mut engine, mut producer, _, mut snapshots) = StimulusEngine::new(layout);
@@ -3057,12 +4110,12 @@ mod tests {
                 },
             );
 
-            let mut data = vec![0.0f32; 512 * TRANSDUCER_COUNT];
-            let mut levels = [0.0f32; TRANSDUCER_COUNT];
+            let mut data = vec![0.0f32; 512 * MAX_TRANSDUCERS];
+            let mut levels = [0.0f32; MAX_TRANSDUCERS];
             let mut latest = None;
             let blocks = (sample_rate as usize).div_ceil(512);
             for _ in 0..blocks {
-                engine.process_block(&mut data, TRANSDUCER_COUNT, sample_rate, &mut levels);
+                engine.process_block(&mut data, MAX_TRANSDUCERS, sample_rate, &mut levels);
                 while let Ok(snapshot) = snapshots.pop() {
                     latest = Some(snapshot);
                 }
@@ -3085,34 +4138,93 @@ mod tests {
     #[test]
     fn reconstruction_fir_has_unity_dc_gain_and_strong_image_rejection() {
         let h = design_reconstruction_fir();
-        // Every polyphase branch must pass DC at unity, or the output would
-        // carry amplitude ripple at the internal rate
-        for p in 0..RENDER_DECIMATION {
-            let sum: f32 = (0..FIR_TAPS_PER_PHASE)
-                .map(|k| h[k * RENDER_DECIMATION + p])
-                .sum();
-            assert!((sum - 1.0).abs() < 0.01, "phase {p} DC gain {sum}");
-        }
-        // Frequency response at the first image of a 200 Hz tone
-        // (internal_rate - 200 Hz): must be deep in the stopband
-        let (sr, f_content) = (48000.0f64, 200.0f64);
-        let f_image = sr / RENDER_DECIMATION as f64 - f_content;
-        let respond = |freq: f64| -> f64 {
-            let (mut re, mut im) = (0.0f64, 0.0f64);
-            for (n, &tap) in h.iter().enumerate() {
-                let w = 2.0 * std::f64::consts::PI * freq * n as f64 / sr;
-                re += tap as f64 * w.cos();
-                im -= tap as f64 * w.sin();
+        // Every fractional position must pass DC at unity, or the output
+        // would carry amplitude ripple at the internal rate
+        let mut coeffs = [0.0f32; FIR_TAPS_PER_PHASE];
+        for step in 0..=1000 {
+            let frac = step as f32 / 1000.0;
+            reconstruction_coefficients(&h, frac, &mut coeffs);
+            let sum: f32 = coeffs.iter().sum();
+            assert!((sum - 1.0).abs() < 1e-4, "frac {frac} DC gain {sum}");
+        }
+        // Effective device-rate response at the first image of a 200 Hz tone
+        // (internal rate - 200 Hz): must be deep in the stopband at every
+        // device rate, including non-integer ratios
+        for sr in [44_100.0f64, 48_000.0, 96_000.0] {
+            let ratio = sr / INTERNAL_RATE as f64;
+            let len = (FIR_TAPS_PER_PHASE as f64 * ratio) as usize;
+            let impulse: Vec<f64> = (0..len)
+                .map(|n| {
+                    let t = n as f64 / ratio;
+                    reconstruction_coefficients(&h, t.fract() as f32, &mut coeffs);
+                    coeffs[t as usize] as f64
+                })
+                .collect();
+            let respond = |freq: f64| -> f64 {
+                let (mut re, mut im) = (0.0f64, 0.0f64);
+                for (n, &tap) in impulse.iter().enumerate() {
+                    let w = 2.0 * std::f64::consts::PI * freq * n as f64 / sr;
+                    re += tap * w.cos();
+                    im -= tap * w.sin();
+                }
+                (re * re + im * im).sqrt() / ratio
+            };
+            let f_content = 200.0f64;
+            let f_image = INTERNAL_RATE as f64 - f_content;
+            let passband_db = 20.0 * respond(f_content).log10();
+            let image_db = 20.0 * respond(f_image).log10();
+            assert!(
+                passband_db.abs() < 0.1,
+                "{sr} Hz: 200 Hz passband gain {passband_db} dB"
+            );
+            assert!(
+                image_db < -90.0,
+                "{sr} Hz: first-image rejection only {image_db} dB"
+            );
+        }
+    }
+
+    #[test]
+    fn internal_render_is_identical_at_every_device_rate() {
+        // The same internal frames reconstructed at different device rates
+        // must agree wherever the device frames coincide in time: every
+        // 1/300 s is 147 frames at 44.1 kHz, 160 at 48 kHz, 320 at 96 kHz.
+        let render = |sample_rate: f32| {
+            let (mut engine, mut producer, _lp, _voices) =
+                StimulusEngine::new(TransducerLayout::default());
+            send(
+                &mut producer,
+                EngineCommand::NoteOn {
+                    instance_id: 0,
+                    note: 60,
+                    velocity: 100,
+                    channel: 1,
+                    mpe: full_mpe(),
+                },
+            );
+            let frames = (sample_rate * 0.3) as usize;
+            let mut data = vec![0.0f32; frames * MAX_TRANSDUCERS];
+            let mut levels = [0.0f32; MAX_TRANSDUCERS];
+            for block in data.chunks_mut(512 * MAX_TRANSDUCERS) {
+                engine.process_block(block, MAX_TRANSDUCERS, sample_rate, &mut levels);
             }
-            (re * re + im * im).sqrt() / RENDER_DECIMATION as f64
+            data
         };
-        let passband_db = 20.0 * respond(f_content).log10();
-        let image_db = 20.0 * respond(f_image).log10();
-        assert!(
-            passband_db.abs() < 0.1,
-            "200 Hz passband gain {passband_db} dB"
-        );
-        assert!(image_db < -90.0, "first-image rejection only {image_db} dB");
+        let reference = render(48_000.0);
+        assert!(reference.iter().any(|&s| s.abs() > 0.01));
+        for (sample_rate, stride) in [(44_100.0, 147usize), (96_000.0, 320)] {
+            let other = render(sample_rate);
+            for m in 0..90 {
+                let a = &reference[m * 160 * MAX_TRANSDUCERS..][..MAX_TRANSDUCERS];
+                let b = &other[m * stride * MAX_TRANSDUCERS..][..MAX_TRANSDUCERS];
+                for (channel, (&a, &b)) in a.iter().zip(b).enumerate() {
+                    assert!(
+                        (a - b).abs() < 1e-5,
+                        "{sample_rate} Hz frame {m}/300 s channel {channel}: {b} vs {a}"
+                    );
+                }
+            }
+        }
     }
 
     #[test]
@@ -3133,7 +4245,7 @@ mod tests {
             (engine, producer)
         };
         let frames = 4800; // 100 ms at the device rate
-        let mut levels = [0.0f32; TRANSDUCER_COUNT];
+        let mut levels = [0.0f32; MAX_TRANSDUCERS];
 
         let (mut a, _keep_a) = make();
         let mut whole = vec![0.0f32; frames * 2];
@@ -3168,7 +4280,7 @@ mod tests {
     #[test]
     fn reconstructed_device_output_is_hard_bounded() {
         let layout = TransducerLayout {
-            gains: [100.0; TRANSDUCER_COUNT],
+            gains: [100.0; MAX_TRANSDUCERS],
             ..TransducerLayout::default()
         };
         let (mut engine, mut producer, _, _) = StimulusEngine::new(layout);
@@ -3184,13 +4296,93 @@ mod tests {
                 },
             );
         }
-        let mut data = vec![0.0f32; 4_800 * TRANSDUCER_COUNT];
-        let mut levels = [0.0f32; TRANSDUCER_COUNT];
-        engine.process_block(&mut data, TRANSDUCER_COUNT, SAMPLE_RATE, &mut levels);
+        let mut data = vec![0.0f32; 4_800 * MAX_TRANSDUCERS];
+        let mut levels = [0.0f32; MAX_TRANSDUCERS];
+        engine.process_block(&mut data, MAX_TRANSDUCERS, SAMPLE_RATE, &mut levels);
         assert!(data.iter().all(|sample| sample.is_finite()));
         assert!(data.iter().all(|sample| sample.abs() <= 1.0));
     }
 
+    #[test]
+    fn capture_spans_exactly_the_frames_between_its_commands() {
+        let (mut engine, mut producer, _, _) = StimulusEngine::new(TransducerLayout::default());
+        let (capture_tx, mut capture_rx) = rtrb::RingBuffer::new(1024);
+        engine.attach_capture(capture_tx);
+        let mut data = vec![0.0f32; 100 * MAX_TRANSDUCERS];
+        let mut levels = [0.0f32; MAX_TRANSDUCERS];
+        engine.process_block(&mut data, MAX_TRANSDUCERS, SAMPLE_RATE, &mut levels);
+        assert!(
+            capture_rx.pop().is_err(),
+            "nothing is captured until enabled"
+        );
+
+        send(&mut producer, EngineCommand::SetCapture { enabled: true });
+        engine.process_block(&mut data, MAX_TRANSDUCERS, SAMPLE_RATE, &mut levels);
+        engine.process_block(&mut data, MAX_TRANSDUCERS, SAMPLE_RATE, &mut levels);
+        send(&mut producer, EngineCommand::SetCapture { enabled: false });
+        engine.process_block(&mut data, MAX_TRANSDUCERS, SAMPLE_RATE, &mut levels);
+
+        match capture_rx.pop().unwrap() {
+            CaptureItem::Begin {
+                sample_rate,
+                device_frame_index,
+            } => {
+                assert_eq!(sample_rate, SAMPLE_RATE);
+                assert_eq!(device_frame_index, 100);
+            }
+            other => panic!("expected Begin, got {other:?}"),
+        }
+        let mut frames = 0;
+        loop {
+            match capture_rx.pop().unwrap() {
+                CaptureItem::Frame(_) => frames += 1,
+                CaptureItem::End { dropped_frames } => {
+                    assert_eq!(dropped_frames, 0);
+                    break;
+                }
+                other => panic!("unexpected {other:?}"),
+            }
+        }
+        assert_eq!(frames, 200);
+        assert!(capture_rx.pop().is_err());
+    }
+
+    #[test]
+    fn clip_counters_attribute_clamps_to_their_channel() {
+        let mut gains = [0.0; MAX_TRANSDUCERS];
+        gains[3] = 100.0;
+        gains[7] = 0.5;
+        let layout = TransducerLayout {
+            gains,
+            ..TransducerLayout::default()
+        };
+        let (mut engine, mut producer, _, _) = StimulusEngine::new(layout);
+        send(
+            &mut producer,
+            EngineCommand::NoteOn {
+                instance_id: 0,
+                note: 40,
+                velocity: 127,
+                channel: 0,
+                mpe: full_mpe(),
+            },
+        );
+        let mut data = vec![0.0f32; 9_600 * MAX_TRANSDUCERS];
+        let mut levels = [0.0f32; MAX_TRANSDUCERS];
+        engine.process_block(&mut data, MAX_TRANSDUCERS, SAMPLE_RATE, &mut levels);
+
+        let clips = engine.clips;
+        assert!(clips.render_clamped[3] > 0);
+        assert!(clips.output_clamped[3] > 0);
+        assert!(clips.render_over_threshold[3] >= clips.render_clamped[3]);
+        assert!(clips.output_over_threshold[3] >= clips.output_clamped[3]);
+        for ch in (0..MAX_TRANSDUCERS).filter(|&ch| ch != 3) {
+            assert_eq!(clips.render_clamped[ch], 0, "channel {ch}");
+            assert_eq!(clips.output_clamped[ch], 0, "channel {ch}");
+            assert_eq!(clips.output_over_threshold[ch], 0, "channel {ch}");
+        }
+    }
+
     /// Machine-local timing harness for the production Wave hot path. It
     /// drives all eight Wave slots, updates every XY target once per callback,
     /// and reports callback percentiles for both a low-latency and the default
@@ -3225,8 +4417,8 @@ mod tests {
             let total_callbacks = (WARMUP_SECS + BENCHMARK_SECS) * callbacks_per_second;
             let measured_callbacks = BENCHMARK_SECS * callbacks_per_second;
             let mut timings_us = Vec::with_capacity(measured_callbacks);
-            let mut data = vec![0.0f32; block_frames * TRANSDUCER_COUNT];
-            let mut levels = [0.0f32; TRANSDUCER_COUNT];
+            let mut data = vec![0.0f32; block_frames * MAX_TRANSDUCERS];
+            let mut levels = [0.0f32; MAX_TRANSDUCERS];
 
             for callback in 0..total_callbacks {
                 let phase =
@@ -3252,7 +4444,7 @@ mod tests {
                 }
 
                 let started = Instant::now();
-                engine.process_block(&mut data, TRANSDUCER_COUNT, SAMPLE_RATE, &mut levels);
+                engine.process_block(&mut data, MAX_TRANSDUCERS, SAMPLE_RATE, &mut levels);
                 let elapsed_us = started.elapsed().as_secs_f64() * 1_000_000.0;
                 if callback >= WARMUP_SECS * callbacks_per_second {
                     timings_us.push(elapsed_us);
@@ -3368,8 +4560,8 @@ mod tests {
         let mut orbit_phase = 0.0f32;
         let radius = env_f32("HAPTIC_CAPTURE_RADIUS_M", 0.35 * width.min(length))
             .clamp(0.0, 0.5 * width.min(length));
-        let mut data = vec![0.0f32; block * TRANSDUCER_COUNT];
-        let mut levels = [0.0f32; TRANSDUCER_COUNT];
+        let mut data = vec![0.0f32; block * MAX_TRANSDUCERS];
+        let mut levels = [0.0f32; MAX_TRANSDUCERS];
         let mut motion_maxima = (0.0f32, 0.0f32, 0.0f32, 0.0f32); // error, speed, acceleration, jerk
         let total_blocks = (secs as f64 * sample_rate as f64 / block as f64) as usize;
         for b in 0..total_blocks {
@@ -3392,7 +4584,7 @@ mod tests {
                 );
                 next_mpe += mpe_interval;
             }
-            engine.process_block(&mut data, TRANSDUCER_COUNT, sample_rate, &mut levels);
+            engine.process_block(&mut data, MAX_TRANSDUCERS, sample_rate, &mut levels);
             let motion = engine.wave_pool.stimuli[0].motion;
             let magnitude = |v: (f32, f32)| (v.0 * v.0 + v.1 * v.1).sqrt();
             motion_maxima.0 = motion_maxima.0.max(magnitude((
@@ -1,17 +1,25 @@
-use crate::config::TransducerLayout;
-use crate::engine::OutputSnapshot;
+use crate::audio::{AudioStats, LoadWindow};
+use crate::capture::{CaptureLink, CaptureRequest};
+use crate::config::{ConfigUpdate, LayoutSet};
+use crate::engine::{
+    ClipCounters, EngineLoad, OutputSnapshot, CLIP_WARN_THRESHOLD, MAX_TRANSDUCERS,
+};
+use crate::state::StateFile;
 use haptic_protocol::{
-    encode_frame, FrameDecoder, FrameError, HapticCommand, InstanceConfig, MpeData, Parameter,
-    ServerStatus, MAX_ATTEN_D0_M, MAX_ATTEN_EXPONENT, MAX_FRAME_SIZE, MAX_WAVELENGTH_M,
-    MAX_WAVE_SPEED, MIDI_CHANNEL_COUNT, MIN_ATTEN_D0_M, MIN_ATTEN_EXPONENT, MIN_WAVELENGTH_M,
-    MIN_WAVE_SPEED, PROTOCOL_VERSION,
+    encode_frame, negotiate_version, Capabilities, FrameDecoder, FrameError, HapticCommand,
+    InstanceConfig, MpeData, Parameter, ServerStatus, TestSignal, MAX_ATTEN_D0_M,
+    MAX_ATTEN_EXPONENT, MAX_CAPTURE_PATH_BYTES, MAX_CONFIG_TEXT_BYTES, MAX_FRAME_SIZE,
+    MAX_LAYOUT_NAME_BYTES, MAX_SOURCE_HEIGHT_M, MAX_SWEEP_PERIOD_S, MAX_TEST_SIGNAL_HZ,
+    MAX_WAVELENGTH_M, MAX_WAVE_SPEED, MIDI_CHANNEL_COUNT, MIN_ATTEN_D0_M, MIN_ATTEN_EXPONENT,
+    MIN_SOURCE_HEIGHT_M, MIN_SWEEP_PERIOD_S, MIN_TEST_SIGNAL_HZ, MIN_WAVELENGTH_M, MIN_WAVE_SPEED,
 };
 use std::collections::{HashSet, VecDeque};
 use std::io::Read;
 use std::io::Write;
 use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
-use std::sync::Arc;
+use std::sync::{mpsc, Arc};
 use std::thread;
 use std::time::Duration;
 use std::time::Instant;
@@ -23,6 +31,10 @@ const LEVELS_BROADCAST_INTERVAL: Duration = Duration::from_millis(16);
 /// socket well ahead of a 120 Hz display without flooding slow clients.
 const VOICE_BROADCAST_INTERVAL: Duration = Duration::from_millis(4);
 
+/// Interval between PerformanceMetrics broadcasts, and so the window their
+/// callback times cover.
+const METRICS_BROADCAST_INTERVAL: Duration = Duration::from_millis(500);
+
 /// Leave enough FIFO capacity for every currently possible voice to receive a
 /// NoteOff plus instance teardown/panic traffic. High-rate MPE and parameter
 /// updates are expendable when the queue approaches this reserve.
@@ -35,6 +47,17 @@ const CRITICAL_COMMAND_RESERVE: usize = 32;
 /// memory growth.
 const MAX_CLIENT_STATUS_BYTES: usize = MAX_FRAME_SIZE * 16;
 
+/// Per-client bytes read from the socket in one IPC iteration. A client with
+/// this much still undecoded is not read at all, so a flood backs up into
+/// its own socket buffer instead of server memory.
+const CLIENT_READ_BUDGET: usize = MAX_FRAME_SIZE * 2;
+
+/// Per-client frames dispatched in one IPC iteration. At the 1 ms loop period
+/// this is far above any controller's legitimate MPE rate, but it keeps one
+/// flooding sender from delaying other clients' lifecycle commands or the
+/// status broadcast.
+const CLIENT_FRAME_BUDGET: usize = 64;
+
 /// A connected plugin instance with its stream-reassembly state.
 struct Client {
     stream: UnixStream,
@@ -48,6 +71,9 @@ struct Client {
     /// one-shot handshake acknowledgement, never the continuous status stream.
     /// False until `Hello`.
     wants_status: bool,
+    /// Features negotiated in the `Hello`; statuses needing others are not
+    /// sent to this client.
+    capabilities: Capabilities,
     /// Whether the one-time layout+routing greeting has been sent (only to
     /// observers, once they identify).
     greeted: bool,
@@ -57,6 +83,9 @@ struct Client {
     status_output: Vec<u8>,
     status_cursor: usize,
     status_overflowed: bool,
+    /// The peer has closed. Frames it sent first are still dispatched,
+    /// within budget, before the connection is dropped.
+    read_closed: bool,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -64,11 +93,18 @@ pub fn listen_loop(
     socket_path: &str,
     running: Arc<AtomicBool>,
     command_producer: rtrb::Producer<crate::engine::EngineCommand>,
-    levels_consumer: rtrb::Consumer<[f32; 32]>,
+    levels_consumer: rtrb::Consumer<[f32; MAX_TRANSDUCERS]>,
     output_consumer: rtrb::Consumer<OutputSnapshot>,
-    layout: TransducerLayout,
-    layout_consumer: rtrb::Consumer<TransducerLayout>,
+    layouts: LayoutSet,
+    config_path: &Path,
+    config_updates: mpsc::Receiver<ConfigUpdate>,
+    routes: [u8; MAX_TRANSDUCERS],
+    state_file: Option<StateFile>,
+    layout_select: mpsc::Sender<String>,
     device_channels: Arc<AtomicU16>,
+    capture: CaptureLink,
+    audio_status: mpsc::Receiver<ServerStatus>,
+    audio_stats: Arc<AudioStats>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     listen_loop_at(
         socket_path,
@@ -76,9 +112,16 @@ pub fn listen_loop(
         command_producer,
         levels_consumer,
         output_consumer,
-        layout,
-        layout_consumer,
+        layouts,
+        config_path,
+        config_updates,
+        routes,
+        state_file,
+        layout_select,
         device_channels,
+        capture,
+        audio_status,
+        audio_stats,
     )
 }
 
@@ -87,11 +130,18 @@ fn listen_loop_at(
     socket_path: &str,
     running: Arc<AtomicBool>,
     mut command_producer: rtrb::Producer<crate::engine::EngineCommand>,
-    mut levels_consumer: rtrb::Consumer<[f32; 32]>,
+    mut levels_consumer: rtrb::Consumer<[f32; MAX_TRANSDUCERS]>,
     mut output_consumer: rtrb::Consumer<OutputSnapshot>,
-    mut layout: TransducerLayout,
-    mut layout_consumer: rtrb::Consumer<TransducerLayout>,
+    mut layouts: LayoutSet,
+    config_path: &Path,
+    config_updates: mpsc::Receiver<ConfigUpdate>,
+    mut routes: [u8; MAX_TRANSDUCERS],
+    mut state_file: Option<StateFile>,
+    layout_select: mpsc::Sender<String>,
     device_channels: Arc<AtomicU16>,
+    mut capture: CaptureLink,
+    audio_status: mpsc::Receiver<ServerStatus>,
+    audio_stats: Arc<AudioStats>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let listener = bind_listener(socket_path)?;
     listener.set_nonblocking(true)?;
@@ -104,17 +154,47 @@ fn listen_loop_at(
     // older connection's delayed cleanup command.
     let mut active_instances: HashSet<u64> = HashSet::new();
     let mut pending_disconnects: VecDeque<u64> = VecDeque::new();
-    let mut latest_levels: Option<[f32; 32]> = None;
+    let mut latest_levels: Option<[f32; MAX_TRANSDUCERS]> = None;
     let mut last_broadcast = Instant::now();
     let mut last_voice_broadcast = Instant::now();
     let mut status_frame = Vec::with_capacity(512);
-
-    // Mirror of the engine's monitor routing (commands pass through this
-    // thread, so a snoop keeps this authoritative for clients)
-    let mut routes: [u8; 32] = std::array::from_fn(|i| i as u8);
+    let mut layout = layouts.active();
+
+    // Config version the engine is running, and the accepted set (if any)
+    // still waiting for the engine to apply it. The latest rejection stays
+    // reported until a newer set is running.
+    let config_path = truncate_text(&config_path.display().to_string());
+    let mut config_version = 0u64;
+    let mut config_error = String::new();
+    let mut pending_layouts: Option<(u64, LayoutSet)> = None;
+
+    // Mirror of the engine's monitor routing, starting from the restored
+    // state (commands pass through this thread, so a snoop keeps this
+    // authoritative for clients and for the state file)
     let mut routing_dirty = false;
     let mut last_device_channels = 0u16;
 
+    // Mirror of the engine's test signal, snooped the same way
+    let mut test_signal = TestSignal::Off;
+    let mut test_signal_dirty = false;
+
+    // Latest cumulative clip counts from the engine; rebroadcast on change
+    let mut clips = ClipCounters::default();
+    let mut clips_dirty = false;
+    let mut last_clip_broadcast = Instant::now();
+
+    // Latest audio stream transition and timing health, replayed to newly
+    // greeted observers
+    let mut audio_stream: Option<ServerStatus> = None;
+    let mut audio_health: Option<ServerStatus> = None;
+
+    // Performance metrics: engine load from the freshest snapshot, callback
+    // timing windows from the audio thread's statistics
+    let mut engine_load = EngineLoad::default();
+    let mut dropped_commands = 0u64;
+    let mut load_window = LoadWindow::default();
+    let mut last_metrics_broadcast = Instant::now();
+
     while running.load(Ordering::Relaxed) {
         // Accept new connections
         match listener.accept() {
@@ -132,10 +212,12 @@ fn listen_loop_at(
                     decoder: FrameDecoder::new(),
                     instance_id: None,
                     wants_status: false,
+                    capabilities: Capabilities::NONE,
                     greeted: false,
                     status_output: Vec::with_capacity(1024),
                     status_cursor: 0,
                     status_overflowed: false,
+                    read_closed: false,
                 });
             }
             Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
@@ -154,6 +236,12 @@ fn listen_loop_at(
                 &mut active_instances,
                 &mut routes,
                 &mut routing_dirty,
+                &mut test_signal,
+                &mut test_signal_dirty,
+                &mut capture,
+                &layouts,
+                &layout_select,
+                &mut dropped_commands,
             );
             if !keep {
                 if let Some(instance_id) = client.instance_id {
@@ -182,8 +270,23 @@ fn listen_loop_at(
         let dc = device_channels.load(Ordering::Relaxed);
         for client in clients.iter_mut() {
             if client.wants_status && !client.greeted {
-                for status in [layout_status(&layout), routing_status(&routes, dc)] {
-                    if encode_frame(&status, &mut status_frame).is_ok() {
+                for status in [
+                    layout_status(&layouts),
+                    config_status(config_version, &config_path, &config_error),
+                    routing_status(&routes[..layout.count], dc),
+                    clip_status(&clips, layout.count),
+                    capture.status(),
+                    ServerStatus::TestSignal {
+                        signal: test_signal,
+                    },
+                ]
+                .into_iter()
+                .chain(audio_stream.clone())
+                .chain(audio_health.clone())
+                {
+                    if client.capabilities.contains(status.capability())
+                        && encode_frame(&status, &mut status_frame).is_ok()
+                    {
                         queue_status_frame(client, &status_frame);
                     }
                 }
@@ -191,6 +294,22 @@ fn listen_loop_at(
             }
         }
 
+        // Every audio stream transition is broadcast, not just the latest
+        while let Ok(status) = audio_status.try_recv() {
+            broadcast(&mut clients, &status, &mut status_frame);
+            if matches!(status, ServerStatus::AudioHealth { .. }) {
+                audio_health = Some(status);
+            } else {
+                audio_stream = Some(status);
+            }
+        }
+
+        // Capture progress and completion reported by the writer thread
+        capture.poll(&mut command_producer);
+        if capture.take_dirty() {
+            broadcast(&mut clients, &capture.status(), &mut status_frame);
+        }
+
         // Broadcast routing when it changes or once the device is known
         if dc != last_device_channels {
             last_device_channels = dc;
@@ -198,16 +317,33 @@ fn listen_loop_at(
         }
         if routing_dirty {
             routing_dirty = false;
-            if encode_frame(&routing_status(&routes, dc), &mut status_frame).is_ok() {
-                broadcast(&mut clients, &status_frame);
-            }
+            broadcast(
+                &mut clients,
+                &routing_status(&routes[..layout.count], dc),
+                &mut status_frame,
+            );
         }
 
-        // Hot reload: adopt and rebroadcast the layout to every client
-        while let Ok(new_layout) = layout_consumer.pop() {
-            layout = new_layout;
-            if encode_frame(&layout_status(&layout), &mut status_frame).is_ok() {
-                broadcast(&mut clients, &status_frame);
+        if test_signal_dirty {
+            test_signal_dirty = false;
+            let status = ServerStatus::TestSignal {
+                signal: test_signal,
+            };
+            broadcast(&mut clients, &status, &mut status_frame);
+        }
+
+        // Hot reload or selection: hold an accepted set until the engine
+        // runs it, report a rejection at once
+        while let Ok(update) = config_updates.try_recv() {
+            match update {
+                ConfigUpdate::Accepted { version, layouts } => {
+                    pending_layouts = Some((version, layouts));
+                }
+                ConfigUpdate::Rejected { error } => {
+                    config_error = truncate_text(&error);
+                    let status = config_status(config_version, &config_path, &config_error);
+                    broadcast(&mut clients, &status, &mut status_frame);
+                }
             }
         }
 
@@ -222,6 +358,29 @@ fn listen_loop_at(
             latest_output = Some(snapshot);
         }
         if let Some(output) = latest_output {
+            engine_load = output.load;
+            // The engine confirmed the pending set: adopt and rebroadcast
+            // the layout and config version to every client
+            if pending_layouts
+                .as_ref()
+                .is_some_and(|(version, _)| engine_load.config_version >= *version)
+            {
+                let (version, new_layouts) = pending_layouts.take().unwrap();
+                layouts = new_layouts;
+                layout = layouts.active();
+                config_version = version;
+                config_error.clear();
+                for status in [
+                    layout_status(&layouts),
+                    config_status(config_version, &config_path, &config_error),
+                ] {
+                    broadcast(&mut clients, &status, &mut status_frame);
+                }
+            }
+            if output.clips != clips {
+                clips = output.clips;
+                clips_dirty = true;
+            }
             if last_voice_broadcast.elapsed() >= VOICE_BROADCAST_INTERVAL {
                 last_voice_broadcast = Instant::now();
                 let status = ServerStatus::OutputState {
@@ -229,13 +388,12 @@ fn listen_loop_at(
                     device_sample_rate: output.device_sample_rate,
                     sample_index: output.sample_index,
                     valid: output.valid,
-                    analytic: output.analytic,
+                    headroom_gain: output.headroom_gain,
+                    analytic: output.analytic[..layout.count].to_vec(),
                     count: output.count,
                     voices: output.voices,
                 };
-                if encode_frame(&status, &mut status_frame).is_ok() {
-                    broadcast(&mut clients, &status_frame);
-                }
+                broadcast(&mut clients, &status, &mut status_frame);
             }
         }
 
@@ -245,23 +403,61 @@ fn listen_loop_at(
                 last_broadcast = Instant::now();
                 let status = ServerStatus::TransducerLevels {
                     timestamp_us: now_us(),
-                    levels,
+                    levels: levels[..layout.count].to_vec(),
                 };
-                if encode_frame(&status, &mut status_frame).is_ok() {
-                    broadcast(&mut clients, &status_frame);
-                }
+                broadcast(&mut clients, &status, &mut status_frame);
             }
         }
 
+        if last_metrics_broadcast.elapsed() >= METRICS_BROADCAST_INTERVAL {
+            last_metrics_broadcast = Instant::now();
+            let callbacks = audio_stats.take_window(&mut load_window);
+            let capacity = command_producer.buffer().capacity();
+            let status = ServerStatus::PerformanceMetrics {
+                active_stimuli: engine_load.wave_voices + engine_load.travelling_wave_voices,
+                cpu_percent: (callbacks.busy * 100.0).round().clamp(0.0, 255.0) as u8,
+                callback_p50: callbacks.p50,
+                callback_p99: callbacks.p99,
+                callback_max: callbacks.max,
+                command_queue_depth: (capacity - command_producer.slots()) as u32,
+                dropped_commands,
+                dropped_snapshots: engine_load.dropped_snapshots,
+                wave_voices: engine_load.wave_voices,
+                travelling_wave_voices: engine_load.travelling_wave_voices,
+                instances: engine_load.instances,
+            };
+            broadcast(&mut clients, &status, &mut status_frame);
+        }
+
+        // Clip counts are cumulative, so coalescing changes loses no events
+        if clips_dirty && last_clip_broadcast.elapsed() >= LEVELS_BROADCAST_INTERVAL {
+            clips_dirty = false;
+            last_clip_broadcast = Instant::now();
+            broadcast(
+                &mut clients,
+                &clip_status(&clips, layout.count),
+                &mut status_frame,
+            );
+        }
+
         // Complete queued observer writes without blocking this IPC loop.
         // Any terminal failure or sustained backlog is removed through the
         // same disconnect queue as a read-side socket closure, guaranteeing
         // that viewer-owned held notes are released.
         flush_status_clients(&mut clients, &mut pending_disconnects);
 
+        if let Some(state_file) = state_file.as_mut() {
+            state_file.poll(&routes[..layout.count], false);
+        }
+
         thread::sleep(Duration::from_millis(1));
     }
 
+    // A change still waiting out the save delay is not lost on shutdown
+    if let Some(state_file) = state_file.as_mut() {
+        state_file.poll(&routes[..layout.count], true);
+    }
+
     // Cleanup
     let _ = std::fs::remove_file(socket_path);
     eprintln!("IPC server stopped");
@@ -295,25 +491,65 @@ fn now_us() -> u64 {
         .as_micros() as u64
 }
 
-fn layout_status(layout: &TransducerLayout) -> ServerStatus {
+fn layout_status(layouts: &LayoutSet) -> ServerStatus {
+    let layout = layouts.active();
     ServerStatus::Layout {
-        positions: layout.positions,
-        gains: layout.gains,
+        positions: layout.positions().to_vec(),
+        heights: layout.heights().to_vec(),
+        gains: layout.gains().to_vec(),
         table_m: layout.table_m,
+        name: layouts.active_name().to_string(),
+        available: layouts.names().map(str::to_string).collect(),
     }
 }
 
-fn routing_status(routes: &[u8; 32], device_channels: u16) -> ServerStatus {
+fn config_status(version: u64, path: &str, error: &str) -> ServerStatus {
+    ServerStatus::ConfigState {
+        version,
+        path: path.to_string(),
+        error: error.to_string(),
+    }
+}
+
+/// Cut `text` to `MAX_CONFIG_TEXT_BYTES` on a character boundary.
+fn truncate_text(text: &str) -> String {
+    let mut end = text.len().min(MAX_CONFIG_TEXT_BYTES);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text[..end].to_string()
+}
+
+fn routing_status(routes: &[u8], device_channels: u16) -> ServerStatus {
     ServerStatus::MonitorRouting {
         device_channels,
-        routes: *routes,
+        routes: routes.to_vec(),
+    }
+}
+
+fn clip_status(clips: &ClipCounters, count: usize) -> ServerStatus {
+    ServerStatus::ClipCounts {
+        timestamp_us: now_us(),
+        warn_threshold: CLIP_WARN_THRESHOLD,
+        render_clamped: clips.render_clamped[..count].to_vec(),
+        render_over_threshold: clips.render_over_threshold[..count].to_vec(),
+        output_clamped: clips.output_clamped[..count].to_vec(),
+        output_over_threshold: clips.output_over_threshold[..count].to_vec(),
     }
 }
 
-/// Queue a complete framed message for every observer. Controllers are skipped
-/// after their one-shot acknowledgement, so their socket buffers never fill.
-fn broadcast(clients: &mut [Client], frame: &[u8]) {
-    for client in clients.iter_mut().filter(|client| client.wants_status) {
+/// Queue `status` for every observer that negotiated its capability.
+/// Controllers are skipped after their one-shot acknowledgement, so their
+/// socket buffers never fill.
+fn broadcast(clients: &mut [Client], status: &ServerStatus, frame: &mut Vec<u8>) {
+    if encode_frame(status, frame).is_err() {
+        return;
+    }
+    let capability = status.capability();
+    for client in clients
+        .iter_mut()
+        .filter(|client| client.wants_status && client.capabilities.contains(capability))
+    {
         queue_status_frame(client, frame);
     }
 }
@@ -384,25 +620,40 @@ fn flush_status_clients(clients: &mut Vec<Client>, pending_disconnects: &mut Vec
     });
 }
 
-/// Drain all available bytes from the client and dispatch every complete
-/// frame. Returns `false` when the connection should be dropped.
+/// Read up to `CLIENT_READ_BUDGET` bytes from the client and dispatch up to
+/// `CLIENT_FRAME_BUDGET` complete frames; the rest waits, partial frames
+/// included, for the next iteration. Returns `false` when the connection
+/// should be dropped.
+#[allow(clippy::too_many_arguments)]
 fn handle_client(
     client: &mut Client,
     command_producer: &mut rtrb::Producer<crate::engine::EngineCommand>,
     active_instances: &mut HashSet<u64>,
-    routes: &mut [u8; 32],
+    routes: &mut [u8; MAX_TRANSDUCERS],
     routing_dirty: &mut bool,
+    test_signal: &mut TestSignal,
+    test_signal_dirty: &mut bool,
+    capture: &mut CaptureLink,
+    layouts: &LayoutSet,
+    layout_select: &mpsc::Sender<String>,
+    dropped_commands: &mut u64,
 ) -> bool {
+    let layout = layouts.active();
     let mut buffer = [0u8; 1024];
 
-    loop {
+    let mut read = 0;
+    while !client.read_closed
+        && read < CLIENT_READ_BUDGET
+        && client.decoder.buffered() < CLIENT_READ_BUDGET
+    {
         match client.stream.read(&mut buffer) {
             Ok(0) => {
-                eprintln!("Client disconnected");
-                return false;
+                // One-shot clients write a command and close straight away
+                client.read_closed = true;
             }
             Ok(n) => {
                 client.decoder.extend(&buffer[..n]);
+                read += n;
             }
             Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
             Err(e) => {
@@ -412,10 +663,10 @@ fn handle_client(
         }
     }
 
-    loop {
+    for _ in 0..CLIENT_FRAME_BUDGET {
         match client.decoder.next_frame::<HapticCommand>() {
             Ok(Some(mut command)) => {
-                if let Err(e) = validate_command(&mut command) {
+                if let Err(e) = validate_command(&mut command, layout.count) {
                     if client.instance_id.is_none()
                         || matches!(&command, HapticCommand::Hello { .. })
                     {
@@ -433,7 +684,10 @@ fn handle_client(
                 // Bind identity and role once. Every other command requires a
                 // successful Hello first.
                 if let HapticCommand::Hello {
-                    instance_id, role, ..
+                    instance_id,
+                    role,
+                    capabilities,
+                    ..
                 } = &command
                 {
                     if client.instance_id.is_some() {
@@ -446,11 +700,39 @@ fn handle_client(
                     }
                     client.instance_id = Some(*instance_id);
                     client.wants_status = *role == haptic_protocol::ClientRole::Observer;
+                    client.capabilities = capabilities.intersection(Capabilities::ALL);
                     active_instances.insert(*instance_id);
                 } else if client.instance_id.is_none() {
                     eprintln!("Protocol error, dropping client: command before Hello");
                     return false;
                 }
+                // Test signals drive the hardware directly; only an
+                // observer, which sees what it is doing, may start one
+                let signal_change = match &command {
+                    HapticCommand::SetTestSignal { signal } => Some(*signal),
+                    HapticCommand::Panic => Some(TestSignal::Off),
+                    _ => None,
+                };
+                if matches!(&command, HapticCommand::SetTestSignal { .. }) && !client.wants_status {
+                    eprintln!("Test signals are observer-only, dropping command");
+                    continue;
+                }
+                // Captures write files where the server can; likewise only
+                // an observer, which sees the result, may start one
+                if matches!(&command, HapticCommand::StartCapture { .. }) && !client.wants_status {
+                    eprintln!("Captures are observer-only, dropping StartCapture");
+                    continue;
+                }
+                // Layouts are switched by the config watcher, which owns the
+                // engine's layout ring and rebroadcasts the result
+                if let HapticCommand::SelectLayout { name } = command {
+                    if !layouts.contains(&name) {
+                        eprintln!("Unknown layout \"{name}\", ignoring SelectLayout");
+                    } else if layout_select.send(name).is_err() {
+                        eprintln!("Config watcher stopped, ignoring SelectLayout");
+                    }
+                    continue;
+                }
                 let routing_change = if let HapticCommand::SetParameter {
                     parameter: Parameter::MonitorRoute { output, source },
                     ..
@@ -461,12 +743,15 @@ fn handle_client(
                     None
                 };
                 let handshake_ack = if let HapticCommand::Hello {
-                    protocol_version,
+                    min_protocol_version,
+                    max_protocol_version,
                     instance_id,
                     ..
                 } = &command
                 {
-                    Some((*protocol_version, *instance_id))
+                    let version = negotiate_version(*min_protocol_version, *max_protocol_version)
+                        .expect("validated handshake version");
+                    Some((version, *instance_id))
                 } else {
                     None
                 };
@@ -476,15 +761,38 @@ fn handle_client(
                     HapticCommand::Hello { .. }
                         | HapticCommand::NoteOff { .. }
                         | HapticCommand::Panic
+                        | HapticCommand::StopCapture
                 );
                 if !critical && command_producer.slots() <= CRITICAL_COMMAND_RESERVE {
                     eprintln!("Command queue reserve reached, dropping non-critical command");
+                    *dropped_commands += 1;
+                    continue;
+                }
+                // The capture destination is opened here, before the engine
+                // is told to start, so an unwritable path is refused up front
+                let capture_start = matches!(&command, HapticCommand::StartCapture { .. });
+                let capture_stop = matches!(&command, HapticCommand::StopCapture);
+                if let HapticCommand::StartCapture { path } = &command {
+                    let request = CaptureRequest {
+                        path: PathBuf::from(path),
+                        requested_by: client.instance_id.expect("validated handshake state"),
+                        layout: layout.clone(),
+                        routes: *routes,
+                    };
+                    if let Err(e) = capture.start(request) {
+                        eprintln!("Capture refused: {e}");
+                        continue;
+                    }
+                } else if capture_stop && !capture.can_stop() {
+                    eprintln!("No capture running, ignoring StopCapture");
                     continue;
                 }
-                let engine_cmd = crate::engine::EngineCommand::from_wire(
+                let Some(engine_cmd) = crate::engine::EngineCommand::from_wire(
                     command,
                     client.instance_id.expect("validated handshake state"),
-                );
+                ) else {
+                    continue;
+                };
                 if command_producer.push(engine_cmd).is_ok() {
                     // A socket connect and successful client-side write do not
                     // prove that the server accepted the protocol or identity.
@@ -494,6 +802,7 @@ fn handle_client(
                         let status = ServerStatus::HelloAccepted {
                             protocol_version,
                             instance_id,
+                            capabilities: client.capabilities,
                         };
                         let mut ack_frame = Vec::with_capacity(32);
                         if encode_frame(&status, &mut ack_frame).is_err() {
@@ -508,14 +817,30 @@ fn handle_client(
                         routes[output as usize] = source;
                         *routing_dirty = true;
                     }
+                    if let Some(signal) = signal_change.filter(|s| s != test_signal) {
+                        *test_signal = signal;
+                        *test_signal_dirty = true;
+                    }
+                    if capture_stop {
+                        capture.stopping();
+                    }
                 } else {
+                    if capture_start {
+                        capture.abort();
+                    }
                     eprintln!("Command queue full, dropping critical command");
                     if is_handshake {
                         return false;
                     }
                 }
             }
-            Ok(None) => break,
+            Ok(None) => {
+                if client.read_closed {
+                    eprintln!("Client disconnected");
+                    return false;
+                }
+                break;
+            }
             Err(FrameError::Deserialize(e)) => {
                 // Frame boundary is intact; skip the bad frame and continue
                 eprintln!("Dropping undecodable frame: {}", e);
@@ -553,6 +878,9 @@ fn validate_config(config: &mut InstanceConfig) -> Result<(), &'static str> {
     if !config.distance_decay.d0_m.is_finite() || !config.distance_decay.exponent.is_finite() {
         return Err("distance decay must be finite");
     }
+    if !config.source_height_m.is_finite() {
+        return Err("source height must be finite");
+    }
     config.wave_speed = config.wave_speed.clamp(MIN_WAVE_SPEED, MAX_WAVE_SPEED);
     config.travelling_wave.wave_speed = config
         .travelling_wave
@@ -570,20 +898,68 @@ fn validate_config(config: &mut InstanceConfig) -> Result<(), &'static str> {
         .distance_decay
         .exponent
         .clamp(MIN_ATTEN_EXPONENT, MAX_ATTEN_EXPONENT);
+    config.source_height_m = config
+        .source_height_m
+        .clamp(MIN_SOURCE_HEIGHT_M, MAX_SOURCE_HEIGHT_M);
+    Ok(())
+}
+
+/// Test signals must stay on configured channels. Finite levels, frequencies,
+/// and sweep periods are clamped into range, as for instance config.
+fn validate_test_signal(
+    signal: &mut TestSignal,
+    transducer_count: usize,
+) -> Result<(), &'static str> {
+    if signal.channels().end > transducer_count {
+        return Err("test signal channel out of range");
+    }
+    let level = match signal {
+        TestSignal::Off => return Ok(()),
+        TestSignal::Tone {
+            frequency_hz,
+            level,
+            ..
+        } => {
+            if !frequency_hz.is_finite() {
+                return Err("test tone frequency must be finite");
+            }
+            *frequency_hz = frequency_hz.clamp(MIN_TEST_SIGNAL_HZ, MAX_TEST_SIGNAL_HZ);
+            level
+        }
+        TestSignal::Sweep {
+            period_s, level, ..
+        } => {
+            if !period_s.is_finite() {
+                return Err("sweep period must be finite");
+            }
+            *period_s = period_s.clamp(MIN_SWEEP_PERIOD_S, MAX_SWEEP_PERIOD_S);
+            level
+        }
+        TestSignal::PinkNoise { level, .. } | TestSignal::PolarityPulses { level, .. } => level,
+    };
+    if !level.is_finite() {
+        return Err("test signal level must be finite");
+    }
+    *level = level.clamp(0.0, 1.0);
     Ok(())
 }
 
 /// Validate and, where documented, normalize one decoded wire command before
-/// it can enter the real-time engine queue.
-fn validate_command(command: &mut HapticCommand) -> Result<(), &'static str> {
+/// it can enter the real-time engine queue. Monitor routes must name one of
+/// the `transducer_count` channels.
+pub(crate) fn validate_command(
+    command: &mut HapticCommand,
+    transducer_count: usize,
+) -> Result<(), &'static str> {
     match command {
         HapticCommand::Hello {
-            protocol_version,
+            min_protocol_version,
+            max_protocol_version,
             instance_id,
             config,
             ..
         } => {
-            if *protocol_version != PROTOCOL_VERSION {
+            if negotiate_version(*min_protocol_version, *max_protocol_version).is_none() {
                 return Err("unsupported protocol version");
             }
             if *instance_id == 0 {
@@ -625,7 +1001,7 @@ fn validate_command(command: &mut HapticCommand) -> Result<(), &'static str> {
             }
             Parameter::StimulusType(_) => Ok(()),
             Parameter::MonitorRoute { output, source } => {
-                if *output >= 32 || *source >= 32 {
+                if *output as usize >= transducer_count || *source as usize >= transducer_count {
                     return Err("monitor route out of range");
                 }
                 Ok(())
@@ -652,8 +1028,32 @@ fn validate_command(command: &mut HapticCommand) -> Result<(), &'static str> {
                 *exponent = exponent.clamp(MIN_ATTEN_EXPONENT, MAX_ATTEN_EXPONENT);
                 Ok(())
             }
+            Parameter::SourceHeight(height_m) => {
+                if !height_m.is_finite() {
+                    return Err("source height must be finite");
+                }
+                *height_m = height_m.clamp(MIN_SOURCE_HEIGHT_M, MAX_SOURCE_HEIGHT_M);
+                Ok(())
+            }
         },
         HapticCommand::Panic => Ok(()),
+        HapticCommand::SetTestSignal { signal } => validate_test_signal(signal, transducer_count),
+        HapticCommand::StartCapture { path } => {
+            if path.is_empty() || path.contains('\0') {
+                return Err("capture path must be non-empty and contain no NUL");
+            }
+            if path.len() > MAX_CAPTURE_PATH_BYTES {
+                return Err("capture path too long");
+            }
+            Ok(())
+        }
+        HapticCommand::StopCapture => Ok(()),
+        HapticCommand::SelectLayout { name } => {
+            if name.is_empty() || name.len() > MAX_LAYOUT_NAME_BYTES {
+                return Err("layout name must be 1 to 64 bytes");
+            }
+            Ok(())
+        }
     }
 }
 
@@ -661,7 +1061,7 @@ fn validate_command(command: &mut HapticCommand) -> Result<(), &'static str> {
 mod tests {
     use super::*;
     use crate::engine::EngineCommand;
-    use haptic_protocol::MpeData;
+    use haptic_protocol::{MpeData, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
 
     fn test_client(stream: UnixStream, instance_id: u64) -> Client {
         Client {
@@ -669,10 +1069,12 @@ mod tests {
             decoder: FrameDecoder::new(),
             instance_id: Some(instance_id),
             wants_status: true,
+            capabilities: Capabilities::ALL,
             greeted: true,
             status_output: Vec::new(),
             status_cursor: 0,
             status_overflowed: false,
+            read_closed: false,
         }
     }
 
@@ -687,6 +1089,34 @@ mod tests {
         assert_eq!(client.status_cursor, 0);
     }
 
+    #[test]
+    fn broadcast_skips_statuses_outside_negotiated_capabilities() {
+        let (stream, _peer) = UnixStream::pair().unwrap();
+        let mut clients = vec![test_client(stream, 42)];
+        clients[0].capabilities = Capabilities::CONFIG_STATE;
+        let mut frame = Vec::new();
+
+        let config = ServerStatus::ConfigState {
+            version: 1,
+            path: String::new(),
+            error: String::new(),
+        };
+        broadcast(&mut clients, &config, &mut frame);
+        let queued = clients[0].status_output.len();
+        assert!(queued > 0);
+
+        let clips = ServerStatus::ClipCounts {
+            timestamp_us: 0,
+            warn_threshold: CLIP_WARN_THRESHOLD,
+            render_clamped: Vec::new(),
+            render_over_threshold: Vec::new(),
+            output_clamped: Vec::new(),
+            output_over_threshold: Vec::new(),
+        };
+        broadcast(&mut clients, &clips, &mut frame);
+        assert_eq!(clients[0].status_output.len(), queued);
+    }
+
     #[test]
     fn terminal_status_failure_queues_instance_cleanup() {
         let (stream, peer) = UnixStream::pair().unwrap();
@@ -716,68 +1146,101 @@ mod tests {
         }
     }
 
-    /// End-to-end over the real Unix socket: coalesced and fragmented
-    /// frames must all arrive intact on the engine's command queue.
-    #[test]
-    fn framed_commands_over_socket_reach_engine_queue() {
-        let socket_path = format!(
-            "/tmp/haptic-vst-test-{}-{}.sock",
-            std::process::id(),
-            now_us()
-        );
-        let running = Arc::new(AtomicBool::new(true));
-        let (tx, mut rx) = rtrb::RingBuffer::new(64);
-        let (_levels_tx, levels_rx) = rtrb::RingBuffer::new(64);
-        let (_voice_tx, voice_rx) = rtrb::RingBuffer::new(64);
-        let (_layout_tx, layout_rx) = rtrb::RingBuffer::new(4);
-        let listener = {
-            let running = running.clone();
-            let socket_path = socket_path.clone();
-            thread::spawn(move || {
-                listen_loop_at(
-                    &socket_path,
-                    running,
-                    tx,
-                    levels_rx,
-                    voice_rx,
-                    TransducerLayout::default(),
-                    layout_rx,
-                    Arc::new(AtomicU16::new(2)),
-                )
-                .map_err(|error| error.to_string())
-            })
-        };
+    /// A listener on its own socket path, with the far end of its engine
+    /// command queue.
+    struct TestServer {
+        socket_path: String,
+        running: Arc<AtomicBool>,
+        commands: rtrb::Consumer<EngineCommand>,
+        listener: Option<thread::JoinHandle<Result<(), String>>>,
+        capture_writer: thread::JoinHandle<()>,
+    }
 
-        // Retry until the listener is accepting (a stale socket file from a
-        // killed server may exist before the fresh bind, so probing the
-        // path is not enough)
-        let mut stream = None;
-        let mut last_connect_error = None;
-        for _ in 0..1000 {
-            match UnixStream::connect(&socket_path) {
-                Ok(s) => {
-                    stream = Some(s);
-                    break;
-                }
-                Err(error) => {
-                    last_connect_error = Some(error);
-                    thread::sleep(Duration::from_millis(5));
-                }
+    impl TestServer {
+        fn start() -> Self {
+            let socket_path = format!(
+                "/tmp/haptic-vst-test-{}-{}.sock",
+                std::process::id(),
+                now_us()
+            );
+            let running = Arc::new(AtomicBool::new(true));
+            let (tx, commands) = rtrb::RingBuffer::new(64);
+            let (_, levels_rx) = rtrb::RingBuffer::new(64);
+            let (_, voice_rx) = rtrb::RingBuffer::new(64);
+            let (_, layout_rx) = mpsc::channel();
+            let (_, capture_rx) = rtrb::RingBuffer::new(4);
+            let (capture, capture_writer) =
+                crate::capture::spawn_writer(capture_rx, running.clone());
+            let listener = {
+                let running = running.clone();
+                let socket_path = socket_path.clone();
+                thread::spawn(move || {
+                    listen_loop_at(
+                        &socket_path,
+                        running,
+                        tx,
+                        levels_rx,
+                        voice_rx,
+                        LayoutSet::default(),
+                        Path::new("haptic.toml"),
+                        layout_rx,
+                        std::array::from_fn(|i| i as u8),
+                        None,
+                        mpsc::channel().0,
+                        Arc::new(AtomicU16::new(2)),
+                        capture,
+                        mpsc::channel().1,
+                        Arc::new(AudioStats::new()),
+                    )
+                    .map_err(|error| error.to_string())
+                })
+            };
+            Self {
+                socket_path,
+                running,
+                commands,
+                listener: Some(listener),
+                capture_writer,
             }
         }
-        let mut stream = match stream {
-            Some(stream) => stream,
-            None => {
-                running.store(false, Ordering::Relaxed);
-                match listener.join().unwrap() {
-                    Ok(()) => panic!("listener never became reachable: {last_connect_error:?}"),
-                    Err(error) => panic!("listener failed to start: {error}"),
+
+        /// Retry until the listener is accepting (a stale socket file from a
+        /// killed server may exist before the fresh bind, so probing the
+        /// path is not enough)
+        fn connect(&mut self) -> UnixStream {
+            let mut last_connect_error = None;
+            for _ in 0..1000 {
+                match UnixStream::connect(&self.socket_path) {
+                    Ok(stream) => return stream,
+                    Err(error) => {
+                        last_connect_error = Some(error);
+                        thread::sleep(Duration::from_millis(5));
+                    }
                 }
             }
-        };
+            self.running.store(false, Ordering::Relaxed);
+            match self.listener.take().unwrap().join().unwrap() {
+                Ok(()) => panic!("listener never became reachable: {last_connect_error:?}"),
+                Err(error) => panic!("listener failed to start: {error}"),
+            }
+        }
+
+        fn stop(mut self) {
+            self.running.store(false, Ordering::Relaxed);
+            self.listener.take().unwrap().join().unwrap().unwrap();
+            self.capture_writer.join().unwrap();
+        }
+    }
+
+    /// End-to-end over the real Unix socket: coalesced and fragmented
+    /// frames must all arrive intact on the engine's command queue.
+    #[test]
+    fn framed_commands_over_socket_reach_engine_queue() {
+        let mut server = TestServer::start();
+        let mut stream = server.connect();
 
         // Commands before Hello are rejected and never reach the engine.
-        let mut invalid = UnixStream::connect(&socket_path).expect("pre-handshake connect");
+        let mut invalid = UnixStream::connect(&server.socket_path).expect("pre-handshake connect");
         let mut frame = Vec::new();
         encode_frame(&HapticCommand::Panic, &mut frame).unwrap();
         invalid.write_all(&frame).unwrap();
@@ -786,16 +1249,19 @@ mod tests {
             .unwrap();
         let mut invalid_buf = [0u8; 1];
         assert_eq!(invalid.read(&mut invalid_buf).unwrap(), 0);
-        assert!(rx.pop().is_err());
+        assert!(server.commands.pop().is_err());
 
         // An incompatible protocol version is rejected before registration.
-        let mut incompatible = UnixStream::connect(&socket_path).expect("version-mismatch connect");
+        let mut incompatible =
+            UnixStream::connect(&server.socket_path).expect("version-mismatch connect");
         encode_frame(
             &HapticCommand::Hello {
-                protocol_version: PROTOCOL_VERSION + 1,
+                min_protocol_version: PROTOCOL_VERSION + 1,
+                max_protocol_version: PROTOCOL_VERSION + 1,
                 instance_id: 41,
                 role: haptic_protocol::ClientRole::Controller,
                 config: haptic_protocol::InstanceConfig::default(),
+                capabilities: Capabilities::NONE,
             },
             &mut frame,
         )
@@ -805,7 +1271,7 @@ mod tests {
             .set_read_timeout(Some(Duration::from_millis(500)))
             .unwrap();
         assert_eq!(incompatible.read(&mut invalid_buf).unwrap(), 0);
-        assert!(rx.pop().is_err());
+        assert!(server.commands.pop().is_err());
 
         // Handshake first (as an Observer), then two frames coalesced into a
         // single write. The Hello binds instance_id 42 to this connection, so
@@ -813,9 +1279,11 @@ mod tests {
         let mut coalesced = Vec::new();
         encode_frame(
             &HapticCommand::Hello {
-                protocol_version: PROTOCOL_VERSION,
+                min_protocol_version: MIN_PROTOCOL_VERSION,
+                max_protocol_version: PROTOCOL_VERSION,
                 instance_id: 42,
                 role: haptic_protocol::ClientRole::Observer,
+                capabilities: Capabilities::ALL,
                 config: haptic_protocol::InstanceConfig {
                     stimulus_type: haptic_protocol::StimulusType::TravellingWave,
                     ..haptic_protocol::InstanceConfig::default()
@@ -889,14 +1357,14 @@ mod tests {
         // Handshake becomes a RegisterInstance; every following command is
         // stamped with the connection's bound instance_id (42).
         assert!(matches!(
-            pop_with_timeout(&mut rx),
+            pop_with_timeout(&mut server.commands),
             EngineCommand::RegisterInstance {
                 instance_id: 42,
                 ..
             }
         ));
         assert!(matches!(
-            pop_with_timeout(&mut rx),
+            pop_with_timeout(&mut server.commands),
             EngineCommand::NoteOn {
                 instance_id: 42,
                 note: 60,
@@ -905,14 +1373,14 @@ mod tests {
             }
         ));
         assert!(matches!(
-            pop_with_timeout(&mut rx),
+            pop_with_timeout(&mut server.commands),
             EngineCommand::SetParameter {
                 instance_id: 42,
                 parameter: Parameter::WaveSpeed(12.0)
             }
         ));
         assert!(matches!(
-            pop_with_timeout(&mut rx),
+            pop_with_timeout(&mut server.commands),
             EngineCommand::SetParameter {
                 instance_id: 42,
                 parameter: Parameter::TravellingWaveScaleMode(
@@ -921,28 +1389,28 @@ mod tests {
             }
         ));
         assert!(matches!(
-            pop_with_timeout(&mut rx),
+            pop_with_timeout(&mut server.commands),
             EngineCommand::SetParameter {
                 instance_id: 42,
                 parameter: Parameter::TravellingWaveWavelength(0.125)
             }
         ));
         assert!(matches!(
-            pop_with_timeout(&mut rx),
+            pop_with_timeout(&mut server.commands),
             EngineCommand::SetParameter {
                 instance_id: 42,
                 parameter: Parameter::AttenuationD0(0.75)
             }
         ));
         assert!(matches!(
-            pop_with_timeout(&mut rx),
+            pop_with_timeout(&mut server.commands),
             EngineCommand::SetParameter {
                 instance_id: 42,
                 parameter: Parameter::AttenuationExponent(1.5)
             }
         ));
         assert!(matches!(
-            pop_with_timeout(&mut rx),
+            pop_with_timeout(&mut server.commands),
             EngineCommand::MpeUpdate {
                 instance_id: 42,
                 channel: 1,
@@ -950,7 +1418,7 @@ mod tests {
             }
         ));
         assert!(matches!(
-            pop_with_timeout(&mut rx),
+            pop_with_timeout(&mut server.commands),
             EngineCommand::NoteOff {
                 instance_id: 42,
                 note: 60,
@@ -959,13 +1427,15 @@ mod tests {
         ));
 
         // A second live connection cannot impersonate an existing instance.
-        let mut duplicate = UnixStream::connect(&socket_path).expect("duplicate-id connect");
+        let mut duplicate = UnixStream::connect(&server.socket_path).expect("duplicate-id connect");
         encode_frame(
             &HapticCommand::Hello {
-                protocol_version: PROTOCOL_VERSION,
+                min_protocol_version: MIN_PROTOCOL_VERSION,
+                max_protocol_version: PROTOCOL_VERSION,
                 instance_id: 42,
                 role: haptic_protocol::ClientRole::Controller,
                 config: haptic_protocol::InstanceConfig::default(),
+                capabilities: Capabilities::NONE,
             },
             &mut frame,
         )
@@ -975,7 +1445,7 @@ mod tests {
             .set_read_timeout(Some(Duration::from_millis(500)))
             .unwrap();
         assert_eq!(duplicate.read(&mut invalid_buf).unwrap(), 0);
-        assert!(rx.pop().is_err());
+        assert!(server.commands.pop().is_err());
 
         // Role-gating: the Observer above receives the layout+routing greeting.
         stream
@@ -989,20 +1459,22 @@ mod tests {
 
         // A Controller receives exactly one handshake acknowledgement, but no
         // continuous observer status stream.
-        let mut ctrl = UnixStream::connect(&socket_path).expect("second connect");
+        let mut ctrl = UnixStream::connect(&server.socket_path).expect("second connect");
         encode_frame(
             &HapticCommand::Hello {
-                protocol_version: PROTOCOL_VERSION,
+                min_protocol_version: MIN_PROTOCOL_VERSION,
+                max_protocol_version: PROTOCOL_VERSION,
                 instance_id: 99,
                 role: haptic_protocol::ClientRole::Controller,
                 config: haptic_protocol::InstanceConfig::default(),
+                capabilities: Capabilities::NONE,
             },
             &mut frame,
         )
         .unwrap();
         ctrl.write_all(&frame).unwrap();
         assert!(matches!(
-            pop_with_timeout(&mut rx),
+            pop_with_timeout(&mut server.commands),
             EngineCommand::RegisterInstance {
                 instance_id: 99,
                 ..
@@ -1019,6 +1491,7 @@ mod tests {
             Some(ServerStatus::HelloAccepted {
                 protocol_version: PROTOCOL_VERSION,
                 instance_id: 99,
+                capabilities: Capabilities::NONE,
             })
         ));
         match ctrl.read(&mut cbuf) {
@@ -1035,7 +1508,19 @@ mod tests {
 
         // A bad post-handshake performance sample is command-local: it must
         // not disconnect the controller or prevent later lifecycle traffic.
+        // Test signals are observer-only, so the controller's is dropped too.
         let mut commands = Vec::new();
+        encode_frame(
+            &HapticCommand::SetTestSignal {
+                signal: TestSignal::PinkNoise {
+                    channel: 0,
+                    level: 0.5,
+                },
+            },
+            &mut frame,
+        )
+        .unwrap();
+        commands.extend_from_slice(&frame);
         encode_frame(
             &HapticCommand::MpeUpdate {
                 timestamp_us: 0,
@@ -1061,7 +1546,7 @@ mod tests {
         commands.extend_from_slice(&frame);
         ctrl.write_all(&commands).unwrap();
         assert!(matches!(
-            pop_with_timeout(&mut rx),
+            pop_with_timeout(&mut server.commands),
             EngineCommand::NoteOff {
                 instance_id: 99,
                 note: 60,
@@ -1073,32 +1558,205 @@ mod tests {
         // command on the same FIFO as its earlier note traffic.
         drop(ctrl);
         assert!(matches!(
-            pop_with_timeout(&mut rx),
+            pop_with_timeout(&mut server.commands),
             EngineCommand::DisconnectInstance { instance_id: 99 }
         ));
         drop(stream);
         assert!(matches!(
-            pop_with_timeout(&mut rx),
+            pop_with_timeout(&mut server.commands),
             EngineCommand::DisconnectInstance { instance_id: 42 }
         ));
 
-        running.store(false, Ordering::Relaxed);
-        listener.join().unwrap().unwrap();
+        // A one-shot client writes and closes at once; the frames read ahead
+        // of its EOF are still dispatched before the cleanup.
+        let mut one_shot = UnixStream::connect(&server.socket_path).expect("one-shot connect");
+        commands.clear();
+        encode_frame(
+            &HapticCommand::Hello {
+                min_protocol_version: MIN_PROTOCOL_VERSION,
+                max_protocol_version: PROTOCOL_VERSION,
+                instance_id: 7,
+                role: haptic_protocol::ClientRole::Controller,
+                config: haptic_protocol::InstanceConfig::default(),
+                capabilities: Capabilities::NONE,
+            },
+            &mut frame,
+        )
+        .unwrap();
+        commands.extend_from_slice(&frame);
+        encode_frame(
+            &HapticCommand::NoteOff {
+                timestamp_us: 0,
+                note: 61,
+                channel: 2,
+            },
+            &mut frame,
+        )
+        .unwrap();
+        commands.extend_from_slice(&frame);
+        one_shot.write_all(&commands).unwrap();
+        drop(one_shot);
+        assert!(matches!(
+            pop_with_timeout(&mut server.commands),
+            EngineCommand::RegisterInstance { instance_id: 7, .. }
+        ));
+        assert!(matches!(
+            pop_with_timeout(&mut server.commands),
+            EngineCommand::NoteOff {
+                instance_id: 7,
+                note: 61,
+                channel: 2,
+            }
+        ));
+        assert!(matches!(
+            pop_with_timeout(&mut server.commands),
+            EngineCommand::DisconnectInstance { instance_id: 7 }
+        ));
+
+        server.stop();
+    }
+
+    fn hello(instance_id: u64, role: haptic_protocol::ClientRole) -> HapticCommand {
+        HapticCommand::Hello {
+            min_protocol_version: MIN_PROTOCOL_VERSION,
+            max_protocol_version: PROTOCOL_VERSION,
+            instance_id,
+            role,
+            config: haptic_protocol::InstanceConfig::default(),
+            capabilities: Capabilities::ALL,
+        }
+    }
+
+    /// A controller flooding MPE must not hold up another controller's
+    /// NoteOff or an observer's handshake and greeting.
+    #[test]
+    fn flooding_controller_cannot_starve_other_clients() {
+        let mut server = TestServer::start();
+        let mut frame = Vec::new();
+
+        let mut flooder = server.connect();
+        encode_frame(
+            &hello(50, haptic_protocol::ClientRole::Controller),
+            &mut frame,
+        )
+        .unwrap();
+        flooder.write_all(&frame).unwrap();
+        let mut flood = Vec::new();
+        encode_frame(
+            &HapticCommand::MpeUpdate {
+                timestamp_us: 0,
+                channel: 1,
+                mpe: MpeData::default(),
+            },
+            &mut frame,
+        )
+        .unwrap();
+        while flood.len() < 64 * 1024 {
+            flood.extend_from_slice(&frame);
+        }
+        let flooding = Arc::new(AtomicBool::new(true));
+        let flood_thread = {
+            let flooding = flooding.clone();
+            thread::spawn(move || {
+                while flooding.load(Ordering::Relaxed) {
+                    flooder.write_all(&flood).unwrap();
+                }
+            })
+        };
+        assert!(matches!(
+            pop_with_timeout(&mut server.commands),
+            EngineCommand::RegisterInstance {
+                instance_id: 50,
+                ..
+            }
+        ));
+        assert!(matches!(
+            pop_with_timeout(&mut server.commands),
+            EngineCommand::MpeUpdate {
+                instance_id: 50,
+                ..
+            }
+        ));
+
+        let mut observer = server.connect();
+        encode_frame(
+            &hello(52, haptic_protocol::ClientRole::Observer),
+            &mut frame,
+        )
+        .unwrap();
+        observer.write_all(&frame).unwrap();
+        let mut controller = server.connect();
+        let mut commands = Vec::new();
+        encode_frame(
+            &hello(51, haptic_protocol::ClientRole::Controller),
+            &mut frame,
+        )
+        .unwrap();
+        commands.extend_from_slice(&frame);
+        encode_frame(
+            &HapticCommand::NoteOff {
+                timestamp_us: 0,
+                note: 60,
+                channel: 2,
+            },
+            &mut frame,
+        )
+        .unwrap();
+        commands.extend_from_slice(&frame);
+        controller.write_all(&commands).unwrap();
+
+        // The queue is full of flood, which is drained past
+        let deadline = std::time::Instant::now() + Duration::from_secs(2);
+        loop {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "controller NoteOff starved"
+            );
+            if let EngineCommand::NoteOff {
+                instance_id: 51,
+                note: 60,
+                channel: 2,
+            } = pop_with_timeout(&mut server.commands)
+            {
+                break;
+            }
+        }
+
+        observer
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        let mut decoder = FrameDecoder::new();
+        let mut input = [0u8; 1024];
+        let mut greeted = false;
+        while !greeted {
+            let n = observer.read(&mut input).expect("observer starved");
+            assert!(n > 0, "observer dropped");
+            decoder.extend(&input[..n]);
+            while let Some(status) = decoder.next_frame::<ServerStatus>().unwrap() {
+                greeted |= matches!(status, ServerStatus::MonitorRouting { .. });
+            }
+        }
+
+        flooding.store(false, Ordering::Relaxed);
+        flood_thread.join().unwrap();
+        server.stop();
     }
 
     #[test]
     fn command_validation_rejects_non_finite_values_and_normalizes_speed() {
         let mut hello = HapticCommand::Hello {
-            protocol_version: PROTOCOL_VERSION,
+            min_protocol_version: MIN_PROTOCOL_VERSION,
+            max_protocol_version: PROTOCOL_VERSION,
             instance_id: 1,
             role: haptic_protocol::ClientRole::Controller,
+            capabilities: Capabilities::NONE,
             config: InstanceConfig {
                 stimulus_type: haptic_protocol::StimulusType::Wave,
                 wave_speed: 20_000.0,
                 ..InstanceConfig::default()
             },
         };
-        validate_command(&mut hello).unwrap();
+        validate_command(&mut hello, 32).unwrap();
         assert!(matches!(
             hello,
             HapticCommand::Hello {
@@ -1119,7 +1777,7 @@ mod tests {
                 timbre: 0.5,
             },
         };
-        assert!(validate_command(&mut bad_mpe).is_err());
+        assert!(validate_command(&mut bad_mpe, 32).is_err());
 
         let mut overshooting_mpe = HapticCommand::MpeUpdate {
             timestamp_us: 0,
@@ -1130,7 +1788,7 @@ mod tests {
                 timbre: 1.5,
             },
         };
-        validate_command(&mut overshooting_mpe).unwrap();
+        validate_command(&mut overshooting_mpe, 32).unwrap();
         assert!(matches!(
             overshooting_mpe,
             HapticCommand::MpeUpdate {
@@ -1147,7 +1805,22 @@ mod tests {
             timestamp_us: 0,
             parameter: Parameter::WaveSpeed(f32::INFINITY),
         };
-        assert!(validate_command(&mut bad_speed).is_err());
+        assert!(validate_command(&mut bad_speed, 32).is_err());
+
+        for path in [
+            String::new(),
+            "a\0b".into(),
+            "x".repeat(MAX_CAPTURE_PATH_BYTES + 1),
+        ] {
+            assert!(validate_command(&mut HapticCommand::StartCapture { path }, 32).is_err());
+        }
+        validate_command(
+            &mut HapticCommand::StartCapture {
+                path: "/tmp/capture.wav".into(),
+            },
+            32,
+        )
+        .unwrap();
 
         for parameter in [
             Parameter::TravellingWaveWavelength(f32::NAN),
@@ -1158,14 +1831,14 @@ mod tests {
                 timestamp_us: 0,
                 parameter,
             };
-            assert!(validate_command(&mut command).is_err());
+            assert!(validate_command(&mut command, 32).is_err());
         }
 
         let mut finite_extremes = HapticCommand::SetParameter {
             timestamp_us: 0,
             parameter: Parameter::TravellingWaveWavelength(1_000.0),
         };
-        validate_command(&mut finite_extremes).unwrap();
+        validate_command(&mut finite_extremes, 32).unwrap();
         assert!(matches!(
             finite_extremes,
             HapticCommand::SetParameter {
@@ -1174,4 +1847,59 @@ mod tests {
             }
         ));
     }
+
+    #[test]
+    fn test_signals_stay_on_configured_channels_and_clamp_finite_values() {
+        let validate = |signal, count| {
+            let mut command = HapticCommand::SetTestSignal { signal };
+            validate_command(&mut command, count).map(|()| match command {
+                HapticCommand::SetTestSignal { signal } => signal,
+                _ => unreachable!(),
+            })
+        };
+        let pulses = |channel| TestSignal::PolarityPulses {
+            channel,
+            level: 0.5,
+        };
+        validate(pulses(10), 12).unwrap();
+        assert!(validate(pulses(11), 12).is_err(), "neighbour past the end");
+        assert!(validate(
+            TestSignal::PinkNoise {
+                channel: 0,
+                level: f32::NAN
+            },
+            12
+        )
+        .is_err());
+        assert_eq!(
+            validate(
+                TestSignal::Tone {
+                    channel: 3,
+                    frequency_hz: 1_000.0,
+                    level: 2.0
+                },
+                12
+            ),
+            Ok(TestSignal::Tone {
+                channel: 3,
+                frequency_hz: MAX_TEST_SIGNAL_HZ,
+                level: 1.0
+            })
+        );
+        assert_eq!(
+            validate(
+                TestSignal::Sweep {
+                    channel: 0,
+                    period_s: 0.0,
+                    level: 0.5
+                },
+                12
+            ),
+            Ok(TestSignal::Sweep {
+                channel: 0,
+                period_s: MIN_SWEEP_PERIOD_S,
+                level: 0.5
+            })
+        );
+    }
 }
@@ -1,18 +1,28 @@
 use std::io::Read;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
 use std::sync::Arc;
 use std::thread;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 
 mod audio;
+mod capture;
+mod check;
 mod config;
 mod engine;
 mod ipc;
+mod offline;
 mod output_analysis;
+mod realtime;
+mod split;
+mod state;
+mod test_signal;
+mod wav;
 
-use config::TransducerLayout;
-use engine::StimulusEngine;
+use config::{ConfigUpdate, LayoutSet, ServerSettings};
+use engine::{LayoutSender, StimulusEngine};
+use realtime::RealtimeMode;
 
 const DEFAULT_CONFIG_PATH: &str = "haptic.toml";
 
@@ -21,7 +31,21 @@ struct ServerOptions {
     test_tone: bool,
     dummy_audio: bool,
     managed_lifetime_stdin: bool,
+    list_devices: bool,
+    device: Option<String>,
+    /// Overrides `[server] realtime`.
+    realtime: Option<RealtimeMode>,
+    lock_memory: bool,
+    /// Offline render: (script, output file). No socket or device is opened.
+    render: Option<(PathBuf, PathBuf)>,
+    /// Validate this config, print the resolved layouts, and exit.
+    check_config: Option<PathBuf>,
     config_path: PathBuf,
+    /// Where global state such as monitor routing persists; `None` in
+    /// headless mode unless `--state` names a file.
+    state_path: Option<PathBuf>,
+    /// Start from identity routing instead of the saved state.
+    fresh_state: bool,
     socket_path: String,
 }
 
@@ -38,20 +62,35 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::env::var("HAPTIC_SOCKET_PATH").ok(),
         std::process::id(),
     )?;
+    if options.list_devices {
+        return audio::list_output_devices();
+    }
+    if let Some(path) = &options.check_config {
+        return check::run(path);
+    }
     let config_path = options.config_path.clone();
-    if options.dummy_audio {
+    if options.render.is_some() {
+        eprintln!("Offline render: no audio device or socket will be opened");
+    } else if options.dummy_audio {
         eprintln!("Headless test mode: physical audio devices will not be opened");
     }
-    eprintln!("Server socket: {}", options.socket_path);
+    if options.render.is_none() {
+        eprintln!("Server socket: {}", options.socket_path);
+    }
 
     // Load the transducer layout: a missing file falls back to the built-in
     // default (4x8 grid over 1m x 2m); a present-but-invalid file is a hard
     // error so a typo can't silently drive the wrong layout.
-    let layout = if config_path.exists() {
-        match config::load_layout(&config_path) {
-            Ok(layout) => {
-                eprintln!("Loaded transducer layout from {}", config_path.display());
-                layout
+    let (layouts, server_settings) = if config_path.exists() {
+        match config::load_config(&config_path) {
+            Ok(loaded) => {
+                eprintln!(
+                    "Loaded transducer layout \"{}\" from {} ({} transducers)",
+                    loaded.0.active_name(),
+                    config_path.display(),
+                    loaded.0.active().count
+                );
+                loaded
             }
             Err(e) => {
                 eprintln!("Invalid config {}: {}", config_path.display(), e);
@@ -63,8 +102,28 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             "No config at {}, using default layout (4x8 grid over 1m x 2m)",
             config_path.display()
         );
-        TransducerLayout::default()
+        (LayoutSet::default(), ServerSettings::default())
     };
+    let layout = layouts.active();
+
+    // --device overrides [server] device and any [[server.output]] split;
+    // neither applies to dummy audio
+    let device_name = options.device.clone().or(server_settings.device);
+    let outputs = if options.device.is_some() {
+        Vec::new()
+    } else {
+        server_settings.outputs
+    };
+    if let (true, Some(name)) = (options.dummy_audio, &device_name) {
+        eprintln!("Ignoring output device \"{name}\" in headless mode");
+    }
+    let mut realtime = server_settings.realtime;
+    realtime.mode = options.realtime.unwrap_or(realtime.mode);
+    realtime.lock_memory |= options.lock_memory;
+
+    if let Some((script_path, out_path)) = &options.render {
+        return offline::run(script_path, out_path, layout.clone());
+    }
 
     // Create shared shutdown flag
     let running = Arc::new(AtomicBool::new(true));
@@ -79,24 +138,84 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Create stimulus engine - the IPC thread gets the command producer and
     // measured-output consumer, the config watcher gets the layout producer
-    let (engine, command_producer, engine_layout_producer, output_consumer) =
-        StimulusEngine::new(layout);
+    let (mut engine, command_producer, engine_layout_producer, output_consumer) =
+        StimulusEngine::new(layout.clone());
+
+    // Restore the saved monitor routing. A state file that no longer fits
+    // the layout is reported and left as it is until the routing changes;
+    // one that cannot be parsed is moved aside to `.bad`. Nothing is written
+    // at startup unless --fresh-state asks for it.
+    let mut routes = std::array::from_fn(|i| i as u8);
+    let state_file = options.state_path.as_ref().map(|path| {
+        let saved = match state::load(path) {
+            Ok(saved) => saved.unwrap_or_default(),
+            Err(e) => {
+                eprintln!("Ignoring unreadable state {}: {}", path.display(), e);
+                match state::set_aside(path) {
+                    Ok(bad) => eprintln!("Moved it aside to {}", bad.display()),
+                    Err(e) => eprintln!("Failed to move {} aside: {}", path.display(), e),
+                }
+                state::SavedState::default()
+            }
+        };
+        if options.fresh_state {
+            eprintln!("Starting with fresh state (--fresh-state)");
+        } else if !saved.routes.is_empty() {
+            match saved.monitor_routes(layout.count) {
+                Ok(saved_routes) => {
+                    routes = saved_routes;
+                    eprintln!("Restored monitor routing from {}", path.display());
+                }
+                Err(e) => eprintln!("Ignoring saved routing in {}: {}", path.display(), e),
+            }
+        }
+        // --fresh-state asks for the file to be replaced, so it is compared
+        // against what the file held; otherwise against the live routing
+        let seed = if options.fresh_state {
+            &saved.routes[..]
+        } else {
+            &routes[..layout.count]
+        };
+        state::StateFile::new(path.clone(), seed)
+    });
+    engine.restore_monitor_routes(routes);
+
+    // Capture path: audio callback → writer thread → disk. The ring is
+    // allocated here so the callback never allocates when a capture starts.
+    let (capture_producer, capture_consumer) = rtrb::RingBuffer::new(engine::CAPTURE_RING_FRAMES);
+    engine.attach_capture(capture_producer);
+    let (capture_link, capture_writer) = capture::spawn_writer(capture_consumer, running.clone());
 
     // Levels path: audio callback → IPC thread → connected clients
     let (levels_producer, levels_consumer) = rtrb::RingBuffer::new(256);
 
-    // Layout path to the IPC thread (for broadcast to clients); the engine
-    // has its own ring, so the watcher feeds both
-    let (ipc_layout_producer, ipc_layout_consumer) = rtrb::RingBuffer::new(4);
+    // Config updates to the IPC thread (for broadcast to clients once the
+    // engine confirms them); the engine has its own ring, so the watcher
+    // feeds both. This one is an unbounded channel: neither end is
+    // real-time, and an accepted update must never be dropped or the IPC
+    // thread would keep waiting on a version it never hears about. Layout
+    // selections travel the other way, from the IPC thread to the watcher.
+    let (ipc_layout_producer, ipc_layout_consumer) = std::sync::mpsc::channel();
+    let (layout_select_sender, layout_select_receiver) = std::sync::mpsc::channel();
 
     // Device output channel count, published by the audio loop once the
     // device is opened, broadcast to clients by the IPC thread
     let device_channels = Arc::new(AtomicU16::new(0));
 
+    // Audio stream transitions (opened, lost, retrying), relayed to observers
+    let (audio_status_producer, audio_status_consumer) = std::sync::mpsc::channel();
+
+    // Callback timing, recorded by the audio thread and sampled by the IPC
+    // thread for performance metrics
+    let audio_stats = Arc::new(audio::AudioStats::new());
+
     // Start IPC listener thread
     let device_channels_for_ipc = device_channels.clone();
+    let layouts_for_ipc = layouts.clone();
+    let config_path_for_ipc = config_path.clone();
     let ipc_handle = {
         let running = running.clone();
+        let audio_stats = audio_stats.clone();
         let socket_path = options.socket_path.clone();
         thread::spawn(move || {
             if let Err(e) = ipc::listen_loop(
@@ -105,22 +224,32 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 command_producer,
                 levels_consumer,
                 output_consumer,
-                layout,
+                layouts_for_ipc,
+                &config_path_for_ipc,
                 ipc_layout_consumer,
+                routes,
+                state_file,
+                layout_select_sender,
                 device_channels_for_ipc,
+                capture_link,
+                audio_status_consumer,
+                audio_stats,
             ) {
                 eprintln!("IPC error: {}", e);
             }
         })
     };
 
-    // Config watcher: hot-reload the layout when the file's mtime changes
+    // Config watcher: hot-reload the layouts when the file's mtime changes
+    // and switch between them on request
     let watcher_handle = {
         let running = running.clone();
         thread::spawn(move || {
             config_watcher(
                 running,
                 config_path,
+                layouts,
+                layout_select_receiver,
                 engine_layout_producer,
                 ipc_layout_producer,
             )
@@ -135,6 +264,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     })?;
 
     // Run audio loop on main thread (highest priority)
+    let mut audio_result = Ok(());
     if options.dummy_audio {
         audio::run_dummy_audio_loop(
             engine,
@@ -142,31 +272,60 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             options.test_tone,
             levels_producer,
             device_channels,
+            audio_status_producer,
+            &outputs,
+            realtime,
+            audio_stats,
         );
     } else if let Err(e) = audio::run_audio_loop(
         engine,
         running.clone(),
         options.test_tone,
+        device_name.as_deref(),
+        &outputs,
+        realtime,
+        audio_stats,
         levels_producer,
         device_channels,
+        audio_status_producer,
     ) {
         eprintln!("Audio error: {}", e);
+        audio_result = Err(e);
     }
 
     // Cleanup
     running.store(false, Ordering::Relaxed);
     ipc_handle.join().ok();
     watcher_handle.join().ok();
+    capture_writer.join().ok();
 
     eprintln!("Haptic VST Server stopped");
-    Ok(())
+    audio_result
 }
 
 fn print_usage() {
     eprintln!(
-        "Usage: haptic-server [--config PATH] [--test-tone] [--headless|--dummy-audio] [--socket PATH] [--managed-lifetime-stdin]\n\
+        "Usage: haptic-server [--config PATH] [--test-tone] [--headless|--dummy-audio] [--device NAME] [--list-devices] [--check-config PATH] [--realtime MODE] [--lock-memory] [--state PATH] [--fresh-state] [--socket PATH] [--managed-lifetime-stdin]\n\
          \n\
-         --headless, --dummy-audio  Use a timed 48 kHz/32-channel memory sink; no hardware.\n\
+         --headless, --dummy-audio  Use a timed 48 kHz memory sink with the configured\n\
+                                    transducer count (up to 64) of channels; no hardware.\n\
+         --device NAME              Open this output device (exact name or unique substring);\n\
+                                    overrides [server] device in the config.\n\
+         --list-devices             Print the available output devices and exit.\n\
+         --check-config PATH        Validate a config, print its resolved layouts and any\n\
+                                    warnings (overlaps, off-table positions, zero gains,\n\
+                                    clamped Wave delays), then exit.\n\
+         --realtime off|fifo|rtkit  Real-time scheduling for the audio threads (Linux):\n\
+                                    SCHED_FIFO directly, or granted by rtkit.\n\
+                                    Overrides [server] realtime in the config.\n\
+         --lock-memory              Lock the server's memory against paging.\n\
+         --render SCRIPT --out PATH Render a scripted timeline offline, as fast as possible,\n\
+                                    to a float .wav or raw f32 file with the configured\n\
+                                    transducer count (up to 64) of channels, then exit.\n\
+         --state PATH               Save and restore global state such as monitor routing\n\
+                                    here (default: haptic-state.toml beside the config;\n\
+                                    headless mode saves nothing unless this is given).\n\
+         --fresh-state              Start with identity routing, replacing the saved state.\n\
          --socket PATH              Override the Unix socket/lock namespace.\n\
          --managed-lifetime-stdin   Exit when a supervising process closes stdin.\n\
          HAPTIC_SOCKET_PATH         Environment alternative to --socket.\n\
@@ -184,7 +343,16 @@ fn parse_options(
     let mut test_tone = false;
     let mut dummy_audio = false;
     let mut managed_lifetime_stdin = false;
+    let mut list_devices = false;
+    let mut device = None;
+    let mut realtime = None;
+    let mut lock_memory = false;
+    let mut render_script = None;
+    let mut render_out = None;
+    let mut check_config = None;
     let mut config_path = PathBuf::from(DEFAULT_CONFIG_PATH);
+    let mut state_path = None;
+    let mut fresh_state = false;
     let mut socket_path = None;
     let mut args = args.into_iter();
 
@@ -193,9 +361,30 @@ fn parse_options(
             "--test-tone" => test_tone = true,
             "--headless" | "--dummy-audio" => dummy_audio = true,
             "--managed-lifetime-stdin" => managed_lifetime_stdin = true,
+            "--list-devices" => list_devices = true,
+            "--device" => device = Some(next_option_value(&mut args, "--device")?),
+            "--realtime" => {
+                let mode = next_option_value(&mut args, "--realtime")?;
+                realtime = Some(RealtimeMode::parse(&mode).map_err(|e| {
+                    std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("--{e}"))
+                })?);
+            }
+            "--lock-memory" => lock_memory = true,
+            "--render" => {
+                render_script = Some(PathBuf::from(next_option_value(&mut args, "--render")?));
+            }
+            "--out" => render_out = Some(PathBuf::from(next_option_value(&mut args, "--out")?)),
+            "--check-config" => {
+                check_config = Some(PathBuf::from(next_option_value(
+                    &mut args,
+                    "--check-config",
+                )?));
+            }
             "--config" => {
                 config_path = PathBuf::from(next_option_value(&mut args, "--config")?);
             }
+            "--state" => state_path = Some(PathBuf::from(next_option_value(&mut args, "--state")?)),
+            "--fresh-state" => fresh_state = true,
             "--socket" => socket_path = Some(next_option_value(&mut args, "--socket")?),
             unknown => {
                 return Err(std::io::Error::new(
@@ -207,6 +396,22 @@ fn parse_options(
         }
     }
 
+    let render = match (render_script, render_out) {
+        (Some(script), Some(out)) => Some((script, out)),
+        (None, None) => None,
+        _ => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "--render and --out must be given together",
+            )
+            .into());
+        }
+    };
+
+    // Like the socket, headless runs stay out of the production state
+    let state_path = state_path
+        .or_else(|| (!dummy_audio).then(|| config_path.with_file_name(state::DEFAULT_STATE_FILE)));
+
     let socket_path = socket_path.or(environment_socket).unwrap_or_else(|| {
         if dummy_audio {
             format!("/tmp/haptic-vst-test-{process_id}.sock")
@@ -219,7 +424,15 @@ fn parse_options(
         test_tone,
         dummy_audio,
         managed_lifetime_stdin,
+        list_devices,
+        device,
+        realtime,
+        lock_memory,
+        render,
+        check_config,
         config_path,
+        state_path,
+        fresh_state,
         socket_path,
     })
 }
@@ -265,36 +478,94 @@ fn next_option_value(
 }
 
 /// Poll the config file's mtime (~1 Hz); on change, parse it off the audio
-/// thread and push the new layout into the engine's layout ring. Parse
-/// errors leave the current layout running.
+/// thread and push the new active layout into the engine's layout ring, and
+/// the whole set to the IPC thread. A reload keeps the selected layout if
+/// the file still declares it. Parse errors, and a changed transducer count,
+/// leave the current layouts running. Selections from clients are applied
+/// as they arrive. Every accepted change takes the next config version,
+/// which travels with the layout so the IPC thread can wait for the engine
+/// to confirm it; every rejection is reported to the IPC thread too.
 fn config_watcher(
     running: Arc<AtomicBool>,
     path: PathBuf,
-    mut engine_producer: rtrb::Producer<TransducerLayout>,
-    mut ipc_producer: rtrb::Producer<TransducerLayout>,
+    mut layouts: LayoutSet,
+    selections: Receiver<String>,
+    mut engine_producer: LayoutSender,
+    ipc_producer: Sender<ConfigUpdate>,
 ) {
     let mtime_of = |path: &std::path::Path| -> Option<SystemTime> {
         std::fs::metadata(path).and_then(|m| m.modified()).ok()
     };
+    let count = layouts.active().count;
+    let mut version = 0u64;
 
     let mut last_mtime = mtime_of(&path);
+    let mut next_poll = Instant::now() + Duration::from_millis(1000);
     while running.load(Ordering::Relaxed) {
-        thread::sleep(Duration::from_millis(1000));
-        let mtime = mtime_of(&path);
-        if mtime.is_some() && mtime != last_mtime {
-            last_mtime = mtime;
-            match config::load_layout(&path) {
-                Ok(layout) => {
-                    if engine_producer.push(layout).is_ok() {
-                        eprintln!("Config reloaded from {}", path.display());
+        let wait = next_poll.saturating_duration_since(Instant::now());
+        let candidate = match selections.recv_timeout(wait) {
+            Ok(name) => {
+                let mut selected = layouts.clone();
+                match selected.select(&name) {
+                    Ok(()) => {
+                        eprintln!("Layout \"{name}\" selected");
+                        Some(Ok(selected))
+                    }
+                    Err(e) => {
+                        eprintln!("Layout selection failed (keeping current layout): {}", e);
+                        Some(Err(format!("layout selection failed: {e}")))
                     }
-                    let _ = ipc_producer.push(layout);
                 }
-                Err(e) => {
-                    eprintln!("Config reload failed (keeping current layout): {}", e);
+            }
+            Err(error) => {
+                // Without the IPC thread nothing selects; keep polling
+                if error == RecvTimeoutError::Disconnected {
+                    thread::sleep(wait);
+                }
+                next_poll = Instant::now() + Duration::from_millis(1000);
+                let mtime = mtime_of(&path);
+                if mtime.is_some() && mtime != last_mtime {
+                    last_mtime = mtime;
+                    match config::load_layouts(&path, count) {
+                        Ok(mut reloaded) => {
+                            let selected = layouts.active_name().to_string();
+                            if reloaded.contains(&selected) {
+                                let _ = reloaded.select(&selected);
+                            }
+                            eprintln!("Config reloaded from {}", path.display());
+                            Some(Ok(reloaded))
+                        }
+                        Err(e) => {
+                            eprintln!("Config reload failed (keeping current layout): {}", e);
+                            Some(Err(format!("config reload failed: {e}")))
+                        }
+                    }
+                } else {
+                    None
                 }
             }
-        }
+        };
+        let update = match candidate {
+            Some(Ok(candidate)) => {
+                if engine_producer.push(version + 1, candidate.active().clone()) {
+                    version += 1;
+                    layouts = candidate;
+                    ConfigUpdate::Accepted {
+                        version,
+                        layouts: layouts.clone(),
+                    }
+                } else {
+                    eprintln!("Engine layout queue full, layout not applied");
+                    ConfigUpdate::Rejected {
+                        error: "engine layout queue full, layout not applied".to_string(),
+                    }
+                }
+            }
+            Some(Err(error)) => ConfigUpdate::Rejected { error },
+            None => continue,
+        };
+        // Fails only once the IPC thread has gone, at shutdown
+        let _ = ipc_producer.send(update);
     }
 }
 
@@ -333,6 +604,95 @@ mod tests {
         assert_eq!(options.socket_path, "/tmp/haptic-vst-test.sock");
     }
 
+    #[test]
+    fn device_selection_options_are_parsed() {
+        let options = parse_options(
+            vec![
+                "--device".into(),
+                "MOTU 24Ao".into(),
+                "--list-devices".into(),
+            ],
+            None,
+            123,
+        )
+        .unwrap();
+        assert_eq!(options.device.as_deref(), Some("MOTU 24Ao"));
+        assert!(options.list_devices);
+        assert!(parse_options(vec!["--device".into()], None, 123).is_err());
+    }
+
+    #[test]
+    fn realtime_options_are_opt_in() {
+        let options = parse_options(Vec::new(), None, 123).unwrap();
+        assert_eq!(options.realtime, None);
+        assert!(!options.lock_memory);
+        let options = parse_options(
+            vec!["--realtime".into(), "fifo".into(), "--lock-memory".into()],
+            None,
+            123,
+        )
+        .unwrap();
+        assert_eq!(options.realtime, Some(RealtimeMode::Fifo));
+        assert!(options.lock_memory);
+        assert!(parse_options(vec!["--realtime".into(), "max".into()], None, 123).is_err());
+    }
+
+    #[test]
+    fn offline_render_needs_both_script_and_output() {
+        let options = parse_options(
+            vec![
+                "--render".into(),
+                "score.toml".into(),
+                "--out".into(),
+                "score.wav".into(),
+            ],
+            None,
+            123,
+        )
+        .unwrap();
+        assert_eq!(
+            options.render,
+            Some((PathBuf::from("score.toml"), PathBuf::from("score.wav")))
+        );
+        assert!(parse_options(vec!["--render".into(), "score.toml".into()], None, 123).is_err());
+        assert!(parse_options(vec!["--out".into(), "score.wav".into()], None, 123).is_err());
+    }
+
+    #[test]
+    fn check_config_takes_a_path() {
+        let options =
+            parse_options(vec!["--check-config".into(), "rig.toml".into()], None, 123).unwrap();
+        assert_eq!(options.check_config, Some(PathBuf::from("rig.toml")));
+        assert!(parse_options(vec!["--check-config".into()], None, 123).is_err());
+    }
+
+    #[test]
+    fn state_file_defaults_beside_the_config_except_headless() {
+        let options =
+            parse_options(vec!["--config".into(), "rigs/a.toml".into()], None, 123).unwrap();
+        assert_eq!(
+            options.state_path,
+            Some(PathBuf::from("rigs/haptic-state.toml"))
+        );
+        assert!(!options.fresh_state);
+
+        let options = parse_options(vec!["--headless".into()], None, 123).unwrap();
+        assert_eq!(options.state_path, None);
+        let options = parse_options(
+            vec![
+                "--headless".into(),
+                "--state".into(),
+                "s.toml".into(),
+                "--fresh-state".into(),
+            ],
+            None,
+            123,
+        )
+        .unwrap();
+        assert_eq!(options.state_path, Some(PathBuf::from("s.toml")));
+        assert!(options.fresh_state);
+    }
+
     #[test]
     fn managed_lifetime_is_explicit_and_independent_of_audio_profile() {
         let options = parse_options(vec!["--managed-lifetime-stdin".into()], None, 123).unwrap();
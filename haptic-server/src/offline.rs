@@ -0,0 +1,582 @@
+//! Offline faster-than-real-time rendering.
+//!
+//! Drives `StimulusEngine::process_block` from a scripted TOML command
+//! timeline as fast as the CPU allows and writes one logical channel per
+//! transducer to a
+//! WAVE_FORMAT_EXTENSIBLE float WAV or a raw interleaved little-endian f32
+//! file. Every scripted event becomes the same `HapticCommand` a controller
+//! would send and passes the same validation as the socket path. Blocks are
+//! split exactly at event frames, so a render depends only on the script, the
+//! layout, and the build: the same inputs reproduce the file bit-for-bit.
+
+use crate::config::TransducerLayout;
+use crate::engine::{EngineCommand, StimulusEngine, MAX_TRANSDUCERS};
+use crate::ipc::validate_command;
+use crate::wav::{write_wav_header, OutputFormat};
+use haptic_protocol::{
+    Capabilities, ClientRole, DistanceDecay, HapticCommand, InstanceConfig, MpeData,
+    SpatialScaleMode, StimulusType, TravellingWaveConfig, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+pub const DEFAULT_RENDER_SAMPLE_RATE: u32 = 48_000;
+
+/// Default chunk size, matching the headless sink's callback. Scripted MPE
+/// ramps advance once per chunk, like a controller updating per callback.
+pub const DEFAULT_RENDER_BLOCK_FRAMES: usize = 512;
+
+/// Instance used by events that do not name one; registered with the default
+/// note-type config when the script declares no `[[instance]]`.
+const DEFAULT_SCRIPT_INSTANCE: u64 = 1;
+
+/// A validated timeline ready to render.
+#[derive(Debug)]
+pub struct RenderScript {
+    pub sample_rate: u32,
+    pub block_frames: usize,
+    pub total_frames: u64,
+    instances: Vec<(u64, InstanceConfig)>,
+    events: Vec<ScriptEvent>,
+}
+
+#[derive(Debug)]
+struct ScriptEvent {
+    frame: u64,
+    instance_id: u64,
+    action: ScriptAction,
+}
+
+#[derive(Debug)]
+enum ScriptAction {
+    Command(HapticCommand),
+    /// Move a channel's MPE linearly to `target` over `frames`, starting
+    /// from wherever that channel is when the ramp begins.
+    Ramp {
+        channel: u8,
+        target: MpeTarget,
+        frames: u64,
+    },
+}
+
+pub struct RenderSummary {
+    pub frames: u64,
+    pub peak: f32,
+}
+
+// ---------------------------------------------------------------------------
+// TOML schema
+// ---------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawScript {
+    duration_s: f64,
+    sample_rate: Option<u32>,
+    block_frames: Option<usize>,
+    #[serde(default, rename = "instance")]
+    instances: Vec<RawInstance>,
+    #[serde(default, rename = "event")]
+    events: Vec<RawEvent>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawInstance {
+    id: u64,
+    stimulus_type: Option<StimulusType>,
+    wave_speed: Option<f32>,
+    scale_mode: Option<SpatialScaleMode>,
+    wavelength_m: Option<f32>,
+    atten_d0_m: Option<f32>,
+    atten_exponent: Option<f32>,
+    source_height_m: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawEvent {
+    at_s: f64,
+    instance: Option<u64>,
+    note_on: Option<RawNoteOn>,
+    note_off: Option<RawNoteOff>,
+    mpe: Option<RawMpe>,
+    panic: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawNoteOn {
+    note: u8,
+    velocity: Option<u8>,
+    channel: Option<u8>,
+    pressure: Option<f32>,
+    pitch_bend: Option<f32>,
+    timbre: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawNoteOff {
+    note: u8,
+    channel: Option<u8>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawMpe {
+    channel: Option<u8>,
+    pressure: Option<f32>,
+    pitch_bend: Option<f32>,
+    timbre: Option<f32>,
+    ramp_s: Option<f64>,
+}
+
+/// MPE dimensions an event sets; omitted ones hold their current value.
+#[derive(Clone, Copy, Debug)]
+struct MpeTarget {
+    pressure: Option<f32>,
+    pitch_bend: Option<f32>,
+    timbre: Option<f32>,
+}
+
+impl MpeTarget {
+    fn lerp_from(&self, from: MpeData, t: f32) -> MpeData {
+        let lerp = |from: f32, to: Option<f32>| to.map_or(from, |to| from + (to - from) * t);
+        MpeData {
+            pressure: lerp(from.pressure, self.pressure),
+            pitch_bend: lerp(from.pitch_bend, self.pitch_bend),
+            timbre: lerp(from.timbre, self.timbre),
+        }
+    }
+}
+
+/// Parse and validate a render script. Commands are checked with the same
+/// validator as the socket path, so an out-of-range note is rejected here
+/// rather than silently altering the render.
+pub fn parse_script(text: &str) -> Result<RenderScript, String> {
+    let raw: RawScript = toml::from_str(text).map_err(|e| format!("TOML parse error: {}", e))?;
+
+    let sample_rate = raw.sample_rate.unwrap_or(DEFAULT_RENDER_SAMPLE_RATE);
+    if !(8_000..=384_000).contains(&sample_rate) {
+        return Err(format!(
+            "sample_rate {sample_rate} Hz out of range (8000-384000)"
+        ));
+    }
+    let block_frames = raw.block_frames.unwrap_or(DEFAULT_RENDER_BLOCK_FRAMES);
+    if !(1..=65_536).contains(&block_frames) {
+        return Err(format!(
+            "block_frames {block_frames} out of range (1-65536)"
+        ));
+    }
+    if !raw.duration_s.is_finite() || raw.duration_s <= 0.0 {
+        return Err("duration_s must be finite and > 0".into());
+    }
+    let to_frame = |seconds: f64| (seconds * sample_rate as f64).round() as u64;
+    let total_frames = to_frame(raw.duration_s);
+
+    let mut instances = Vec::new();
+    for r in &raw.instances {
+        if instances.iter().any(|&(id, _)| id == r.id) {
+            return Err(format!("instance {} is declared twice", r.id));
+        }
+        let defaults = InstanceConfig::default();
+        let config = InstanceConfig {
+            stimulus_type: r.stimulus_type.unwrap_or(defaults.stimulus_type),
+            wave_speed: r.wave_speed.unwrap_or(defaults.wave_speed),
+            travelling_wave: TravellingWaveConfig {
+                scale_mode: r.scale_mode.unwrap_or(defaults.travelling_wave.scale_mode),
+                wave_speed: r.wave_speed.unwrap_or(defaults.travelling_wave.wave_speed),
+                wavelength_m: r
+                    .wavelength_m
+                    .unwrap_or(defaults.travelling_wave.wavelength_m),
+            },
+            distance_decay: DistanceDecay {
+                d0_m: r.atten_d0_m.unwrap_or(defaults.distance_decay.d0_m),
+                exponent: r.atten_exponent.unwrap_or(defaults.distance_decay.exponent),
+            },
+            source_height_m: r.source_height_m.unwrap_or(defaults.source_height_m),
+        };
+        let mut hello = HapticCommand::Hello {
+            min_protocol_version: MIN_PROTOCOL_VERSION,
+            max_protocol_version: PROTOCOL_VERSION,
+            instance_id: r.id,
+            role: ClientRole::Controller,
+            config,
+            capabilities: Capabilities::NONE,
+        };
+        validate_command(&mut hello, MAX_TRANSDUCERS)
+            .map_err(|e| format!("instance {}: {}", r.id, e))?;
+        let HapticCommand::Hello { config, .. } = hello else {
+            unreachable!("validation preserves the command variant");
+        };
+        instances.push((r.id, config));
+    }
+    if instances.is_empty() {
+        instances.push((DEFAULT_SCRIPT_INSTANCE, InstanceConfig::default()));
+    }
+
+    let mut events = Vec::with_capacity(raw.events.len());
+    for (index, e) in raw.events.iter().enumerate() {
+        let context = |message: &str| format!("event {} (at {} s): {}", index, e.at_s, message);
+        if !e.at_s.is_finite() || e.at_s < 0.0 {
+            return Err(context("at_s must be finite and >= 0"));
+        }
+        let frame = to_frame(e.at_s);
+        if frame >= total_frames {
+            return Err(context("scheduled at or after the end of the render"));
+        }
+        let instance_id = e.instance.unwrap_or(instances[0].0);
+        if !instances.iter().any(|&(id, _)| id == instance_id) {
+            return Err(context(&format!("unknown instance {instance_id}")));
+        }
+
+        let actions = [
+            e.note_on.is_some(),
+            e.note_off.is_some(),
+            e.mpe.is_some(),
+            e.panic.is_some(),
+        ];
+        if actions.iter().filter(|&&present| present).count() != 1 {
+            return Err(context(
+                "exactly one of note_on, note_off, mpe, or panic is required",
+            ));
+        }
+
+        let mut command = if let Some(n) = &e.note_on {
+            HapticCommand::NoteOn {
+                timestamp_us: 0,
+                note: n.note,
+                velocity: n.velocity.unwrap_or(100),
+                channel: n.channel.unwrap_or(0),
+                // A scripted note sounds at full pressure unless told
+                // otherwise; the wire default of zero would be silent.
+                mpe: MpeData {
+                    pressure: n.pressure.unwrap_or(1.0),
+                    pitch_bend: n.pitch_bend.unwrap_or(0.0),
+                    timbre: n.timbre.unwrap_or(MpeData::default().timbre),
+                },
+            }
+        } else if let Some(n) = &e.note_off {
+            HapticCommand::NoteOff {
+                timestamp_us: 0,
+                note: n.note,
+                channel: n.channel.unwrap_or(0),
+            }
+        } else if let Some(m) = &e.mpe {
+            let channel = m.channel.unwrap_or(0);
+            let target = MpeTarget {
+                pressure: m.pressure,
+                pitch_bend: m.pitch_bend,
+                timbre: m.timbre,
+            };
+            // Validate the end point; interpolated values stay between
+            // two valid points and are normalised again on the way in.
+            let mut probe = HapticCommand::MpeUpdate {
+                timestamp_us: 0,
+                channel,
+                mpe: target.lerp_from(MpeData::default(), 1.0),
+            };
+            validate_command(&mut probe, MAX_TRANSDUCERS).map_err(&context)?;
+            let ramp_s = m.ramp_s.unwrap_or(0.0);
+            if !ramp_s.is_finite() || ramp_s < 0.0 {
+                return Err(context("ramp_s must be finite and >= 0"));
+            }
+            events.push(ScriptEvent {
+                frame,
+                instance_id,
+                action: ScriptAction::Ramp {
+                    channel,
+                    target,
+                    frames: to_frame(ramp_s),
+                },
+            });
+            continue;
+        } else if e.panic == Some(true) {
+            HapticCommand::Panic
+        } else {
+            return Err(context("panic = false has no effect; remove the event"));
+        };
+        validate_command(&mut command, MAX_TRANSDUCERS).map_err(context)?;
+        events.push(ScriptEvent {
+            frame,
+            instance_id,
+            action: ScriptAction::Command(command),
+        });
+    }
+    // Stable: events at the same instant keep their script order
+    events.sort_by_key(|event| event.frame);
+
+    Ok(RenderScript {
+        sample_rate,
+        block_frames,
+        total_frames,
+        instances,
+        events,
+    })
+}
+
+pub fn load_script(path: &Path) -> Result<RenderScript, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    parse_script(&text)
+}
+
+struct ActiveRamp {
+    instance_id: u64,
+    channel: u8,
+    from: MpeData,
+    target: MpeTarget,
+    start: u64,
+    frames: u64,
+}
+
+/// Render `script` through a fresh engine built from `layout`, streaming one
+/// logical channel per transducer to `out`.
+pub fn render(
+    script: &RenderScript,
+    layout: TransducerLayout,
+    format: OutputFormat,
+    out: &mut impl Write,
+) -> Result<RenderSummary, String> {
+    let io_error = |e: std::io::Error| format!("write failed: {}", e);
+    let channels = layout.count;
+    if format == OutputFormat::Wav {
+        write_wav_header(out, script.sample_rate, channels, script.total_frames)?;
+    }
+
+    let (mut engine, mut commands, _layouts, _outputs) = StimulusEngine::new(layout);
+    let sample_rate = script.sample_rate as f32;
+    let mut levels = [0.0f32; MAX_TRANSDUCERS];
+    let mut push = |engine: &mut StimulusEngine, command: EngineCommand| {
+        let mut command = command;
+        loop {
+            match commands.push(command) {
+                Ok(()) => return,
+                Err(rtrb::PushError::Full(rejected)) => {
+                    // An empty block drains the queue without advancing time
+                    command = rejected;
+                    let mut unused = [0.0f32; MAX_TRANSDUCERS];
+                    engine.process_block(&mut [], channels, sample_rate, &mut unused);
+                }
+            }
+        }
+    };
+
+    for &(instance_id, config) in &script.instances {
+        push(
+            &mut engine,
+            EngineCommand::RegisterInstance {
+                instance_id,
+                config,
+            },
+        );
+    }
+
+    let mut mpe_state: HashMap<(u64, u8), MpeData> = HashMap::new();
+    let mut ramps: Vec<ActiveRamp> = Vec::new();
+    let mut data = vec![0.0f32; script.block_frames * channels];
+    let mut bytes = Vec::with_capacity(data.len() * 4);
+    let mut next_event = 0;
+    let mut frame = 0u64;
+    let mut peak = 0.0f32;
+
+    while frame < script.total_frames {
+        while let Some(event) = script.events.get(next_event).filter(|e| e.frame <= frame) {
+            next_event += 1;
+            match &event.action {
+                ScriptAction::Command(command) => {
+                    if let HapticCommand::NoteOn { channel, mpe, .. } = command {
+                        mpe_state.insert((event.instance_id, *channel), *mpe);
+                    }
+                    if let Some(command) =
+                        EngineCommand::from_wire(command.clone(), event.instance_id)
+                    {
+                        push(&mut engine, command);
+                    }
+                }
+                ScriptAction::Ramp {
+                    channel,
+                    target,
+                    frames,
+                } => {
+                    ramps.retain(|r| (r.instance_id, r.channel) != (event.instance_id, *channel));
+                    ramps.push(ActiveRamp {
+                        instance_id: event.instance_id,
+                        channel: *channel,
+                        from: mpe_state
+                            .get(&(event.instance_id, *channel))
+                            .copied()
+                            .unwrap_or_default(),
+                        target: *target,
+                        start: frame,
+                        frames: *frames,
+                    });
+                }
+            }
+        }
+
+        for ramp in &ramps {
+            let t = if ramp.frames == 0 {
+                1.0
+            } else {
+                ((frame - ramp.start) as f64 / ramp.frames as f64).min(1.0) as f32
+            };
+            let mut command = HapticCommand::MpeUpdate {
+                timestamp_us: 0,
+                channel: ramp.channel,
+                mpe: ramp.target.lerp_from(ramp.from, t),
+            };
+            validate_command(&mut command, MAX_TRANSDUCERS)
+                .expect("ramp end points were validated");
+            if let HapticCommand::MpeUpdate { mpe, .. } = command {
+                mpe_state.insert((ramp.instance_id, ramp.channel), mpe);
+            }
+            push(
+                &mut engine,
+                EngineCommand::from_wire(command, ramp.instance_id)
+                    .expect("MPE updates reach the engine"),
+            );
+        }
+        ramps.retain(|ramp| frame - ramp.start < ramp.frames);
+
+        let mut chunk = (script.block_frames as u64).min(script.total_frames - frame);
+        if let Some(event) = script.events.get(next_event) {
+            chunk = chunk.min(event.frame - frame);
+        }
+        let block = &mut data[..chunk as usize * channels];
+        engine.process_block(block, channels, sample_rate, &mut levels);
+
+        bytes.clear();
+        for &sample in block.iter() {
+            peak = peak.max(sample.abs());
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        out.write_all(&bytes).map_err(io_error)?;
+        frame += chunk;
+    }
+    out.flush().map_err(io_error)?;
+
+    Ok(RenderSummary {
+        frames: script.total_frames,
+        peak,
+    })
+}
+
+/// `--render` entry point: load the script, render it, and report how much
+/// faster than real time the render ran.
+pub fn run(
+    script_path: &Path,
+    out_path: &Path,
+    layout: TransducerLayout,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let script = load_script(script_path)?;
+    let format = OutputFormat::for_path(out_path);
+    let file = std::fs::File::create(out_path)
+        .map_err(|e| format!("cannot create {}: {}", out_path.display(), e))?;
+    let mut out = std::io::BufWriter::new(file);
+
+    let channels = layout.count;
+    let started = std::time::Instant::now();
+    let summary = render(&script, layout, format, &mut out)?;
+    let elapsed = started.elapsed().as_secs_f64();
+    let seconds = summary.frames as f64 / script.sample_rate as f64;
+    eprintln!(
+        "Rendered {:.2} s ({} frames, {} Hz, {} channels) to {} in {:.2} s ({:.1}x real time), peak {:.1} dBFS",
+        seconds,
+        summary.frames,
+        script.sample_rate,
+        channels,
+        out_path.display(),
+        elapsed,
+        seconds / elapsed.max(f64::MIN_POSITIVE),
+        20.0 * summary.peak.max(1.0e-9).log10()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = r#"
+        duration_s = 0.5
+        block_frames = 256
+
+        [[instance]]
+        id = 7
+        wave_speed = 12.0
+
+        [[event]]
+        at_s = 0.0
+        note_on = { note = 40, velocity = 110, pitch_bend = -0.5 }
+
+        [[event]]
+        at_s = 0.05
+        mpe = { pitch_bend = 0.5, timbre = 0.9, ramp_s = 0.2 }
+
+        [[event]]
+        at_s = 0.3
+        note_off = { note = 40 }
+    "#;
+
+    fn render_bytes(script: &RenderScript, format: OutputFormat) -> Vec<u8> {
+        let mut out = Vec::new();
+        render(script, TransducerLayout::default(), format, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn renders_are_bit_identical_and_not_silent() {
+        let script = parse_script(SCRIPT).unwrap();
+        assert_eq!(script.total_frames, 24_000);
+        let first = render_bytes(&script, OutputFormat::RawF32);
+        let second = render_bytes(&parse_script(SCRIPT).unwrap(), OutputFormat::RawF32);
+        assert_eq!(first.len(), 24_000 * 32 * 4);
+        assert!(first == second, "offline render is not reproducible");
+        assert!(first
+            .chunks_exact(4)
+            .any(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()).abs() > 1.0e-3));
+    }
+
+    #[test]
+    fn wav_output_is_a_float_extensible_header_before_the_raw_samples() {
+        use crate::wav::IEEE_FLOAT_SUBFORMAT;
+
+        let script = parse_script(SCRIPT).unwrap();
+        let wav = render_bytes(&script, OutputFormat::Wav);
+        let raw = render_bytes(&script, OutputFormat::RawF32);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(&wav[8..12], b"WAVE");
+        assert_eq!(u16::from_le_bytes([wav[20], wav[21]]), 0xfffe);
+        assert_eq!(u16::from_le_bytes([wav[22], wav[23]]), 32);
+        assert_eq!(u32::from_le_bytes(wav[24..28].try_into().unwrap()), 48_000);
+        assert_eq!(&wav[44..60], &IEEE_FLOAT_SUBFORMAT);
+        assert_eq!(&wav[72..76], b"data");
+        assert_eq!(
+            u32::from_le_bytes(wav[4..8].try_into().unwrap()) as usize + 8,
+            wav.len()
+        );
+        assert_eq!(&wav[80..], &raw[..]);
+    }
+
+    #[test]
+    fn invalid_scripts_are_rejected_before_rendering() {
+        let event = |body: &str| format!("duration_s = 1.0\n[[event]]\n{body}");
+        assert!(parse_script(&event("at_s = 0.0\nnote_on = { note = 200 }")).is_err());
+        assert!(parse_script(&event("at_s = 2.0\nnote_off = { note = 40 }")).is_err());
+        assert!(parse_script(&event("at_s = 0.0\ninstance = 9\npanic = true")).is_err());
+        assert!(parse_script(&event(
+            "at_s = 0.0\nnote_on = { note = 40 }\nnote_off = { note = 40 }"
+        ))
+        .is_err());
+        assert!(parse_script("duration_s = 0.0").is_err());
+    }
+
+    #[test]
+    fn example_script_parses() {
+        parse_script(include_str!("../../tools/offline_render_example.toml")).unwrap();
+    }
+}
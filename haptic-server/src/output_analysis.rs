@@ -2,8 +2,8 @@
 //!
 //! The engine feeds this module samples only after reconstruction and final
 //! safety bounding. Analysis is decimated to approximately 1.5 kHz: this is
-//! comfortably above the 20--200 Hz haptic band while keeping the 32-channel
-//! FIR bounded enough for the audio callback. Every analysed value is still an
+//! comfortably above the 20--200 Hz haptic band while keeping the
+//! many-channel FIR bounded enough for the audio callback. Every analysed value is still an
 //! actual sample from the device-rate logical stream, not a geometric model.
 
 use std::f32::consts::PI;
@@ -19,8 +19,10 @@ pub struct AnalyticFrame<const CHANNELS: usize> {
 }
 
 /// Odd-symmetric, Blackman-windowed ideal Hilbert transformer. The matching
-/// real component is delayed by the FIR's integer group delay.
+/// real component is delayed by the FIR's integer group delay. Storage holds
+/// `CHANNELS`; only the first `active` are analysed, the rest report zero.
 pub struct OutputAnalyzer<const CHANNELS: usize> {
+    active: usize,
     history: Box<[[f32; CHANNELS]; HILBERT_TAPS]>,
     coefficients: [f32; HILBERT_TAPS],
     history_pos: usize,
@@ -31,8 +33,9 @@ pub struct OutputAnalyzer<const CHANNELS: usize> {
 }
 
 impl<const CHANNELS: usize> OutputAnalyzer<CHANNELS> {
-    pub fn new() -> Self {
+    pub fn new(active: usize) -> Self {
         Self {
+            active: active.min(CHANNELS),
             history: Box::new([[0.0; CHANNELS]; HILBERT_TAPS]),
             coefficients: design_hilbert_fir(),
             history_pos: 0,
@@ -52,7 +55,7 @@ impl<const CHANNELS: usize> OutputAnalyzer<CHANNELS> {
         device_sample_rate: f32,
     ) {
         self.configure_rate(device_sample_rate);
-        if sample_index % self.decimation as u64 != 0 {
+        if !sample_index.is_multiple_of(self.decimation as u64) {
             return;
         }
 
@@ -62,7 +65,7 @@ impl<const CHANNELS: usize> OutputAnalyzer<CHANNELS> {
 
         let delayed_pos = (self.history_pos + HILBERT_DELAY_SAMPLES) % HILBERT_TAPS;
         let mut analytic = [(0.0f32, 0.0f32); CHANNELS];
-        for (channel, output) in analytic.iter_mut().enumerate() {
+        for (channel, output) in analytic[..self.active].iter_mut().enumerate() {
             let real = self.history[delayed_pos][channel];
             let mut imaginary = 0.0f32;
             for (tap, &coefficient) in self.coefficients.iter().enumerate() {
@@ -123,7 +126,7 @@ mod tests {
     fn sine_becomes_phase_aligned_analytic_signal() {
         const SAMPLE_RATE: f32 = 48_000.0;
         const FREQUENCY: f32 = 65.406;
-        let mut analyzer = OutputAnalyzer::<1>::new();
+        let mut analyzer = OutputAnalyzer::<1>::new(1);
         for sample_index in 0..48_000u64 {
             let phase = std::f32::consts::TAU * FREQUENCY * sample_index as f32 / SAMPLE_RATE;
             analyzer.process(&[phase.sin()], sample_index, SAMPLE_RATE);
@@ -142,7 +145,7 @@ mod tests {
 
     #[test]
     fn reports_nothing_until_the_full_history_is_available() {
-        let mut analyzer = OutputAnalyzer::<1>::new();
+        let mut analyzer = OutputAnalyzer::<1>::new(1);
         for sample_index in 0..(HILBERT_TAPS as u64 - 1) * 32 {
             analyzer.process(&[0.5], sample_index, 48_000.0);
         }
@@ -0,0 +1,255 @@
+//! Opt-in real-time scheduling and memory locking for the audio threads.
+//!
+//! `fifo` asks the kernel for SCHED_FIFO directly, which needs CAP_SYS_NICE
+//! or an `rtprio` limit; `rtkit` asks the RealtimeKit daemon on the system
+//! bus, which grants desktop users a modest priority without either. Both
+//! are Linux only. Audio callback threads belong to the backend, so each
+//! callback publishes its thread id once and the supervising thread promotes
+//! it from outside: nothing here runs on the audio path.
+
+use std::sync::atomic::{AtomicI32, AtomicU8, Ordering};
+use std::sync::Arc;
+
+/// Within rtkit's default `MaxRealtimePriority`, and well above the
+/// default-policy threads it has to preempt.
+pub const DEFAULT_REALTIME_PRIORITY: i32 = 20;
+
+/// rtkit only promotes threads of a process that limits its real-time CPU
+/// time; a callback that spins this long without blocking gets SIGXCPU.
+#[cfg(target_os = "linux")]
+const RTKIT_RTTIME_LIMIT_US: u64 = 200_000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RealtimeMode {
+    #[default]
+    Off,
+    Fifo,
+    Rtkit,
+}
+
+impl RealtimeMode {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "off" => Ok(Self::Off),
+            "fifo" => Ok(Self::Fifo),
+            "rtkit" => Ok(Self::Rtkit),
+            other => Err(format!(
+                "realtime must be \"off\", \"fifo\" or \"rtkit\", not \"{other}\""
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RealtimeSettings {
+    pub mode: RealtimeMode,
+    /// SCHED_FIFO priority, 1..=99.
+    pub priority: i32,
+    /// `mlockall` the server so page faults cannot stall the callback.
+    pub lock_memory: bool,
+}
+
+impl Default for RealtimeSettings {
+    fn default() -> Self {
+        Self {
+            mode: RealtimeMode::Off,
+            priority: DEFAULT_REALTIME_PRIORITY,
+            lock_memory: false,
+        }
+    }
+}
+
+const WAITING: u8 = 0;
+const PROMOTING: u8 = 1;
+const PROMOTED: u8 = 2;
+const FAILED: u8 = 3;
+
+/// One audio thread: its id once it has run, and how promotion went.
+#[derive(Default)]
+pub struct ThreadSlot {
+    tid: AtomicI32,
+    state: AtomicU8,
+}
+
+impl ThreadSlot {
+    /// Record the calling thread's id the first time it runs. Callback-safe:
+    /// one atomic load afterwards.
+    pub fn publish(&self) {
+        if self.tid.load(Ordering::Relaxed) == 0 {
+            self.tid.store(current_thread_id(), Ordering::Relaxed);
+        }
+    }
+}
+
+/// The audio threads of one stream (or of the headless sinks), promoted as
+/// they publish their ids.
+pub struct Promoter {
+    settings: RealtimeSettings,
+    threads: Vec<Arc<ThreadSlot>>,
+}
+
+impl Promoter {
+    pub fn new(settings: RealtimeSettings) -> Self {
+        Self {
+            settings,
+            threads: Vec::new(),
+        }
+    }
+
+    /// Add a thread; hand the slot to its callback.
+    pub fn register(&mut self) -> Arc<ThreadSlot> {
+        let slot = Arc::new(ThreadSlot::default());
+        self.threads.push(slot.clone());
+        slot
+    }
+
+    /// Promote every thread that has published its id and not been tried.
+    /// May block on rtkit for a moment, so never call it from a callback.
+    pub fn poll(&self, name: &str) {
+        if self.settings.mode == RealtimeMode::Off {
+            return;
+        }
+        for (index, slot) in self.threads.iter().enumerate() {
+            let tid = slot.tid.load(Ordering::Relaxed);
+            if tid == 0
+                || slot
+                    .state
+                    .compare_exchange(WAITING, PROMOTING, Ordering::Relaxed, Ordering::Relaxed)
+                    .is_err()
+            {
+                continue;
+            }
+            let outcome = promote(tid, self.settings);
+            let state = match &outcome {
+                Ok(()) => PROMOTED,
+                Err(_) => FAILED,
+            };
+            slot.state.store(state, Ordering::Relaxed);
+            match outcome {
+                Ok(()) => eprintln!(
+                    "{name} thread {index} running {:?} at priority {}",
+                    self.settings.mode, self.settings.priority
+                ),
+                Err(e) => eprintln!("Warning: {name} thread {index} stays at normal priority: {e}"),
+            }
+        }
+    }
+
+    /// Whether real-time scheduling was requested and every thread has it.
+    pub fn realtime(&self) -> bool {
+        self.settings.mode != RealtimeMode::Off
+            && !self.threads.is_empty()
+            && self
+                .threads
+                .iter()
+                .all(|slot| slot.state.load(Ordering::Relaxed) == PROMOTED)
+    }
+}
+
+/// Lock the server's current and future pages into RAM if requested;
+/// returns whether they are locked. Failure is a warning, not an error.
+pub fn lock_memory(settings: &RealtimeSettings) -> bool {
+    if !settings.lock_memory {
+        return false;
+    }
+    match lock_all() {
+        Ok(()) => {
+            eprintln!("Memory locked");
+            true
+        }
+        Err(e) => {
+            eprintln!("Warning: could not lock memory ({e}); check `ulimit -l`");
+            false
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn current_thread_id() -> i32 {
+    // SAFETY: gettid takes no arguments and cannot fail
+    unsafe { libc::syscall(libc::SYS_gettid) as i32 }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn current_thread_id() -> i32 {
+    -1
+}
+
+#[cfg(target_os = "linux")]
+fn lock_all() -> Result<(), String> {
+    // SAFETY: mlockall only changes the process's paging
+    if unsafe { libc::mlockall(libc::MCL_CURRENT | libc::MCL_FUTURE) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error().to_string())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn lock_all() -> Result<(), String> {
+    Err("memory locking is only supported on Linux".into())
+}
+
+#[cfg(target_os = "linux")]
+fn promote(tid: i32, settings: RealtimeSettings) -> Result<(), String> {
+    match settings.mode {
+        RealtimeMode::Off => Ok(()),
+        RealtimeMode::Fifo => {
+            let param = libc::sched_param {
+                sched_priority: settings.priority,
+            };
+            // SAFETY: `tid` is a thread of this process and `param` outlives
+            // the call
+            let result = unsafe {
+                libc::sched_setscheduler(tid, libc::SCHED_FIFO | libc::SCHED_RESET_ON_FORK, &param)
+            };
+            if result == 0 {
+                Ok(())
+            } else {
+                Err(format!(
+                    "SCHED_FIFO refused ({}); grant an rtprio limit or try realtime = \"rtkit\"",
+                    std::io::Error::last_os_error()
+                ))
+            }
+        }
+        RealtimeMode::Rtkit => {
+            let limit = libc::rlimit {
+                rlim_cur: RTKIT_RTTIME_LIMIT_US,
+                rlim_max: RTKIT_RTTIME_LIMIT_US,
+            };
+            // SAFETY: lowering our own limit; `limit` outlives the call
+            if unsafe { libc::setrlimit(libc::RLIMIT_RTTIME, &limit) } != 0 {
+                return Err(format!(
+                    "could not limit RLIMIT_RTTIME for rtkit ({})",
+                    std::io::Error::last_os_error()
+                ));
+            }
+            let output = std::process::Command::new("dbus-send")
+                .args([
+                    "--system",
+                    "--print-reply",
+                    "--dest=org.freedesktop.RealtimeKit1",
+                    "/org/freedesktop/RealtimeKit1",
+                    "org.freedesktop.RealtimeKit1.MakeThreadRealtimeWithPID",
+                    &format!("uint64:{}", std::process::id()),
+                    &format!("uint64:{tid}"),
+                    &format!("uint32:{}", settings.priority),
+                ])
+                .output()
+                .map_err(|e| format!("could not run dbus-send for rtkit ({e})"))?;
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(format!(
+                    "rtkit refused: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn promote(_tid: i32, _settings: RealtimeSettings) -> Result<(), String> {
+    Err("real-time scheduling is only supported on Linux".into())
+}
@@ -0,0 +1,426 @@
+//! One engine render spread across several output devices.
+//!
+//! With a `[[server.output]]` split, the first device is the primary: its
+//! callback renders a block of the configured transducer count (up to
+//! `MAX_TRANSDUCERS`) and pushes every device's logical range, its own
+//! included, into that device's lock-free ring. Each device then plays from
+//! its ring once it holds `SPLIT_LATENCY_FRAMES`, so every range leaves the
+//! engine with the same added latency. Separate interfaces run from separate
+//! clocks, so each secondary holds its ring near that target by dropping or
+//! repeating a single frame (a "slip") when the smoothed fill wanders, and
+//! counters let the supervisor report the drift between streams.
+
+use crate::config::OutputSplit;
+use crate::engine::MAX_TRANSDUCERS;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Ring fill each device plays behind the render (21 ms at 48 kHz). Must
+/// cover a couple of callbacks of the largest device block.
+pub const SPLIT_LATENCY_FRAMES: usize = 1024;
+
+/// Ring capacity per device, in frames.
+const SPLIT_RING_FRAMES: usize = 8 * SPLIT_LATENCY_FRAMES;
+
+/// Largest block rendered in one pass; larger callbacks render in pieces.
+const MAX_RENDER_FRAMES: usize = 4096;
+
+/// Smoothing of the fill estimate that drives slips, per callback. Slow
+/// enough that block-size jitter between the streams never triggers one.
+const FILL_SMOOTHING: f32 = 0.01;
+
+/// Callbacks after priming before slips start, so the smoothed fill has
+/// settled on the phase between this device's callbacks and the primary's.
+const SETTLE_CALLBACKS: u32 = (3.0 / FILL_SMOOTHING) as u32;
+
+/// Smoothed fill may stray this far from the target before a slip.
+const SLIP_TOLERANCE_FRAMES: f32 = SPLIT_LATENCY_FRAMES as f32 / 4.0;
+
+/// Counters for one device of a split, written by its callback (and the
+/// primary's, for overflows) and read by the drift monitor.
+#[derive(Default)]
+pub struct SplitHealth {
+    /// Device frames played.
+    pub frames: AtomicU64,
+    /// Ring fill after the latest callback, in frames.
+    pub fill: AtomicU64,
+    /// Frames played as silence because the ring ran dry.
+    pub underruns: AtomicU64,
+    /// Rendered frames that did not fit in the ring.
+    pub overflows: AtomicU64,
+    /// Frames dropped or repeated to follow the primary's clock.
+    pub slips: AtomicU64,
+}
+
+struct SplitFeed {
+    channels: Range<usize>,
+    ring: rtrb::Producer<f32>,
+    health: Arc<SplitHealth>,
+}
+
+/// The primary callback's side: renders and distributes, then plays its own
+/// range through `local`.
+pub struct SplitRouter {
+    /// Logical channels per rendered frame: the transducer count.
+    width: usize,
+    scratch: Box<[f32]>,
+    feeds: Vec<SplitFeed>,
+    local: SplitSink,
+}
+
+/// One device's side: drains its ring into the device buffer.
+pub struct SplitSink {
+    ring: rtrb::Consumer<f32>,
+    width: usize,
+    health: Arc<SplitHealth>,
+    primed: bool,
+    /// Whether this sink follows another clock and may slip.
+    follows: bool,
+    /// Smoothed fill once settled, which it is then held near.
+    reference_fill: f32,
+    settling: u32,
+    smoothed_fill: f32,
+    held: [f32; MAX_TRANSDUCERS],
+    repeat_pending: bool,
+}
+
+/// Build the router for `outputs[0]` and a sink for each further output,
+/// with the health counters of every output in order. Allocates; call
+/// before the streams start.
+pub fn build(outputs: &[OutputSplit]) -> (SplitRouter, Vec<SplitSink>, Vec<Arc<SplitHealth>>) {
+    let mut feeds = Vec::with_capacity(outputs.len());
+    let mut sinks = Vec::with_capacity(outputs.len());
+    let mut healths = Vec::with_capacity(outputs.len());
+    for (index, output) in outputs.iter().enumerate() {
+        let width = output.channel_count;
+        let (producer, consumer) = rtrb::RingBuffer::new(SPLIT_RING_FRAMES * width);
+        let health = Arc::new(SplitHealth::default());
+        feeds.push(SplitFeed {
+            channels: output.channels(),
+            ring: producer,
+            health: health.clone(),
+        });
+        sinks.push(SplitSink {
+            ring: consumer,
+            width,
+            health: health.clone(),
+            primed: false,
+            follows: index > 0,
+            reference_fill: SPLIT_LATENCY_FRAMES as f32,
+            settling: SETTLE_CALLBACKS,
+            smoothed_fill: SPLIT_LATENCY_FRAMES as f32,
+            held: [0.0; MAX_TRANSDUCERS],
+            repeat_pending: false,
+        });
+        healths.push(health);
+    }
+    let local = sinks.remove(0);
+    let width = outputs
+        .iter()
+        .map(|output| output.channels().end)
+        .max()
+        .unwrap_or(0);
+    let router = SplitRouter {
+        width,
+        scratch: vec![0.0; MAX_RENDER_FRAMES * width].into_boxed_slice(),
+        feeds,
+        local,
+    };
+    (router, sinks, healths)
+}
+
+impl SplitRouter {
+    /// Channels in each block passed to `render`.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Render `data.len() / channels` frames through `render`, which fills
+    /// an interleaved `width()`-channel block and its per-channel RMS, distribute
+    /// every range, and play the primary's own range into `data`. Writes
+    /// the RMS over the whole callback into `levels`. MUST NOT block or
+    /// allocate.
+    pub fn process_block(
+        &mut self,
+        data: &mut [f32],
+        channels: usize,
+        levels: &mut [f32; MAX_TRANSDUCERS],
+        mut render: impl FnMut(&mut [f32], &mut [f32; MAX_TRANSDUCERS]),
+    ) {
+        let frames = data.len() / channels;
+        let mut sum_squares = [0.0f32; MAX_TRANSDUCERS];
+        let mut done = 0;
+        while done < frames {
+            let n = (frames - done).min(MAX_RENDER_FRAMES);
+            let block = &mut self.scratch[..n * self.width];
+            let mut block_levels = [0.0f32; MAX_TRANSDUCERS];
+            render(block, &mut block_levels);
+            for (sum, level) in sum_squares.iter_mut().zip(block_levels) {
+                *sum += level * level * n as f32;
+            }
+            for feed in &mut self.feeds {
+                let width = feed.channels.len();
+                let fits = (feed.ring.slots() / width).min(n);
+                if let Ok(chunk) = feed.ring.write_chunk_uninit(fits * width) {
+                    chunk.fill_from_iter(
+                        block
+                            .chunks_exact(self.width)
+                            .take(fits)
+                            .flat_map(|frame| frame[feed.channels.clone()].iter().copied()),
+                    );
+                }
+                if fits < n {
+                    feed.health
+                        .overflows
+                        .fetch_add((n - fits) as u64, Ordering::Relaxed);
+                }
+            }
+            done += n;
+        }
+        let inv_frames = 1.0 / frames.max(1) as f32;
+        for (level, sum) in levels.iter_mut().zip(sum_squares) {
+            *level = (sum * inv_frames).sqrt();
+        }
+        self.local.process_block(data, channels);
+    }
+}
+
+impl SplitSink {
+    /// Fill `data` (interleaved, `channels` wide) from the ring; outputs
+    /// beyond this device's range are silent. Plays silence until the ring
+    /// first reaches `SPLIT_LATENCY_FRAMES`, and again after an underrun.
+    /// MUST NOT block or allocate.
+    pub fn process_block(&mut self, data: &mut [f32], channels: usize) {
+        let width = self.width.min(channels);
+        let frames = data.len() / channels;
+        // Fill is measured before playing, so a steady stream sees the same
+        // value each callback whatever its block size
+        let fill = self.fill();
+        if !self.primed && fill >= SPLIT_LATENCY_FRAMES {
+            self.primed = true;
+            self.settling = SETTLE_CALLBACKS;
+            self.smoothed_fill = fill as f32;
+        }
+        let mut underruns = 0;
+        for frame in data.chunks_exact_mut(channels) {
+            frame.fill(0.0);
+            if !self.primed {
+                continue;
+            }
+            if self.repeat_pending {
+                // Replay the previous frame without consuming one
+                self.repeat_pending = false;
+            } else if self.fill() >= 1 {
+                for sample in self.held[..self.width].iter_mut() {
+                    *sample = self.ring.pop().unwrap_or(0.0);
+                }
+            } else {
+                self.primed = false;
+                self.held = [0.0; MAX_TRANSDUCERS];
+                underruns += 1;
+                continue;
+            }
+            frame[..width].copy_from_slice(&self.held[..width]);
+        }
+
+        if self.primed && self.follows {
+            self.smoothed_fill += FILL_SMOOTHING * (fill as f32 - self.smoothed_fill);
+            let target = self.reference_fill;
+            if self.settling > 0 {
+                self.settling -= 1;
+                if self.settling == 0 {
+                    self.reference_fill = self.smoothed_fill;
+                }
+            } else if self.smoothed_fill > target + SLIP_TOLERANCE_FRAMES && self.fill() > 1 {
+                // This device runs slow: skip a frame
+                for _ in 0..self.width {
+                    let _ = self.ring.pop();
+                }
+                self.smoothed_fill -= 1.0;
+                self.health.slips.fetch_add(1, Ordering::Relaxed);
+            } else if self.smoothed_fill < target - SLIP_TOLERANCE_FRAMES {
+                // This device runs fast: play the next frame twice
+                self.repeat_pending = true;
+                self.smoothed_fill += 1.0;
+                self.health.slips.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.health
+            .frames
+            .fetch_add(frames as u64, Ordering::Relaxed);
+        self.health.fill.store(fill as u64, Ordering::Relaxed);
+        if underruns > 0 {
+            self.health
+                .underruns
+                .fetch_add(underruns, Ordering::Relaxed);
+        }
+    }
+
+    fn fill(&self) -> usize {
+        self.ring.slots() / self.width
+    }
+}
+
+/// Periodic drift report across the devices of a split, comparing each
+/// device's played frames with the primary's.
+pub struct DriftMonitor {
+    devices: Vec<(String, Arc<SplitHealth>)>,
+    last_frames: Vec<u64>,
+}
+
+impl DriftMonitor {
+    pub fn new(devices: Vec<(String, Arc<SplitHealth>)>) -> Self {
+        let last_frames = devices
+            .iter()
+            .map(|(_, health)| health.frames.load(Ordering::Relaxed))
+            .collect();
+        Self {
+            devices,
+            last_frames,
+        }
+    }
+
+    /// Frames played by the device furthest behind; stops advancing when
+    /// any device of the split stalls.
+    pub fn slowest_frames(&self) -> u64 {
+        self.devices
+            .iter()
+            .map(|(_, health)| health.frames.load(Ordering::Relaxed))
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// One line per secondary since the previous report: clock offset from
+    /// the primary in ppm, current fill, and cumulative counters.
+    pub fn report(&mut self) -> String {
+        let frames: Vec<u64> = self
+            .devices
+            .iter()
+            .map(|(_, health)| health.frames.load(Ordering::Relaxed))
+            .collect();
+        let delta = |index: usize| frames[index].saturating_sub(self.last_frames[index]);
+        let primary = delta(0);
+        let mut lines = Vec::with_capacity(self.devices.len());
+        for (index, (name, health)) in self.devices.iter().enumerate().skip(1) {
+            let drift = if primary > 0 {
+                format!(
+                    "{:+.1} ppm",
+                    (delta(index) as f64 - primary as f64) / primary as f64 * 1e6
+                )
+            } else {
+                "no primary frames".to_string()
+            };
+            lines.push(format!(
+                "split {name}: {drift} vs {}, fill {}/{SPLIT_LATENCY_FRAMES}, {} slips, {} underruns, {} overflows",
+                self.devices[0].0,
+                health.fill.load(Ordering::Relaxed),
+                health.slips.load(Ordering::Relaxed),
+                health.underruns.load(Ordering::Relaxed),
+                health.overflows.load(Ordering::Relaxed),
+            ));
+        }
+        self.last_frames = frames;
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn halves() -> Vec<OutputSplit> {
+        vec![
+            OutputSplit {
+                device: "A".into(),
+                first_channel: 0,
+                channel_count: 16,
+            },
+            OutputSplit {
+                device: "B".into(),
+                first_channel: 16,
+                channel_count: 16,
+            },
+        ]
+    }
+
+    /// Logical channel `c` of render frame `n` carries `c * 1000 + n % 1000`.
+    fn ramp(counter: &mut u64) -> impl FnMut(&mut [f32], &mut [f32; MAX_TRANSDUCERS]) + '_ {
+        move |block, _levels| {
+            for frame in block.chunks_exact_mut(32) {
+                for (channel, sample) in frame.iter_mut().enumerate() {
+                    *sample = (channel * 1000) as f32 + (*counter % 1000) as f32;
+                }
+                *counter += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn two_dummy_sinks_play_their_ranges_in_step() {
+        let (mut router, mut sinks, healths) = build(&halves());
+        let mut counter = 0;
+        let mut levels = [0.0; MAX_TRANSDUCERS];
+        let mut a = vec![0.0f32; 512 * 18];
+        let mut b = vec![0.0f32; 512 * 16];
+        for _ in 0..40 {
+            router.process_block(&mut a, 18, &mut levels, ramp(&mut counter));
+            sinks[0].process_block(&mut b, 16);
+        }
+        // Same latency on both devices: frame i of A and B come from the
+        // same render frame, each device carrying only its own range
+        for (frame_a, frame_b) in a.chunks_exact(18).zip(b.chunks_exact(16)) {
+            let n = frame_a[0];
+            for channel in 0..16 {
+                assert_eq!(frame_a[channel], channel as f32 * 1000.0 + n);
+                assert_eq!(frame_b[channel], (channel + 16) as f32 * 1000.0 + n);
+            }
+            assert_eq!(&frame_a[16..], &[0.0, 0.0]);
+        }
+        for health in &healths {
+            assert_eq!(health.underruns.load(Ordering::Relaxed), 0);
+            assert_eq!(health.slips.load(Ordering::Relaxed), 0);
+            assert_eq!(
+                health.fill.load(Ordering::Relaxed),
+                SPLIT_LATENCY_FRAMES as u64
+            );
+        }
+    }
+
+    #[test]
+    fn a_fast_secondary_slips_to_follow_the_primary_clock() {
+        let (mut router, mut sinks, healths) = build(&halves());
+        let mut counter = 0;
+        let mut levels = [0.0; MAX_TRANSDUCERS];
+        let mut a = vec![0.0f32; 480 * 16];
+        let mut b = vec![0.0f32; 480 * 16];
+        // The secondary's clock runs 500 ppm fast: it takes one extra
+        // 480-frame callback per 2000 of the primary's, over 100 s at 48 kHz
+        let mut monitor = DriftMonitor::new(vec![
+            ("A".into(), healths[0].clone()),
+            ("B".into(), healths[1].clone()),
+        ]);
+        for callback in 0..10_000 {
+            router.process_block(&mut a, 16, &mut levels, ramp(&mut counter));
+            sinks[0].process_block(&mut b, 16);
+            if callback % 2000 == 1999 {
+                sinks[0].process_block(&mut b, 16);
+            }
+            if callback > 100 {
+                let fill = healths[1].fill.load(Ordering::Relaxed) as usize;
+                assert!(
+                    fill <= 2 * SPLIT_LATENCY_FRAMES,
+                    "fill {fill} at {callback}"
+                );
+            }
+        }
+        let slips = healths[1].slips.load(Ordering::Relaxed);
+        // 5 extra callbacks of 480 frames must be absorbed by repeats,
+        // less whatever the ring still holds below its target
+        assert!((1000..=2400).contains(&slips), "{slips} slips");
+        assert_eq!(healths[1].underruns.load(Ordering::Relaxed), 0);
+        assert_eq!(healths[1].overflows.load(Ordering::Relaxed), 0);
+        let report = monitor.report();
+        assert!(report.contains("split B: +500.0 ppm vs A"), "{report}");
+    }
+}
@@ -0,0 +1,180 @@
+//! Server-global operational state that survives a restart. Today that is
+//! the monitor routing; the layout and per-instance patches already live in
+//! the config and the clients. The IPC thread owns the live copy and saves
+//! it shortly after it changes; startup restores it unless `--fresh-state`.
+
+use crate::engine::MAX_TRANSDUCERS;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Default state file name, placed next to the config.
+pub const DEFAULT_STATE_FILE: &str = "haptic-state.toml";
+
+/// How long a change waits before it is written, so a burst of routing
+/// edits costs one write.
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedState {
+    /// Logical source played by each physical output, one per transducer.
+    #[serde(default)]
+    pub routes: Vec<u8>,
+}
+
+impl SavedState {
+    /// The saved routing as the engine holds it, if it was saved for a
+    /// layout of `count` transducers.
+    pub fn monitor_routes(&self, count: usize) -> Result<[u8; MAX_TRANSDUCERS], String> {
+        if self.routes.len() != count {
+            return Err(format!(
+                "routing was saved for {} transducers, the layout has {count}",
+                self.routes.len()
+            ));
+        }
+        if let Some(&source) = self.routes.iter().find(|&&source| source as usize >= count) {
+            return Err(format!("routing names source {source}, beyond the layout"));
+        }
+        let mut routes: [u8; MAX_TRANSDUCERS] = std::array::from_fn(|i| i as u8);
+        routes[..count].copy_from_slice(&self.routes);
+        Ok(routes)
+    }
+}
+
+/// The state saved at `path`, or `None` if there is no file yet.
+pub fn load(path: &Path) -> Result<Option<SavedState>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => toml::from_str(&text).map(Some).map_err(|e| e.to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Move an unparseable state file to `<path>.bad` so it can be inspected
+/// rather than overwritten, returning where it went.
+pub fn set_aside(path: &Path) -> Result<PathBuf, String> {
+    let mut bad = path.as_os_str().to_owned();
+    bad.push(".bad");
+    let bad = PathBuf::from(bad);
+    std::fs::rename(path, &bad).map_err(|e| e.to_string())?;
+    Ok(bad)
+}
+
+/// Write `state` through a temporary file and a rename, so a crash mid-write
+/// leaves the previous state intact.
+pub fn save(path: &Path, state: &SavedState) -> Result<(), String> {
+    let text = toml::to_string(state).map_err(|e| e.to_string())?;
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    std::fs::write(&temporary, text).map_err(|e| e.to_string())?;
+    std::fs::rename(&temporary, path).map_err(|e| e.to_string())
+}
+
+/// The IPC thread's handle on the state file: compares the live routing with
+/// what was last written (or started with) and saves once a change has
+/// waited `SAVE_DELAY`.
+pub struct StateFile {
+    path: PathBuf,
+    saved: SavedState,
+    changed_at: Option<Instant>,
+}
+
+impl StateFile {
+    /// `routes` is the routing the engine starts with, restored or not. The
+    /// file is left alone until the live routing differs from it, so a
+    /// startup without changes never writes.
+    pub fn new(path: PathBuf, routes: &[u8]) -> Self {
+        Self {
+            path,
+            saved: SavedState {
+                routes: routes.to_vec(),
+            },
+            changed_at: None,
+        }
+    }
+
+    /// Save `routes` if they differ from the file and the change is due, or
+    /// at once when `force` (on shutdown). A failed write is reported and
+    /// not retried until the routing changes again.
+    pub fn poll(&mut self, routes: &[u8], force: bool) {
+        if routes == self.saved.routes.as_slice() {
+            self.changed_at = None;
+            return;
+        }
+        let changed_at = *self.changed_at.get_or_insert_with(Instant::now);
+        if !force && changed_at.elapsed() < SAVE_DELAY {
+            return;
+        }
+        self.saved = SavedState {
+            routes: routes.to_vec(),
+        };
+        self.changed_at = None;
+        if let Err(e) = save(&self.path, &self.saved) {
+            eprintln!("Failed to save state to {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routing_round_trips_and_is_checked_against_the_layout() {
+        let path = std::env::temp_dir().join(format!("haptic-state-{}.toml", std::process::id()));
+        let mut file = StateFile::new(path.clone(), &[0, 1, 2, 3]);
+        let routes = [1, 0, 2, 2];
+
+        // Not yet due, then forced
+        file.poll(&routes, false);
+        assert_eq!(load(&path).unwrap(), None);
+        file.poll(&routes, true);
+        let saved = load(&path).unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let restored = saved.monitor_routes(4).unwrap();
+        assert_eq!(&restored[..5], &[1, 0, 2, 2, 4]);
+        assert!(saved.monitor_routes(8).is_err());
+        let beyond = SavedState { routes: vec![0, 7] };
+        assert!(beyond.monitor_routes(2).is_err());
+    }
+
+    #[test]
+    fn startup_without_changes_writes_nothing() {
+        let dir = std::env::temp_dir().join(format!("haptic-state-idle-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let identity = [0, 1, 2, 3];
+
+        // No file yet: nothing is created
+        let missing = dir.join("missing.toml");
+        let mut file = StateFile::new(missing.clone(), &identity);
+        file.poll(&identity, false);
+        file.poll(&identity, true);
+        assert!(!missing.exists());
+
+        // Saved for another transducer count: kept as it was
+        let other = dir.join("other.toml");
+        let eight = SavedState {
+            routes: (0..8).rev().collect(),
+        };
+        save(&other, &eight).unwrap();
+        assert!(eight.monitor_routes(identity.len()).is_err());
+        let mut file = StateFile::new(other.clone(), &identity);
+        file.poll(&identity, true);
+        assert_eq!(load(&other).unwrap(), Some(eight));
+
+        // Unparseable: moved aside, not overwritten
+        let corrupt = dir.join("corrupt.toml");
+        std::fs::write(&corrupt, "routes = [").unwrap();
+        assert!(load(&corrupt).is_err());
+        let bad = set_aside(&corrupt).unwrap();
+        assert!(!corrupt.exists());
+        assert_eq!(std::fs::read_to_string(&bad).unwrap(), "routes = [");
+        let mut file = StateFile::new(corrupt.clone(), &identity);
+        file.poll(&identity, true);
+        assert!(!corrupt.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,314 @@
+//! Runtime test signals for physical bring-up.
+//!
+//! The engine mixes the selected `TestSignal` onto its final logical frame
+//! after every voice, the headroom normaliser, and reconstruction, and before
+//! the output bound, analysis, capture, and monitor routing. The viewer and a
+//! capture therefore see exactly what the transducers are driven with. All
+//! state is fixed-size, so switching signals is callback-safe. A change fades
+//! the old signal out and the new one in, so switching never clicks.
+
+use haptic_protocol::{TestSignal, MAX_TEST_SIGNAL_HZ, MIN_TEST_SIGNAL_HZ};
+use std::f32::consts::TAU;
+
+/// Fade applied when a signal starts, stops, or is replaced.
+const FADE_SECS: f32 = 0.01;
+
+/// Raised-cosine polarity pulse: one half cycle at 40 Hz.
+const PULSE_SECS: f32 = 0.0125;
+/// The first channel pulses this far into each period, clear of the
+/// fade-in, and its neighbour the same gap later.
+const PULSE_GAP_SECS: f32 = 0.25;
+const PULSE_PERIOD_SECS: f32 = 1.0;
+
+/// Paul Kellet's refined pink filter has a peak gain around 9 for unit white
+/// noise; this brings typical peaks back to about the requested level.
+const PINK_NORMALISE: f32 = 0.11;
+
+pub struct TestSignalGenerator {
+    signal: TestSignal,
+    /// Replacement waiting for the running signal to fade out.
+    pending: Option<TestSignal>,
+    /// Current fade gain, 0..=1.
+    fade: f32,
+    phase: f32,
+    /// Device frames since the signal started, wrapped at its repeat
+    /// period. Counted in frames so long runs do not drift.
+    frame: u64,
+    rng: u32,
+    pink: [f32; 7],
+}
+
+impl Default for TestSignalGenerator {
+    fn default() -> Self {
+        Self {
+            signal: TestSignal::Off,
+            pending: None,
+            fade: 0.0,
+            phase: 0.0,
+            frame: 0,
+            rng: 0x9e37_79b9,
+            pink: [0.0; 7],
+        }
+    }
+}
+
+impl TestSignalGenerator {
+    /// Switch to `signal` after fading out the running one.
+    pub fn set(&mut self, signal: TestSignal) {
+        if self.signal == TestSignal::Off {
+            self.start(signal);
+        } else {
+            self.pending = Some(signal);
+        }
+    }
+
+    /// Stop immediately, without a fade (used by `Panic`).
+    pub fn stop(&mut self) {
+        self.signal = TestSignal::Off;
+        self.pending = None;
+        self.fade = 0.0;
+    }
+
+    fn start(&mut self, signal: TestSignal) {
+        self.signal = signal;
+        self.pending = None;
+        self.fade = 0.0;
+        self.phase = 0.0;
+        self.frame = 0;
+        self.pink = [0.0; 7];
+    }
+
+    /// Add one device frame of the signal to `frame`. Channels beyond the
+    /// frame are ignored. MUST NOT block or allocate.
+    pub fn mix(&mut self, frame: &mut [f32], sample_rate: f32) {
+        if self.signal == TestSignal::Off {
+            return;
+        }
+        let dt = 1.0 / sample_rate.max(1.0);
+        let fade_step = dt / FADE_SECS;
+        if self.pending.is_some() {
+            self.fade -= fade_step;
+            if self.fade <= 0.0 {
+                let next = self.pending.take().unwrap_or_default();
+                self.start(next);
+                if self.signal == TestSignal::Off {
+                    return;
+                }
+            }
+        } else {
+            self.fade = (self.fade + fade_step).min(1.0);
+        }
+        let gain = self.fade.max(0.0);
+
+        match self.signal {
+            TestSignal::Off => {}
+            TestSignal::Tone {
+                channel,
+                frequency_hz,
+                level,
+            } => {
+                let sample = (self.phase * TAU).sin() * level * gain;
+                self.advance_phase(frequency_hz * dt);
+                add(frame, channel as usize, sample);
+            }
+            TestSignal::Sweep {
+                channel,
+                period_s,
+                level,
+            } => {
+                // Exponential in time: equal time per octave
+                let t = self.frame as f32 * dt;
+                let ratio = MAX_TEST_SIGNAL_HZ / MIN_TEST_SIGNAL_HZ;
+                let frequency = MIN_TEST_SIGNAL_HZ * ratio.powf(t / period_s);
+                // Short fades at the wrap hide the 200 -> 20 Hz jump
+                let edge = (t / FADE_SECS).min((period_s - t) / FADE_SECS).min(1.0);
+                let sample = (self.phase * TAU).sin() * level * gain * edge;
+                self.advance_phase(frequency * dt);
+                self.advance_frame(period_s, sample_rate);
+                add(frame, channel as usize, sample);
+            }
+            TestSignal::PinkNoise { channel, level } => {
+                let sample = self.pink_sample() * level * gain;
+                add(frame, channel as usize, sample);
+            }
+            TestSignal::PolarityPulses { channel, level } => {
+                let pulse = |t: f32| {
+                    if (0.0..PULSE_SECS).contains(&t) {
+                        0.5 - 0.5 * (t / PULSE_SECS * TAU).cos()
+                    } else {
+                        0.0
+                    }
+                };
+                let t = self.frame as f32 * dt;
+                let first = pulse(t - PULSE_GAP_SECS) * level * gain;
+                let second = pulse(t - 2.0 * PULSE_GAP_SECS) * level * gain;
+                self.advance_frame(PULSE_PERIOD_SECS, sample_rate);
+                add(frame, channel as usize, first);
+                add(frame, channel as usize + 1, second);
+            }
+        }
+    }
+
+    fn advance_frame(&mut self, period_s: f32, sample_rate: f32) {
+        let period_frames = ((period_s * sample_rate).round() as u64).max(1);
+        self.frame = (self.frame + 1) % period_frames;
+    }
+
+    fn advance_phase(&mut self, cycles: f32) {
+        self.phase += cycles;
+        if self.phase >= 1.0 {
+            self.phase -= 1.0;
+        }
+    }
+
+    /// Paul Kellet's refined pink filter over xorshift white noise.
+    fn pink_sample(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        let white = self.rng as f32 / u32::MAX as f32 * 2.0 - 1.0;
+        let b = &mut self.pink;
+        b[0] = 0.99886 * b[0] + white * 0.0555179;
+        b[1] = 0.99332 * b[1] + white * 0.0750759;
+        b[2] = 0.96900 * b[2] + white * 0.153852;
+        b[3] = 0.86650 * b[3] + white * 0.3104856;
+        b[4] = 0.55000 * b[4] + white * 0.5329522;
+        b[5] = -0.7616 * b[5] - white * 0.0168980;
+        let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+        b[6] = white * 0.115926;
+        pink * PINK_NORMALISE
+    }
+}
+
+fn add(frame: &mut [f32], channel: usize, sample: f32) {
+    if let Some(out) = frame.get_mut(channel) {
+        *out += sample;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f32 = 48_000.0;
+
+    fn run(generator: &mut TestSignalGenerator, seconds: f32, channels: usize) -> Vec<Vec<f32>> {
+        let mut out = vec![Vec::new(); channels];
+        for _ in 0..(seconds * RATE) as usize {
+            let mut frame = vec![0.0f32; channels];
+            generator.mix(&mut frame, RATE);
+            for (channel, sample) in frame.into_iter().enumerate() {
+                out[channel].push(sample);
+            }
+        }
+        out
+    }
+
+    fn peak(samples: &[f32]) -> f32 {
+        samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()))
+    }
+
+    #[test]
+    fn tone_drives_only_its_channel_at_its_level() {
+        let mut generator = TestSignalGenerator::default();
+        generator.set(TestSignal::Tone {
+            channel: 2,
+            frequency_hz: 100.0,
+            level: 0.5,
+        });
+        let out = run(&mut generator, 0.5, 4);
+        assert!((peak(&out[2]) - 0.5).abs() < 1.0e-3);
+        for channel in [0, 1, 3] {
+            assert_eq!(peak(&out[channel]), 0.0);
+        }
+        // 100 Hz: 50 rising zero crossings in half a second
+        let crossings = out[2]
+            .windows(2)
+            .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+            .count();
+        assert!((49..=51).contains(&crossings), "{crossings} crossings");
+    }
+
+    #[test]
+    fn sweep_rises_from_20_to_200_hz_each_period() {
+        let mut generator = TestSignalGenerator::default();
+        generator.set(TestSignal::Sweep {
+            channel: 0,
+            period_s: 2.0,
+            level: 1.0,
+        });
+        let out = run(&mut generator, 2.0, 1);
+        let crossings = |range: std::ops::Range<usize>| {
+            out[0][range]
+                .windows(2)
+                .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+                .count()
+        };
+        // The first and last tenth of a second sit near 20 and 200 Hz
+        let start = crossings(0..4_800);
+        let end = crossings(91_200..96_000);
+        assert!((1..=4).contains(&start), "{start} crossings at the start");
+        assert!((17..=21).contains(&end), "{end} crossings at the end");
+    }
+
+    #[test]
+    fn pink_noise_is_bounded_and_not_silent() {
+        let mut generator = TestSignalGenerator::default();
+        generator.set(TestSignal::PinkNoise {
+            channel: 0,
+            level: 0.5,
+        });
+        let out = run(&mut generator, 1.0, 1);
+        let rms = (out[0].iter().map(|s| s * s).sum::<f32>() / out[0].len() as f32).sqrt();
+        assert!(rms > 0.02, "rms {rms}");
+        assert!(peak(&out[0]) < 1.0);
+    }
+
+    #[test]
+    fn polarity_pulses_are_positive_and_staggered_on_neighbours() {
+        let mut generator = TestSignalGenerator::default();
+        generator.set(TestSignal::PolarityPulses {
+            channel: 1,
+            level: 0.8,
+        });
+        let out = run(&mut generator, 1.0, 3);
+        assert_eq!(peak(&out[0]), 0.0);
+        let first = out[1].iter().position(|&s| s > 0.4).unwrap();
+        let second = out[2].iter().position(|&s| s > 0.4).unwrap();
+        assert!(out[1].iter().chain(&out[2]).all(|&s| s >= 0.0));
+        assert!(
+            (second - first).abs_diff((PULSE_GAP_SECS * RATE) as usize) <= 1,
+            "pulses {} frames apart",
+            second - first
+        );
+    }
+
+    #[test]
+    fn replacing_a_signal_fades_instead_of_stepping() {
+        let mut generator = TestSignalGenerator::default();
+        generator.set(TestSignal::Tone {
+            channel: 0,
+            frequency_hz: 50.0,
+            level: 1.0,
+        });
+        let before = run(&mut generator, 0.1, 2);
+        generator.set(TestSignal::Tone {
+            channel: 1,
+            frequency_hz: 50.0,
+            level: 1.0,
+        });
+        let after = run(&mut generator, 0.1, 2);
+        let all: Vec<f32> = before[0].iter().chain(&after[0]).copied().collect();
+        let max_step = all
+            .windows(2)
+            .map(|w| (w[1] - w[0]).abs())
+            .fold(0.0, f32::max);
+        // A 50 Hz full-scale sine moves at most 2*pi*50/48000 per sample
+        assert!(max_step < 0.01, "step {max_step}");
+        assert!(peak(&after[1]) > 0.99);
+
+        generator.set(TestSignal::Off);
+        run(&mut generator, 0.1, 2);
+        assert_eq!(generator.signal, TestSignal::Off);
+    }
+}
@@ -0,0 +1,98 @@
+//! Multichannel float output files shared by offline renders and live capture.
+
+use std::io::Write;
+use std::path::Path;
+
+/// Bytes in the header written by `write_wav_header`.
+pub const WAV_HEADER_BYTES: u64 = 80;
+
+/// WAV chunk sizes are u32; refuse files that cannot be described.
+pub const MAX_WAV_DATA_BYTES: u64 = u32::MAX as u64 - (WAV_HEADER_BYTES - 8);
+
+/// Bytes per interleaved f32 frame of `channels` channels.
+pub const fn frame_bytes(channels: usize) -> u64 {
+    (channels * 4) as u64
+}
+
+/// KSDATAFORMAT_SUBTYPE_IEEE_FLOAT, as stored in the WAVEFORMATEXTENSIBLE
+/// SubFormat field.
+pub const IEEE_FLOAT_SUBFORMAT: [u8; 16] = [
+    0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// One channel per transducer, WAVE_FORMAT_EXTENSIBLE with 32-bit float samples.
+    Wav,
+    /// Headerless interleaved little-endian f32, as read by
+    /// `tools/analyze_f32_capture.rs`.
+    RawF32,
+}
+
+impl OutputFormat {
+    /// `.wav` selects WAV; any other extension writes raw f32.
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("wav") => OutputFormat::Wav,
+            _ => OutputFormat::RawF32,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Wav => "wav",
+            OutputFormat::RawF32 => "f32",
+        }
+    }
+}
+
+/// RIFF/WAVE header for `frames` frames of `channels`-channel float audio. The header
+/// has a fixed size, so a writer that does not know the final length up
+/// front can write it with zero frames and overwrite it in place at the end.
+pub fn write_wav_header(
+    out: &mut impl Write,
+    sample_rate: u32,
+    channels: usize,
+    frames: u64,
+) -> Result<(), String> {
+    const FMT_EXTENSIBLE: u16 = 0xfffe;
+    let block_align = frame_bytes(channels) as u16;
+    let data_bytes = frames * frame_bytes(channels);
+    if data_bytes > MAX_WAV_DATA_BYTES {
+        return Err(format!(
+            "{} frames exceed the 4 GiB WAV limit; write a raw .f32 file instead",
+            frames
+        ));
+    }
+    let data_bytes = data_bytes as u32;
+
+    let mut header = Vec::with_capacity(WAV_HEADER_BYTES as usize);
+    header.extend_from_slice(b"RIFF");
+    // WAVE + fmt chunk (8 + 40) + fact chunk (8 + 4) + data chunk header (8)
+    header.extend_from_slice(&(4 + 48 + 12 + 8 + data_bytes).to_le_bytes());
+    header.extend_from_slice(b"WAVE");
+
+    header.extend_from_slice(b"fmt ");
+    header.extend_from_slice(&40u32.to_le_bytes());
+    header.extend_from_slice(&FMT_EXTENSIBLE.to_le_bytes());
+    header.extend_from_slice(&(channels as u16).to_le_bytes());
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&32u16.to_le_bytes()); // bits per sample
+    header.extend_from_slice(&22u16.to_le_bytes()); // extension size
+    header.extend_from_slice(&32u16.to_le_bytes()); // valid bits per sample
+    header.extend_from_slice(&0u32.to_le_bytes()); // no speaker positions
+    header.extend_from_slice(&IEEE_FLOAT_SUBFORMAT);
+
+    // Non-PCM formats carry a fact chunk with the per-channel frame count
+    header.extend_from_slice(b"fact");
+    header.extend_from_slice(&4u32.to_le_bytes());
+    header.extend_from_slice(&(frames as u32).to_le_bytes());
+
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_bytes.to_le_bytes());
+    debug_assert_eq!(header.len() as u64, WAV_HEADER_BYTES);
+    out.write_all(&header)
+        .map_err(|e| format!("write failed: {}", e))
+}
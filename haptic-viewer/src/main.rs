@@ -24,7 +24,7 @@
 //! renders at 120 fps (see the on-screen fps counter).
 
 use std::collections::VecDeque;
-use std::io::{BufRead, BufReader, Read, Write};
+use std::io::{BufRead, BufReader, Read};
 use std::os::unix::net::UnixStream;
 use std::path::PathBuf;
 use std::process::{Child, ChildStdin, Command, Stdio};
@@ -32,14 +32,16 @@ use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 
+use crossbeam_channel::Receiver;
 use eframe::egui;
+use haptic_client::{ClientOptions, CommandSender, Observer, ObserverEvent};
 use haptic_protocol::{
-    encode_frame, ClientRole, FrameDecoder, HapticCommand, InstanceConfig, MpeData, Parameter,
-    ServerStatus, SpatialScaleMode, StimulusType, PROTOCOL_VERSION, SOCKET_PATH,
+    AudioStreamState, HapticCommand, MpeData, Parameter, ServerStatus, SpatialScaleMode,
+    StimulusType, TestSignal, MAX_SWEEP_PERIOD_S, MAX_TEST_SIGNAL_HZ, MIN_SWEEP_PERIOD_S,
+    MIN_TEST_SIGNAL_HZ, SOCKET_PATH,
 };
 use parking_lot::Mutex;
 
-const TRANSDUCERS: usize = 32;
 const TRANSDUCER_RADIUS_M: f32 = 0.09;
 const DISPLAY_EDGE_PADDING_PX: f32 = 8.0;
 
@@ -52,6 +54,10 @@ const OUTPUT_SILENCE_THRESHOLD: f32 = 1.0e-4;
 const SILENT_SNAPSHOTS_TO_RELEASE_REFERENCE: u8 = 3;
 const REFERENCE_FILTER_TAIL_HOLD_S: f32 = 0.25;
 
+/// How long a transducer keeps its clip indicator after the server last
+/// reported a new clamp or over-threshold sample on it.
+const CLIP_INDICATOR_HOLD: Duration = Duration::from_secs(1);
+
 /// MIDI channel used for the viewer's test note (avoids the low channels
 /// a DAW/MPE zone will typically use first).
 const TEST_CHANNEL: u8 = 15;
@@ -460,10 +466,15 @@ fn push_server_log(logs: &Mutex<VecDeque<String>>, line: String) {
 // Shared state between the socket reader thread and the UI thread
 // ---------------------------------------------------------------------------
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 struct LayoutView {
-    positions: [(f32, f32); TRANSDUCERS],
+    positions: Vec<(f32, f32)>,
+    /// Height of each transducer above the layout plane, in metres.
+    heights: Vec<f32>,
     table_m: (f32, f32),
+    /// Active layout and every layout the server's config declares.
+    name: String,
+    available: Vec<String>,
 }
 
 #[derive(Clone, Copy)]
@@ -485,26 +496,208 @@ struct OutputView {
     device_sample_rate: f32,
     sample_index: u64,
     valid: bool,
-    analytic: [(f32, f32); TRANSDUCERS],
+    /// Gain the engine's headroom normaliser is applying (1.0 = none).
+    headroom_gain: f32,
+    analytic: Vec<(f32, f32)>,
     voices: Vec<VoiceView>,
     received_at: Instant,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 struct RoutingView {
     device_channels: u16,
-    routes: [u8; TRANSDUCERS],
+    routes: Vec<u8>,
+}
+
+/// Latest `AudioStream` transition from the server.
+#[derive(Clone)]
+struct AudioStreamView {
+    state: AudioStreamState,
+    device: String,
+    fallback: bool,
+    attempt: u32,
+    retry_in_ms: u32,
+    message: String,
+}
+
+/// Latest `AudioHealth` from the server. Its counts are cumulative, so
+/// `last_xrun` marks when they last rose.
+#[derive(Clone)]
+struct AudioHealthView {
+    late_callbacks: u64,
+    gaps: u64,
+    realtime: bool,
+    memory_locked: bool,
+    last_xrun: Option<Instant>,
+}
+
+/// Latest `ConfigState` from the server: the config version the engine is
+/// running and why the latest reload or selection was rejected, if it was.
+#[derive(Clone)]
+struct ConfigView {
+    version: u64,
+    path: String,
+    error: String,
+}
+
+/// How long new xruns stay highlighted.
+const XRUN_HIGHLIGHT: Duration = Duration::from_secs(10);
+
+/// One `PerformanceMetrics` report.
+#[derive(Clone, Copy)]
+struct MetricsSample {
+    cpu_percent: u8,
+    callback_p50: f32,
+    callback_p99: f32,
+    callback_max: f32,
+    command_queue_depth: u32,
+    dropped_commands: u64,
+    dropped_snapshots: u64,
+    wave_voices: u8,
+    travelling_wave_voices: u8,
+    instances: u8,
+}
+
+/// Reports kept for the performance charts: a minute at the server's 2 Hz.
+const METRICS_HISTORY: usize = 120;
+
+/// Latest `CaptureState` from the server.
+#[derive(Clone, Default)]
+struct CaptureView {
+    active: bool,
+    path: String,
+    frames: u64,
+    dropped_frames: u64,
+    error: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum ClipIndicator {
+    #[default]
+    None,
+    /// Exceeded the server's warning threshold but was not clamped.
+    Hot,
+    Clamped,
+}
+
+/// Recent per-transducer clipping, derived from the server's cumulative
+/// `ClipCounts`. The first report after connecting is only a baseline, so
+/// clips that happened before the viewer attached are not flagged, nor is a
+/// report whose transducer count differs from the last one.
+#[derive(Default)]
+struct ClipView {
+    totals: Option<(Vec<u64>, Vec<u64>)>,
+    last_clamped: Vec<Option<Instant>>,
+    last_hot: Vec<Option<Instant>>,
+}
+
+impl ClipView {
+    /// Fold in new totals (render and output stages already combined).
+    fn update(&mut self, clamped: Vec<u64>, hot: Vec<u64>, now: Instant) {
+        let count = clamped.len().min(hot.len());
+        if self.last_clamped.len() != count {
+            *self = Self {
+                totals: None,
+                last_clamped: vec![None; count],
+                last_hot: vec![None; count],
+            };
+        }
+        if let Some((prev_clamped, prev_hot)) = &self.totals {
+            for i in 0..count {
+                if clamped[i] > prev_clamped[i] {
+                    self.last_clamped[i] = Some(now);
+                }
+                if hot[i] > prev_hot[i] {
+                    self.last_hot[i] = Some(now);
+                }
+            }
+        }
+        self.totals = Some((clamped, hot));
+    }
+
+    fn indicators(&self, now: Instant) -> Vec<ClipIndicator> {
+        let recent = |at: Option<Instant>| {
+            at.is_some_and(|at| now.saturating_duration_since(at) < CLIP_INDICATOR_HOLD)
+        };
+        self.last_clamped
+            .iter()
+            .zip(&self.last_hot)
+            .map(|(&clamped, &hot)| {
+                if recent(clamped) {
+                    ClipIndicator::Clamped
+                } else if recent(hot) {
+                    ClipIndicator::Hot
+                } else {
+                    ClipIndicator::None
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod clip_indicator_tests {
+    use super::{ClipIndicator, ClipView, CLIP_INDICATOR_HOLD};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn only_increases_after_the_baseline_light_indicators_until_the_hold_expires() {
+        let start = Instant::now();
+        let mut clips = ClipView::default();
+        clips.update(vec![5; 32], vec![9; 32], start);
+        assert!(clips
+            .indicators(start)
+            .iter()
+            .all(|&indicator| indicator == ClipIndicator::None));
+
+        let mut clamped = vec![5; 32];
+        let mut hot = vec![9; 32];
+        clamped[2] += 1;
+        hot[2] += 1;
+        hot[6] += 4;
+        let later = start + Duration::from_millis(100);
+        clips.update(clamped, hot, later);
+        let indicators = clips.indicators(later);
+        assert_eq!(indicators[2], ClipIndicator::Clamped);
+        assert_eq!(indicators[6], ClipIndicator::Hot);
+        assert_eq!(indicators[0], ClipIndicator::None);
+
+        let expired = later + CLIP_INDICATOR_HOLD;
+        assert_eq!(clips.indicators(expired)[2], ClipIndicator::None);
+    }
+
+    #[test]
+    fn a_changed_transducer_count_restarts_the_baseline() {
+        let start = Instant::now();
+        let mut clips = ClipView::default();
+        clips.update(vec![5; 32], vec![9; 32], start);
+        clips.update(vec![7; 16], vec![9; 16], start);
+        let indicators = clips.indicators(start);
+        assert_eq!(indicators.len(), 16);
+        assert!(indicators
+            .iter()
+            .all(|&indicator| indicator == ClipIndicator::None));
+    }
 }
 
 #[derive(Default)]
 struct Shared {
     connected: bool,
-    /// Writable clone of the socket for sending commands from the UI.
-    writer: Option<UnixStream>,
+    /// Writes on the observer connection, for commands from the UI.
+    commands: CommandSender,
     layout: Option<LayoutView>,
     /// Measured final output and its synchronized oscillator references.
     output: Option<OutputView>,
     routing: Option<RoutingView>,
+    clips: ClipView,
+    capture: CaptureView,
+    audio: Option<AudioStreamView>,
+    audio_health: Option<AudioHealthView>,
+    config: Option<ConfigView>,
+    /// Recent performance reports, oldest first.
+    metrics: VecDeque<MetricsSample>,
+    /// Test signal the server reports running.
+    test_signal: TestSignal,
     output_rate: RateCounter,
 }
 
@@ -536,93 +729,36 @@ impl RateCounter {
     }
 }
 
-/// Send a command over the shared writer; on failure the connection is
-/// considered dead (the reader thread will re-establish it).
+/// Send a command on the observer connection; dropped while disconnected
+/// (the client re-establishes the connection).
 fn send_command(shared: &Mutex<Shared>, cmd: &HapticCommand) {
-    let mut frame = Vec::with_capacity(64);
-    if encode_frame(cmd, &mut frame).is_err() {
-        return;
-    }
-    let mut state = shared.lock();
-    if let Some(writer) = state.writer.as_mut() {
-        if writer.write_all(&frame).is_err() {
-            state.writer = None;
-        }
-    }
+    let commands = shared.lock().commands.clone();
+    commands.send(cmd);
 }
 
 // ---------------------------------------------------------------------------
 // Socket reader
 // ---------------------------------------------------------------------------
 
-fn reader_thread(shared: Arc<Mutex<Shared>>, instance_id: u64, socket_path: String) {
-    loop {
-        let Ok(mut stream) = UnixStream::connect(&socket_path) else {
-            thread::sleep(Duration::from_millis(500));
-            continue;
-        };
-        // Handshake as an Observer so the server sends us the status stream
-        // (controllers receive none). Our own test notes are stamped with this
-        // same instance_id server-side, so the viewer's wave-speed slider sets
-        // its own config and never contends with a plugin's.
-        {
-            let mut hello = Vec::with_capacity(64);
-            if encode_frame(
-                &HapticCommand::Hello {
-                    protocol_version: PROTOCOL_VERSION,
-                    instance_id,
-                    role: ClientRole::Observer,
-                    config: InstanceConfig::default(),
-                },
-                &mut hello,
-            )
-            .is_err()
-                || stream.write_all(&hello).is_err()
-            {
-                thread::sleep(Duration::from_millis(500));
-                continue;
-            }
-        }
-        let mut decoder = FrameDecoder::new();
-        let mut buffer = [0u8; 8192];
-        let mut pending_writer = stream.try_clone().ok();
-        let mut accepted = false;
-        'connection: loop {
-            match stream.read(&mut buffer) {
-                Ok(0) | Err(_) => break 'connection,
-                Ok(n) => {
-                    decoder.extend(&buffer[..n]);
-                    loop {
-                        match decoder.next_frame::<ServerStatus>() {
-                            Ok(Some(ServerStatus::HelloAccepted {
-                                protocol_version,
-                                instance_id: accepted_id,
-                            })) if protocol_version == PROTOCOL_VERSION
-                                && accepted_id == instance_id =>
-                            {
-                                accepted = true;
-                                let mut state = shared.lock();
-                                state.connected = true;
-                                state.writer = pending_writer.take();
-                            }
-                            Ok(Some(msg)) if accepted => apply_message(&shared, msg),
-                            Ok(Some(_)) => break 'connection,
-                            Ok(None) => break,
-                            // Framing errors are unrecoverable mid-stream;
-                            // reconnect to resynchronise
-                            Err(_) => break 'connection,
-                        }
-                    }
-                }
+/// Fold the observer client's events into the shared view state.
+fn reader_thread(shared: Arc<Mutex<Shared>>, events: Receiver<ObserverEvent>) {
+    for event in events {
+        match event {
+            ObserverEvent::Connected(_) => shared.lock().connected = true,
+            ObserverEvent::Status(msg) => apply_message(&shared, msg),
+            ObserverEvent::Disconnected => {
+                let mut state = shared.lock();
+                state.connected = false;
+                state.output = None;
+                state.clips = ClipView::default();
+                state.capture = CaptureView::default();
+                state.audio = None;
+                state.audio_health = None;
+                state.config = None;
+                state.metrics.clear();
+                state.test_signal = TestSignal::Off;
             }
         }
-
-        let mut state = shared.lock();
-        state.connected = false;
-        state.writer = None;
-        state.output = None;
-        drop(state);
-        thread::sleep(Duration::from_millis(500));
     }
 }
 
@@ -630,10 +766,19 @@ fn apply_message(shared: &Mutex<Shared>, msg: ServerStatus) {
     match msg {
         ServerStatus::Layout {
             positions,
+            heights,
             gains: _,
             table_m,
+            name,
+            available,
         } => {
-            shared.lock().layout = Some(LayoutView { positions, table_m });
+            shared.lock().layout = Some(LayoutView {
+                positions,
+                heights,
+                table_m,
+                name,
+                available,
+            });
         }
         ServerStatus::MonitorRouting {
             device_channels,
@@ -648,6 +793,7 @@ fn apply_message(shared: &Mutex<Shared>, msg: ServerStatus) {
             device_sample_rate,
             sample_index,
             valid,
+            headroom_gain,
             analytic,
             count,
             voices,
@@ -672,16 +818,315 @@ fn apply_message(shared: &Mutex<Shared>, msg: ServerStatus) {
                 device_sample_rate,
                 sample_index,
                 valid,
+                headroom_gain,
                 analytic,
                 voices: list,
                 received_at: Instant::now(),
             });
             state.output_rate.tick();
         }
+        ServerStatus::ClipCounts {
+            render_clamped,
+            render_over_threshold,
+            output_clamped,
+            output_over_threshold,
+            ..
+        } => {
+            let sum = |a: Vec<u64>, b: Vec<u64>| a.iter().zip(&b).map(|(a, b)| a + b).collect();
+            let clamped = sum(render_clamped, output_clamped);
+            let hot = sum(render_over_threshold, output_over_threshold);
+            shared.lock().clips.update(clamped, hot, Instant::now());
+        }
+        ServerStatus::AudioStream {
+            state,
+            device,
+            fallback,
+            attempt,
+            retry_in_ms,
+            message,
+            ..
+        } => {
+            shared.lock().audio = Some(AudioStreamView {
+                state,
+                device,
+                fallback,
+                attempt,
+                retry_in_ms,
+                message,
+            });
+        }
+        ServerStatus::CaptureState {
+            active,
+            path,
+            frames,
+            dropped_frames,
+            error,
+        } => {
+            shared.lock().capture = CaptureView {
+                active,
+                path,
+                frames,
+                dropped_frames,
+                error,
+            };
+        }
+        ServerStatus::TestSignal { signal } => {
+            shared.lock().test_signal = signal;
+        }
+        ServerStatus::PerformanceMetrics {
+            cpu_percent,
+            callback_p50,
+            callback_p99,
+            callback_max,
+            command_queue_depth,
+            dropped_commands,
+            dropped_snapshots,
+            wave_voices,
+            travelling_wave_voices,
+            instances,
+            ..
+        } => {
+            let mut state = shared.lock();
+            if state.metrics.len() == METRICS_HISTORY {
+                state.metrics.pop_front();
+            }
+            state.metrics.push_back(MetricsSample {
+                cpu_percent,
+                callback_p50,
+                callback_p99,
+                callback_max,
+                command_queue_depth,
+                dropped_commands,
+                dropped_snapshots,
+                wave_voices,
+                travelling_wave_voices,
+                instances,
+            });
+        }
+        ServerStatus::AudioHealth {
+            late_callbacks,
+            gaps,
+            realtime,
+            memory_locked,
+            ..
+        } => {
+            let mut state = shared.lock();
+            let last_xrun = match &state.audio_health {
+                Some(previous)
+                    if previous.late_callbacks + previous.gaps == late_callbacks + gaps =>
+                {
+                    previous.last_xrun
+                }
+                // The first report after connecting only shows history
+                None => None,
+                Some(_) => Some(Instant::now()),
+            };
+            state.audio_health = Some(AudioHealthView {
+                late_callbacks,
+                gaps,
+                realtime,
+                memory_locked,
+                last_xrun,
+            });
+        }
+        ServerStatus::ConfigState {
+            version,
+            path,
+            error,
+        } => {
+            shared.lock().config = Some(ConfigView {
+                version,
+                path,
+                error,
+            });
+        }
         _ => {}
     }
 }
 
+/// Flag a lost, reopening, or stand-in audio device next to the connection
+/// state; a healthy stream on its preferred device shows nothing.
+fn audio_stream_ui(ui: &mut egui::Ui, audio: &AudioStreamView) {
+    let red = egui::Color32::from_rgb(220, 80, 80);
+    let amber = egui::Color32::from_rgb(230, 170, 60);
+    match audio.state {
+        AudioStreamState::Running if audio.fallback => {
+            ui.separator();
+            ui.colored_label(amber, format!("● fallback device: {}", audio.device))
+                .on_hover_text("the configured output device is unavailable");
+        }
+        AudioStreamState::Running => {}
+        AudioStreamState::Lost => {
+            ui.separator();
+            ui.colored_label(red, "● audio lost, reopening")
+                .on_hover_text(format!("{}: {}", audio.device, audio.message));
+        }
+        AudioStreamState::Retrying => {
+            ui.separator();
+            ui.colored_label(
+                red,
+                format!(
+                    "● no audio device, retry {} in {:.1} s",
+                    audio.attempt + 1,
+                    audio.retry_in_ms as f32 / 1000.0
+                ),
+            )
+            .on_hover_text(&audio.message);
+        }
+    }
+}
+
+/// Xrun count since the server started, highlighted while new ones arrive;
+/// nothing at all while there have been none.
+fn audio_health_ui(ui: &mut egui::Ui, health: &AudioHealthView) {
+    let xruns = health.late_callbacks + health.gaps;
+    if xruns == 0 {
+        return;
+    }
+    ui.separator();
+    let text = format!("{xruns} xruns");
+    let label = if health
+        .last_xrun
+        .is_some_and(|at| at.elapsed() < XRUN_HIGHLIGHT)
+    {
+        ui.colored_label(egui::Color32::from_rgb(230, 170, 60), format!("● {text}"))
+    } else {
+        ui.weak(text)
+    };
+    let on_off = |on: bool| if on { "on" } else { "off" };
+    label.on_hover_text(format!(
+        "{} late callbacks, {} gaps since the server started\nreal-time scheduling {}, memory locked {}",
+        health.late_callbacks,
+        health.gaps,
+        on_off(health.realtime),
+        on_off(health.memory_locked)
+    ));
+}
+
+/// The running config version, with the file on hover.
+fn config_ui(ui: &mut egui::Ui, config: &ConfigView) {
+    ui.separator();
+    ui.weak(format!("config v{}", config.version))
+        .on_hover_text(&config.path);
+}
+
+/// Charts of recent performance reports: callback time against the block
+/// budget, voices and instances, and the engine command queue.
+fn performance_ui(ui: &mut egui::Ui, metrics: &VecDeque<MetricsSample>) {
+    let Some(latest) = metrics.back() else {
+        ui.weak("waiting for performance metrics…");
+        return;
+    };
+    let series = |value: fn(&MetricsSample) -> f32| metrics.iter().map(value).collect::<Vec<_>>();
+    let blue = egui::Color32::from_rgb(90, 150, 230);
+    let amber = egui::Color32::from_rgb(230, 170, 60);
+    let red = egui::Color32::from_rgb(220, 80, 80);
+    let green = egui::Color32::from_rgb(64, 200, 120);
+    ui.horizontal(|ui| {
+        let peak_max = metrics.iter().fold(1.0f32, |m, s| m.max(s.callback_max));
+        chart(
+            ui,
+            &format!(
+                "callback p50 {:.0}% · p99 {:.0}% · max {:.0}% · cpu {}%",
+                latest.callback_p50 * 100.0,
+                latest.callback_p99 * 100.0,
+                latest.callback_max * 100.0,
+                latest.cpu_percent
+            ),
+            &[
+                (blue, series(|s| s.callback_p50)),
+                (amber, series(|s| s.callback_p99)),
+                (red, series(|s| s.callback_max)),
+            ],
+            peak_max,
+            Some(1.0),
+        )
+        .on_hover_text(
+            "callback time as a share of the block's real-time budget; the line marks 100%",
+        );
+        ui.separator();
+        let voices = metrics.iter().fold(1u8, |m, s| {
+            m.max(s.wave_voices + s.travelling_wave_voices)
+                .max(s.instances)
+        });
+        chart(
+            ui,
+            &format!(
+                "wave {} · tw {} · instances {}",
+                latest.wave_voices, latest.travelling_wave_voices, latest.instances
+            ),
+            &[
+                (blue, series(|s| s.wave_voices as f32)),
+                (green, series(|s| s.travelling_wave_voices as f32)),
+                (amber, series(|s| s.instances as f32)),
+            ],
+            voices as f32,
+            None,
+        );
+        ui.separator();
+        let depth = metrics
+            .iter()
+            .fold(1u32, |m, s| m.max(s.command_queue_depth));
+        chart(
+            ui,
+            &format!(
+                "queue {} · dropped commands {} · snapshots {}",
+                latest.command_queue_depth, latest.dropped_commands, latest.dropped_snapshots
+            ),
+            &[(blue, series(|s| s.command_queue_depth as f32))],
+            depth as f32,
+            None,
+        )
+        .on_hover_text("engine command queue depth; drops are counted since the server started");
+    });
+}
+
+/// Caption over a fixed-size line chart of `series`, scaled so `top` is the
+/// upper edge, with an optional dashed reference line.
+fn chart(
+    ui: &mut egui::Ui,
+    caption: &str,
+    series: &[(egui::Color32, Vec<f32>)],
+    top: f32,
+    reference: Option<f32>,
+) -> egui::Response {
+    ui.vertical(|ui| {
+        ui.small(caption);
+        let (rect, response) =
+            ui.allocate_exact_size(egui::vec2(260.0, 48.0), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+        let point = |i: usize, value: f32| {
+            egui::pos2(
+                rect.left() + rect.width() * i as f32 / (METRICS_HISTORY - 1) as f32,
+                rect.bottom()
+                    - rect.height() * (value / top.max(f32::MIN_POSITIVE)).clamp(0.0, 1.0),
+            )
+        };
+        if let Some(value) = reference {
+            let y = point(0, value).y;
+            painter.add(egui::Shape::dashed_line(
+                &[egui::pos2(rect.left(), y), egui::pos2(rect.right(), y)],
+                egui::Stroke::new(1.0, ui.visuals().weak_text_color()),
+                4.0,
+                4.0,
+            ));
+        }
+        for (color, values) in series {
+            // Newest at the right edge
+            let offset = METRICS_HISTORY.saturating_sub(values.len());
+            let points: Vec<_> = values
+                .iter()
+                .enumerate()
+                .map(|(i, &value)| point(offset + i, value))
+                .collect();
+            painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, *color)));
+        }
+        response
+    })
+    .inner
+}
+
 // ---------------------------------------------------------------------------
 // OKLCH -> sRGB with gamut-clamped chroma
 // ---------------------------------------------------------------------------
@@ -756,6 +1201,7 @@ struct TestControls {
     wavelength_m: f32,
     atten_d0_m: f32,
     atten_exponent: f32,
+    source_height_m: f32,
     orbit: bool,
     orbit_period_s: f32,
     orbit_phase: f32,
@@ -776,6 +1222,7 @@ impl Default for TestControls {
             wavelength_m: 0.2,
             atten_d0_m: haptic_protocol::DEFAULT_ATTEN_D0_M,
             atten_exponent: haptic_protocol::DEFAULT_ATTEN_EXPONENT,
+            source_height_m: haptic_protocol::DEFAULT_SOURCE_HEIGHT_M,
             orbit: false,
             orbit_period_s: 6.0,
             orbit_phase: 0.0,
@@ -852,6 +1299,13 @@ impl TestControls {
                 parameter: Parameter::AttenuationExponent(self.atten_exponent),
             },
         );
+        send_command(
+            shared,
+            &HapticCommand::SetParameter {
+                timestamp_us: 0,
+                parameter: Parameter::SourceHeight(self.source_height_m),
+            },
+        );
     }
 
     fn stop(&mut self, shared: &Mutex<Shared>) {
@@ -886,6 +1340,58 @@ impl TestControls {
 // App
 // ---------------------------------------------------------------------------
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SignalKind {
+    Tone,
+    Sweep,
+    PinkNoise,
+    PolarityPulses,
+}
+
+/// Test-signal settings. While a signal runs, edits are sent live.
+struct SignalControls {
+    kind: SignalKind,
+    channel: u8,
+    frequency_hz: f32,
+    period_s: f32,
+    level: f32,
+    /// Last signal requested, so a live edit is sent once.
+    last_sent: TestSignal,
+}
+
+impl Default for SignalControls {
+    fn default() -> Self {
+        Self {
+            kind: SignalKind::Tone,
+            channel: 0,
+            frequency_hz: 55.0,
+            period_s: 10.0,
+            level: 0.25,
+            last_sent: TestSignal::Off,
+        }
+    }
+}
+
+impl SignalControls {
+    fn signal(&self) -> TestSignal {
+        let (channel, level) = (self.channel, self.level);
+        match self.kind {
+            SignalKind::Tone => TestSignal::Tone {
+                channel,
+                frequency_hz: self.frequency_hz,
+                level,
+            },
+            SignalKind::Sweep => TestSignal::Sweep {
+                channel,
+                period_s: self.period_s,
+                level,
+            },
+            SignalKind::PinkNoise => TestSignal::PinkNoise { channel, level },
+            SignalKind::PolarityPulses => TestSignal::PolarityPulses { channel, level },
+        }
+    }
+}
+
 /// Rule for choosing the oscillator against which the same measured summed
 /// output is compared. This never filters or otherwise changes the field.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -944,6 +1450,8 @@ struct ViewerApp {
     server: ServerSupervisor,
     fps: RateCounter,
     test: TestControls,
+    signal: SignalControls,
+    show_performance: bool,
     instance_id: u64,
     reference_rule: ReferenceRule,
     reference: Option<SelectedReference>,
@@ -952,7 +1460,7 @@ struct ViewerApp {
     /// Parameter values in effect for the sounding note, to retrigger when
     /// the user lands on new slider values.
     sounding_params: (u8, u8, StimulusType, f32),
-    last_live_config: (f32, SpatialScaleMode, f32, f32, f32),
+    last_live_config: (f32, SpatialScaleMode, f32, f32, f32, f32),
 }
 
 impl ViewerApp {
@@ -1095,7 +1603,7 @@ fn note_name(note: u8) -> String {
 
 #[cfg(test)]
 mod note_name_tests {
-    use super::{note_name, relative_to_reference, OutputView, SelectedReference, TRANSDUCERS};
+    use super::{note_name, relative_to_reference, OutputView, SelectedReference};
 
     #[test]
     fn uses_ableton_octave_numbers() {
@@ -1134,7 +1642,8 @@ mod note_name_tests {
             device_sample_rate: 48_000.0,
             sample_index: 1_480,
             valid: true,
-            analytic: [(0.0, 0.0); TRANSDUCERS],
+            headroom_gain: 1.0,
+            analytic: vec![(0.0, 0.0); 32],
             voices: Vec::new(),
             received_at: std::time::Instant::now(),
         };
@@ -1159,17 +1668,44 @@ impl eframe::App for ViewerApp {
         self.fps.tick();
         let fps = self.fps.rate();
 
-        let (connected, layout, output, routing, output_rate) = {
+        let (
+            connected,
+            layout,
+            output,
+            routing,
+            clips,
+            capture,
+            audio,
+            audio_health,
+            config,
+            metrics,
+            test_signal,
+            output_rate,
+        ) = {
             let mut state = self.shared.lock();
+            let clips = state.clips.indicators(Instant::now());
             let rate = state.output_rate.rate();
             let output = state
                 .output
                 .as_ref()
                 .filter(|output| output.received_at.elapsed() < OUTPUT_STALE)
                 .cloned();
-            (state.connected, state.layout, output, state.routing, rate)
+            (
+                state.connected,
+                state.layout.clone(),
+                output,
+                state.routing.clone(),
+                clips,
+                state.capture.clone(),
+                state.audio.clone(),
+                state.audio_health.clone(),
+                state.config.clone(),
+                state.metrics.clone(),
+                state.test_signal,
+                rate,
+            )
         };
-        let table = layout.map(|l| l.table_m).unwrap_or((1.0, 2.0));
+        let table = layout.as_ref().map_or((1.0, 2.0), |l| l.table_m);
         if let Some(output) = output.as_ref() {
             self.update_reference(output);
         }
@@ -1186,9 +1722,11 @@ impl eframe::App for ViewerApp {
         let relative_analytic = output.as_ref().and_then(|output| {
             let reference = self.reference?;
             output.valid.then(|| {
-                std::array::from_fn(|channel| {
-                    relative_to_reference(output.analytic[channel], reference.phase)
-                })
+                output
+                    .analytic
+                    .iter()
+                    .map(|&analytic| relative_to_reference(analytic, reference.phase))
+                    .collect::<Vec<_>>()
             })
         });
 
@@ -1202,6 +1740,10 @@ impl eframe::App for ViewerApp {
                     // Keep FPS at the fixed right edge. Changes in message rate or
                     // activity text can only consume space to its left.
                     ui.label(format!("{output_rate} msg/s · {fps} fps"));
+                    ui.toggle_value(&mut self.show_performance, "perf")
+                        .on_hover_text("chart server performance");
+                    ui.separator();
+                    self.capture_ui(ui, connected, &capture, output.as_ref());
                     ui.separator();
                     ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
                         if connected {
@@ -1209,15 +1751,37 @@ impl eframe::App for ViewerApp {
                         } else {
                             ui.colored_label(egui::Color32::from_rgb(220, 80, 80), "● waiting");
                         }
+                        if let Some(audio) = audio.as_ref().filter(|_| connected) {
+                            audio_stream_ui(ui, audio);
+                        }
+                        if let Some(health) = audio_health.as_ref().filter(|_| connected) {
+                            audio_health_ui(ui, health);
+                        }
+                        if let Some(layout) = layout.as_ref().filter(|_| connected) {
+                            self.layout_ui(ui, layout);
+                        }
+                        if let Some(config) = config.as_ref().filter(|_| connected) {
+                            config_ui(ui, config);
+                        }
                         ui.separator();
                         let activity = match voices.len() {
                             0 => "idle".to_string(),
                             1 => "1 voice".to_string(),
                             n => format!("{n} voices · {} instances", instances.len()),
                         };
-                        let summary = routing.map_or(activity.clone(), |routing| {
+                        let mut summary = routing.as_ref().map_or(activity.clone(), |routing| {
                             format!("{} ch · {activity}", routing.device_channels)
                         });
+                        if let Some(gain) = output
+                            .as_ref()
+                            .map(|output| output.headroom_gain)
+                            .filter(|&gain| gain < 0.999)
+                        {
+                            summary.push_str(&format!(
+                                " · headroom {:.1} dB",
+                                20.0 * gain.max(1.0e-6).log10()
+                            ));
+                        }
                         ui.add(egui::Label::new(&summary).truncate())
                             .on_hover_text(&summary);
                     });
@@ -1225,11 +1789,39 @@ impl eframe::App for ViewerApp {
             });
         });
 
+        // A rejected reload or selection stays on screen until a newer
+        // config is running, so a typo in the file can't go unnoticed
+        if let Some(config) = config
+            .as_ref()
+            .filter(|config| connected && !config.error.is_empty())
+        {
+            egui::TopBottomPanel::top("config_error").show(ctx, |ui| {
+                ui.colored_label(
+                    egui::Color32::from_rgb(220, 80, 80),
+                    format!(
+                        "● {} (still running config v{})",
+                        config.error, config.version
+                    ),
+                )
+                .on_hover_text(&config.path);
+            });
+        }
+
         egui::TopBottomPanel::bottom("controls").show(ctx, |ui| {
             self.reference_ui(ui);
+            let transducers = layout.as_ref().map_or(0, |l| l.positions.len());
+            ui.add_enabled_ui(connected && transducers > 0, |ui| {
+                self.signal_ui(ui, test_signal, transducers)
+            });
             ui.add_enabled_ui(connected, |ui| self.test_controls_ui(ui, table));
         });
 
+        if self.show_performance {
+            egui::TopBottomPanel::bottom("performance").show(ctx, |ui| {
+                performance_ui(ui, &metrics);
+            });
+        }
+
         let mut interaction = TableInteraction::default();
         egui::CentralPanel::default()
             .frame(egui::Frame::central_panel(&ctx.style()).inner_margin(0))
@@ -1245,7 +1837,8 @@ impl eframe::App for ViewerApp {
                     &layout,
                     voices,
                     routing.as_ref(),
-                    relative_analytic.as_ref(),
+                    relative_analytic.as_deref(),
+                    &clips,
                 );
             });
 
@@ -1303,6 +1896,7 @@ impl eframe::App for ViewerApp {
             self.test.wavelength_m,
             self.test.atten_d0_m,
             self.test.atten_exponent,
+            self.test.source_height_m,
         );
         if live_config != self.last_live_config {
             self.test.send_config(&self.shared);
@@ -1380,6 +1974,159 @@ impl ViewerApp {
         });
     }
 
+    /// Test-signal picker for hardware bring-up. The signal is mixed on the
+    /// server after the engine, onto logical channels.
+    fn signal_ui(&mut self, ui: &mut egui::Ui, running: TestSignal, transducers: usize) {
+        let controls = &mut self.signal;
+        ui.horizontal(|ui| {
+            let active = running != TestSignal::Off;
+            let button = if active { "■ signal" } else { "▶ signal" };
+            if ui.button(button).clicked() {
+                let signal = if active {
+                    TestSignal::Off
+                } else {
+                    controls.signal()
+                };
+                send_command(&self.shared, &HapticCommand::SetTestSignal { signal });
+                controls.last_sent = signal;
+            }
+            egui::ComboBox::from_id_salt("test_signal_kind")
+                .selected_text(match controls.kind {
+                    SignalKind::Tone => "tone",
+                    SignalKind::Sweep => "sweep",
+                    SignalKind::PinkNoise => "pink noise",
+                    SignalKind::PolarityPulses => "polarity",
+                })
+                .width(90.0)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut controls.kind, SignalKind::Tone, "steady tone");
+                    ui.selectable_value(&mut controls.kind, SignalKind::Sweep, "20-200 Hz sweep");
+                    ui.selectable_value(&mut controls.kind, SignalKind::PinkNoise, "pink noise");
+                    ui.selectable_value(
+                        &mut controls.kind,
+                        SignalKind::PolarityPulses,
+                        "polarity pulse pair",
+                    );
+                });
+            // A pulse pair also drives the next channel
+            let last_channel = match controls.kind {
+                SignalKind::PolarityPulses => transducers.saturating_sub(2),
+                _ => transducers.saturating_sub(1),
+            };
+            controls.channel = controls.channel.min(last_channel as u8);
+            ui.add(egui::DragValue::new(&mut controls.channel).range(0..=last_channel))
+                .on_hover_text("logical channel");
+            match controls.kind {
+                SignalKind::Tone => {
+                    ui.add(
+                        egui::Slider::new(
+                            &mut controls.frequency_hz,
+                            MIN_TEST_SIGNAL_HZ..=MAX_TEST_SIGNAL_HZ,
+                        )
+                        .logarithmic(true)
+                        .suffix(" Hz"),
+                    );
+                }
+                SignalKind::Sweep => {
+                    ui.add(
+                        egui::Slider::new(
+                            &mut controls.period_s,
+                            MIN_SWEEP_PERIOD_S..=MAX_SWEEP_PERIOD_S,
+                        )
+                        .logarithmic(true)
+                        .suffix(" s"),
+                    );
+                }
+                SignalKind::PinkNoise | SignalKind::PolarityPulses => {}
+            }
+            ui.add(egui::Slider::new(&mut controls.level, 0.0..=1.0).text("level"));
+        });
+        let desired = controls.signal();
+        if running != TestSignal::Off
+            && controls.last_sent != TestSignal::Off
+            && desired != controls.last_sent
+        {
+            send_command(
+                &self.shared,
+                &HapticCommand::SetTestSignal { signal: desired },
+            );
+            controls.last_sent = desired;
+        }
+    }
+
+    /// Picker for the server's named layouts; hidden when its config
+    /// declares only one. The server rebroadcasts the layout once switched.
+    fn layout_ui(&self, ui: &mut egui::Ui, layout: &LayoutView) {
+        if layout.available.len() < 2 {
+            return;
+        }
+        let mut selected = layout.name.clone();
+        egui::ComboBox::from_id_salt("server_layout")
+            .selected_text(format!("layout: {}", layout.name))
+            .show_ui(ui, |ui| {
+                for name in &layout.available {
+                    ui.selectable_value(&mut selected, name.clone(), name);
+                }
+            });
+        if selected != layout.name {
+            send_command(
+                &self.shared,
+                &HapticCommand::SelectLayout { name: selected },
+            );
+        }
+    }
+
+    /// Record/stop button and capture progress. The server opens the file,
+    /// so the path is made absolute against the viewer's working directory.
+    fn capture_ui(
+        &self,
+        ui: &mut egui::Ui,
+        connected: bool,
+        capture: &CaptureView,
+        output: Option<&OutputView>,
+    ) {
+        if capture.active {
+            if ui.button("■ stop capture").clicked() {
+                send_command(&self.shared, &HapticCommand::StopCapture);
+            }
+            let seconds = output
+                .map(|output| capture.frames as f32 / output.device_sample_rate.max(1.0))
+                .unwrap_or_default();
+            ui.colored_label(
+                egui::Color32::from_rgb(220, 80, 80),
+                format!("● REC {seconds:.0} s"),
+            )
+            .on_hover_text(&capture.path);
+        } else {
+            let clicked = ui
+                .add_enabled(connected, egui::Button::new("● capture"))
+                .clicked();
+            if clicked {
+                let unix_s = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                let name = format!("haptic-capture-{unix_s}.wav");
+                let path = std::env::current_dir().unwrap_or_default().join(name);
+                send_command(
+                    &self.shared,
+                    &HapticCommand::StartCapture {
+                        path: path.display().to_string(),
+                    },
+                );
+            }
+            if !capture.error.is_empty() {
+                ui.colored_label(egui::Color32::from_rgb(230, 170, 60), "capture failed")
+                    .on_hover_text(&capture.error);
+            } else if !capture.path.is_empty() {
+                ui.label("saved").on_hover_text(format!(
+                    "{} ({} frames, {} dropped)",
+                    capture.path, capture.frames, capture.dropped_frames
+                ));
+            }
+        }
+    }
+
     fn test_controls_ui(&mut self, ui: &mut egui::Ui, table: (f32, f32)) {
         ui.spacing_mut().item_spacing.y = 3.0;
 
@@ -1573,6 +2320,14 @@ impl ViewerApp {
                 .text("exponent"),
             );
         });
+        ui.add(
+            egui::Slider::new(
+                &mut self.test.source_height_m,
+                haptic_protocol::MIN_SOURCE_HEIGHT_M..=haptic_protocol::MAX_SOURCE_HEIGHT_M,
+            )
+            .suffix(" m")
+            .text("source height"),
+        );
 
         if self.test.stimulus_type == StimulusType::TravellingWave {
             ui.columns(2, |columns| {
@@ -1600,7 +2355,8 @@ fn draw_table(
     layout: &LayoutView,
     voices: &[VoiceView],
     routing: Option<&RoutingView>,
-    relative_analytic: Option<&[(f32, f32); TRANSDUCERS]>,
+    relative_analytic: Option<&[(f32, f32)]>,
+    clips: &[ClipIndicator],
 ) -> TableInteraction {
     let mut interaction = TableInteraction::default();
     let size = ui.available_size();
@@ -1659,10 +2415,9 @@ fn draw_table(
 
     for (i, &(x, y)) in layout.positions.iter().enumerate() {
         let center = to_screen(x, y);
-        let color = if let Some(relative_analytic) = relative_analytic {
+        let color = if let Some(&(re, im)) = relative_analytic.and_then(|r| r.get(i)) {
             // Hue retains the original zero-phase-blue convention. Magnitude
             // is the measured final output and already includes layout gain.
-            let (re, im) = relative_analytic[i];
             let hue = ZERO_PHASE_HUE_DEG + im.atan2(re).to_degrees();
             let amp = re.hypot(im);
             let vis = (amp * 2.0).clamp(0.0, 1.0).sqrt();
@@ -1678,6 +2433,16 @@ fn draw_table(
             radius,
             egui::Stroke::new(1.0, egui::Color32::from_gray(30)),
         );
+        // Clip indicator: red ring for a recent clamp, amber when only over
+        // the server's warning threshold
+        let clip_color = match clips.get(i).copied().unwrap_or_default() {
+            ClipIndicator::None => None,
+            ClipIndicator::Hot => Some(egui::Color32::from_rgb(240, 170, 40)),
+            ClipIndicator::Clamped => Some(egui::Color32::from_rgb(235, 50, 50)),
+        };
+        if let Some(clip_color) = clip_color {
+            painter.circle_stroke(center, radius + 2.0, egui::Stroke::new(2.5, clip_color));
+        }
 
         if radius > 9.0 {
             painter.text(
@@ -1692,10 +2457,11 @@ fn draw_table(
 
     // Monitor-routing badges: which physical output plays which circle
     if let Some(r) = routing {
-        let outputs = (r.device_channels as usize).min(TRANSDUCERS).min(4);
+        let outputs = (r.device_channels as usize).min(r.routes.len()).min(4);
         for output in 0..outputs {
-            let source = r.routes[output] as usize % TRANSDUCERS;
-            let (x, y) = layout.positions[source];
+            let Some(&(x, y)) = layout.positions.get(r.routes[output] as usize) else {
+                continue;
+            };
             let center = to_screen(x, y) + egui::vec2(radius * 0.9, -radius * 0.9);
             let label = match output {
                 0 => "L".to_string(),
@@ -1760,10 +2526,19 @@ fn draw_table(
         }
     }
 
-    response.on_hover_text(
-        "Drag to move the test source (○ requested, ✚ effective).\n\
-         Left-click a transducer to route output 1 (L); right-click for output 2 (R).",
-    );
+    let hovered = response.hover_pos().and_then(circle_under);
+    let help = "Drag to move the test source (○ requested, ✚ effective).\n\
+                Left-click a transducer to route output 1 (L); right-click for output 2 (R).";
+    match hovered {
+        Some(i) => {
+            let (x, y) = layout.positions[i];
+            let z = layout.heights.get(i).copied().unwrap_or(0.0);
+            response.on_hover_text(format!(
+                "transducer {i}: x {x:.3} m, y {y:.3} m, z {z:.3} m\n{help}"
+            ))
+        }
+        None => response.on_hover_text(help),
+    };
 
     interaction
 }
@@ -1782,24 +2557,30 @@ fn table_world_bounds(layout: &LayoutView) -> (f32, f32, f32, f32) {
 
 #[cfg(test)]
 mod table_layout_tests {
-    use super::{table_world_bounds, LayoutView, TRANSDUCERS, TRANSDUCER_RADIUS_M};
+    use super::{table_world_bounds, LayoutView, TRANSDUCER_RADIUS_M};
 
     #[test]
     fn table_bounds_add_no_padding_for_inset_transducers() {
         let layout = LayoutView {
-            positions: [(0.5, 1.0); TRANSDUCERS],
+            positions: vec![(0.5, 1.0); 32],
+            heights: vec![0.0; 32],
             table_m: (1.0, 2.0),
+            name: "default".into(),
+            available: vec!["default".into()],
         };
         assert_eq!(table_world_bounds(&layout), (0.0, 0.0, 1.0, 2.0));
     }
 
     #[test]
     fn table_bounds_keep_outside_transducers_visible() {
-        let mut positions = [(0.5, 1.0); TRANSDUCERS];
+        let mut positions = vec![(0.5, 1.0); 32];
         positions[0] = (-0.2, 2.3);
         let layout = LayoutView {
             positions,
+            heights: vec![0.0; 32],
             table_m: (1.0, 2.0),
+            name: "default".into(),
+            available: vec!["default".into()],
         };
         let (min_x, min_y, max_x, max_y) = table_world_bounds(&layout);
         assert!((min_x - (-0.2 - TRANSDUCER_RADIUS_M)).abs() < f32::EPSILON);
@@ -1841,9 +2622,18 @@ fn main() -> eframe::Result<()> {
             .unwrap_or(1);
         nanos ^ ((std::process::id() as u64) << 32) | 1
     };
+    // Handshake as an Observer so the server sends us the status stream
+    // (controllers receive none). Our own test notes are stamped with this
+    // same instance_id server-side, so the viewer's wave-speed slider sets
+    // its own config and never contends with a plugin's.
+    let mut client = ClientOptions::new(instance_id);
+    client.socket_path = socket_path.into();
+    let observer = Observer::spawn(client);
+    shared.lock().commands = observer.commands();
     {
         let shared = shared.clone();
-        thread::spawn(move || reader_thread(shared, instance_id, socket_path));
+        let events = observer.events();
+        thread::spawn(move || reader_thread(shared, events));
     }
 
     let options = eframe::NativeOptions {
@@ -1864,6 +2654,8 @@ fn main() -> eframe::Result<()> {
                 server,
                 fps: RateCounter::default(),
                 test: TestControls::default(),
+                signal: SignalControls::default(),
+                show_performance: false,
                 instance_id,
                 reference_rule: ReferenceRule::StickyNewest,
                 reference: None,
@@ -1881,6 +2673,7 @@ fn main() -> eframe::Result<()> {
                     0.2,
                     haptic_protocol::DEFAULT_ATTEN_D0_M,
                     haptic_protocol::DEFAULT_ATTEN_EXPONENT,
+                    haptic_protocol::DEFAULT_SOURCE_HEIGHT_M,
                 ),
             }))
         }),